@@ -0,0 +1,122 @@
+//! Throughput of the CPU, PPU and APU on small synthetic ROMs. Every component runs in each
+//! benchmark but `ppu/redraw`, which only draws lines; the ROMs turn off what a benchmark doesn't
+//! measure where they can, so that the measured component dominates.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use dmg::dmg::core::{Core, CoreBuilder, CYCLES_PER_FRAME};
+use dmg::dmg::frame::SCREEN_HEIGHT;
+use dmg::dmg::input::JoypadInput;
+use dmg::dmg::traits::{NullAudioSink, NullVideoSink};
+
+const INSTRUCTIONS: u64 = 100_000;
+
+// Turns off the LCD and spins on loads, ALU operations and RAM writes
+const CPU_LOOP: &[u8] = &[
+    0xf3, // di
+    0xaf, // xor a
+    0xe0, 0x40, // ldh (LCDC),a
+    0x21, 0x00, 0xc0, // ld hl,$c000
+    0x3c, // loop: inc a
+    0x47, // ld b,a
+    0x80, // add a,b
+    0x77, // ld (hl),a
+    0x2c, // inc l
+    0xcb, 0x37, // swap a
+    0xa9, // xor c
+    0x18, 0xf6, // jr loop
+];
+
+// Draws the background, window and sprites on every line
+const PPU_LOOP: &[u8] = &[
+    0x3e, 0xb3, // ld a,$b3
+    0xe0, 0x40, // ldh (LCDC),a
+    0x3e, 0x40, // ld a,$40
+    0xe0, 0x4a, // ldh (WY),a
+    0x3e, 0x57, // ld a,$57
+    0xe0, 0x4b, // ldh (WX),a
+    0x18, 0xfe, // jr -2
+];
+
+// Turns off the LCD and plays all four channels on both outputs
+const APU_LOOP: &[u8] = &[
+    0xaf, // xor a
+    0xe0, 0x40, // ldh (LCDC),a
+    0x3e, 0xf0, // ld a,$f0
+    0xe0, 0x12, // ldh (NR12),a
+    0xe0, 0x17, // ldh (NR22),a
+    0xe0, 0x21, // ldh (NR42),a
+    0x3e, 0x80, // ld a,$80
+    0xe0, 0x1a, // ldh (NR30),a
+    0xe0, 0x23, // ldh (NR44),a
+    0x3e, 0x20, // ld a,$20
+    0xe0, 0x1c, // ldh (NR32),a
+    0x3e, 0x77, // ld a,$77
+    0xe0, 0x24, // ldh (NR50),a
+    0x3e, 0xff, // ld a,$ff
+    0xe0, 0x25, // ldh (NR51),a
+    0x3e, 0x87, // ld a,$87
+    0xe0, 0x14, // ldh (NR14),a
+    0xe0, 0x19, // ldh (NR24),a
+    0xe0, 0x1e, // ldh (NR34),a
+    0x18, 0xfe, // jr -2
+];
+
+/// A core running `program` from the entry point of a 32K ROM, past the setup at its start.
+fn core_running(program: &[u8]) -> Core {
+    let mut rom = vec![0; 0x8000];
+    rom[0x100..0x100 + program.len()].copy_from_slice(program);
+
+    let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+    core.run_cycles(CYCLES_PER_FRAME as u64, &mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+    core
+}
+
+fn cpu(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cpu");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+    group.bench_function("instructions", |b| {
+        b.iter_batched_ref(
+            || core_running(CPU_LOOP),
+            |core| {
+                for _ in 0..INSTRUCTIONS {
+                    core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+                }
+            },
+            BatchSize::LargeInput,
+        );
+    });
+    group.finish();
+}
+
+fn ppu(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ppu");
+    group.throughput(Throughput::Elements(SCREEN_HEIGHT as u64));
+    group.bench_function("scanlines", |b| {
+        let mut core = core_running(PPU_LOOP);
+        b.iter(|| {
+            core.run_cycles(CYCLES_PER_FRAME as u64, &mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+        });
+    });
+    // Only the line rendering, without the CPU and APU running alongside
+    group.bench_function("redraw", |b| {
+        let mut core = core_running(PPU_LOOP);
+        b.iter(|| core.redraw(&mut NullVideoSink));
+    });
+    group.finish();
+}
+
+fn apu(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apu");
+    group.throughput(Throughput::Elements(CYCLES_PER_FRAME as u64));
+    group.bench_function("ticks", |b| {
+        let mut core = core_running(APU_LOOP);
+        b.iter(|| {
+            core.run_cycles(CYCLES_PER_FRAME as u64, &mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, cpu, ppu, apu);
+criterion_main!(benches);
@@ -0,0 +1,20 @@
+// With the `cbindgen` feature, generates the C header from src/ffi.rs into OUT_DIR, where
+// tests/header.rs compares it with the checked-in include/gameboy.h
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    #[cfg(feature = "cbindgen")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out_dir = std::env::var("OUT_DIR").unwrap();
+        cbindgen::Builder::new()
+            .with_src(format!("{}/src/ffi.rs", crate_dir))
+            .with_config(cbindgen::Config::from_root_or_default(&crate_dir))
+            .generate()
+            .expect("Failed generating gameboy.h")
+            .write_to_file(format!("{}/gameboy.h", out_dir));
+    }
+}
@@ -0,0 +1,92 @@
+/* C API of the gameboy-rust core, generated from src/ffi.rs by cbindgen. Don't edit. */
+
+#ifndef GAMEBOY_H
+#define GAMEBOY_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define GB_SCREEN_WIDTH 160
+
+#define GB_SCREEN_HEIGHT 144
+
+#define GB_BUTTON_DOWN (1 << 0)
+
+#define GB_BUTTON_LEFT (1 << 1)
+
+#define GB_BUTTON_UP (1 << 2)
+
+#define GB_BUTTON_RIGHT (1 << 3)
+
+#define GB_BUTTON_START (1 << 4)
+
+#define GB_BUTTON_SELECT (1 << 5)
+
+#define GB_BUTTON_A (1 << 6)
+
+#define GB_BUTTON_B (1 << 7)
+
+typedef struct GbHandle GbHandle;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates an emulator without a cartridge. Returns null if it could not be created.
+GbHandle *gb_create(void);
+
+// # Safety
+// `gb` must come from `gb_create` and must not be used afterwards.
+void gb_destroy(GbHandle *gb);
+
+// Inserts the cartridge in a ROM file and restarts emulation. Returns 0 on success and -1 on
+// failure.
+//
+// # Safety
+// `gb` must come from `gb_create` and `path` must be a NUL-terminated string.
+int gb_load_rom(GbHandle *gb, const char *path);
+
+// Sets the buttons held down from the next frame on, using the `GB_BUTTON_*` bits.
+//
+// # Safety
+// `gb` must come from `gb_create`.
+void gb_set_input(GbHandle *gb, uint8_t buttons);
+
+// Emulates one frame.
+//
+// # Safety
+// `gb` must come from `gb_create`.
+void gb_run_frame(GbHandle *gb);
+
+// Returns the last frame as 160x144 0RGB pixels. The pointer is valid until the next
+// `gb_run_frame` or `gb_destroy`.
+//
+// # Safety
+// `gb` must come from `gb_create`.
+const uint32_t *gb_get_framebuffer(const GbHandle *gb);
+
+// Copies up to `max_samples` interleaved stereo samples from the last frame into `out` and
+// returns how many samples were copied.
+//
+// # Safety
+// `gb` must come from `gb_create` and `out` must have room for `max_samples * 2` floats.
+size_t gb_get_audio(const GbHandle *gb, float *out, size_t max_samples);
+
+// Writes a save state to `path`. Returns 0 on success and -1 on failure.
+//
+// # Safety
+// `gb` must come from `gb_create` and `path` must be a NUL-terminated string.
+int gb_save_state(const GbHandle *gb, const char *path);
+
+// Restores a save state written by `gb_save_state` for the same ROM. Returns 0 on success and
+// -1 on failure.
+//
+// # Safety
+// `gb` must come from `gb_create` and `path` must be a NUL-terminated string.
+int gb_load_state(GbHandle *gb, const char *path);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* GAMEBOY_H */
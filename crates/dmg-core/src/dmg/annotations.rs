@@ -0,0 +1,65 @@
+use crate::dmg::io_registers::io_register;
+
+const OAM_FIELDS: [&str; 4] = ["Y", "X", "TILE", "ATTR"];
+
+/// The name of the hardware register at `addr`, e.g. `LCDC` for 0xFF40, as in `IO_REGISTERS`.
+/// The disassembler falls back on these for addresses without a symbol.
+pub fn io_register_name(addr: u16) -> Option<&'static str> {
+    io_register(addr).map(|register| register.name).filter(|name| !name.is_empty())
+}
+
+/// What the byte at `addr` means to the hardware, for debuggers: register names, `WAVE 3` in wave
+/// RAM, `OBJ 5 X` for the fields of OAM entries, `TILE 12` in tile data and `MAP0 4,7` for a
+/// tile map entry. `None` for ROM and plain RAM.
+pub fn annotation(addr: u16) -> Option<String> {
+    if let Some(name) = io_register_name(addr) {
+        return Some(name.to_string());
+    }
+
+    match addr {
+        0x8000..=0x97ff => Some(format!("TILE {}", (addr - 0x8000) / 16)),
+        0x9800..=0x9fff => {
+            let map = (addr - 0x9800) / 0x400;
+            let entry = (addr - 0x9800) % 0x400;
+            Some(format!("MAP{} {},{}", map, entry % 32, entry / 32))
+        }
+        0xfe00..=0xfe9f => {
+            let offset = addr - 0xfe00;
+            Some(format!("OBJ {} {}", offset / 4, OAM_FIELDS[offset as usize % 4]))
+        }
+        0xff30..=0xff3f => Some(format!("WAVE {}", addr - 0xff30)),
+        _ => None,
+    }
+}
+
+/// `addr` in hex, followed by its `annotation` if it has one, e.g. `FF40 LCDC` or `C000`.
+pub fn describe_address(addr: u16) -> String {
+    match annotation(addr) {
+        Some(annotation) => format!("{:04X} {}", addr, annotation),
+        None => format!("{:04X}", addr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::annotations::{annotation, describe_address, io_register_name};
+
+    #[test]
+    fn registers_are_named() {
+        assert_eq!(io_register_name(0xff40), Some("LCDC"));
+        assert_eq!(io_register_name(0xff72), None);
+        assert_eq!(io_register_name(0xffff), Some("IE"));
+        assert_eq!(io_register_name(0xff03), None);
+    }
+
+    #[test]
+    fn annotates_hardware_regions() {
+        assert_eq!(describe_address(0xff40), "FF40 LCDC");
+        assert_eq!(describe_address(0xc000), "C000");
+        assert_eq!(annotation(0x8010).as_deref(), Some("TILE 1"));
+        assert_eq!(annotation(0x9c21).as_deref(), Some("MAP1 1,1"));
+        assert_eq!(annotation(0xfe05).as_deref(), Some("OBJ 1 X"));
+        assert_eq!(annotation(0xff33).as_deref(), Some("WAVE 3"));
+        assert_eq!(annotation(0x0150), None);
+    }
+}
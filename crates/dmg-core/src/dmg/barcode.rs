@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::dmg::traits::SerialDevice;
+use crate::error::{Error, Result};
+
+// The game clocks these out and the reader answers with the replies, after which it's ready to
+// send barcodes on its own clock
+const HANDSHAKE: [u8; 4] = [0x10, 0x07, 0x10, 0x07];
+const HANDSHAKE_REPLY: [u8; 4] = [0xff, 0xff, 0x10, 0x07];
+
+// Each barcode is framed by STX and ETX, and sent twice
+const STX: u8 = 0x02;
+const ETX: u8 = 0x03;
+const REPEATS: usize = 2;
+
+const DIGITS: usize = 13;
+
+/// The Barcode Boy card reader, for Barcode Taisen Bardigun and the other games that came with
+/// or supported it. Clones share the reader, so keep one to swipe cards with after connecting
+/// another through `Core::connect_serial`.
+#[derive(Debug, Clone, Default)]
+pub struct BarcodeBoy {
+    reader: Arc<Mutex<Reader>>,
+}
+
+#[derive(Debug, Default)]
+struct Reader {
+    // Handshake bytes the game has sent so far, all four once it's done
+    handshake: usize,
+    pending: VecDeque<u8>,
+}
+
+impl BarcodeBoy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Swipes a card with the 13 digit EAN-13 `barcode`. It's sent once the game has greeted the
+    /// reader and clocks the transfers, after any card swiped before it.
+    pub fn scan(&self, barcode: &str) -> Result<()> {
+        if barcode.len() != DIGITS || !barcode.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::InvalidBarcode(barcode.to_string()));
+        }
+
+        let mut reader = self.reader.lock().unwrap();
+        for _ in 0..REPEATS {
+            reader.pending.push_back(STX);
+            reader.pending.extend(barcode.bytes());
+            reader.pending.push_back(ETX);
+        }
+        Ok(())
+    }
+
+    /// Whether a swiped card is still being sent.
+    pub fn is_sending(&self) -> bool {
+        !self.reader.lock().unwrap().pending.is_empty()
+    }
+}
+
+impl SerialDevice for BarcodeBoy {
+    fn exchange(&self, sent: u8) -> u8 {
+        let mut reader = self.reader.lock().unwrap();
+
+        // Anything out of order starts the handshake over
+        let step = if reader.handshake < HANDSHAKE.len() && sent == HANDSHAKE[reader.handshake] {
+            reader.handshake
+        } else if sent == HANDSHAKE[0] {
+            0
+        } else {
+            reader.handshake = 0;
+            return 0xff;
+        };
+
+        reader.handshake = step + 1;
+        HANDSHAKE_REPLY[step]
+    }
+
+    fn clock_transfer(&self, _sent: u8) -> Option<u8> {
+        let mut reader = self.reader.lock().unwrap();
+
+        if reader.handshake < HANDSHAKE.len() {
+            return None;
+        }
+        reader.pending.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::barcode::BarcodeBoy;
+    use crate::dmg::traits::SerialDevice;
+
+    #[test]
+    fn sends_barcodes_twice_after_the_handshake() {
+        let reader = BarcodeBoy::new();
+        reader.scan("4907981000301").unwrap();
+        assert_eq!(reader.clock_transfer(0x00), None);
+
+        let replies: Vec<u8> = [0x10, 0x07, 0x10, 0x07].iter().map(|&b| reader.exchange(b)).collect();
+        assert_eq!(replies, [0xff, 0xff, 0x10, 0x07]);
+
+        let sent: Vec<u8> = std::iter::from_fn(|| reader.clock_transfer(0x00)).collect();
+        let packet = [&[0x02][..], b"4907981000301", &[0x03]].concat();
+        assert_eq!(sent, [packet.clone(), packet].concat());
+        assert!(!reader.is_sending());
+    }
+
+    #[test]
+    fn rejects_anything_but_13_digits() {
+        let reader = BarcodeBoy::new();
+
+        assert!(reader.scan("490798100030").is_err());
+        assert!(reader.scan("490798100030X").is_err());
+        assert!(!reader.is_sending());
+    }
+}
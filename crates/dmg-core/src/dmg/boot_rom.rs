@@ -0,0 +1,177 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+pub const DMG_BOOT_ROM_SIZE: usize = 0x100;
+pub const CGB_BOOT_ROM_SIZE: usize = 0x900;
+
+// File names tried, in order, when looking for a boot ROM. CGB boot ROMs have to be given explicitly
+// until CGB mode is emulated.
+const BOOT_ROM_NAMES: [&str; 2] = ["dmg_boot.bin", "DMG_ROM.bin"];
+
+/// IO registers as the DMG boot ROM leaves them, in the order they are written. The sound channels
+/// are not retriggered, so channel 1 doesn't replay the tail of the boot sound and NR52 reads F0
+/// rather than F1.
+pub const DMG_POST_BOOT_IO: [(u16, u8); 38] = [
+    (0xff00, 0xcf), (0xff02, 0x7e), (0xff05, 0x00), (0xff06, 0x00), (0xff07, 0xf8), (0xff0f, 0xe1),
+    (0xff26, 0xf1),
+    (0xff10, 0x80), (0xff11, 0xbf), (0xff12, 0xf3), (0xff13, 0xff), (0xff14, 0x3f),
+    (0xff16, 0x3f), (0xff17, 0x00), (0xff18, 0xff), (0xff19, 0x3f),
+    (0xff1a, 0x7f), (0xff1b, 0xff), (0xff1c, 0x9f), (0xff1d, 0xff), (0xff1e, 0x3f),
+    (0xff20, 0xff), (0xff21, 0x00), (0xff22, 0x00), (0xff23, 0x3f),
+    (0xff24, 0x77), (0xff25, 0xf3),
+    (0xff40, 0x91), (0xff41, 0x85), (0xff42, 0x00), (0xff43, 0x00), (0xff45, 0x00),
+    (0xff47, 0xfc), (0xff48, 0xff), (0xff49, 0xff), (0xff4a, 0x00), (0xff4b, 0x00),
+    (0xffff, 0x00),
+];
+
+/// What `CoreBuilder::build` does about the boot ROM.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BootRomPolicy {
+    /// Fails unless a boot ROM is given and can be read.
+    Require,
+    /// Runs the boot ROM if one is given. If its file can't be read, logs a warning and starts from
+    /// the post-boot state instead, as if none was given.
+    #[default]
+    Optional,
+    /// Always starts from the post-boot state, ignoring any boot ROM given.
+    Never,
+}
+
+/// Looks for a boot ROM named by `$GAMEBOY_BOOT_ROM`, then for one of the usual file names next to
+/// the game ROM and in the working directory.
+pub fn find_boot_rom(game_rom: Option<&str>) -> Option<PathBuf> {
+    if let Some(path) = env::var_os("GAMEBOY_BOOT_ROM") {
+        return Some(PathBuf::from(path));
+    }
+
+    let game_dir = game_rom.and_then(|path| Path::new(path).parent()).map(Path::to_path_buf);
+    let dirs = game_dir.into_iter().chain([PathBuf::from(".")]);
+
+    dirs.flat_map(|dir| BOOT_ROM_NAMES.map(|name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+/// A free DMG boot ROM written for this emulator. It scrolls the cartridge logo into view like the
+/// original, skips the logo and header checks, and leaves the CPU in the documented post-boot state.
+#[cfg(feature = "bundled-boot-rom")]
+#[rustfmt::skip]
+pub const BUNDLED_DMG_BOOT_ROM: [u8; DMG_BOOT_ROM_SIZE] = [
+    // start:
+    0x31, 0xfe, 0xff,       // 0000  ld sp, $fffe
+    0xaf,                   // 0003  xor a
+    0x21, 0xff, 0x9f,       // 0004  ld hl, $9fff
+    // clear_vram:
+    0x32,                   // 0007  ld (hl-), a
+    0xcb, 0x7c,             // 0008  bit 7, h
+    0x20, 0xfb,             // 000a  jr nz, clear_vram
+    0x21, 0x26, 0xff,       // 000c  ld hl, $ff26
+    0x0e, 0x11,             // 000f  ld c, $11
+    0x3e, 0x80,             // 0011  ld a, $80
+    0x32,                   // 0013  ld (hl-), a
+    0xe2,                   // 0014  ldh (c), a
+    0x0c,                   // 0015  inc c
+    0x3e, 0xf3,             // 0016  ld a, $f3
+    0xe2,                   // 0018  ldh (c), a
+    0x32,                   // 0019  ld (hl-), a
+    0x3e, 0x77,             // 001a  ld a, $77
+    0x77,                   // 001c  ld (hl), a
+    0x3e, 0xfc,             // 001d  ld a, $fc
+    0xe0, 0x47,             // 001f  ldh ($47), a
+    0x11, 0x04, 0x01,       // 0021  ld de, $0104
+    0x21, 0x10, 0x80,       // 0024  ld hl, $8010
+    // decompress:
+    0x1a,                   // 0027  ld a, (de)
+    0xcb, 0x37,             // 0028  swap a
+    0xe6, 0x0f,             // 002a  and $0f
+    0xcd, 0x7e, 0x00,       // 002c  call expand
+    0x1a,                   // 002f  ld a, (de)
+    0xe6, 0x0f,             // 0030  and $0f
+    0xcd, 0x7e, 0x00,       // 0032  call expand
+    0x13,                   // 0035  inc de
+    0x7b,                   // 0036  ld a, e
+    0xfe, 0x34,             // 0037  cp $34
+    0x20, 0xec,             // 0039  jr nz, decompress
+    0x21, 0x04, 0x99,       // 003b  ld hl, $9904
+    0x3e, 0x01,             // 003e  ld a, 1
+    0x06, 0x0c,             // 0040  ld b, 12
+    // top_row:
+    0x22,                   // 0042  ld (hl+), a
+    0x3c,                   // 0043  inc a
+    0x05,                   // 0044  dec b
+    0x20, 0xfb,             // 0045  jr nz, top_row
+    0x2e, 0x24,             // 0047  ld l, $24
+    0x06, 0x0c,             // 0049  ld b, 12
+    // bottom_row:
+    0x22,                   // 004b  ld (hl+), a
+    0x3c,                   // 004c  inc a
+    0x05,                   // 004d  dec b
+    0x20, 0xfb,             // 004e  jr nz, bottom_row
+    0x3e, 0x64,             // 0050  ld a, $64
+    0x57,                   // 0052  ld d, a
+    0xe0, 0x42,             // 0053  ldh ($42), a
+    0x3e, 0x91,             // 0055  ld a, $91
+    0xe0, 0x40,             // 0057  ldh ($40), a
+    // scroll:
+    0xcd, 0x89, 0x00,       // 0059  call wait_frame
+    0xcd, 0x89, 0x00,       // 005c  call wait_frame
+    0x15,                   // 005f  dec d
+    0x7a,                   // 0060  ld a, d
+    0xe0, 0x42,             // 0061  ldh ($42), a
+    0x20, 0xf4,             // 0063  jr nz, scroll
+    0x16, 0x40,             // 0065  ld d, $40
+    // hold:
+    0xcd, 0x89, 0x00,       // 0067  call wait_frame
+    0x15,                   // 006a  dec d
+    0x20, 0xfa,             // 006b  jr nz, hold
+    0x01, 0xb0, 0x01,       // 006d  ld bc, $01b0
+    0xc5,                   // 0070  push bc
+    0xf1,                   // 0071  pop af
+    0x01, 0x13, 0x00,       // 0072  ld bc, $0013
+    0x11, 0xd8, 0x00,       // 0075  ld de, $00d8
+    0x21, 0x4d, 0x01,       // 0078  ld hl, $014d
+    0xc3, 0xfe, 0x00,       // 007b  jp $00fe
+    // expand:
+    0xc6, 0x96,             // 007e  add a, table
+    0x4f,                   // 0080  ld c, a
+    0x06, 0x00,             // 0081  ld b, 0
+    0x0a,                   // 0083  ld a, (bc)
+    0x22,                   // 0084  ld (hl+), a
+    0x23,                   // 0085  inc hl
+    0x22,                   // 0086  ld (hl+), a
+    0x23,                   // 0087  inc hl
+    0xc9,                   // 0088  ret
+    // wait_frame:
+    0xf0, 0x44,             // 0089  ldh a, ($44)
+    0xfe, 0x90,             // 008b  cp $90
+    0x20, 0xfa,             // 008d  jr nz, wait_frame
+    // leave_vblank_line:
+    0xf0, 0x44,             // 008f  ldh a, ($44)
+    0xfe, 0x90,             // 0091  cp $90
+    0x28, 0xfa,             // 0093  jr z, leave_vblank_line
+    0xc9,                   // 0095  ret
+    // table:
+    0x00,                   // 0096  db $00
+    0x03,                   // 0097  db $03
+    0x0c,                   // 0098  db $0c
+    0x0f,                   // 0099  db $0f
+    0x30,                   // 009a  db $30
+    0x33,                   // 009b  db $33
+    0x3c,                   // 009c  db $3c
+    0x3f,                   // 009d  db $3f
+    0xc0,                   // 009e  db $c0
+    0xc3,                   // 009f  db $c3
+    0xcc,                   // 00a0  db $cc
+    0xcf,                   // 00a1  db $cf
+    0xf0,                   // 00a2  db $f0
+    0xf3,                   // 00a3  db $f3
+    0xfc,                   // 00a4  db $fc
+    0xff,                   // 00a5  db $ff
+    // padding up to $00fe
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xe0, 0x50,             // 00fe  ldh ($50), a
+];
@@ -0,0 +1,338 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dmg::input::JoypadInput;
+use crate::error::{Error, Result};
+
+/// Four colors for the DMG shades, lightest first, as 0RGB pixels.
+pub type Palette = [u32; 4];
+
+/// The colors the CGB boot ROM gives DMG games: one palette for the background and window and
+/// one for each of the two sprite palettes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Colorization {
+    pub bg: Palette,
+    pub obj0: Palette,
+    pub obj1: Palette,
+}
+
+impl Colorization {
+    const fn new(bg: Palette, obj0: Palette, obj1: Palette) -> Self {
+        Self { bg, obj0, obj1 }
+    }
+
+    const fn uniform(palette: Palette) -> Self {
+        Self::new(palette, palette, palette)
+    }
+
+    /// Reads a JASC-PAL palette, the `.pal` text format of Paint Shop Pro that most palette
+    /// editors save: 4 colors, lightest first, for everything, or 12 for the background, OBP0
+    /// and OBP1 in turn.
+    pub fn parse(text: &str) -> Result<Self> {
+        let invalid = |reason: &str| Error::InvalidPalette(reason.to_string());
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+        if lines.next() != Some("JASC-PAL") || lines.next() != Some("0100") {
+            return Err(invalid("not a JASC-PAL file"));
+        }
+        let count = lines.next().and_then(|count| count.parse::<usize>().ok());
+        if count != Some(4) && count != Some(12) {
+            return Err(invalid("expected 4 or 12 colors"));
+        }
+
+        let colors = lines
+            .map(|line| {
+                let mut channels = line.split_whitespace().map(str::parse::<u8>);
+                match (channels.next(), channels.next(), channels.next(), channels.next()) {
+                    (Some(Ok(r)), Some(Ok(g)), Some(Ok(b)), None) => Ok(u32::from_be_bytes([0xff, r, g, b])),
+                    _ => Err(invalid(&format!("bad color {:?}", line))),
+                }
+            })
+            .collect::<Result<Vec<u32>>>()?;
+        if Some(colors.len()) != count {
+            return Err(invalid("the number of colors doesn't match the count"));
+        }
+
+        let palette = |i: usize| -> Palette { colors[i * 4..i * 4 + 4].try_into().unwrap() };
+        Ok(match colors.len() {
+            4 => Self::uniform(palette(0)),
+            _ => Self { bg: palette(0), obj0: palette(1), obj1: palette(2) },
+        })
+    }
+
+    /// Reads a palette file, see `parse`.
+    pub fn load(path: &str) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|source| Error::PaletteLoad { path: path.to_string(), source })?;
+        Self::parse(&text)
+    }
+}
+
+const WHITE_RED: Palette = [0xffffffff, 0xffff8484, 0xff943a3a, 0xff000000];
+const WHITE_GREEN: Palette = [0xffffffff, 0xff7bff31, 0xff008400, 0xff000000];
+const WHITE_BLUE: Palette = [0xffffffff, 0xff63a5ff, 0xff0000ff, 0xff000000];
+const WHITE_BROWN: Palette = [0xffffffff, 0xffffad63, 0xff843100, 0xff000000];
+const WHITE_ORANGE: Palette = [0xffffffff, 0xffff9c00, 0xffff0000, 0xff000000];
+const WHITE_GOLD: Palette = [0xffffffff, 0xffffce00, 0xff9c6300, 0xff000000];
+const WHITE_OLIVE: Palette = [0xffffffff, 0xffadad84, 0xff42737b, 0xff000000];
+const WHITE_RUST: Palette = [0xffffffff, 0xffff7300, 0xff944200, 0xff000000];
+const WHITE_LIME: Palette = [0xffffffff, 0xff7bff00, 0xffb57300, 0xff000000];
+const WHITE_SKY: Palette = [0xffffffff, 0xff5abdff, 0xffff0000, 0xff0000ff];
+const WHITE_AQUA: Palette = [0xffffffff, 0xffffff7b, 0xff0084ff, 0xffff0000];
+const WHITE_FOREST: Palette = [0xffffffff, 0xff00ff00, 0xff318400, 0xff004a00];
+const WHITE_MOSS: Palette = [0xffffffff, 0xffadd663, 0xff849c31, 0xff000000];
+const PALE_BLUE: Palette = [0xffffffff, 0xffffffff, 0xff63a5ff, 0xff0000ff];
+const BLACK_RED: Palette = [0xff000000, 0xffffffff, 0xffff8484, 0xff943a3a];
+const LAVENDER: Palette = [0xffa59cff, 0xffffff00, 0xff006300, 0xff000000];
+const PERIWINKLE: Palette = [0xffb5b5ff, 0xffffff94, 0xffad5a42, 0xff000000];
+const CREAM: Palette = [0xffffff9c, 0xff94b5ff, 0xff639473, 0xff003a3a];
+const SAND: Palette = [0xffffffce, 0xff63efef, 0xff9c8431, 0xff5a5a5a];
+const GOLD_BROWN: Palette = [0xffffc542, 0xffffd600, 0xff943a00, 0xff4a0000];
+const SALMON: Palette = [0xffff6352, 0xffd60000, 0xff630000, 0xff000000];
+const YELLOW_RED: Palette = [0xffffff00, 0xffff0000, 0xff630000, 0xff000000];
+const DEEP_BLUE: Palette = [0xff0000ff, 0xffffffff, 0xffffff7b, 0xff0084ff];
+const FIELD: Palette = [0xff6bff00, 0xffffffff, 0xffff524a, 0xff000000];
+const GRASS: Palette = [0xff52de00, 0xffff8400, 0xffffff00, 0xffffffff];
+
+pub const BROWN: Colorization = Colorization::uniform(WHITE_BROWN);
+pub const RED: Colorization = Colorization { bg: WHITE_RED, obj0: WHITE_GREEN, obj1: WHITE_BLUE };
+pub const DARK_BROWN: Colorization = Colorization::uniform([0xffffe6c5, 0xffce9c84, 0xff846b29, 0xff5a3108]);
+pub const BLUE: Colorization = Colorization { bg: WHITE_BLUE, obj0: WHITE_RED, obj1: WHITE_GREEN };
+pub const DARK_BLUE: Colorization = Colorization { bg: [0xffffffff, 0xff8c8cde, 0xff52528c, 0xff000000], obj0: WHITE_RED, obj1: WHITE_BROWN };
+pub const GRAY: Colorization = Colorization::uniform([0xffffffff, 0xffa5a5a5, 0xff525252, 0xff000000]);
+pub const PALE_YELLOW: Colorization = Colorization::uniform([0xffffffa5, 0xffff9494, 0xff9494ff, 0xff000000]);
+pub const ORANGE: Colorization = Colorization::uniform([0xffffffff, 0xffffff00, 0xffff0000, 0xff000000]);
+pub const YELLOW: Colorization = Colorization { bg: [0xffffffff, 0xffffff00, 0xff7b4a00, 0xff000000], obj0: WHITE_BLUE, obj1: WHITE_GREEN };
+pub const GREEN: Colorization = Colorization::uniform([0xffffffff, 0xff52ff00, 0xffff4200, 0xff000000]);
+pub const DARK_GREEN: Colorization = Colorization { bg: [0xffffffff, 0xff7bff31, 0xff0063c5, 0xff000000], obj0: WHITE_RED, obj1: WHITE_RED };
+pub const REVERSE: Colorization = Colorization::uniform([0xff000000, 0xff008484, 0xffffde00, 0xffffffff]);
+
+/// Used for games that aren't in the boot ROM's table, same as holding Right + A.
+pub const DEFAULT_COLORIZATION: Colorization = DARK_GREEN;
+
+// Title checksum, the title's fourth letter for checksums shared by several games, and the
+// colorization, in the order of the boot ROM's table. The first match wins, and a checksum listed
+// with letters only matches games with one of them.
+const TITLE_COLORIZATIONS: &[(u8, Option<u8>, Colorization)] = &[
+    (0x88, None, Colorization::uniform(LAVENDER)),                                // ALLEY WAY
+    (0x16, None, BROWN),                                                          // YAKUMAN
+    (0x36, None, Colorization::new(GRASS, PALE_BLUE, WHITE_RED)),                 // BASEBALL
+    (0xd1, None, Colorization::new(FIELD, PALE_BLUE, WHITE_BROWN)),               // TENNIS
+    (0xdb, None, ORANGE),                                                         // TETRIS
+    (0xf2, None, Colorization::new(ORANGE.bg, ORANGE.bg, WHITE_SKY)),             // QIX
+    (0x3c, None, Colorization::new(WHITE_BLUE, WHITE_BLUE, WHITE_RED)),           // DR.MARIO
+    (0x8c, None, Colorization::new(WHITE_OLIVE, WHITE_RUST, WHITE_OLIVE)),        // RADARMISSION
+    (0x92, None, BROWN),                                                          // F1RACE
+    (0x3d, None, Colorization::new(GREEN.bg, WHITE_RED, WHITE_RED)),              // YOSSY NO TAMAGO
+    (0x5c, None, Colorization::new(LAVENDER, SALMON, DEEP_BLUE)),                 // HOSHINOKA-BI
+    (0x58, None, GRAY),                                                           // X
+    (0xc9, None, Colorization::new(SAND, WHITE_RUST, WHITE_BLUE)),                // MARIOLAND2
+    (0x3e, None, Colorization::new(WHITE_ORANGE, WHITE_ORANGE, WHITE_SKY)),       // YOSSY NO COOKIE
+    (0x70, None, Colorization::new(WHITE_FOREST, WHITE_RED, WHITE_BLUE)),         // ZELDA
+    (0x1d, None, Colorization::new(LAVENDER, SALMON, SALMON)),                    // KIRBY'S PINBALL
+    (0x59, None, Colorization::new(WHITE_OLIVE, WHITE_RUST, WHITE_SKY)),          // SUPERMARIOLAND3
+    (0x69, None, Colorization::new(ORANGE.bg, ORANGE.bg, WHITE_SKY)),             // TETRIS FLASH
+    (0x19, None, Colorization::new(WHITE_ORANGE, WHITE_RED, WHITE_RED)),          // DONKEY KONG
+    (0x35, None, BROWN),                                                          // MARIO'S PICROSS
+    (0xa8, None, Colorization::new(CREAM, GOLD_BROWN, WHITE_RED)),
+    (0x14, None, Colorization::new(WHITE_RED, WHITE_GREEN, WHITE_RED)),           // POKEMON RED
+    (0xaa, None, Colorization::new(DARK_GREEN.bg, WHITE_RED, DARK_GREEN.bg)),     // POKEMON GREEN
+    (0x75, None, BROWN),                                                          // PICROSS 2
+    (0x95, None, Colorization::new(GREEN.bg, GREEN.bg, WHITE_SKY)),               // YOSSY NO PANEPON
+    (0x99, None, BROWN),                                                          // KIRAKIRA KIDS
+    (0x34, None, Colorization::new(WHITE_LIME, WHITE_RED, WHITE_RED)),            // GAMEBOY GALLERY
+    (0x6f, None, Colorization::uniform(WHITE_GOLD)),                              // POCKETCAMERA
+    (0x15, None, ORANGE),                                                         // POKEMON YELLOW
+    (0xff, None, Colorization::uniform(WHITE_ORANGE)),                            // BALLOON KID
+    (0x97, None, Colorization::new(WHITE_BROWN, WHITE_BLUE, WHITE_BLUE)),         // KINGOFTHEZOO
+    (0x4b, None, Colorization::new(WHITE_GREEN, WHITE_RED, WHITE_RED)),           // DMG FOOTBALL
+    (0x90, None, Colorization::new(WHITE_GREEN, WHITE_RED, WHITE_RED)),           // WORLD CUP
+    (0x17, None, Colorization::new(WHITE_GREEN, WHITE_RED, WHITE_BLUE)),          // OTHELLO
+    (0x10, None, Colorization::new(WHITE_BROWN, WHITE_GREEN, WHITE_BLUE)),        // SUPER RC PRO-AM
+    (0x39, None, Colorization::new(WHITE_BROWN, WHITE_BLUE, WHITE_BLUE)),         // DYNABLASTER
+    (0xf7, None, Colorization::new(WHITE_OLIVE, WHITE_RUST, WHITE_BLUE)),         // BOY AND BLOB GB2
+    (0xf6, None, Colorization::new(WHITE_BROWN, WHITE_GREEN, WHITE_BLUE)),        // MEGAMAN
+    (0xa2, None, Colorization::new(WHITE_OLIVE, WHITE_RUST, WHITE_BLUE)),         // STAR WARS-NOA
+    (0x49, None, Colorization::new(LAVENDER, SALMON, DEEP_BLUE)),                 // KIRBY DREAM LAND
+    (0x4e, None, Colorization::new(WHITE_AQUA, WHITE_RED, WHITE_GREEN)),          // WAVERACE
+    (0x43, None, Colorization::new(WHITE_BROWN, WHITE_BLUE, WHITE_BLUE)),         // THE CHESSMASTER
+    (0x68, None, Colorization::new(WHITE_BROWN, WHITE_GREEN, WHITE_BLUE)),        // LOLO2
+    (0xe0, None, Colorization::new(WHITE_ORANGE, WHITE_ORANGE, WHITE_SKY)),       // YOSHI'S COOKIE
+    (0x8b, None, Colorization::new(WHITE_GREEN, WHITE_RED, WHITE_BLUE)),          // MYSTIC QUEST
+    (0xf0, None, Colorization::new(FIELD, PALE_BLUE, WHITE_BROWN)),               // TOPRANKTENNIS
+    (0xce, None, Colorization::new(FIELD, PALE_BLUE, WHITE_BROWN)),               // TOPRANKINGTENNIS
+    (0x0c, None, BROWN),                                                          // MANSELL
+    (0x29, None, Colorization::new(WHITE_BROWN, WHITE_GREEN, WHITE_BLUE)),        // MEGAMAN3
+    (0xe8, None, REVERSE),                                                        // SPACE INVADERS
+    (0xb7, None, BROWN),                                                          // GAME&WATCH
+    (0x86, None, Colorization::new(CREAM, GOLD_BROWN, WHITE_RED)),                // DONKEYKONGLAND95
+    (0x9a, None, Colorization::new(WHITE_GREEN, WHITE_RED, WHITE_RED)),           // ASTEROIDS/MISCMD
+    (0x52, None, Colorization::new(WHITE_BROWN, WHITE_GREEN, WHITE_BLUE)),        // STREET FIGHTER 2
+    (0x01, None, Colorization::new(WHITE_BROWN, WHITE_GREEN, WHITE_BLUE)),        // DEFENDER/JOUST
+    (0x9d, None, DARK_BLUE),                                                      // KILLERINSTINCT95
+    (0x71, None, Colorization::uniform(WHITE_ORANGE)),                            // TETRIS BLAST
+    (0x9c, None, Colorization::new(DARK_BLUE.bg, DARK_BLUE.bg, GOLD_BROWN)),      // PINOCCHIO
+    (0xbd, None, Colorization::new(WHITE_GREEN, WHITE_RED, WHITE_RED)),           // TOY STORY
+    (0x5d, None, Colorization::new(WHITE_BROWN, WHITE_GREEN, WHITE_BLUE)),        // BA.TOSHINDEN
+    (0x6d, None, Colorization::new(WHITE_BROWN, WHITE_GREEN, WHITE_BLUE)),        // NETTOU KOF 95
+    (0x67, None, BROWN),                                                          // STAR STACKER
+    (0x3f, None, DARK_GREEN),                                                     // TETRIS PLUS
+    (0x6b, None, Colorization::new(DARK_BLUE.bg, WHITE_RED, GOLD_BROWN)),         // DONKEYKONGLAND 3
+    (0xb3, Some(b'B'), Colorization::new(LAVENDER, SALMON, DEEP_BLUE)),           // KIRBY2
+    (0x46, Some(b'E'), Colorization::new(PERIWINKLE, BLACK_RED, BLACK_RED)),      // SUPER MARIOLAND
+    (0x28, Some(b'F'), Colorization::new(WHITE_GREEN, WHITE_RED, WHITE_RED)),     // GOLF
+    (0xa5, Some(b'A'), REVERSE),                                                  // SOLARSTRIKER
+    (0xc6, Some(b'A'), Colorization::new(WHITE_OLIVE, WHITE_RUST, WHITE_SKY)),    // GBWARS
+    (0xd3, Some(b'R'), Colorization::new(DARK_BLUE.bg, WHITE_RED, DARK_BLUE.bg)), // KAERUNOTAMENI
+    (0x27, Some(b'B'), Colorization::new(LAVENDER, SALMON, DEEP_BLUE)),           // KIRBY BLOCKBALL
+    (0x61, Some(b'E'), Colorization::new(WHITE_BLUE, WHITE_RED, WHITE_BLUE)),     // POKEMON BLUE
+    (0x18, Some(b'K'), Colorization::new(DARK_BLUE.bg, WHITE_RED, GOLD_BROWN)),   // DONKEYKONGLAND
+    (0x66, Some(b'E'), Colorization::new(WHITE_LIME, WHITE_RED, WHITE_RED)),      // GAMEBOY GALLERY2
+    (0x6a, Some(b'K'), Colorization::new(DARK_BLUE.bg, WHITE_RED, GOLD_BROWN)),   // DONKEYKONGLAND 2
+    (0xbf, Some(b' '), Colorization::new(DARK_BLUE.bg, WHITE_RED, WHITE_RED)),    // KID ICARUS
+    (0x0d, Some(b'R'), Colorization::new(ORANGE.bg, ORANGE.bg, WHITE_SKY)),       // TETRIS2
+    (0xf4, Some(b'-'), Colorization::new(DARK_GREEN.bg, WHITE_RED, WHITE_BLUE)),  // PAC-IN-TIME
+    (0xb3, Some(b'U'), Colorization::new(WHITE_OLIVE, WHITE_RUST, WHITE_RUST)),   // MOGURANYA
+    (0x46, Some(b'R'), Colorization::new(WHITE_BLUE, YELLOW_RED, WHITE_GREEN)),   // METROID2
+    (0x28, Some(b'A'), REVERSE),
+    (0xa5, Some(b'R'), Colorization::new(WHITE_BROWN, WHITE_GREEN, WHITE_GREEN)), // BT2RAGNAROKWORLD
+    (0xc6, Some(b' '), DARK_GREEN),                                               // KEN GRIFFEY JR
+    (0xd3, Some(b'I'), Colorization::new(WHITE_MOSS, WHITE_RED, WHITE_BROWN)),    // WARIOLAND2
+    (0x27, Some(b'N'), Colorization::new(WHITE_GREEN, WHITE_RED, WHITE_BLUE)),    // MAGNETIC SOCCER
+    (0x61, Some(b'A'), Colorization::new(WHITE_GREEN, WHITE_RED, WHITE_BLUE)),    // VEGAS STAKES
+    (0x18, Some(b'I'), DARK_GREEN),                                               // WARIO BLAST
+    (0x66, Some(b'L'), DARK_GREEN),                                               // MILLI/CENTI/PEDE
+    (0x6a, Some(b'I'), Colorization::new(GREEN.bg, WHITE_RED, WHITE_RED)),        // MARIO & YOSHI
+    (0xbf, Some(b'C'), Colorization::new(FIELD, PALE_BLUE, WHITE_BROWN)),         // SOCCER
+    (0x0d, Some(b'E'), Colorization::new(DARK_BLUE.bg, GOLD_BROWN, GOLD_BROWN)),  // POKEBOM
+    (0xf4, Some(b' '), Colorization::new(WHITE_LIME, WHITE_RED, WHITE_RED)),      // G&W GALLERY
+    (0xb3, Some(b'R'), Colorization::new(GREEN.bg, GREEN.bg, WHITE_SKY)),         // TETRIS ATTACK
+];
+
+/// The colorization the CGB boot ROM picks when a direction, optionally with A or B, is held
+/// while the logo is shown.
+pub fn manual_colorization(keys: JoypadInput) -> Option<Colorization> {
+    let a = keys.contains(JoypadInput::A);
+    let b = keys.contains(JoypadInput::B);
+
+    let colorization = if keys.contains(JoypadInput::UP) {
+        [BROWN, RED, DARK_BROWN]
+    } else if keys.contains(JoypadInput::LEFT) {
+        [BLUE, DARK_BLUE, GRAY]
+    } else if keys.contains(JoypadInput::DOWN) {
+        [PALE_YELLOW, ORANGE, YELLOW]
+    } else if keys.contains(JoypadInput::RIGHT) {
+        [GREEN, DARK_GREEN, REVERSE]
+    } else {
+        return None;
+    };
+
+    Some(match (a, b) {
+        (true, _) => colorization[1],
+        (false, true) => colorization[2],
+        (false, false) => colorization[0],
+    })
+}
+
+/// Sum of the title bytes in the cartridge header, which the CGB boot ROM identifies games by.
+pub fn title_checksum(rom: &[u8]) -> u8 {
+    rom.get(0x134..=0x143).unwrap_or_default().iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
+
+/// The colorization the CGB boot ROM picks for a DMG game. Only games licensed by Nintendo are
+/// looked up by title.
+pub fn colorization_for_rom(rom: &[u8]) -> Colorization {
+    let old_licensee = rom.get(0x14b).copied();
+    let nintendo = old_licensee == Some(0x01) || (old_licensee == Some(0x33) && rom.get(0x144..=0x145) == Some(b"01"));
+    if !nintendo {
+        return DEFAULT_COLORIZATION;
+    }
+
+    let checksum = title_checksum(rom);
+    let fourth_letter = rom.get(0x137).copied();
+
+    TITLE_COLORIZATIONS
+        .iter()
+        .find(|&&(sum, letter, _)| sum == checksum && letter.is_none_or(|l| Some(l) == fourth_letter))
+        .map_or(DEFAULT_COLORIZATION, |&(_, _, colorization)| colorization)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::colorization::{
+        colorization_for_rom, manual_colorization, title_checksum, Colorization, BLACK_RED, DEEP_BLUE, DEFAULT_COLORIZATION, GRAY, GREEN,
+        LAVENDER, ORANGE, PERIWINKLE, RED, REVERSE, SALMON, WHITE_BLUE, WHITE_FOREST, WHITE_GREEN, WHITE_OLIVE, WHITE_RED, WHITE_RUST,
+        WHITE_SKY, YELLOW_RED,
+    };
+    use crate::dmg::input::JoypadInput;
+
+    fn rom_with_title(title: &str, old_licensee: u8) -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x134..0x134 + title.len()].copy_from_slice(title.as_bytes());
+        rom[0x14b] = old_licensee;
+        rom
+    }
+
+    #[test]
+    fn looks_up_nintendo_titles_by_checksum() {
+        assert_eq!(title_checksum(&rom_with_title("POKEMON RED", 0x01)), 0x14);
+        assert_eq!(colorization_for_rom(&rom_with_title("POKEMON RED", 0x01)), Colorization::new(WHITE_RED, WHITE_GREEN, WHITE_RED));
+        assert_eq!(colorization_for_rom(&rom_with_title("TETRIS", 0x01)), ORANGE);
+        assert_eq!(colorization_for_rom(&rom_with_title("X", 0x01)), GRAY);
+        // Found through the new licensee code
+        let mut zelda = rom_with_title("ZELDA", 0x33);
+        zelda[0x144..=0x145].copy_from_slice(b"01");
+        assert_eq!(colorization_for_rom(&zelda), Colorization::new(WHITE_FOREST, WHITE_RED, WHITE_BLUE));
+
+        // Same checksum as POKEMON BLUE, different fourth letter
+        assert_eq!(colorization_for_rom(&rom_with_title("POKFMON BLUD", 0x01)), DEFAULT_COLORIZATION);
+        // Not licensed by Nintendo
+        assert_eq!(colorization_for_rom(&rom_with_title("POKEMON RED", 0x08)), DEFAULT_COLORIZATION);
+    }
+
+    #[test]
+    fn tells_shared_checksums_apart_by_the_fourth_letter() {
+        let colorization = |title| colorization_for_rom(&rom_with_title(title, 0x01));
+
+        assert_eq!(title_checksum(&rom_with_title("SUPER MARIOLAND", 0x01)), title_checksum(&rom_with_title("METROID2", 0x01)));
+        assert_eq!(colorization("SUPER MARIOLAND"), Colorization::new(PERIWINKLE, BLACK_RED, BLACK_RED));
+        assert_eq!(colorization("METROID2"), Colorization::new(WHITE_BLUE, YELLOW_RED, WHITE_GREEN));
+
+        assert_eq!(colorization("POKEMON BLUE"), Colorization::new(WHITE_BLUE, WHITE_RED, WHITE_BLUE));
+        assert_eq!(colorization("VEGAS STAKES"), Colorization::new(WHITE_GREEN, WHITE_RED, WHITE_BLUE));
+
+        // Three games share 0xB3, and one with a fourth letter the table doesn't list gets the default
+        assert_eq!(colorization("KIRBY2"), Colorization::new(LAVENDER, SALMON, DEEP_BLUE));
+        assert_eq!(colorization("MOGURANYA"), Colorization::new(WHITE_OLIVE, WHITE_RUST, WHITE_RUST));
+        assert_eq!(colorization("TETRIS ATTACK"), Colorization::new(GREEN.bg, GREEN.bg, WHITE_SKY));
+        assert_eq!(title_checksum(&rom_with_title("ROCKMAN WORLD", 0x01)), 0xb3);
+        assert_eq!(colorization("ROCKMAN WORLD"), DEFAULT_COLORIZATION);
+    }
+
+    #[test]
+    fn picks_manual_colorization_from_held_keys() {
+        assert_eq!(manual_colorization(JoypadInput::UP | JoypadInput::A), Some(RED));
+        assert_eq!(manual_colorization(JoypadInput::LEFT | JoypadInput::B), Some(GRAY));
+        assert_eq!(manual_colorization(JoypadInput::RIGHT | JoypadInput::B), Some(REVERSE));
+        assert_eq!(manual_colorization(JoypadInput::A), None);
+    }
+
+    #[test]
+    fn reads_jasc_palettes() {
+        let gray = Colorization::parse("JASC-PAL\r\n0100\r\n4\r\n255 255 255\r\n165 165 165\r\n82 82 82\r\n0 0 0\r\n").unwrap();
+        assert_eq!(gray, GRAY);
+
+        let mut text = String::from("JASC-PAL\n0100\n12\n");
+        for palette in [RED.bg, RED.obj0, RED.obj1] {
+            for color in palette {
+                let [_, r, g, b] = color.to_be_bytes();
+                text += &format!("{} {} {}\n", r, g, b);
+            }
+        }
+        assert_eq!(Colorization::parse(&text).unwrap(), RED);
+
+        assert!(Colorization::parse("JASC-PAL\n0100\n4\n255 255 255\n").is_err());
+        assert!(Colorization::parse("JASC-PAL\n0100\n3\n0 0 0\n0 0 0\n0 0 0\n").is_err());
+        assert!(Colorization::parse("GIMP Palette\n").is_err());
+    }
+}
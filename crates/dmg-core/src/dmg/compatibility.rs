@@ -0,0 +1,148 @@
+use std::fmt;
+
+/// Something about a game that this emulator is known not to handle, found from its cartridge
+/// header or the list of known games below, keyed by ROM hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompatibilityNote {
+    /// The cartridge's mapper isn't emulated, so the game won't load.
+    UnsupportedMapper(&'static str),
+    /// The cartridge has an MBC3 real-time clock, which isn't emulated. Its registers keep what
+    /// the game writes but the clock stays stopped.
+    StoppedClock,
+    /// A CGB-only game, whose CGB graphics aren't emulated yet.
+    CgbOnly,
+    /// The header checksum is wrong, which the boot ROM locks up on. Usually a bad dump or a hack.
+    BadHeaderChecksum,
+    /// The file size differs from the ROM size in the header. Usually a bad dump or an overdump.
+    SizeMismatch { header: usize, file: usize },
+}
+
+impl fmt::Display for CompatibilityNote {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompatibilityNote::UnsupportedMapper(mapper) => write!(f, "needs {}, which isn't emulated", mapper),
+            CompatibilityNote::StoppedClock => write!(f, "has a real-time clock, which isn't emulated and stays stopped"),
+            CompatibilityNote::CgbOnly => write!(f, "CGB-only game, CGB graphics aren't emulated yet"),
+            CompatibilityNote::BadHeaderChecksum => write!(f, "header checksum is wrong, the ROM may be a bad dump"),
+            CompatibilityNote::SizeMismatch { header, file } => {
+                write!(f, "header says the ROM is {} bytes but the file is {}, the ROM may be a bad dump", header, file)
+            }
+        }
+    }
+}
+
+// CRC32s of whole ROM files, as `Core::rom_hash` computes them, and what the games rely on that
+// isn't emulated. Hacks, translations and revisions have their own hashes, so they only match if
+// listed themselves.
+const KNOWN_GAMES: &[(u32, &[CompatibilityNote])] = &[
+    // Pokemon - Gold Version (USA, Europe)
+    (0x6bde3c3e, &[CompatibilityNote::StoppedClock]),
+    // Pokemon - Silver Version (USA, Europe)
+    (0x8ad48636, &[CompatibilityNote::StoppedClock]),
+    // Pokemon - Crystal Version (USA, Europe)
+    (0xee6f5188, &[CompatibilityNote::StoppedClock, CompatibilityNote::CgbOnly]),
+    // Pokemon - Crystal Version (USA, Europe) (Rev 1)
+    (0x3358e30a, &[CompatibilityNote::StoppedClock, CompatibilityNote::CgbOnly]),
+];
+
+/// Everything known to go wrong when running `rom`, from its header and the known games.
+pub fn compatibility_notes(rom: &[u8]) -> Vec<CompatibilityNote> {
+    let mut notes = vec![];
+
+    if let Some(mapper) = rom.get(0x147).and_then(|&t| unsupported_mapper(t)) {
+        notes.push(CompatibilityNote::UnsupportedMapper(mapper));
+    }
+    if matches!(rom.get(0x147), Some(0x0f | 0x10)) {
+        notes.push(CompatibilityNote::StoppedClock);
+    }
+    if rom.get(0x143) == Some(&0xc0) {
+        notes.push(CompatibilityNote::CgbOnly);
+    }
+    if rom.get(0x14d).is_some_and(|&checksum| checksum != header_checksum(rom)) {
+        notes.push(CompatibilityNote::BadHeaderChecksum);
+    }
+    if let Some(&size) = rom.get(0x148).filter(|&&size| size <= 0x08) {
+        let header = 0x8000 << size;
+        if header != rom.len() {
+            notes.push(CompatibilityNote::SizeMismatch { header, file: rom.len() });
+        }
+    }
+
+    for &note in known_game_notes(crc32fast::hash(rom)) {
+        // Known games are mostly listed for what their header already gives away
+        if !notes.contains(&note) {
+            notes.push(note);
+        }
+    }
+
+    notes
+}
+
+/// What the known game whose ROM hashes to `rom_hash` relies on, empty for unknown games.
+fn known_game_notes(rom_hash: u32) -> &'static [CompatibilityNote] {
+    KNOWN_GAMES.iter().find(|&&(hash, _)| hash == rom_hash).map_or(&[], |&(_, notes)| notes)
+}
+
+/// The mapper a cartridge type needs, when it's one that isn't emulated.
+fn unsupported_mapper(cartridge_type: u8) -> Option<&'static str> {
+    Some(match cartridge_type {
+        0x00..=0x03 | 0x0f..=0x13 => return None,
+        0x05 | 0x06 => "MBC2",
+        0x08 | 0x09 => "ROM with RAM",
+        0x0b..=0x0d => "MMM01",
+        0x19..=0x1e => "MBC5",
+        0x20 => "MBC6",
+        0x22 => "MBC7",
+        0xfc => "Pocket Camera",
+        0xfd => "TAMA5",
+        0xfe => "HuC3",
+        0xff => "HuC1",
+        _ => "an unknown mapper",
+    })
+}
+
+/// The checksum of 0x134-0x14C that the boot ROM checks against 0x14D.
+fn header_checksum(rom: &[u8]) -> u8 {
+    rom.get(0x134..=0x14c).unwrap_or_default().iter().fold(0u8, |sum, &b| sum.wrapping_sub(b).wrapping_sub(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::compatibility::{compatibility_notes, header_checksum, known_game_notes, CompatibilityNote};
+
+    fn rom_with(title: &str, cartridge_type: u8, len: usize) -> Vec<u8> {
+        let mut rom = vec![0; len];
+        rom[0x134..0x134 + title.len()].copy_from_slice(title.as_bytes());
+        rom[0x147] = cartridge_type;
+        rom[0x14d] = header_checksum(&rom);
+        rom
+    }
+
+    #[test]
+    fn clean_roms_have_no_notes() {
+        assert_eq!(compatibility_notes(&rom_with("TETRIS", 0x00, 0x8000)), vec![]);
+    }
+
+    #[test]
+    fn notes_come_from_the_header_and_known_games() {
+        assert_eq!(compatibility_notes(&rom_with("POKEMON_GLD", 0x10, 0x8000)), vec![CompatibilityNote::StoppedClock]);
+        assert_eq!(compatibility_notes(&rom_with("ZELDA", 0x1b, 0x8000)), vec![CompatibilityNote::UnsupportedMapper("MBC5")]);
+        // Known games are matched by hash, not by title
+        assert_eq!(compatibility_notes(&rom_with("POKEMON_SLV", 0x01, 0x8000)), vec![]);
+        assert_eq!(
+            compatibility_notes(&rom_with("TETRIS", 0x00, 0x9000)),
+            vec![CompatibilityNote::SizeMismatch { header: 0x8000, file: 0x9000 }]
+        );
+
+        let mut rom = rom_with("TETRIS", 0x00, 0x8000);
+        rom[0x14d] ^= 1;
+        assert_eq!(compatibility_notes(&rom), vec![CompatibilityNote::BadHeaderChecksum]);
+    }
+
+    #[test]
+    fn known_games_are_looked_up_by_rom_hash() {
+        assert_eq!(known_game_notes(0x8ad48636), [CompatibilityNote::StoppedClock]);
+        assert_eq!(known_game_notes(0x3358e30a), [CompatibilityNote::StoppedClock, CompatibilityNote::CgbOnly]);
+        assert!(known_game_notes(0x12345678).is_empty());
+    }
+}
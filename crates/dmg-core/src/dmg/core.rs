@@ -0,0 +1,2893 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, VecDeque};
+use std::io;
+use std::io::{Read, Write};
+use std::iter;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::dmg::boot_rom::{BootRomPolicy, CGB_BOOT_ROM_SIZE, DMG_BOOT_ROM_SIZE};
+use crate::dmg::colorization::{colorization_for_rom, Colorization};
+use crate::dmg::compatibility::{compatibility_notes, CompatibilityNote};
+use crate::dmg::coverage::Coverage;
+use crate::dmg::cpu::{disassemble, ProcessingUnit};
+use crate::dmg::crash::{CrashReason, CrashReport};
+use crate::dmg::events::{Event, Subscribers, SubscriptionId};
+use crate::dmg::diagnostics::{DeveloperChecks, DeveloperWarning, Diagnostic, ExecutionDiagnostics, ExecutionWarning};
+use crate::dmg::debug::{format_hexdump, DoctorLog, FrameStats, Instruction, MemoryRegion, PausePoint, PpuState, Registers, TraceEntry, VRAM_VIEW_HEIGHT, VRAM_VIEW_WIDTH};
+use crate::dmg::export::{oam_csv, vram_tiles, Export};
+use crate::dmg::frame::{ColorIndices, Frame, FrameBuffer, SCREEN_WIDTH};
+use crate::dmg::header::CartridgeHeader;
+use crate::dmg::input::JoypadInput;
+use crate::dmg::intf::InterruptFlag;
+use crate::dmg::mem::MemoryBus;
+use crate::dmg::model::{Model, PowerOnRam, Quirks};
+use crate::dmg::observation::Observation;
+use crate::dmg::rom::Rom;
+use crate::dmg::sound::sampler::AudioSampler;
+// Part of the API as the argument of `Core::set_resampler`
+pub use crate::dmg::sound::sampler::Resampler;
+use crate::dmg::symbols::Symbols;
+use crate::dmg::traits::{AudioSink, EmuCallbacks, InputSource, IrTransceiver, NullAudioSink, NullVideoSink, SerialDevice, Tick, VideoSink};
+use crate::emulator::state::{decode_state, encode_state, StateError};
+use crate::error::{Error, Result};
+
+/// Number of clock cycles in one video frame (154 lines of 456 cycles)
+pub const CYCLES_PER_FRAME: u32 = 70224;
+
+/// Clock cycles per second of emulated time
+pub const CLOCK_SPEED: u64 = 4_194_304;
+
+/// Emulated time per video frame, a little under 1/60 s
+pub const FRAME_DURATION: Duration = cycles_to_duration(CYCLES_PER_FRAME as u64);
+
+/// Frames per second on real hardware, about 59.73
+pub const REFRESH_RATE: f64 = CLOCK_SPEED as f64 / CYCLES_PER_FRAME as f64;
+
+/// How long `cycles` clock cycles take on real hardware.
+pub const fn cycles_to_duration(cycles: u64) -> Duration {
+    Duration::from_nanos((cycles as u128 * 1_000_000_000 / CLOCK_SPEED as u128) as u64)
+}
+
+/// Loudest accepted `Core::set_volume`, still short of clipping with all four channels at full volume
+pub const MAX_VOLUME: f32 = 2.0;
+
+/// Slowest and fastest accepted `Core::set_speed`, as multiples of normal speed
+pub const MIN_SPEED: f32 = 0.25;
+pub const MAX_SPEED: f32 = 8.0;
+
+/// Cloning a core gives an independent in-memory snapshot that can be restored by assignment.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Core {
+    bus: MemoryBus,
+    cpu: ProcessingUnit,
+    audio_sampler: AudioSampler,
+
+    #[serde(default)]
+    cycles_elapsed: u64,
+    #[serde(default)]
+    frames_rendered: u64,
+
+    #[serde(default)]
+    deterministic: bool,
+    #[serde(default)]
+    model: Model,
+    #[serde(default)]
+    quirks: Quirks,
+    #[serde(default)]
+    power_on_ram: PowerOnRam,
+    #[serde(default = "JoypadInput::empty")]
+    latched_input: JoypadInput,
+    #[serde(default)]
+    latch_input: bool,
+    // Cycles run with the LCD off since input was last latched
+    #[serde(default)]
+    lcd_off_cycles: u32,
+
+    #[serde(skip)]
+    paused: bool,
+    // Set by `request_pause` and `PauseHandle`s, and taken at the next instruction boundary
+    #[serde(skip)]
+    pause_request: Arc<AtomicBool>,
+    #[serde(skip)]
+    input_queue: VecDeque<(u32, JoypadInput)>,
+    #[serde(skip)]
+    last_frame: Frame,
+    #[serde(skip)]
+    frame_stats: FrameStats,
+    #[serde(skip)]
+    subscribers: Subscribers,
+    #[serde(skip)]
+    breakpoints: BTreeSet<u16>,
+    #[serde(skip)]
+    scanline_callback: Option<ScanlineCallback>,
+    // Frames owed to the next `frames_for_update` at speeds that aren't a whole multiple
+    #[serde(skip)]
+    frame_carry: f32,
+    // Run again when a cartridge is inserted or ejected
+    #[serde(skip)]
+    boot_rom: Option<Vec<u8>>,
+    // Addresses read into `Observation::taps`
+    #[serde(skip)]
+    memory_taps: Vec<u16>,
+    // Until picked up with `take_crash`
+    #[serde(skip)]
+    crash: Option<Box<CrashReport>>,
+}
+
+/// Asks a core on another thread to pause, see `Core::request_pause`.
+#[derive(Clone)]
+pub struct PauseHandle(Arc<AtomicBool>);
+
+impl PauseHandle {
+    pub fn request_pause(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+type ScanlineCallback = Arc<Mutex<dyn FnMut(u8, &[u32; SCREEN_WIDTH]) + Send>>;
+
+/// Passes lines on to both the video sink and the `Core::on_scanline` callback.
+struct ScanlineTee<'a, V: VideoSink> {
+    video: &'a mut V,
+    callback: &'a mut (dyn FnMut(u8, &[u32; SCREEN_WIDTH]) + Send),
+}
+
+impl<V: VideoSink> VideoSink for ScanlineTee<'_, V> {
+    fn push_line(&mut self, y: usize, line: &[u32; SCREEN_WIDTH]) {
+        self.video.push_line(y, line);
+        (self.callback)(y as u8, line);
+    }
+}
+
+enum RomSource {
+    File(String),
+    Data(Rom),
+}
+
+impl RomSource {
+    fn read(self) -> Result<Rom> {
+        match self {
+            RomSource::File(path) => read_rom_file(&path),
+            RomSource::Data(data) => Ok(data),
+        }
+    }
+}
+
+/// Builds a core from a game ROM and an optional boot ROM.
+#[derive(Default)]
+pub struct CoreBuilder {
+    boot_rom: Option<RomSource>,
+    boot_rom_policy: BootRomPolicy,
+    game_rom: Option<RomSource>,
+    deterministic: bool,
+    model: Model,
+    quirks: Option<Quirks>,
+    power_on_ram: PowerOnRam,
+}
+
+impl CoreBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs the boot ROM at `path` instead of starting from the post-boot state. Both DMG and CGB
+    /// boot ROMs are accepted.
+    pub fn boot_rom(mut self, path: impl Into<String>) -> Self {
+        self.boot_rom = Some(RomSource::File(path.into()));
+        self
+    }
+
+    pub fn boot_rom_data(mut self, boot_rom: Vec<u8>) -> Self {
+        self.boot_rom = Some(RomSource::Data(boot_rom.into()));
+        self
+    }
+
+    /// Whether a boot ROM must be run, by default `BootRomPolicy::Optional`: a boot ROM file that
+    /// can't be read is skipped with a warning, so the same code runs on machines without one.
+    pub fn boot_rom_policy(mut self, policy: BootRomPolicy) -> Self {
+        self.boot_rom_policy = policy;
+        self
+    }
+
+    /// Runs the boot ROM bundled with the emulator, see `BUNDLED_DMG_BOOT_ROM`.
+    #[cfg(feature = "bundled-boot-rom")]
+    pub fn bundled_boot_rom(self) -> Self {
+        self.boot_rom_data(crate::dmg::boot_rom::BUNDLED_DMG_BOOT_ROM.to_vec())
+    }
+
+    pub fn game_rom(mut self, path: impl Into<String>) -> Self {
+        self.game_rom = Some(RomSource::File(path.into()));
+        self
+    }
+
+    /// Runs `game_rom`, a `Vec<u8>` or a `Rom` shared with other cores.
+    pub fn game_rom_data(mut self, game_rom: impl Into<Rom>) -> Self {
+        self.game_rom = Some(RomSource::Data(game_rom.into()));
+        self
+    }
+
+    /// Makes identical per-frame inputs always produce identical states, as needed for replays
+    /// and netplay. Emulation has no wall-clock or host sample rate inputs, and audio mixing only
+    /// affects the output samples, so the one remaining source is input changing mid-frame with
+    /// host timing. In deterministic mode input is latched once per frame, at the start of VBlank:
+    /// the keys polled by the first `step`, `step_frame` or `run_cycles` call from then on are
+    /// held until the next VBlank, however often the host calls in between. With the LCD off
+    /// there is no VBlank, so input is latched every `CYCLES_PER_FRAME` cycles instead.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Emulates `model` instead of a DMG. Without a boot ROM this sets the registers the model's boot
+    /// ROM leaves, which games check to detect the console, and a CGB colorizes DMG games like its
+    /// boot ROM does.
+    pub fn model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Emulates a revision of the model with other quirks than its usual one, see `Quirks`.
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = Some(quirks);
+        self
+    }
+
+    /// What WRAM, HRAM and, with a boot ROM, VRAM hold at power on, zeroed by default. Applies
+    /// again on every `Core::reset`.
+    pub fn power_on_ram(mut self, power_on_ram: PowerOnRam) -> Self {
+        self.power_on_ram = power_on_ram;
+        self
+    }
+
+    pub fn build(self) -> Result<Core> {
+        let boot_rom = match (self.boot_rom_policy, self.boot_rom) {
+            (BootRomPolicy::Never, _) => None,
+            (BootRomPolicy::Require, None) => return Err(Error::BootRomRequired),
+            (BootRomPolicy::Require, Some(source)) => Some(source.read()?),
+            (BootRomPolicy::Optional, source) => match source.map(RomSource::read).transpose() {
+                Err(Error::RomLoad { path, source }) => {
+                    warn!("Failed reading boot ROM {}, starting without it: {}", path, source);
+                    None
+                }
+                result => result?,
+            },
+        }
+        .map(|rom| rom.to_vec());
+        let game_rom = self.game_rom.map(RomSource::read).transpose()?;
+
+        let quirks = self.quirks.unwrap_or(self.model.quirks());
+        let mut core = Core::from_roms(boot_rom, game_rom, self.model, quirks)?;
+        core.deterministic = self.deterministic;
+        core.power_on_ram = self.power_on_ram;
+        core.power_on();
+        Ok(core)
+    }
+}
+
+// Mapped rather than read with the `mmap` feature, see `Rom::map`
+fn read_rom_file(filename: &str) -> Result<Rom> {
+    #[cfg(feature = "mmap")]
+    let rom = Rom::map(filename);
+    #[cfg(not(feature = "mmap"))]
+    let rom = std::fs::read(filename).map(Rom::from);
+
+    rom.map_err(|source| rom_load_error(filename, source))
+}
+
+fn rom_load_error(filename: &str, source: io::Error) -> Error {
+    Error::RomLoad { path: filename.to_string(), source }
+}
+
+impl Core {
+    pub fn load(boot_rom: &str, game_rom: Option<String>) -> Result<Core> {
+        Self::load_with_boot_rom(read_rom_file(boot_rom)?.to_vec(), game_rom)
+    }
+
+    pub fn load_with_boot_rom(boot_rom_buffer: Vec<u8>, game_rom: Option<String>) -> Result<Core> {
+        let game_rom_buffer = game_rom.map(|filename| read_rom_file(&filename)).transpose()?;
+        Self::from_roms(Some(boot_rom_buffer), game_rom_buffer, Model::Dmg, Model::Dmg.quirks())
+    }
+
+    pub fn load_without_boot_rom(game_rom: Option<String>) -> Result<Core> {
+        let game_rom_buffer = game_rom.map(|filename| read_rom_file(&filename)).transpose()?;
+        Self::from_roms(None, game_rom_buffer, Model::Dmg, Model::Dmg.quirks())
+    }
+
+    fn from_roms(boot_rom: Option<Vec<u8>>, game_rom: Option<Rom>, model: Model, quirks: Quirks) -> Result<Core> {
+        // Before loading, so that unsupported mappers are explained too
+        for note in game_rom.as_deref().map(compatibility_notes).unwrap_or_default() {
+            warn!(target: "compat", "{}", note);
+        }
+
+        let cgb_mode = game_rom.as_deref().is_some_and(|rom| model.cgb_mode(rom));
+        let mut cpu = ProcessingUnit::new();
+        let mut bus = match boot_rom.clone() {
+            Some(boot_rom) => MemoryBus::new(Some(boot_rom), game_rom)?,
+            None => {
+                cpu.skip_boot_rom(model, quirks, cgb_mode);
+                MemoryBus::new_without_boot_rom(game_rom)?
+            }
+        };
+        bus.set_cgb_mode(cgb_mode);
+        bus.set_cgb_hardware(model == Model::Cgb);
+        bus.input.set_sgb(model == Model::Sgb);
+        bus.ppu.set_stat_write_bug(quirks.stat_write_bug);
+
+        Ok(Self {
+            cpu,
+            bus,
+            audio_sampler: AudioSampler::default(),
+            cycles_elapsed: 0,
+            frames_rendered: 0,
+            deterministic: false,
+            model,
+            quirks,
+            power_on_ram: PowerOnRam::default(),
+            latched_input: JoypadInput::empty(),
+            latch_input: true,
+            lcd_off_cycles: 0,
+            paused: false,
+            pause_request: Arc::default(),
+            input_queue: VecDeque::new(),
+            last_frame: Frame::default(),
+            frame_stats: FrameStats::default(),
+            subscribers: Subscribers::default(),
+            breakpoints: BTreeSet::new(),
+            scanline_callback: None,
+            frame_carry: 0.0,
+            boot_rom,
+            memory_taps: vec![],
+            crash: None,
+        })
+    }
+
+    // What the builder sets up on top of `from_roms`
+    fn power_on(&mut self) {
+        self.bus.fill_power_on_ram(self.power_on_ram);
+        if self.model == Model::Cgb && !self.bus.cgb_mode() {
+            self.set_colorization(Some(self.boot_rom_colorization()));
+        }
+    }
+
+    /// Restarts the game from the post-boot state, like power cycling with the same cartridge
+    /// inserted. Cartridge RAM is kept.
+    pub fn reset(&mut self) {
+        let volume = self.volume();
+        let colorization = self.colorization();
+        self.bus.reset();
+        self.bus.fill_power_on_ram(self.power_on_ram);
+        self.set_volume(volume);
+        self.set_colorization(colorization);
+        self.bus.input.set_sgb(self.model == Model::Sgb);
+        self.bus.ppu.set_stat_write_bug(self.quirks.stat_write_bug);
+        let symbols = self.cpu.symbols().cloned();
+        self.cpu = ProcessingUnit::new();
+        self.cpu.set_symbols(symbols);
+        self.cpu.skip_boot_rom(self.model, self.quirks, self.bus.cgb_mode());
+        self.audio_sampler.reset();
+        self.cycles_elapsed = 0;
+        self.frames_rendered = 0;
+        self.latch_input = true;
+        self.lcd_off_cycles = 0;
+        self.input_queue.clear();
+        self.crash = None;
+    }
+
+    /// Prepares for gameboy-doctor: starts from the state the DMG boot ROM leaves, reads LY as 0x90 and
+    /// prints the CPU state before each instruction to stdout.
+    pub fn initialize_gameboy_doctor(&mut self) {
+        self.initialize_gameboy_doctor_with_log(io::stdout());
+    }
+
+    /// Like `initialize_gameboy_doctor`, but writes the log to `log`, e.g. a file or a checker
+    /// comparing it against a known good log.
+    pub fn initialize_gameboy_doctor_with_log(&mut self, log: impl Write + Send + 'static) {
+        self.cpu.initialize_gameboy_doctor(DoctorLog::new(log));
+        self.bus.ppu.initialize_gameboy_doctor();
+    }
+
+    /// Holds `keys` for the next `frames` rendered frames, overriding the keys passed to step.
+    /// Queued inputs are applied back to back in the order they were queued.
+    pub fn queue_input(&mut self, frames: u32, keys: JoypadInput) {
+        if frames > 0 {
+            self.input_queue.push_back((frames, keys));
+        }
+    }
+
+    pub fn has_queued_input(&self) -> bool {
+        !self.input_queue.is_empty()
+    }
+
+    fn advance_input_queue(&mut self) {
+        if let Some((frames, _)) = self.input_queue.front_mut() {
+            *frames -= 1;
+            if *frames == 0 {
+                self.input_queue.pop_front();
+            }
+        }
+    }
+
+    /// Sets the output volume, 0.0 being silent and 1.0 the default level. Values up to 2.0 are
+    /// accepted; anything louder would clip. The volume is a host setting, so it isn't saved in
+    /// states: cores loaded from a state start at the default level.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.bus.apu.set_volume(volume.clamp(0.0, MAX_VOLUME));
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.bus.apu.volume()
+    }
+
+    /// Filters out the DC offset of the output, so channels and DACs switching on and off, like
+    /// games playing samples on channel 3 do, click less harshly. Off by default, leaving the raw
+    /// emulated output. A host setting like the volume.
+    pub fn set_pop_filter(&mut self, enabled: bool) {
+        self.audio_sampler.set_pop_filter(enabled);
+    }
+
+    pub fn pop_filter(&self) -> bool {
+        self.audio_sampler.pop_filter()
+    }
+
+    /// Picks how audio is resampled to the host's rate: `Resampler::Sinc` sounds cleanest, most
+    /// of all on high square waves, but costs CPU that low-power devices may not have to spare.
+    /// `Resampler::Nearest` by default. A host setting like the volume.
+    pub fn set_resampler(&mut self, resampler: Resampler) {
+        self.audio_sampler.set_resampler(resampler);
+    }
+
+    pub fn resampler(&self) -> Resampler {
+        self.audio_sampler.resampler()
+    }
+
+    /// Runs at `speed` times normal speed, clamped to `MIN_SPEED`..=`MAX_SPEED`. Audio is
+    /// resampled to match, so it keeps the host's sample rate, and `run_at_speed` runs as many
+    /// frames as are due per host frame. Pace those calls with `FramePacer::wait` at this speed.
+    /// A host setting like the volume.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.audio_sampler.set_speed(speed.clamp(MIN_SPEED, MAX_SPEED));
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.audio_sampler.speed()
+    }
+
+    /// Draws with CGB colors, as a CGB does for DMG games, or in DMG green when `None`. See
+    /// `dmg::colorization` for the combinations the CGB boot ROM offers. Takes effect from the
+    /// next line drawn, and while paused the frame returned by `run_frame` is redrawn with it.
+    pub fn set_colorization(&mut self, colorization: Option<Colorization>) {
+        self.bus.ppu.colorization = colorization;
+        self.redraw_if_paused();
+    }
+
+    /// Draws with the colors from a JASC-PAL palette file, see `Colorization::parse`. Load it again
+    /// after editing it to see the changes in the running game.
+    pub fn load_palette(&mut self, path: &str) -> Result<()> {
+        self.set_colorization(Some(Colorization::load(path)?));
+        Ok(())
+    }
+
+    pub fn colorization(&self) -> Option<Colorization> {
+        self.bus.ppu.colorization
+    }
+
+    /// The colorization a CGB boot ROM would pick for the loaded game.
+    pub fn boot_rom_colorization(&self) -> Colorization {
+        colorization_for_rom(self.bus.rom())
+    }
+
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Swaps the boot ROM without restarting the game, for comparing boot ROMs: it runs on the next
+    /// power cycle, e.g. `insert_cartridge`, and while it's running now the rest of it comes from the
+    /// new one. `None` skips it from the next power cycle on. Fails, leaving the boot ROM as it was,
+    /// unless it's the size of a DMG or CGB boot ROM.
+    pub fn set_boot_rom(&mut self, boot_rom: Option<Vec<u8>>) -> Result<()> {
+        if let Some(boot_rom) = &boot_rom {
+            if boot_rom.len() != DMG_BOOT_ROM_SIZE && boot_rom.len() != CGB_BOOT_ROM_SIZE {
+                return Err(Error::InvalidBootRom(boot_rom.len()));
+            }
+            self.bus.set_boot_rom(boot_rom.clone());
+        }
+        self.boot_rom = boot_rom;
+        Ok(())
+    }
+
+    /// Swaps in the boot ROM in the file at `path`, see `set_boot_rom`.
+    pub fn load_boot_rom(&mut self, path: &str) -> Result<()> {
+        self.set_boot_rom(Some(read_rom_file(path)?.to_vec()))
+    }
+
+    /// The boot ROM run on power cycles, `None` when starting from the post-boot state.
+    pub fn boot_rom(&self) -> Option<&[u8]> {
+        self.boot_rom.as_deref()
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// What RAM held at power on, with the seed when it was random.
+    pub fn power_on_ram(&self) -> PowerOnRam {
+        self.power_on_ram
+    }
+
+    /// Holds `keys` on joypad `player` of an SGB multitap, counting from 0. The first player's keys
+    /// are the ones passed to `step` and friends, so only players 1 to 3 are taken here. The keys
+    /// are held until changed.
+    pub fn set_player_input(&mut self, player: usize, keys: JoypadInput) {
+        self.bus.input.update_player(player, keys);
+    }
+
+    /// How many joypads the game reads: 1, or 2 or 4 once an SGB game enables multiplayer.
+    pub fn players(&self) -> usize {
+        self.bus.input.players()
+    }
+
+    /// Points the CGB infrared port at `transceiver`, e.g. `IrLoopback` or one end of an `IrLink`.
+    /// The port is only there for CGB games on a CGB, see `CoreBuilder::model`. The transceiver is
+    /// kept across resets and state loads.
+    pub fn connect_infrared(&mut self, transceiver: impl IrTransceiver + 'static) {
+        self.bus.infrared.connect(Some(Arc::new(transceiver)));
+    }
+
+    pub fn disconnect_infrared(&mut self) {
+        self.bus.infrared.connect(None);
+    }
+
+    /// Plugs `device` into the link port, e.g. a `BarcodeBoy`. The device is kept across resets and
+    /// state loads. Bytes sent are still collected for `take_serial_output`.
+    pub fn connect_serial(&mut self, device: impl SerialDevice + 'static) {
+        self.bus.serial.connect(Some(Arc::new(device)));
+    }
+
+    pub fn disconnect_serial(&mut self) {
+        self.bus.serial.connect(None);
+    }
+
+    /// Calls `subscriber` with each `Event` from now on, for autosaving, recording or debugging
+    /// without polling the core every frame. Subscribers are called on the emulation thread while
+    /// the core is stepped, and are kept across resets and state loads.
+    pub fn subscribe(&mut self, subscriber: impl FnMut(&Event) + Send + 'static) -> SubscriptionId {
+        self.subscribers.add(subscriber)
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) {
+        self.subscribers.remove(id);
+    }
+
+    /// Calls `callback` with each line as soon as it's drawn, whatever video sink the core is
+    /// stepped with, for frontends driving LCD panels or applying per-line effects. Lines aren't
+    /// drawn while frames are skipped. Replaces the previous callback, and is kept across resets
+    /// and state loads.
+    pub fn on_scanline(&mut self, callback: impl FnMut(u8, &[u32; SCREEN_WIDTH]) + Send + 'static) {
+        self.scanline_callback = Some(Arc::new(Mutex::new(callback)));
+    }
+
+    pub fn clear_scanline_callback(&mut self) {
+        self.scanline_callback = None;
+    }
+
+    /// Sends `Event::BreakpointHit` whenever the CPU is about to run the instruction at `addr`.
+    /// Emulation carries on, so a debugger wanting to stop there pauses the core from its frame
+    /// loop. Breakpoints are kept across resets and state loads.
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.remove(&addr);
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// While paused, stepping does nothing and `run_frame` returns the last frame without audio.
+    /// Unpausing drops pause requests made before it.
+    pub fn set_paused(&mut self, paused: bool) {
+        if paused && !self.paused {
+            self.pause();
+        } else if !paused {
+            self.pause_request.store(false, Ordering::Relaxed);
+            self.paused = false;
+        }
+    }
+
+    /// Whether paused by `set_paused` or a pause request that has taken effect.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pauses at the next instruction boundary: right away between calls, or after the current
+    /// instruction when stepping, in the middle of `step_frame` or `run_cycles` too, which then
+    /// return early. Unlike `set_paused` from outside the step loop, this stops at a well-defined
+    /// point, reported by `pause_point` and `Event::Paused`, for debugger UIs. Use a `pause_handle`
+    /// to ask from another thread.
+    pub fn request_pause(&self) {
+        self.pause_request.store(true, Ordering::Relaxed);
+    }
+
+    /// A handle for `request_pause` from other threads, e.g. a debugger UI's. It keeps working
+    /// across resets, state loads and cartridge swaps.
+    pub fn pause_handle(&self) -> PauseHandle {
+        PauseHandle(self.pause_request.clone())
+    }
+
+    /// Where the core is paused: the instruction it runs next and the cycle count. `None` while
+    /// running. Single-stepping with `step_instruction` moves it along.
+    pub fn pause_point(&self) -> Option<PausePoint> {
+        self.paused.then(|| PausePoint { pc: self.cpu.registers().pc, cycle: self.cycles_elapsed })
+    }
+
+    // Pauses if asked to, returning whether paused
+    fn pause_if_requested(&mut self) -> bool {
+        if self.pause_request.swap(false, Ordering::Relaxed) && !self.paused {
+            self.pause();
+        }
+        self.paused
+    }
+
+    fn pause(&mut self) {
+        self.paused = true;
+        if let Some(point) = self.pause_point() {
+            self.subscribers.emit(Event::Paused(point));
+        }
+    }
+
+    pub fn step(&mut self, video: &mut impl VideoSink, audio: &mut impl AudioSink, input: &mut impl InputSource) -> bool {
+        if self.pause_if_requested() {
+            return false;
+        }
+
+        self.step_instruction(video, audio, input)
+    }
+
+    /// Runs one instruction like `step`, but also while paused, for single-stepping in a debugger.
+    pub fn step_instruction(&mut self, video: &mut impl VideoSink, audio: &mut impl AudioSink, input: &mut impl InputSource) -> bool {
+        let keys_pressed = input.poll();
+        self.step_cycles(video, audio, keys_pressed).0
+    }
+
+    /// Whether the boot ROM is still running, before it hands over to the game. Frontends waiting
+    /// for the game to start can also subscribe to `Event::BootRomFinished`.
+    pub fn is_running_boot_rom(&self) -> bool {
+        self.bus.boot_rom_mapped()
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// While set, frames are emulated without drawing them, for fast-forwarding. The video sink
+    /// still gets `frame_ready` calls but no lines.
+    pub fn set_frame_skip(&mut self, skip: bool) {
+        self.bus.ppu.skip_render = skip;
+    }
+
+    /// Clock cycles emulated since power on.
+    pub fn cycles_elapsed(&self) -> u64 {
+        self.cycles_elapsed
+    }
+
+    /// Time emulated since power on, going by `cycles_elapsed`. Anything that depends on how
+    /// much time has passed in the game, rather than on the host, should use this.
+    pub fn emulated_time(&self) -> Duration {
+        cycles_to_duration(self.cycles_elapsed)
+    }
+
+    /// Frames rendered since power on. Frames skipped while the LCD is off are not counted.
+    pub fn frames_rendered(&self) -> u64 {
+        self.frames_rendered
+    }
+
+    /// Runs until the next frame has been rendered into `video`, holding the keys polled from
+    /// `input` for the whole frame. With the LCD off there is no frame to wait for, so this returns
+    /// once a frame's worth of cycles has passed, unless the game turns the LCD back on by then.
+    /// A pause request stops it after the current instruction, in the middle of the frame.
+    pub fn step_frame(&mut self, video: &mut impl VideoSink, audio: &mut impl AudioSink, input: &mut impl InputSource) {
+        if self.pause_if_requested() {
+            return;
+        }
+
+        let keys_pressed = input.poll();
+        let mut cycles = 0;
+
+        loop {
+            let (should_render, elapsed) = self.step_cycles(video, audio, keys_pressed);
+            cycles += elapsed;
+            if should_render || (cycles >= CYCLES_PER_FRAME && !self.bus.ppu.lcd_enabled()) || self.pause_if_requested() {
+                break;
+            }
+        }
+    }
+
+    /// Runs at least `cycles` clock cycles with the keys polled from `input` held, and returns the
+    /// number actually run. This overshoots by the remainder of the last instruction or HALT, and
+    /// runs nothing while paused. A pause request stops it early, after the current instruction.
+    pub fn run_cycles(&mut self, cycles: u64, video: &mut impl VideoSink, audio: &mut impl AudioSink, input: &mut impl InputSource) -> u64 {
+        if self.pause_if_requested() {
+            return 0;
+        }
+
+        let keys_pressed = input.poll();
+        let mut elapsed = 0;
+
+        while elapsed < cycles {
+            elapsed += self.step_cycles(video, audio, keys_pressed).1 as u64;
+            if self.pause_if_requested() {
+                break;
+            }
+        }
+
+        elapsed
+    }
+
+    /// Runs for `duration` of emulated time, see `run_cycles`.
+    pub fn run_duration(&mut self, duration: Duration, video: &mut impl VideoSink, audio: &mut impl AudioSink, input: &mut impl InputSource) -> u64 {
+        let cycles = duration.as_nanos() * CLOCK_SPEED as u128 / 1_000_000_000;
+        self.run_cycles(cycles as u64, video, audio, input)
+    }
+
+    /// Runs one frame without a window or audio device, stopping at the same frame boundary as
+    /// `step_frame`, which makes it the call to step movies, recordings and agents with. The
+    /// returned frame holds the rendered pixels and the audio samples generated during it, and is
+    /// overwritten by the next call.
+    pub fn run_frame(&mut self, mut keys_pressed: JoypadInput) -> &Frame {
+        let mut audio = mem::take(&mut self.last_frame.audio);
+
+        audio.clear();
+        self.step_frame(&mut NullVideoSink, &mut audio, &mut keys_pressed);
+        self.last_frame.audio = audio;
+        *self.last_frame.pixels = *self.frame();
+
+        &self.last_frame
+    }
+
+    /// Runs a frame like `run_frame`, but returns the picture from `frames` frames later, predicted
+    /// with `predict_frame`, while the emulation stays at the real frame. Games take a frame or
+    /// more to show a key press, which running ahead hides at the cost of emulating `frames + 1`
+    /// frames per frame; one or two is usually enough. The audio is the real frame's. Only a
+    /// deterministic core, see `CoreBuilder::deterministic`, runs ahead: elsewhere input isn't
+    /// latched per frame, so the frames run ahead wouldn't see the keys as the real ones do, and
+    /// this is `run_frame`.
+    pub fn run_frame_ahead(&mut self, keys_pressed: JoypadInput, frames: u32) -> &Frame {
+        self.run_frame(keys_pressed);
+        if self.deterministic && frames > 0 && !self.paused {
+            self.last_frame.pixels = self.predict_frame(keys_pressed, frames);
+        }
+        &self.last_frame
+    }
+
+    /// The picture `frames` frames from now if `keys_pressed` stay held, after which the core is
+    /// rolled back to where it was. Subscribers, the scanline callback and breakpoints don't see
+    /// the frames run ahead, and devices on the link port and infrared are left out of them. Pause
+    /// requests wait for the real frames.
+    pub fn predict_frame(&mut self, mut keys_pressed: JoypadInput, frames: u32) -> Box<FrameBuffer> {
+        // Detached first, so the snapshot doesn't carry them
+        let subscribers = mem::take(&mut self.subscribers);
+        let scanline_callback = self.scanline_callback.take();
+        let breakpoints = mem::take(&mut self.breakpoints);
+        let pause_request = mem::take(&mut self.pause_request);
+        let serial_device = self.bus.serial.device();
+        let ir_transceiver = self.bus.infrared.transceiver();
+        self.bus.serial.connect(None);
+        self.bus.infrared.connect(None);
+
+        let snapshot = self.clone();
+        for i in 0..frames {
+            self.set_frame_skip(i + 1 < frames);
+            self.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut keys_pressed);
+        }
+        let pixels = Box::new(*self.frame());
+        *self = snapshot;
+
+        self.subscribers = subscribers;
+        self.scanline_callback = scanline_callback;
+        self.breakpoints = breakpoints;
+        self.pause_request = pause_request;
+        self.bus.serial.connect(serial_device);
+        self.bus.infrared.connect(ir_transceiver);
+        pixels
+    }
+
+    /// Frames on demand: each item runs one frame like `run_frame`, polling `input` once for its
+    /// keys, and is a copy the consumer keeps. The stream doesn't end by itself, so bound it with
+    /// e.g. `take`. A paused core yields its last frame again, without audio.
+    pub fn frames<'a>(&'a mut self, mut input: impl InputSource + 'a) -> impl Iterator<Item = Frame> + 'a {
+        iter::from_fn(move || Some(self.run_frame(input.poll()).clone()))
+    }
+
+    /// How many frames to run for the next host frame at the current speed, the rest of a
+    /// fraction carried over: 2 and 3 in turn at 2.5x. At most one below normal speed, where
+    /// pacing by the emulated time stretches out the frames instead.
+    pub fn frames_for_update(&mut self) -> u32 {
+        self.frame_carry += self.speed().max(1.0);
+        let frames = self.frame_carry as u32;
+        self.frame_carry -= frames as f32;
+        frames
+    }
+
+    /// Runs the frames due for a host frame at the current speed, see `frames_for_update`. Only
+    /// the last of them is drawn into `video`, the others are skipped. Returns the number of
+    /// frames run.
+    pub fn run_at_speed(&mut self, video: &mut impl VideoSink, audio: &mut impl AudioSink, input: &mut impl InputSource) -> u32 {
+        let frames = self.frames_for_update();
+        for i in 0..frames {
+            self.set_frame_skip(i + 1 < frames);
+            self.step_frame(video, audio, input);
+        }
+        frames
+    }
+
+    /// Runs frame after frame, reporting each one to `callbacks`, until `on_frame` returns false.
+    pub fn run(&mut self, callbacks: &mut impl EmuCallbacks) {
+        loop {
+            let keys_pressed = callbacks.input();
+            let frame = self.run_frame(keys_pressed);
+
+            callbacks.on_audio(&frame.audio);
+            for byte in self.bus.take_serial_output() {
+                callbacks.on_serial(byte);
+            }
+            if !callbacks.on_frame(&self.last_frame.pixels[..]) {
+                break;
+            }
+        }
+    }
+
+    fn step_cycles(&mut self, video: &mut impl VideoSink, audio: &mut impl AudioSink, keys_pressed: JoypadInput) -> (bool, u32) {
+        let keys_pressed = self.input_queue.front().map_or(keys_pressed, |&(_, keys)| keys);
+        // Deterministic input only changes at the latching points, see `CoreBuilder::deterministic`
+        if !self.deterministic || self.latch_input {
+            self.latched_input = keys_pressed;
+            self.latch_input = false;
+        }
+        self.bus.input.update(self.latched_input);
+        let lcd_enabled = self.bus.ppu.lcd_enabled();
+        let boot_rom_mapped = self.bus.boot_rom_mapped();
+        let diagnosed_pc = self.bus.diagnostics.is_some().then(|| {
+            let pc = self.cpu.registers().pc;
+            (pc, self.bus.bank_of(pc))
+        });
+        if self.bus.developer_checks.is_some() {
+            let registers = self.cpu.registers();
+            let opcode = self.bus.read_byte(registers.pc);
+            if let Some(checks) = self.bus.developer_checks.as_mut().filter(|_| !registers.halted) {
+                checks.get_mut().start_instruction(&registers, opcode, &self.bus.ppu);
+            }
+        }
+        let elapsed = self.cpu.next(&mut self.bus);
+        if let Some(reason) = self.bus.take_crash() {
+            self.crash = Some(Box::new(self.crash_report(reason)));
+        }
+        if let Some((pc, bank)) = diagnosed_pc {
+            let bank_after = self.bus.bank_of(pc);
+            if let Some(diagnostics) = &mut self.bus.diagnostics {
+                diagnostics.finish_instruction(pc, bank, bank_after);
+            }
+        }
+        self.cycles_elapsed += elapsed as u64;
+        let serial_sent = self.bus.take_serial_sent();
+
+        let should_render = match &self.scanline_callback {
+            Some(callback) => self.bus.ppu.next(elapsed, &mut ScanlineTee { video, callback: &mut *callback.lock().unwrap() }),
+            None => self.bus.ppu.next(elapsed, video),
+        };
+        if self.bus.serial.step(elapsed) {
+            self.bus.ppu.interrupt_flag.insert(InterruptFlag::SERIAL);
+        }
+
+        for _ in 0..elapsed {
+            self.bus.apu.tick();
+            self.audio_sampler.tick(&self.bus.apu, audio);
+        }
+
+        if lcd_enabled {
+            self.lcd_off_cycles = 0;
+        } else {
+            self.lcd_off_cycles += elapsed;
+            if self.lcd_off_cycles >= CYCLES_PER_FRAME {
+                self.lcd_off_cycles -= CYCLES_PER_FRAME;
+                self.latch_input = true;
+            }
+        }
+
+        if should_render {
+            self.frames_rendered += 1;
+            self.frame_stats = mem::take(&mut self.bus.stats);
+            self.latch_input = true;
+            video.frame_ready();
+            self.advance_input_queue();
+        }
+
+        if !self.subscribers.is_empty() {
+            let boot_rom_finished = boot_rom_mapped && !self.bus.boot_rom_mapped();
+            self.emit_events(lcd_enabled, serial_sent, should_render, boot_rom_finished);
+        }
+
+        (should_render, elapsed)
+    }
+
+    fn emit_events(&mut self, lcd_was_enabled: bool, serial_sent: Option<u8>, frame_ready: bool, boot_rom_finished: bool) {
+        if boot_rom_finished {
+            self.subscribers.emit(Event::BootRomFinished);
+        }
+        let lcd_enabled = self.bus.ppu.lcd_enabled();
+        if lcd_enabled != lcd_was_enabled {
+            self.subscribers.emit(Event::LcdToggled(lcd_enabled));
+        }
+        if let Some(byte) = serial_sent {
+            self.subscribers.emit(Event::SerialByte(byte));
+        }
+        let mbc = self.bus.mbc();
+        self.subscribers.update_battery_ram_dirty(mbc.ram_written() && mbc.has_battery());
+        if frame_ready {
+            self.subscribers.emit(Event::FrameReady);
+        }
+        // Not again for every step spent halted on one
+        let registers = self.cpu.registers();
+        if !registers.halted && self.breakpoints.contains(&registers.pc) {
+            self.subscribers.emit(Event::BreakpointHit(registers.pc));
+        }
+    }
+
+    /// The loaded game's cartridge header, with its title and CGB flag.
+    pub fn cartridge_header(&self) -> CartridgeHeader {
+        CartridgeHeader::from_rom(self.bus.rom())
+    }
+
+    pub fn has_cartridge(&self) -> bool {
+        self.bus.mbc().is_inserted()
+    }
+
+    /// Power cycles with `rom` in the cartridge slot, replacing any cartridge already there, and runs
+    /// the boot ROM again if the core was built with one. Settings like the volume and speed are
+    /// kept, as are debugger breakpoints, but the RAM of the old cartridge is lost, so flush its
+    /// battery save first.
+    pub fn insert_cartridge(&mut self, rom: impl Into<Rom>) -> Result<()> {
+        self.power_cycle(Some(rom.into()))
+    }
+
+    /// Power cycles with an empty cartridge slot. Like on hardware the boot ROM then hangs at the
+    /// logo, which reads as 0xFF, and without a boot ROM the CPU runs into `RST 38` from 0x100.
+    pub fn eject_cartridge(&mut self) {
+        // Only a new cartridge's ROM can fail to load
+        self.power_cycle(None).expect("failed powering on without a cartridge");
+    }
+
+    fn power_cycle(&mut self, game_rom: Option<Rom>) -> Result<()> {
+        let mut core = Core::from_roms(self.boot_rom.clone(), game_rom, self.model, self.quirks)?;
+        core.deterministic = self.deterministic;
+        core.power_on_ram = self.power_on_ram;
+        core.power_on();
+        if self.bus.coverage.is_some() {
+            core.enable_coverage();
+        }
+        if self.bus.diagnostics.is_some() {
+            core.enable_execution_diagnostics();
+        }
+        if self.bus.developer_checks.is_some() {
+            core.enable_developer_mode();
+        }
+        self.replace_with(core);
+        Ok(())
+    }
+
+    #[deprecated(note = "use `cartridge_header().title()`, which handles CGB flags and manufacturer codes")]
+    pub fn read_rom_name(&self) -> String {
+        self.cartridge_header().title()
+    }
+
+    /// Writes a save state in the versioned format used by the state files.
+    pub fn save_state_to(&self, mut writer: impl Write) -> Result<()> {
+        let data = encode_state(self).map_err(Error::StateSave)?;
+        writer.write_all(&data).and_then(|()| writer.flush()).map_err(|e| Error::StateSave(e.into()))
+    }
+
+    /// Replaces the emulation state with a save state made with the same ROM. A state that fails
+    /// its checksum or doesn't decode leaves the core as it was.
+    pub fn load_state_from(&mut self, mut reader: impl Read) -> Result<()> {
+        let mut data = vec![];
+        reader.read_to_end(&mut data).map_err(StateError::Io)?;
+        self.restore_from(decode_state(&data, Some(self.rom_hash()))?);
+        Ok(())
+    }
+
+    /// Continues from `core`, e.g. a save state or rewind snapshot read elsewhere, keeping the
+    /// symbols, coverage, diagnostics and other host settings of this one.
+    pub fn restore_from(&mut self, mut core: Core) {
+        core.cpu.set_symbols(self.cpu.symbols().cloned());
+        core.bus.coverage = self.bus.coverage.take();
+        core.bus.diagnostics = self.bus.diagnostics.take();
+        core.bus.diagnostic_queue = mem::take(&mut self.bus.diagnostic_queue);
+        core.bus.developer_checks = self.bus.developer_checks.take();
+        self.replace_with(core);
+    }
+
+    // Replaces the emulation with `core`, keeping the host's settings and connections
+    fn replace_with(&mut self, mut core: Core) {
+        core.set_volume(self.volume());
+        core.set_pop_filter(self.pop_filter());
+        core.set_resampler(self.resampler());
+        core.set_speed(self.speed());
+        core.bus.infrared.connect(self.bus.infrared.transceiver());
+        core.bus.serial.connect(self.bus.serial.device());
+        core.subscribers = mem::take(&mut self.subscribers);
+        core.breakpoints = mem::take(&mut self.breakpoints);
+        core.scanline_callback = self.scanline_callback.take();
+        core.boot_rom = self.boot_rom.take();
+        core.memory_taps = mem::take(&mut self.memory_taps);
+        core.pause_request = self.pause_request.clone();
+        *self = core;
+    }
+
+    pub fn read_byte(&self, addr: u16) -> u8 {
+        self.bus.read_byte(addr)
+    }
+
+    pub fn write_byte(&mut self, addr: u16, value: u8) {
+        self.bus.write_byte(addr, value);
+    }
+
+    /// Cartridge RAM that the game expects to survive power off, to be written to a `.sav` file.
+    /// `None` for cartridges without a battery.
+    pub fn battery_ram(&self) -> Option<&[u8]> {
+        let mbc = self.bus.mbc();
+        (mbc.has_battery() && !mbc.ram().is_empty()).then(|| mbc.ram())
+    }
+
+    /// Restores cartridge RAM saved from `battery_ram` by an earlier run. Extra bytes are ignored
+    /// and missing ones left as they are, so files from other emulators with trailers still load.
+    pub fn load_battery_ram(&mut self, data: &[u8]) {
+        let ram = self.bus.mbc_mut().ram_mut();
+        let len = ram.len().min(data.len());
+        ram[..len].copy_from_slice(&data[..len]);
+    }
+
+    /// Returns whether battery-backed RAM changed since the last call.
+    pub fn take_battery_ram_written(&mut self) -> bool {
+        self.subscribers.update_battery_ram_dirty(false);
+        self.bus.mbc_mut().take_ram_written() && self.bus.mbc().has_battery()
+    }
+
+    pub fn registers(&self) -> Registers {
+        self.cpu.registers()
+    }
+
+    /// Interrupts, HALT time, DMA transfers and LCD toggles during the last completed frame, for
+    /// finding out why a game runs slow or misbehaves.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    /// Starts tracking which ROM bytes are executed, see `coverage`. Tracking carries on across
+    /// resets and state loads.
+    pub fn enable_coverage(&mut self) {
+        if self.bus.coverage.is_none() {
+            self.bus.coverage = Some(Coverage::new(self.bus.rom().len()));
+        }
+    }
+
+    /// The ROM bytes executed since `enable_coverage`, `None` when not tracking.
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.bus.coverage.as_ref()
+    }
+
+    /// Starts watching for code running from cartridge RAM, code modifying itself and code switching
+    /// the bank it runs from, see `ExecutionWarning`. Each is logged as a warning with the target
+    /// `cpu` the first time it happens at an address, to give context to bug reports. Watching
+    /// carries on across resets and state loads.
+    pub fn enable_execution_diagnostics(&mut self) {
+        if self.bus.diagnostics.is_none() {
+            self.bus.diagnostics = Some(ExecutionDiagnostics::default());
+        }
+    }
+
+    /// The warnings since the last call, oldest first. Empty when not watching.
+    pub fn take_execution_warnings(&mut self) -> Vec<ExecutionWarning> {
+        self.bus.diagnostics.as_mut().map(ExecutionDiagnostics::take_warnings).unwrap_or_default()
+    }
+
+    /// Starts watching for mistakes in how the game uses the LCD that the emulator lets pass but
+    /// hardware doesn't, see `DeveloperWarning`: writing VRAM or OAM while the PPU uses them,
+    /// turning the LCD off outside VBlank, reading LY while the LCD is off and triggering the DMG's
+    /// OAM bug. Meant for homebrew developers checking their games. Each is logged as a warning
+    /// with the target `developer` the first time it happens at an address. Watching carries on
+    /// across resets and state loads.
+    pub fn enable_developer_mode(&mut self) {
+        if self.bus.developer_checks.is_none() {
+            let dmg_cpu = !self.bus.cgb_hardware();
+            self.bus.developer_checks = Some(RefCell::new(DeveloperChecks::new(dmg_cpu)));
+        }
+    }
+
+    /// The developer warnings since the last call, oldest first. Empty when not watching.
+    pub fn take_developer_warnings(&mut self) -> Vec<DeveloperWarning> {
+        self.bus.developer_checks.as_mut().map(|checks| checks.get_mut().take_warnings()).unwrap_or_default()
+    }
+
+    /// The issues the emulator ran into since the last call, oldest first: reads and writes of IO
+    /// addresses nothing answers at, mapper writes that aren't emulated and sound register writes
+    /// while the APU is off. None of them stop emulation, but they point at why a game misbehaves,
+    /// so frontends can show them as warnings. Each is reported once per address and logged with
+    /// the target `diagnostics`.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        self.bus.diagnostic_queue.get_mut().take()
+    }
+
+    /// Shows the labels from an RGBDS `.sym` file in the disassembly and in the instruction trace
+    /// printed when emulation panics.
+    pub fn set_symbols(&mut self, symbols: Option<Symbols>) {
+        self.cpu.set_symbols(symbols.map(Arc::new));
+    }
+
+    pub fn load_symbols(&mut self, path: &str) -> Result<()> {
+        self.set_symbols(Some(Symbols::load(path)?));
+        Ok(())
+    }
+
+    pub fn symbols(&self) -> Option<&Symbols> {
+        self.cpu.symbols().map(Arc::as_ref)
+    }
+
+    /// The last `TRACE_LENGTH` executed instructions, oldest first. The same trace is printed to
+    /// stderr when emulation panics, and is part of `CrashReport`s.
+    pub fn instruction_trace(&self) -> Vec<TraceEntry> {
+        self.cpu.trace()
+    }
+
+    /// A report of what happened, the first time it's called after the emulated CPU crashed, e.g.
+    /// by running an illegal opcode. The CPU stays locked up like the hardware's, with the rest of
+    /// the Game Boy still running, until the core is reset, so frontends can show the last frame
+    /// along with the report.
+    pub fn take_crash(&mut self) -> Option<CrashReport> {
+        self.crash.take().map(|report| *report)
+    }
+
+    // The instruction that just ran, which is the last one traced
+    fn crash_report(&self, reason: CrashReason) -> CrashReport {
+        let trace = self.cpu.trace();
+        let last = trace.last().copied();
+        CrashReport {
+            reason,
+            registers: last.map_or_else(|| self.cpu.registers(), |entry| entry.registers),
+            opcode: last.map_or(0, |entry| entry.opcode),
+            rom_bank: self.bus.bank_of(0x4000),
+            wram_bank: self.bus.bank_of(0xd000),
+            trace,
+            rom_hash: self.rom_hash(),
+        }
+    }
+
+    /// Disassembles `count` instructions starting at `addr`.
+    pub fn disassemble(&self, addr: u16, count: usize) -> Vec<Instruction> {
+        let mut instructions = Vec::with_capacity(count);
+        let mut addr = addr;
+
+        for _ in 0..count {
+            let (text, len) = disassemble(&self.bus, addr, self.symbols());
+            instructions.push(Instruction { addr, len, text });
+            addr = addr.wrapping_add(len);
+        }
+
+        instructions
+    }
+
+    /// `len` bytes of memory from `addr` on, formatted by `format_hexdump`. Reading has no side
+    /// effects, so any region can be dumped while the game runs. Wraps around at 0xFFFF.
+    pub fn hexdump(&self, addr: u16, len: usize) -> String {
+        let bytes: Vec<u8> = (0..len.min(0x10000)).map(|i| self.bus.read_byte(addr.wrapping_add(i as u16))).collect();
+        format_hexdump(addr, &bytes)
+    }
+
+    /// The whole of `region`, all banks included.
+    pub fn memory(&self, region: MemoryRegion) -> &[u8] {
+        self.bus.region(region)
+    }
+
+    /// Memory in a format other tools open, see `Export`, for writing to a file named
+    /// `Export::file_name`. Only the WRAM and VRAM banks there are in the current mode are included.
+    pub fn export(&self, export: Export) -> Vec<u8> {
+        let (wram_size, vram_banks) = if self.bus.cgb_mode() { (0x8000, 2) } else { (0x2000, 1) };
+        match export {
+            Export::Wram => self.memory(MemoryRegion::Wram)[..wram_size].to_vec(),
+            Export::CartridgeRam => self.memory(MemoryRegion::CartridgeRam).to_vec(),
+            Export::VramTiles => vram_tiles(self.memory(MemoryRegion::Vram), vram_banks),
+            Export::OamCsv => oam_csv(self.memory(MemoryRegion::Oam)).into_bytes(),
+        }
+    }
+
+    /// Overwrites `region` from `offset` on with `bytes`, for memory editors. Unlike `write_byte`
+    /// this bypasses banking and has no side effects on the hardware. Bytes past the end of the
+    /// region are dropped. Edited cartridge RAM counts as written for `take_battery_ram_written`,
+    /// and while paused the frame returned by `run_frame` is redrawn to show the edit.
+    pub fn edit_memory(&mut self, region: MemoryRegion, offset: usize, bytes: &[u8]) {
+        let memory = self.bus.region_mut(region);
+        let start = offset.min(memory.len());
+        let len = bytes.len().min(memory.len() - start);
+        memory[start..start + len].copy_from_slice(&bytes[..len]);
+
+        if region == MemoryRegion::CartridgeRam && len > 0 {
+            self.bus.mbc_mut().mark_ram_written();
+        }
+        self.redraw_if_paused();
+    }
+
+    // Shows changes to memory or colors in the frame `run_frame` returns while paused
+    fn redraw_if_paused(&mut self) {
+        if self.paused {
+            self.bus.ppu.redraw(&mut NullVideoSink);
+            *self.last_frame.pixels = *self.frame();
+        }
+    }
+
+    /// Edits the byte the CPU sees at `addr` with the current banks, like `edit_memory`. Returns
+    /// false, leaving memory as it was, for addresses outside RAM, VRAM and OAM.
+    pub fn poke(&mut self, addr: u16, value: u8) -> bool {
+        match self.bus.region_offset(addr) {
+            Some((region, offset)) => {
+                self.edit_memory(region, offset, &[value]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Draws the screen from the current memory into `frame` and `video`, e.g. to show edits made
+    /// while paused.
+    pub fn redraw(&mut self, video: &mut impl VideoSink) {
+        self.bus.ppu.redraw(video);
+    }
+
+    /// Cartridge RAM was replaced by a state's, which the `.sav` file hasn't seen. It counts as
+    /// written for `take_battery_ram_written`, so the battery save follows the state that was
+    /// loaded instead of keeping progress the game no longer knows about.
+    pub(crate) fn mark_battery_ram_restored(&mut self) {
+        self.bus.mbc_mut().mark_ram_written();
+    }
+
+    /// Rebuilds what save states leave out, after deserializing a core from one.
+    pub(crate) fn restore_unsaved(&mut self) {
+        self.bus.update_pages();
+        // States from before the PPU and APU kept their own copies of the mode
+        self.bus.set_cgb_mode(self.bus.cgb_mode());
+        self.bus.set_cgb_hardware(self.bus.cgb_hardware());
+        self.bus.ppu.set_stat_write_bug(self.quirks.stat_write_bug);
+        self.redraw(&mut NullVideoSink);
+    }
+
+    /// The screen as last drawn. Lines are drawn as the frame is emulated, so this is only a
+    /// whole frame once `step` returns true, as `step_frame` and `run_frame` wait for.
+    pub fn frame(&self) -> &FrameBuffer {
+        self.bus.ppu.frame()
+    }
+
+    /// The color numbers of `frame`'s pixels, before the palettes map them to shades.
+    pub fn color_indices(&self) -> &ColorIndices {
+        self.bus.ppu.color_indices()
+    }
+
+    /// Sets the addresses whose bytes `observe` reads into `Observation::taps`, e.g. a game's
+    /// score, lives and player position. A host setting like the volume.
+    pub fn set_memory_taps(&mut self, addrs: impl IntoIterator<Item = u16>) {
+        self.memory_taps = addrs.into_iter().collect();
+    }
+
+    pub fn memory_taps(&self) -> &[u16] {
+        &self.memory_taps
+    }
+
+    /// A compact view of the game for agents trained on it, a fraction of the size of the RGB
+    /// frame: its color numbers, the frame count and the memory taps. Call it once `run_frame` or
+    /// `step_frame` returns for a whole frame.
+    pub fn observe(&self) -> Observation {
+        Observation {
+            frame: self.frames_rendered,
+            pixels: Box::new(*self.color_indices()),
+            taps: self.memory_taps.iter().map(|&addr| self.read_byte(addr)).collect(),
+        }
+    }
+
+    pub fn ppu_state(&self) -> PpuState {
+        PpuState {
+            lcdc: self.read_byte(0xff40),
+            stat: self.read_byte(0xff41),
+            ly: self.read_byte(0xff44),
+            lyc: self.read_byte(0xff45),
+            scy: self.read_byte(0xff42),
+            scx: self.read_byte(0xff43),
+            wy: self.read_byte(0xff4a),
+            wx: self.read_byte(0xff4b),
+            window_line: self.bus.ppu.window_line(),
+        }
+    }
+
+    /// Renders the tile data and background tile map for a VRAM viewer, into a buffer of
+    /// `VRAM_VIEW_WIDTH` x `VRAM_VIEW_HEIGHT` pixels.
+    pub fn render_vram(&self, buffer: &mut [u32]) {
+        assert_eq!(buffer.len(), VRAM_VIEW_WIDTH * VRAM_VIEW_HEIGHT);
+        self.bus.ppu.render_vram(buffer, VRAM_VIEW_WIDTH);
+    }
+
+    /// Returns the bytes sent over the link port since the last call, oldest first. `run` hands
+    /// these to `EmuCallbacks::on_serial` instead.
+    pub fn take_serial_output(&mut self) -> Vec<u8> {
+        self.bus.take_serial_output()
+    }
+
+    /// Which of the four sound channels are playing, from NR52.
+    pub fn apu_channels_enabled(&self) -> [bool; 4] {
+        let nr52 = self.read_byte(0xff26);
+        [0, 1, 2, 3].map(|channel| nr52 & (1 << channel) != 0)
+    }
+
+    /// The sound registers FF10-FF3F, from `Apu::register_snapshot`: NR10-NR52 with their
+    /// write-only bits reading as 1, then wave RAM even while channel 3 is playing it.
+    pub fn apu_register_snapshot(&self) -> [u8; 0x30] {
+        self.bus.apu.register_snapshot()
+    }
+
+    /// CRC32 of the serialized emulation state, for checking that two runs have not diverged.
+    pub fn state_hash(&self) -> u32 {
+        crc32fast::hash(&serde_cbor::to_vec(self).expect("core state always serializes"))
+    }
+
+    /// What the loaded game is known to rely on that isn't emulated, or signs of a bad dump.
+    pub fn compatibility_notes(&self) -> Vec<CompatibilityNote> {
+        compatibility_notes(self.bus.rom())
+    }
+
+    /// CRC32 of the loaded game ROM, used to check that movies and states belong to this game.
+    pub fn rom_hash(&self) -> u32 {
+        crc32fast::hash(self.bus.rom())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::env;
+    use std::fs;
+    use std::io::{self, Write};
+    use std::mem;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::dmg::barcode::BarcodeBoy;
+    use crate::dmg::boot_rom::BootRomPolicy;
+    use crate::dmg::colorization::{DEFAULT_COLORIZATION, REVERSE};
+    use crate::dmg::core::{cycles_to_duration, Core, CoreBuilder, CLOCK_SPEED, CYCLES_PER_FRAME, FRAME_DURATION, MAX_SPEED, MAX_VOLUME, REFRESH_RATE};
+    use crate::dmg::crash::CrashReason;
+    use crate::dmg::diagnostics::{DeveloperWarning, Diagnostic, ExecutionWarning};
+    use crate::dmg::events::Event;
+    use crate::dmg::export::Export;
+    use crate::dmg::debug::{LcdControl, MemoryRegion, PausePoint, PpuMode, TRACE_LENGTH, VRAM_VIEW_HEIGHT, VRAM_VIEW_WIDTH};
+    use crate::dmg::frame::{rgb565, Frame, Rgb565Lines, SCREEN_HEIGHT, SCREEN_WIDTH};
+    use crate::dmg::infrared::{IrLink, IrLoopback};
+    use crate::dmg::input::JoypadInput;
+    use crate::dmg::link::LinkCable;
+    use crate::dmg::model::{Model, PowerOnRam, Quirks};
+    use crate::dmg::sgb::tests::packet_writes;
+    use crate::dmg::symbols::Symbols;
+    use crate::dmg::traits::{EmuCallbacks, NullAudioSink, NullVideoSink, VideoSink};
+    use crate::error::Error;
+    use crate::test_support::looping_rom;
+
+    #[test]
+    fn run_frame_works_headless() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+
+        let frame = core.run_frame(JoypadInput::empty());
+
+        assert_eq!(frame.pixels.len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+        assert!(!frame.audio.is_empty());
+    }
+
+    #[test]
+    fn queued_input_is_held_for_its_frames_then_falls_back_to_polled_input() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        // Selects the action buttons, read active low in P1
+        core.write_byte(0xff00, 0x10);
+        core.queue_input(2, JoypadInput::A);
+
+        let buttons: Vec<u8> = (0..3).map(|_| {
+            core.run_frame(JoypadInput::B);
+            core.read_byte(0xff00) & 0x0f
+        }).collect();
+
+        assert_eq!(buttons, [0x0e, 0x0e, 0x0d]);
+        assert!(!core.has_queued_input());
+    }
+
+    #[test]
+    fn emulated_time_follows_cycles() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        assert_eq!(core.emulated_time(), Duration::ZERO);
+
+        core.run_duration(Duration::from_secs(1), &mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        assert!(core.emulated_time().abs_diff(Duration::from_secs(1)) < Duration::from_micros(3));
+        assert_eq!(cycles_to_duration(CLOCK_SPEED), Duration::from_secs(1));
+        assert!((REFRESH_RATE - 59.7275).abs() < 0.0001);
+        assert!((FRAME_DURATION.as_secs_f64() * REFRESH_RATE - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn run_frame_waits_for_a_frame_started_late() {
+        #[rustfmt::skip]
+        let program = [
+            0x3e, 0x11,       // ld a, $11
+            0xe0, 0x40,       // ldh ($40), a: LCD off
+            0x0e, 0x08,       // ld c, 8
+            0x06, 0x00,       // ld b, 0
+            0x05,             // dec b
+            0x20, 0xfd,       // jr nz, -3
+            0x0d,             // dec c
+            0x20, 0xf8,       // jr nz, -8: about 33000 cycles in all
+            0x3e, 0x91,       // ld a, $91
+            0xe0, 0x40,       // ldh ($40), a: LCD on
+        ];
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&program)).build().unwrap();
+
+        core.run_frame(JoypadInput::empty());
+
+        assert_eq!(core.frames_rendered(), 1);
+        assert!(core.cycles_elapsed() > CYCLES_PER_FRAME as u64);
+    }
+
+    #[test]
+    fn rom_bank_switches_reach_the_page_table() {
+        // A 64K MBC1 ROM with each bank starting with its number
+        let mut rom = vec![0; 0x10000];
+        rom[0x147] = 0x01;
+        rom[0x148] = 0x01;
+        for bank in 0..4 {
+            rom[bank * 0x4000 + 0x10] = bank as u8;
+        }
+        let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+        assert_eq!(core.read_byte(0x4010), 1);
+
+        core.bus.write_byte(0x2000, 2);
+        assert_eq!(core.read_byte(0x4010), 2);
+
+        let mut state = vec![];
+        core.save_state_to(&mut state).unwrap();
+        core.bus.write_byte(0x2000, 3);
+        core.load_state_from(&state[..]).unwrap();
+        assert_eq!(core.read_byte(0x4010), 2);
+    }
+
+    #[test]
+    fn mbc30_maps_every_rom_and_ram_bank() {
+        // A 4M MBC30 ROM with RTC and 64K of RAM, each ROM bank starting with its number
+        let mut rom = vec![0; 0x400000];
+        rom[0x147] = 0x10;
+        rom[0x148] = 0x07;
+        rom[0x149] = 0x05;
+        for bank in 0..0x100 {
+            rom[bank * 0x4000 + 0x10] = bank as u8;
+        }
+        let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+
+        core.write_byte(0x2000, 0xc1);
+        assert_eq!(core.read_byte(0x4010), 0xc1);
+
+        core.write_byte(0x0000, 0x0a);
+        for bank in [7, 3] {
+            core.write_byte(0x4000, bank);
+            core.write_byte(0xa000, bank * 0x11);
+        }
+        core.write_byte(0x4000, 7);
+        assert_eq!(core.read_byte(0xa000), 0x77);
+        assert_eq!(core.battery_ram().unwrap().len(), 0x10000);
+
+        // RTC registers hold what was written
+        core.write_byte(0x4000, 0x08);
+        core.write_byte(0xa000, 0x15);
+        assert_eq!(core.read_byte(0xa000), 0x15);
+    }
+
+    #[test]
+    fn mbc3_masks_rom_banks_to_seven_bits() {
+        let mut rom = vec![0; 0x200000];
+        rom[0x147] = 0x13;
+        rom[0x148] = 0x06;
+        rom[0x149] = 0x03;
+        for bank in 0..0x80 {
+            rom[bank * 0x4000 + 0x10] = bank as u8;
+        }
+        let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+
+        core.write_byte(0x2000, 0xc1);
+        assert_eq!(core.read_byte(0x4010), 0x41);
+        core.write_byte(0x2000, 0x80);
+        assert_eq!(core.read_byte(0x4010), 0x01);
+    }
+
+    #[test]
+    fn frames_last_a_whole_frame_of_cycles() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        let start = core.cycles_elapsed();
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        // Frames end on an instruction boundary, and `jr` takes 12 cycles
+        assert!((core.cycles_elapsed() - start).abs_diff(CYCLES_PER_FRAME as u64) < 12);
+    }
+
+    struct CountingSink {
+        lines: u32,
+        frames: u32,
+    }
+
+    impl VideoSink for CountingSink {
+        fn push_line(&mut self, _y: usize, _line: &[u32; SCREEN_WIDTH]) {
+            self.lines += 1;
+        }
+
+        fn frame_ready(&mut self) {
+            self.frames += 1;
+        }
+    }
+
+    #[test]
+    fn step_frame_drives_custom_sinks() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        let mut video = CountingSink { lines: 0, frames: 0 };
+        let mut audio = vec![];
+
+        core.step_frame(&mut video, &mut audio, &mut JoypadInput::empty());
+
+        assert_eq!(video.frames, 1);
+        assert!(video.lines > 0);
+        assert!(!audio.is_empty());
+    }
+
+    #[test]
+    fn frame_skip_emulates_without_drawing() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        let mut video = CountingSink { lines: 0, frames: 0 };
+
+        core.set_frame_skip(true);
+        core.step_frame(&mut video, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        assert_eq!(video.frames, 1);
+        assert_eq!(video.lines, 0);
+    }
+
+    #[derive(Default)]
+    struct StopAfter {
+        frames: u32,
+        samples: usize,
+    }
+
+    impl EmuCallbacks for StopAfter {
+        fn on_frame(&mut self, pixels: &[u32]) -> bool {
+            assert_eq!(pixels.len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+            self.frames += 1;
+            self.frames < 3
+        }
+
+        fn on_audio(&mut self, samples: &[(f32, f32)]) {
+            self.samples += samples.len();
+        }
+    }
+
+    #[test]
+    fn run_stops_when_callback_asks() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        let mut callbacks = StopAfter::default();
+
+        core.run(&mut callbacks);
+
+        assert_eq!(callbacks.frames, 3);
+        assert_eq!(core.frames_rendered(), 3);
+        assert!(callbacks.samples > 0);
+    }
+
+    #[test]
+    fn deterministic_step_latches_input_per_frame() {
+        let rom = looping_rom(&[]);
+        let mut core = CoreBuilder::new().game_rom_data(rom).deterministic(true).build().unwrap();
+        let mut audio = vec![];
+        let (mut a, mut b) = (JoypadInput::A, JoypadInput::B);
+
+        core.step(&mut NullVideoSink, &mut audio, &mut a);
+        while !core.step(&mut NullVideoSink, &mut audio, &mut b) {
+            assert_eq!(core.latched_input, JoypadInput::A);
+        }
+        core.step(&mut NullVideoSink, &mut audio, &mut b);
+
+        assert_eq!(core.latched_input, JoypadInput::B);
+    }
+
+    #[test]
+    fn deterministic_input_is_latched_at_vblank_by_every_run_method() {
+        let rom = looping_rom(&[]);
+        let mut core = CoreBuilder::new().game_rom_data(rom).deterministic(true).build().unwrap();
+        let (mut a, mut b, mut start) = (JoypadInput::A, JoypadInput::B, JoypadInput::START);
+
+        core.run_cycles(1000, &mut NullVideoSink, &mut NullAudioSink, &mut a);
+        core.run_cycles(1000, &mut NullVideoSink, &mut NullAudioSink, &mut b);
+        assert_eq!(core.latched_input, JoypadInput::A);
+
+        // The frame ends at the start of VBlank, where the next call's keys are latched
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut b);
+        assert_eq!(core.read_byte(0xff44), 144);
+        assert_eq!(core.latched_input, JoypadInput::A);
+        core.run_cycles(4, &mut NullVideoSink, &mut NullAudioSink, &mut b);
+        assert_eq!(core.latched_input, JoypadInput::B);
+
+        // Without VBlanks, every frame's worth of cycles
+        core.write_byte(0xff40, 0x00);
+        core.run_cycles(1000, &mut NullVideoSink, &mut NullAudioSink, &mut start);
+        assert_eq!(core.latched_input, JoypadInput::B);
+        core.run_cycles(CYCLES_PER_FRAME as u64, &mut NullVideoSink, &mut NullAudioSink, &mut start);
+        core.run_cycles(4, &mut NullVideoSink, &mut NullAudioSink, &mut start);
+        assert_eq!(core.latched_input, JoypadInput::START);
+    }
+
+    #[test]
+    fn run_cycles_stops_within_one_instruction() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+
+        let elapsed = core.run_cycles(1001, &mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        // `jr -2` takes 12 cycles
+        assert!((1001..1001 + 12).contains(&elapsed));
+        assert_eq!(core.cycles_elapsed(), elapsed);
+    }
+
+    #[test]
+    fn run_duration_converts_to_cycles() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+
+        let elapsed = core.run_duration(Duration::from_millis(250), &mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        assert!((CLOCK_SPEED / 4..CLOCK_SPEED / 4 + 12).contains(&elapsed));
+    }
+
+    #[cfg(feature = "bundled-boot-rom")]
+    #[test]
+    fn bundled_boot_rom_hands_over_to_cartridge() {
+        let rom = looping_rom(&[]);
+        let mut core = CoreBuilder::new().game_rom_data(rom).bundled_boot_rom().build().unwrap();
+        assert_eq!(core.read_byte(0x0000), 0x31);
+
+        for _ in 0..300 {
+            core.run_frame(JoypadInput::empty());
+        }
+
+        assert_eq!(core.read_byte(0x0000), 0x00);
+        assert_eq!(core.read_byte(0xff40), 0x91);
+    }
+
+    #[test]
+    fn boot_rom_policy_decides_what_a_missing_boot_rom_does() {
+        let rom = looping_rom(&[]);
+        let missing = env::temp_dir().join("boot_rom_policy_missing.bin").to_string_lossy().into_owned();
+        let builder = |policy| CoreBuilder::new().game_rom_data(rom.clone()).boot_rom_policy(policy);
+
+        let core = builder(BootRomPolicy::Optional).boot_rom(&missing).build().unwrap();
+        assert_eq!((core.registers().pc, core.read_byte(0xff40)), (0x0100, 0x91));
+        assert!(matches!(builder(BootRomPolicy::Require).boot_rom(&missing).build(), Err(Error::RomLoad { .. })));
+        assert!(matches!(builder(BootRomPolicy::Require).build(), Err(Error::BootRomRequired)));
+
+        let core = builder(BootRomPolicy::Never).boot_rom_data(vec![0; 0x100]).build().unwrap();
+        assert_eq!(core.registers().pc, 0x0100);
+        let core = builder(BootRomPolicy::Require).boot_rom_data(vec![0; 0x100]).build().unwrap();
+        assert_eq!(core.registers().pc, 0x0000);
+    }
+
+    #[test]
+    fn boot_rom_can_be_swapped_while_running() {
+        let rom = looping_rom(&[]);
+        let mut spinning = vec![0; 0x100];
+        spinning[..2].copy_from_slice(&[0x18, 0xfe]);
+        let mut core = CoreBuilder::new().game_rom_data(rom).boot_rom_data(spinning.clone()).build().unwrap();
+        core.run_frame(JoypadInput::empty());
+        assert!(core.is_running_boot_rom());
+
+        assert!(matches!(core.set_boot_rom(Some(vec![0; 0x200])), Err(Error::InvalidBootRom(0x200))));
+        assert_eq!(core.boot_rom(), Some(&spinning[..]));
+
+        // Runs into unmapping itself from wherever the old one was
+        let mut quick = vec![0; 0x100];
+        quick[0xfc..].copy_from_slice(&[0x3e, 0x01, 0xe0, 0x50]);
+        core.set_boot_rom(Some(quick.clone())).unwrap();
+        core.run_frame(JoypadInput::empty());
+        assert!(!core.is_running_boot_rom());
+        assert_eq!(core.boot_rom(), Some(&quick[..]));
+    }
+
+    #[test]
+    fn palettes_load_into_the_paused_frame() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.run_frame(JoypadInput::empty());
+        core.set_paused(true);
+
+        let path = env::temp_dir().join("palettes_load_into_the_paused_frame.pal");
+        fs::write(&path, "JASC-PAL\n0100\n4\n1 2 3\n4 5 6\n7 8 9\n10 11 12\n").unwrap();
+        core.load_palette(&path.to_string_lossy()).unwrap();
+
+        assert_eq!(core.run_frame(JoypadInput::empty()).pixels[0], 0xff010203);
+        assert!(matches!(core.load_palette("missing.pal"), Err(Error::PaletteLoad { .. })));
+    }
+
+    #[test]
+    fn skipping_boot_rom_sets_post_boot_registers() {
+        let core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+
+        assert_eq!(core.read_byte(0xff04), 0xab);
+        assert_eq!(core.read_byte(0xff07), 0xf8);
+        assert_eq!(core.read_byte(0xff24), 0x77);
+        assert_eq!(core.read_byte(0xff25), 0xf3);
+        assert_eq!(core.read_byte(0xff40), 0x91);
+        assert_eq!(core.read_byte(0xff47), 0xfc);
+        assert_eq!(core.read_byte(0xff0f) & 0x1f, 0x01);
+    }
+
+    #[test]
+    fn resetting_div_clocks_the_timer_when_its_bit_is_set() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        // Enabled, following bit 3, which is set in the 0xabcc the boot ROM leaves the counter at
+        core.write_byte(0xff07, 0x05);
+        core.write_byte(0xff05, 0x00);
+
+        core.write_byte(0xff04, 0x12);
+        assert_eq!(core.read_byte(0xff04), 0x00);
+        assert_eq!(core.read_byte(0xff05), 0x01);
+
+        // Bit 3 is clear now, so nothing more happens
+        core.write_byte(0xff04, 0x12);
+        assert_eq!(core.read_byte(0xff05), 0x01);
+    }
+
+    #[test]
+    fn div_follows_cycles_elapsed() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.run_frame(JoypadInput::empty());
+
+        let counter = 0xabcc + core.cycles_elapsed();
+        assert_eq!(core.read_byte(0xff04), (counter >> 8) as u8);
+    }
+
+    #[test]
+    fn reset_keeps_cartridge_ram() {
+        // MBC1 with one bank of RAM
+        let mut rom = looping_rom(&[]);
+        rom[0x147] = 0x03;
+        rom[0x149] = 0x02;
+
+        let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+        core.write_byte(0x0000, 0x0a);
+        core.write_byte(0xa000, 0x42);
+        core.write_byte(0xc000, 0x42);
+        core.run_frame(JoypadInput::empty());
+
+        core.reset();
+
+        assert_eq!(core.read_byte(0xa000), 0xff);
+        core.write_byte(0x0000, 0x0a);
+        assert_eq!(core.read_byte(0xa000), 0x42);
+        assert_eq!(core.read_byte(0xc000), 0x00);
+        assert_eq!(core.read_byte(0xff04), 0xab);
+        assert_eq!(core.frames_rendered(), 0);
+    }
+
+    #[test]
+    fn renders_rgb565_lines() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.run_frame(JoypadInput::empty());
+
+        let mut lines = vec![];
+        let mut video = Rgb565Lines { push_line: |y: usize, line: &[u16; SCREEN_WIDTH]| lines.push((y, line[0])) };
+        core.step_frame(&mut video, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        assert_eq!(lines.iter().map(|&(y, _)| y).collect::<Vec<_>>(), (0..SCREEN_HEIGHT).collect::<Vec<_>>());
+        assert_eq!(lines[0].1, rgb565(core.run_frame(JoypadInput::empty()).pixels[0]));
+    }
+
+    #[test]
+    fn paused_core_keeps_last_frame_and_goes_silent() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.run_frame(JoypadInput::empty());
+        let cycles = core.cycles_elapsed();
+
+        core.set_paused(true);
+        let frame = core.run_frame(JoypadInput::empty());
+
+        assert!(frame.audio.is_empty());
+        assert_eq!(frame.pixels.len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+        assert_eq!(core.cycles_elapsed(), cycles);
+    }
+
+    #[test]
+    fn debugger_reads_registers_and_disassembles() {
+        let core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+
+        let registers = core.registers();
+        let instructions = core.disassemble(registers.pc, 2);
+
+        assert_eq!((registers.pc, registers.a, registers.f), (0x100, 0x01, 0xb0));
+        assert_eq!(instructions[0].text, "JR $0100");
+        assert_eq!(instructions[0].len, 2);
+        assert_eq!(instructions[1].addr, 0x102);
+        assert_eq!(core.ppu_state().lcdc, 0x91);
+    }
+
+    #[test]
+    fn ppu_state_decodes_registers() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.write_byte(0xff45, 0x40);
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        let ppu = core.ppu_state();
+        assert_eq!((ppu.ly, ppu.lyc, ppu.mode()), (144, 0x40, PpuMode::VBlank));
+        assert_eq!(
+            ppu.lcd_control(),
+            LcdControl {
+                lcd_enabled: true,
+                window_tile_map: 0x9800,
+                window_enabled: false,
+                tile_data: 0x8000,
+                bg_tile_map: 0x9800,
+                sprite_height: 8,
+                sprites_enabled: false,
+                bg_enabled: true,
+            }
+        );
+    }
+
+    #[test]
+    fn step_instruction_runs_while_paused() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.set_paused(true);
+
+        core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        assert_eq!(core.cycles_elapsed(), 12);
+        assert_eq!(core.registers().pc, 0x100);
+    }
+
+    #[test]
+    fn pause_requests_stop_at_the_next_instruction() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        let events = Arc::new(Mutex::new(vec![]));
+        core.subscribe({
+            let events = events.clone();
+            move |event: &Event| events.lock().unwrap().push(*event)
+        });
+        core.run_frame(JoypadInput::empty());
+        let cycles = core.cycles_elapsed();
+
+        // Asked for from elsewhere, taking effect before anything more is run
+        let handle = core.pause_handle();
+        thread::spawn(move || handle.request_pause()).join().unwrap();
+        assert!(!core.is_paused());
+        assert_eq!(core.run_cycles(1000, &mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty()), 0);
+        let point = PausePoint { pc: 0x100, cycle: cycles };
+        assert_eq!(core.pause_point(), Some(point));
+        assert_eq!(events.lock().unwrap().last(), Some(&Event::Paused(point)));
+
+        core.set_paused(false);
+        assert_eq!(core.pause_point(), None);
+
+        // Asked for in the middle of a frame, which stops there
+        let handle = core.pause_handle();
+        core.on_scanline(move |y, _| {
+            if y == 10 {
+                handle.request_pause();
+            }
+        });
+        core.run_frame(JoypadInput::empty());
+        assert!(core.is_paused());
+        assert_eq!(core.ppu_state().ly, 10);
+        assert!(core.pause_point().is_some_and(|point| point.cycle - cycles < CYCLES_PER_FRAME as u64 / 2));
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedLog(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedLog {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn gameboy_doctor_logs_each_instruction_to_writer() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        let log = SharedLog::default();
+        core.initialize_gameboy_doctor_with_log(log.clone());
+
+        core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+        core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        let line = "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:18,FE,00,00\n";
+        assert_eq!(String::from_utf8(log.0.lock().unwrap().clone()).unwrap(), line.repeat(2));
+        assert_eq!(core.read_byte(0xff44), 0x90);
+    }
+
+    #[test]
+    fn colorization_changes_colors_and_survives_reset() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+
+        core.set_colorization(Some(REVERSE));
+        core.reset();
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        // The empty background is color 0, the first color of the palette
+        assert_eq!(core.colorization(), Some(REVERSE));
+        assert_eq!(core.frame()[0], REVERSE.bg[0]);
+        assert_eq!(core.boot_rom_colorization(), DEFAULT_COLORIZATION);
+    }
+
+    #[test]
+    fn model_sets_post_boot_registers() {
+        let rom = looping_rom(&[]);
+        let registers = |model| CoreBuilder::new().game_rom_data(rom.clone()).model(model).build().unwrap().registers();
+
+        assert_eq!((registers(Model::Dmg).a, registers(Model::Dmg).f, registers(Model::Dmg).c), (0x01, 0xb0, 0x13));
+        assert_eq!((registers(Model::Mgb).a, registers(Model::Mgb).f), (0xff, 0xb0));
+        assert_eq!((registers(Model::Sgb).a, registers(Model::Sgb).c, registers(Model::Sgb).h), (0x01, 0x14, 0xc0));
+        assert_eq!((registers(Model::Cgb).a, registers(Model::Cgb).e, registers(Model::Cgb).l), (0x11, 0x08, 0x7c));
+    }
+
+    #[test]
+    fn cgb_registers_need_a_cgb_game_on_a_cgb() {
+        let mut rom = looping_rom(&[]);
+        rom[0x143] = 0x80;
+
+        let mut dmg = CoreBuilder::new().game_rom_data(rom.clone()).build().unwrap();
+        dmg.write_byte(0xff70, 0x02);
+        assert_eq!(dmg.read_byte(0xff70), 0xff);
+
+        let mut cgb = CoreBuilder::new().game_rom_data(rom).model(Model::Cgb).build().unwrap();
+        assert_eq!((cgb.registers().a, cgb.registers().d), (0x11, 0xff));
+        assert_eq!(cgb.colorization(), None);
+        cgb.write_byte(0xd000, 0x12);
+        cgb.write_byte(0xff70, 0x02);
+        assert_eq!(cgb.read_byte(0xff70), 0xfa);
+        assert_eq!(cgb.read_byte(0xd000), 0x00);
+        cgb.write_byte(0xff70, 0x00);
+        assert_eq!(cgb.read_byte(0xd000), 0x12);
+
+        cgb.reset();
+        assert_eq!((cgb.model(), cgb.registers().d), (Model::Cgb, 0xff));
+    }
+
+    #[test]
+    fn apu_takes_only_length_loads_while_powered_off() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.write_byte(0xff26, 0x00);
+
+        core.write_byte(0xff11, 0xff);
+        core.write_byte(0xff12, 0xf0);
+        core.write_byte(0xff24, 0x77);
+        // Two 256 Hz length clocks left on channel 4
+        core.write_byte(0xff20, 0x3e);
+        core.write_byte(0xff26, 0x80);
+
+        assert_eq!((core.read_byte(0xff11), core.read_byte(0xff12), core.read_byte(0xff24)), (0x3f, 0x00, 0x00));
+        assert_eq!(core.read_byte(0xff1a), 0x7f);
+        assert_eq!(core.read_byte(0xff26), 0xf0);
+
+        core.write_byte(0xff21, 0xf0);
+        core.write_byte(0xff23, 0xc0);
+        assert_eq!(core.apu_channels_enabled(), [false, false, false, true]);
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+        assert_eq!(core.apu_channels_enabled(), [false; 4]);
+
+        // A CGB drops them too, so channel 4 keeps the two clocks it had instead of a full 64
+        let rom = looping_rom(&[]);
+        let mut cgb = CoreBuilder::new().game_rom_data(rom.clone()).model(Model::Cgb).build().unwrap();
+        cgb.write_byte(0xff20, 0x3e);
+        cgb.write_byte(0xff26, 0x00);
+        cgb.write_byte(0xff20, 0x00);
+        cgb.write_byte(0xff26, 0x80);
+
+        cgb.write_byte(0xff21, 0xf0);
+        cgb.write_byte(0xff23, 0xc0);
+        assert_eq!(cgb.apu_channels_enabled(), [false, false, false, true]);
+        cgb.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+        assert_eq!(cgb.apu_channels_enabled(), [false; 4]);
+    }
+
+    #[test]
+    fn apu_register_snapshot_applies_read_masks_and_keeps_wave_ram() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        for (i, addr) in (0xff30..0xff40).enumerate() {
+            core.write_byte(addr, i as u8 * 0x11);
+        }
+        core.write_byte(0xff11, 0xc5);
+        // Playing channel 3, which hides wave RAM from the CPU
+        core.write_byte(0xff1a, 0x80);
+        core.write_byte(0xff1e, 0x80);
+
+        let snapshot = core.apu_register_snapshot();
+        assert_eq!(snapshot[0x01], 0xff);
+        assert_eq!(snapshot[0x03], 0xff);
+        assert_eq!(snapshot[0x17..0x20], [0xff; 9]);
+        assert_eq!(snapshot[0x20..], std::array::from_fn::<u8, 16, _>(|i| i as u8 * 0x11));
+        assert!(snapshot[..0x20].iter().zip(0xff10..).all(|(&byte, addr)| byte == core.read_byte(addr)));
+    }
+
+    #[test]
+    fn wave_ram_stays_accessible_while_playing_on_a_cgb() {
+        let rom = looping_rom(&[]);
+        let wave_ram_read_while_playing = |model: Model| {
+            let mut core = CoreBuilder::new().game_rom_data(rom.clone()).model(model).build().unwrap();
+            for (i, addr) in (0xff30..0xff40).enumerate() {
+                core.write_byte(addr, 0x10 + i as u8);
+            }
+            core.write_byte(0xff1a, 0x80);
+            core.write_byte(0xff1e, 0x80);
+            core.run_cycles(1000, &mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+            core.read_byte(0xff30)
+        };
+
+        // Only right as the channel reads a byte on a DMG, the byte being played on a CGB
+        assert_eq!(wave_ram_read_while_playing(Model::Dmg), 0xff);
+        assert!((0x10..0x20).contains(&wave_ram_read_while_playing(Model::Cgb)));
+    }
+
+    #[test]
+    fn frames_yields_one_owned_frame_per_poll() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        let mut polls = 0;
+
+        let frames: Vec<Frame> = core.frames(|| {
+            polls += 1;
+            JoypadInput::empty()
+        }).take(3).collect();
+
+        assert_eq!(polls, 3);
+        assert_eq!(frames.len(), 3);
+        assert!(frames.iter().all(|frame| !frame.audio.is_empty()));
+        assert_eq!(core.frames_rendered(), 3);
+    }
+
+    #[test]
+    fn cgb_bg_attributes_and_lcdc_bit_0_decide_sprite_priority() {
+        let mut rom = looping_rom(&[]);
+        rom[0x143] = 0x80;
+        let mut core = CoreBuilder::new().game_rom_data(rom).model(Model::Cgb).build().unwrap();
+
+        // Background tile 0 in color 1 everywhere, sprite tile 1 in color 3
+        for addr in 0x8000..0x8010 {
+            core.write_byte(addr, if addr % 2 == 0 { 0xff } else { 0x00 });
+        }
+        for addr in 0x8010..0x8020 {
+            core.write_byte(addr, 0xff);
+        }
+        for addr in 0x9800..0x9c00 {
+            core.write_byte(addr, 0x00);
+        }
+        // BG priority for the top left tile only
+        core.write_byte(0xff4f, 0x01);
+        for addr in 0x9800..0x9c00 {
+            core.write_byte(addr, if addr == 0x9800 { 0x80 } else { 0x00 });
+        }
+        core.write_byte(0xff4f, 0x00);
+        // Two sprites side by side over the top left tiles
+        for (i, x) in [8u16, 16].into_iter().enumerate() {
+            let addr = 0xfe00 + i as u16 * 4;
+            for (offset, value) in [16, x as u8, 0x01, 0x00].into_iter().enumerate() {
+                core.write_byte(addr + offset as u16, value);
+            }
+        }
+        for addr in 0xfe08..0xfea0 {
+            core.write_byte(addr, 0x00);
+        }
+        core.write_byte(0xff47, 0xe4);
+        core.write_byte(0xff48, 0xe4);
+
+        core.write_byte(0xff40, 0x93);
+        core.redraw(&mut NullVideoSink);
+        let (bg, sprite) = (core.frame()[0], core.frame()[8]);
+        assert_ne!(bg, sprite);
+
+        // Without master priority every sprite is drawn on top, over a background that's still there
+        core.write_byte(0xff40, 0x92);
+        core.redraw(&mut NullVideoSink);
+        assert_eq!((core.frame()[0], core.frame()[8], core.frame()[SCREEN_WIDTH * 8]), (sprite, sprite, bg));
+    }
+
+    #[test]
+    fn undocumented_cgb_registers() {
+        let rom = looping_rom(&[]);
+        let mut cgb_rom = rom.clone();
+        cgb_rom[0x143] = 0x80;
+
+        let mut dmg = CoreBuilder::new().game_rom_data(rom.clone()).build().unwrap();
+        dmg.write_byte(0xff72, 0x12);
+        assert_eq!((dmg.read_byte(0xff72), dmg.read_byte(0xff76)), (0xff, 0xff));
+
+        // FF74 is locked for DMG games, the rest are there on any CGB
+        let mut compat = CoreBuilder::new().game_rom_data(rom).model(Model::Cgb).build().unwrap();
+        for addr in [0xff72, 0xff73, 0xff74, 0xff75] {
+            compat.write_byte(addr, 0xff);
+        }
+        let read = |core: &Core| [0xff72, 0xff73, 0xff74, 0xff75].map(|addr| core.read_byte(addr));
+        assert_eq!(read(&compat), [0xff, 0xff, 0xff, 0xff]);
+        compat.write_byte(0xff75, 0x00);
+        assert_eq!(compat.read_byte(0xff75), 0x8f);
+
+        let mut cgb = CoreBuilder::new().game_rom_data(cgb_rom).model(Model::Cgb).build().unwrap();
+        cgb.write_byte(0xff72, 0x12);
+        cgb.write_byte(0xff73, 0x34);
+        cgb.write_byte(0xff74, 0x56);
+        cgb.write_byte(0xff75, 0x00);
+        assert_eq!(read(&cgb), [0x12, 0x34, 0x56, 0x8f]);
+
+        // Channel 2 at full volume shows up in PCM12's high nibble as its duty cycle goes high
+        cgb.write_byte(0xff26, 0x80);
+        cgb.write_byte(0xff16, 0x80);
+        cgb.write_byte(0xff17, 0xf0);
+        cgb.write_byte(0xff19, 0x87);
+        let mut seen = HashSet::new();
+        for _ in 0..1000 {
+            cgb.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+            seen.insert(cgb.read_byte(0xff76));
+        }
+        assert_eq!(seen, HashSet::from([0x00, 0xf0]));
+        assert_eq!(cgb.read_byte(0xff77), 0x00);
+    }
+
+    #[test]
+    fn cgb_colorizes_dmg_games() {
+        let rom = looping_rom(&[]);
+        let core = CoreBuilder::new().game_rom_data(rom.clone()).model(Model::Cgb).build().unwrap();
+
+        assert_eq!(core.colorization(), Some(DEFAULT_COLORIZATION));
+    }
+
+    #[test]
+    fn linked_cores_see_each_others_infrared_light() {
+        let mut rom = looping_rom(&[]);
+        rom[0x143] = 0x80;
+        let cgb = || CoreBuilder::new().game_rom_data(rom.clone()).model(Model::Cgb).build().unwrap();
+        let (a, b) = IrLink::pair();
+        let mut sender = cgb();
+        let mut receiver = cgb();
+        sender.connect_infrared(a);
+        receiver.connect_infrared(b);
+
+        receiver.write_byte(0xff56, 0xc0);
+        assert_eq!(receiver.read_byte(0xff56), 0xfe);
+        sender.write_byte(0xff56, 0x01);
+        assert_eq!(receiver.read_byte(0xff56), 0xfc);
+
+        let mut state = vec![];
+        receiver.save_state_to(&mut state).unwrap();
+        receiver.load_state_from(&state[..]).unwrap();
+        receiver.reset();
+        receiver.write_byte(0xff56, 0xc0);
+        assert_eq!(receiver.read_byte(0xff56), 0xfc);
+    }
+
+    #[test]
+    fn serial_transfers_swap_bytes_with_the_connected_device() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        let transfer = |core: &mut Core, sent: u8, control: u8| {
+            core.write_byte(0xff0f, 0x00);
+            core.write_byte(0xff01, sent);
+            core.write_byte(0xff02, control);
+            core.run_cycles(5000, &mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+            (core.read_byte(0xff01), core.read_byte(0xff0f) & 0x08 != 0)
+        };
+
+        // Nothing plugged in shifts in 0xFF on the internal clock and never clocks an external one
+        assert_eq!(transfer(&mut core, 0x10, 0x81), (0xff, true));
+        assert_eq!(transfer(&mut core, 0x10, 0x80), (0x10, false));
+
+        let barcode_boy = BarcodeBoy::new();
+        core.connect_serial(barcode_boy.clone());
+        let replies: Vec<(u8, bool)> = [0x10, 0x07, 0x10, 0x07].iter().map(|&b| transfer(&mut core, b, 0x81)).collect();
+        assert_eq!(replies, [(0xff, true), (0xff, true), (0x10, true), (0x07, true)]);
+
+        barcode_boy.scan("4907981000301").unwrap();
+        assert_eq!(transfer(&mut core, 0x00, 0x80), (0x02, true));
+        assert_eq!(transfer(&mut core, 0x00, 0x80), (b'4', true));
+    }
+
+    #[test]
+    fn linked_cores_swap_bytes() {
+        let rom = looping_rom(&[]);
+        let mut master = CoreBuilder::new().game_rom_data(rom.clone()).build().unwrap();
+        let mut slave = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+        let (a, b) = LinkCable::pair();
+        master.connect_serial(a);
+        slave.connect_serial(b);
+
+        let start = |core: &mut Core, sent: u8, control: u8| {
+            core.write_byte(0xff0f, 0x00);
+            core.write_byte(0xff01, sent);
+            core.write_byte(0xff02, control);
+        };
+        let run = |core: &mut Core| {
+            core.run_cycles(5000, &mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+            (core.read_byte(0xff01), core.read_byte(0xff0f) & 0x08 != 0)
+        };
+
+        start(&mut slave, 0x24, 0x80);
+        assert_eq!(run(&mut slave), (0x24, false));
+        start(&mut master, 0x42, 0x81);
+        assert_eq!(run(&mut master), (0x24, true));
+        assert_eq!(run(&mut slave), (0x42, true));
+    }
+
+    #[test]
+    fn infrared_port_is_missing_outside_cgb_mode() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.connect_infrared(IrLoopback::default());
+        core.write_byte(0xff56, 0xc1);
+
+        assert_eq!(core.read_byte(0xff56), 0xff);
+    }
+
+    #[test]
+    fn sgb_game_enables_four_players() {
+        let mut packet = [0; 16];
+        packet[0] = 0x11 << 3 | 1;
+        packet[1] = 0x03;
+        let rom = looping_rom(&[]);
+        let mut sgb = CoreBuilder::new().game_rom_data(rom.clone()).model(Model::Sgb).build().unwrap();
+        let mut dmg = CoreBuilder::new().game_rom_data(rom.clone()).build().unwrap();
+
+        for p1 in packet_writes(packet) {
+            sgb.write_byte(0xff00, p1);
+            dmg.write_byte(0xff00, p1);
+        }
+        assert_eq!((sgb.players(), dmg.players()), (4, 1));
+
+        sgb.set_player_input(3, JoypadInput::START);
+        for _ in 0..3 {
+            sgb.write_byte(0xff00, 0x10);
+            sgb.write_byte(0xff00, 0x30);
+        }
+        sgb.write_byte(0xff00, 0x10);
+        assert_eq!(sgb.read_byte(0xff00) & 0x0f, 0x07);
+
+        sgb.reset();
+        assert_eq!(sgb.players(), 1);
+    }
+
+    #[test]
+    fn hexdump_formats_memory_without_side_effects() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        for (i, &b) in b"Hello, world!\0\x01\xff".iter().enumerate() {
+            core.write_byte(0xc000 + i as u16, b);
+        }
+        let state = core.state_hash();
+
+        assert_eq!(
+            core.hexdump(0xc000, 18),
+            "C000  48 65 6C 6C 6F 2C 20 77  6F 72 6C 64 21 00 01 FF  |Hello, world!...|\n\
+             C010  00 00                                             |..|\n"
+        );
+        assert_eq!(core.hexdump(0xffff, 2).lines().count(), 1);
+        assert_eq!(core.state_hash(), state);
+    }
+
+    #[test]
+    fn instruction_trace_keeps_the_last_instructions() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        assert!(core.instruction_trace().is_empty());
+
+        for _ in 0..100 {
+            core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+        }
+
+        let trace = core.instruction_trace();
+        assert_eq!(trace.len(), TRACE_LENGTH);
+        assert!(trace.iter().all(|entry| entry.registers.pc == 0x100 && entry.opcode == 0x18));
+        assert_eq!(trace[0].mnemonic, "JR r8");
+    }
+
+    #[test]
+    fn coverage_records_executed_rom_bytes() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+        assert!(core.coverage().is_none());
+
+        core.enable_coverage();
+        core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+        core.reset();
+        core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        let coverage = core.coverage().unwrap();
+        assert!(coverage.is_executed(0, 0x100));
+        assert!(coverage.is_executed(0, 0x101));
+        assert_eq!(coverage.executed_per_bank(), [2, 0]);
+    }
+
+    #[test]
+    fn restoring_another_core_keeps_coverage_and_diagnostics() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        let snapshot: Core = serde_cbor::from_slice(&serde_cbor::to_vec(&core).unwrap()).unwrap();
+        core.enable_coverage();
+        core.enable_execution_diagnostics();
+        core.enable_developer_mode();
+
+        core.restore_from(snapshot);
+        core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        assert!(core.coverage().unwrap().is_executed(0, 0x100));
+        assert!(core.bus.diagnostics.is_some());
+        assert!(core.bus.developer_checks.is_some());
+    }
+
+    #[test]
+    fn disassembly_shows_symbols() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.set_symbols(Some(Symbols::parse("00:0100 Main\n00:ff80 hFrame\n")));
+        core.write_byte(0xc000, 0xcd);
+        core.write_byte(0xc001, 0x00);
+        core.write_byte(0xc002, 0x01);
+        core.write_byte(0xc003, 0xe0);
+        core.write_byte(0xc004, 0x80);
+        core.write_byte(0xc005, 0xe0);
+        core.write_byte(0xc006, 0x40);
+
+        let text: Vec<String> = core.disassemble(0xc000, 3).into_iter().map(|i| i.text).collect();
+        assert_eq!(text, ["CALL Main", "LDH hFrame,A", "LDH LCDC,A"]);
+        assert_eq!(core.disassemble(0x100, 1)[0].text, "JR Main");
+
+        core.reset();
+        assert_eq!(core.symbols().map(Symbols::len), Some(2));
+    }
+
+    #[test]
+    fn halt_skips_ahead_to_the_next_event() {
+        // ei; halt; jr -3, waking up for each VBlank interrupt, whose handler at 0x40 is reti
+        let mut rom = looping_rom(&[0x3e, 0x01, 0xe0, 0xff, 0xfb, 0x76, 0x18, 0xfd]);
+        rom[0x40] = 0xd9;
+        let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        let start = core.cycles_elapsed();
+        let mut steps = 0;
+        while !core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty()) {
+            steps += 1;
+        }
+
+        // A few steps per line rather than one per 4 cycles
+        assert!(steps < 1000);
+        assert!((core.cycles_elapsed() - start).abs_diff(CYCLES_PER_FRAME as u64) < 12);
+    }
+
+    #[test]
+    fn frame_stats_count_events_in_the_last_frame() {
+        // ei; halt; jr -3, waking up for each VBlank interrupt, whose handler at 0x40 is reti.
+        // Each frame also starts a DMA transfer and turns the LCD off and on from the handler.
+        let mut rom = looping_rom(&[0x3e, 0x01, 0xe0, 0xff, 0xfb, 0x76, 0x18, 0xfd]);
+        rom[0x40..0x4a].copy_from_slice(&[0xe0, 0x46, 0xaf, 0xe0, 0x40, 0x3e, 0x91, 0xe0, 0x40, 0xd9]);
+        let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+        assert_eq!(core.frame_stats(), Default::default());
+
+        for _ in 0..3 {
+            core.run_frame(JoypadInput::empty());
+        }
+
+        let stats = core.frame_stats();
+        assert_eq!(stats.interrupts, [1, 0, 0, 0, 0]);
+        assert_eq!(stats.dma_transfers, 1);
+        assert_eq!(stats.lcd_toggles, 2);
+        assert!(stats.halt_cycles > 50000);
+    }
+
+    #[test]
+    fn render_vram_draws_tiles_and_background_map() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.write_byte(0x8000, 0xff);
+        core.write_byte(0x8001, 0xff);
+        let mut buffer = vec![0; VRAM_VIEW_WIDTH * VRAM_VIEW_HEIGHT];
+
+        core.render_vram(&mut buffer);
+
+        // Row 0 of tile 0 is color 3, and the background map points every tile at tile 0
+        assert_ne!(buffer[0], buffer[VRAM_VIEW_WIDTH]);
+        assert_eq!(buffer[0], buffer[128]);
+        assert_eq!(buffer[VRAM_VIEW_WIDTH], buffer[VRAM_VIEW_WIDTH + 128]);
+    }
+
+    #[test]
+    fn volume_is_clamped_and_survives_reset() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+
+        core.set_volume(5.0);
+        assert_eq!(core.volume(), MAX_VOLUME);
+
+        core.set_volume(0.5);
+        core.reset();
+        assert_eq!(core.volume(), 0.5);
+    }
+
+    #[test]
+    fn speed_sets_frames_per_update_and_resamples_audio() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        // The first frame after boot starts partway through
+        core.run_frame(JoypadInput::empty());
+        let normal = core.run_frame(JoypadInput::empty()).audio.len();
+
+        core.set_speed(2.5);
+        assert_eq!([core.frames_for_update(), core.frames_for_update()], [2, 3]);
+        let fast = core.run_frame(JoypadInput::empty()).audio.len();
+        assert!(fast.abs_diff(normal * 2 / 5) <= 1, "{} samples at 2.5x, {} at 1x", fast, normal);
+
+        core.set_speed(0.5);
+        assert_eq!(core.frames_for_update(), 1);
+        let slow = core.run_frame(JoypadInput::empty()).audio.len();
+        assert!(slow.abs_diff(normal * 2) <= 1, "{} samples at 0.5x, {} at 1x", slow, normal);
+
+        core.set_speed(100.0);
+        assert_eq!(core.speed(), MAX_SPEED);
+        let frames = core.frames_rendered();
+        let mut audio = vec![];
+        assert_eq!(core.run_at_speed(&mut NullVideoSink, &mut audio, &mut JoypadInput::empty()), 8);
+        assert_eq!(core.frames_rendered(), frames + 8);
+
+        core.reset();
+        assert_eq!(core.speed(), MAX_SPEED);
+    }
+
+    #[test]
+    fn pop_filter_survives_reset_and_state_loads() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        let mut state = vec![];
+        core.save_state_to(&mut state).unwrap();
+
+        core.set_pop_filter(true);
+        core.reset();
+        assert!(core.pop_filter());
+
+        core.load_state_from(state.as_slice()).unwrap();
+        assert!(core.pop_filter());
+    }
+
+    #[test]
+    fn editing_vram_while_paused_redraws_the_frame() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        let before = core.run_frame(JoypadInput::empty()).pixels[0];
+        core.set_paused(true);
+
+        // Tile 0, which fills the background map, becomes solid color 3
+        core.edit_memory(MemoryRegion::Vram, 0, &[0xff; 16]);
+
+        assert_eq!(core.memory(MemoryRegion::Vram)[..16], [0xff; 16]);
+        assert_ne!(core.run_frame(JoypadInput::empty()).pixels[0], before);
+    }
+
+    #[test]
+    fn poke_edits_ram_at_cpu_addresses() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+
+        assert!(core.poke(0xc010, 0x42));
+        assert!(core.poke(0xff80, 0x43));
+        assert_eq!(core.read_byte(0xc010), 0x42);
+        assert_eq!(core.memory(MemoryRegion::Hram)[0], 0x43);
+
+        assert!(!core.poke(0x0100, 0x00));
+        assert!(!core.poke(0xa000, 0x00));
+        assert_eq!(core.read_byte(0x0100), 0x18);
+    }
+
+    #[test]
+    fn exports_cover_the_current_mode() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.poke(0xc001, 0x42);
+        core.edit_memory(MemoryRegion::Oam, 0, &[16, 8, 0x01, 0x20]);
+
+        let wram = core.export(Export::Wram);
+        assert_eq!(wram.len(), 0x2000);
+        assert_eq!(wram[1], 0x42);
+        assert_eq!(core.export(Export::VramTiles).len(), 384 * 16);
+        assert!(core.export(Export::CartridgeRam).is_empty());
+
+        let oam = String::from_utf8(core.export(Export::OamCsv)).unwrap();
+        assert_eq!(oam.lines().nth(1), Some("0,16,8,1,0,0,1,0,0,0"));
+    }
+
+    #[test]
+    fn boot_rom_tells_when_the_game_starts() {
+        let rom = looping_rom(&[]);
+        // ld a,1; ldh [$50],a, then the cartridge's zeros up to the loop at 0x100
+        let mut boot_rom = vec![0; 0x100];
+        boot_rom[..4].copy_from_slice(&[0x3e, 0x01, 0xe0, 0x50]);
+        let mut core = CoreBuilder::new().game_rom_data(rom.clone()).boot_rom_data(boot_rom).build().unwrap();
+        let events = Arc::new(Mutex::new(vec![]));
+        core.subscribe({
+            let events = events.clone();
+            move |event: &Event| events.lock().unwrap().push(*event)
+        });
+        assert!(core.is_running_boot_rom());
+
+        core.run_frame(JoypadInput::empty());
+        core.run_frame(JoypadInput::empty());
+        assert!(!core.is_running_boot_rom());
+        assert_eq!(events.lock().unwrap().iter().filter(|&&e| e == Event::BootRomFinished).count(), 1);
+
+        let core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+        assert!(!core.is_running_boot_rom());
+    }
+
+    #[test]
+    fn subscribers_are_told_about_events() {
+        // An MBC1+RAM+BATTERY ROM that writes cartridge RAM, sends a byte over the link port and
+        // turns the LCD off and on again before spinning at 0x117
+        #[rustfmt::skip]
+        let code = [
+            0x3e, 0x0a, 0xea, 0x00, 0x00, // ld a,$0a; ld [$0000],a
+            0x3e, 0x42, 0xea, 0x00, 0xa0, // ld a,$42; ld [$a000],a
+            0xe0, 0x01, 0x3e, 0x81, 0xe0, 0x02, // ldh [$01],a; ld a,$81; ldh [$02],a
+            0xaf, 0xe0, 0x40, 0x3e, 0x91, 0xe0, 0x40, // xor a; ldh [$40],a; ld a,$91; ldh [$40],a
+        ];
+        let mut rom = looping_rom(&code);
+        rom[0x147] = 0x03;
+        rom[0x149] = 0x02;
+
+        let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+        let events = Arc::new(Mutex::new(vec![]));
+        let id = core.subscribe({
+            let events = events.clone();
+            move |event: &Event| events.lock().unwrap().push(*event)
+        });
+        core.add_breakpoint(0x117);
+
+        core.run_frame(JoypadInput::empty());
+        let seen = events.lock().unwrap().clone();
+        assert_eq!(seen[..5], [
+            Event::BatteryRamDirty,
+            Event::SerialByte(0x42),
+            Event::LcdToggled(false),
+            Event::LcdToggled(true),
+            Event::BreakpointHit(0x117),
+        ]);
+        assert_eq!(seen.iter().filter(|&&e| e == Event::FrameReady).count(), 1);
+        assert_eq!(seen.iter().filter(|&&e| e == Event::BatteryRamDirty).count(), 1);
+
+        // Only once saved is a change news again
+        events.lock().unwrap().clear();
+        core.write_byte(0xa000, 0x43);
+        core.run_frame(JoypadInput::empty());
+        assert!(!events.lock().unwrap().contains(&Event::BatteryRamDirty));
+        assert!(core.take_battery_ram_written());
+        core.write_byte(0xa000, 0x44);
+        core.run_frame(JoypadInput::empty());
+        assert!(events.lock().unwrap().contains(&Event::BatteryRamDirty));
+
+        events.lock().unwrap().clear();
+        core.unsubscribe(id);
+        core.run_frame(JoypadInput::empty());
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn quirks_follow_the_model_unless_chosen() {
+        let rom = looping_rom(&[]);
+        let stat_write_raises_interrupt = |core: &mut Core| {
+            core.write_byte(0xff0f, 0x00);
+            core.write_byte(0xff41, 0x00);
+            core.read_byte(0xff0f) & 0x02 != 0
+        };
+
+        let mut dmg = CoreBuilder::new().game_rom_data(rom.clone()).build().unwrap();
+        assert!(stat_write_raises_interrupt(&mut dmg));
+        let mut cgb = CoreBuilder::new().game_rom_data(rom.clone()).model(Model::Cgb).build().unwrap();
+        assert!(!stat_write_raises_interrupt(&mut cgb));
+
+        let dmg0 = Quirks { stat_write_bug: false, dmg0_registers: true };
+        let mut core = CoreBuilder::new().game_rom_data(rom.clone()).quirks(dmg0).build().unwrap();
+        assert!(!stat_write_raises_interrupt(&mut core));
+        let registers = core.registers();
+        assert_eq!([registers.a, registers.f, registers.b, registers.e, registers.h, registers.l], [0x01, 0x00, 0xff, 0xc1, 0x84, 0x03]);
+
+        core.reset();
+        assert_eq!(core.quirks(), dmg0);
+        assert_eq!(core.registers().b, 0xff);
+        assert!(!stat_write_raises_interrupt(&mut core));
+    }
+
+    #[test]
+    fn cartridges_can_be_inserted_and_ejected() {
+        let rom = looping_rom(&[]);
+        let mut core = CoreBuilder::new().build().unwrap();
+        assert!(!core.has_cartridge());
+        assert_eq!(core.read_byte(0x0134), 0xff);
+        assert_eq!(core.read_byte(0xa000), 0xff);
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+        core.set_volume(0.5);
+        core.insert_cartridge(rom.clone()).unwrap();
+        assert!(core.has_cartridge());
+        assert_eq!(core.rom_hash(), crc32fast::hash(&rom));
+        assert_eq!(core.registers().pc, 0x0100);
+        assert_eq!(core.cycles_elapsed(), 0);
+        assert_eq!(core.volume(), 0.5);
+
+        let mut unsupported = rom.clone();
+        unsupported[0x147] = 0xfc;
+        assert!(matches!(core.insert_cartridge(unsupported), Err(Error::UnsupportedMapper(0xfc))));
+        assert_eq!(core.rom_hash(), crc32fast::hash(&rom));
+
+        core.eject_cartridge();
+        assert!(!core.has_cartridge());
+        assert_eq!(core.read_byte(0x0100), 0xff);
+
+        // The boot ROM starts over with the new cartridge
+        let mut core = CoreBuilder::new().boot_rom_data(vec![0; 0x100]).build().unwrap();
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+        core.insert_cartridge(rom).unwrap();
+        assert_eq!(core.registers().pc, 0x0000);
+    }
+
+    #[test]
+    fn power_on_ram_follows_the_policy() {
+        let rom = looping_rom(&[]);
+        let build = |ram| CoreBuilder::new().game_rom_data(rom.clone()).power_on_ram(ram).build().unwrap();
+
+        assert!(build(PowerOnRam::Zeroed).memory(MemoryRegion::Wram).iter().all(|&b| b == 0));
+        let filled = build(PowerOnRam::Fill(0xff));
+        assert!(filled.memory(MemoryRegion::Wram).iter().all(|&b| b == 0xff));
+        assert!(filled.memory(MemoryRegion::Hram).iter().all(|&b| b == 0xff));
+        // Without a boot ROM to clear it, VRAM is as the boot ROM leaves it
+        assert!(filled.memory(MemoryRegion::Vram).iter().all(|&b| b == 0));
+
+        let mut random = build(PowerOnRam::Random(42));
+        let wram = random.memory(MemoryRegion::Wram).to_vec();
+        assert!(wram.iter().any(|&b| b != 0));
+        assert_eq!(build(PowerOnRam::Random(42)).memory(MemoryRegion::Wram), wram);
+        assert_ne!(build(PowerOnRam::Random(43)).memory(MemoryRegion::Wram), wram);
+
+        random.write_byte(0xc000, !wram[0]);
+        random.reset();
+        assert_eq!(random.memory(MemoryRegion::Wram), wram);
+
+        let mut state = vec![];
+        random.save_state_to(&mut state).unwrap();
+        let mut loaded = CoreBuilder::new().game_rom_data(rom.clone()).build().unwrap();
+        loaded.load_state_from(state.as_slice()).unwrap();
+        assert_eq!(loaded.power_on_ram(), PowerOnRam::Random(42));
+    }
+
+    #[test]
+    fn stat_write_bug_needs_the_stat_line_to_rise() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        core.write_byte(0xff41, 0x00);
+        core.write_byte(0xff0f, 0x00);
+
+        // Starting out in HBlank with nothing enabled, the write raises the line
+        core.write_byte(0xff41, 0x08);
+        assert_eq!(core.read_byte(0xff0f) & 0x02, 0x02);
+
+        // With HBlank already enabled it's high before the write, so nothing happens
+        core.write_byte(0xff0f, 0x00);
+        core.write_byte(0xff41, 0x00);
+        assert_eq!(core.read_byte(0xff0f) & 0x02, 0x00);
+    }
+
+    #[test]
+    fn scanline_callback_sees_every_line_of_a_frame() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        // The first frame after boot starts past line 0
+        core.run_frame(JoypadInput::empty());
+        let lines = Arc::new(Mutex::new(vec![]));
+        core.on_scanline({
+            let lines = lines.clone();
+            move |y, line| lines.lock().unwrap().push((y, *line))
+        });
+
+        core.run_frame(JoypadInput::empty());
+        let seen = mem::take(&mut *lines.lock().unwrap());
+        assert_eq!(seen.iter().map(|&(y, _)| y).collect::<Vec<_>>(), (0..SCREEN_HEIGHT as u8).collect::<Vec<_>>());
+        for (y, line) in &seen {
+            assert_eq!(line[..], core.frame()[*y as usize * SCREEN_WIDTH..][..SCREEN_WIDTH]);
+        }
+
+        core.clear_scanline_callback();
+        core.run_frame(JoypadInput::empty());
+        assert!(lines.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn execution_diagnostics_flag_confusing_code() {
+        // A 64K MBC1+RAM ROM that runs a routine in WRAM and then overwrites it, runs one in
+        // cartridge RAM, and switches away from bank 1 while running from it
+        let mut rom = vec![0; 0x10000];
+        rom[0x100..0x103].copy_from_slice(&[0xc3, 0x50, 0x01]);
+        #[rustfmt::skip]
+        let code = [
+            0x21, 0x00, 0xc0, 0x36, 0xc9, 0xcd, 0x00, 0xc0, // ld hl,$c000; ld [hl],$c9; call $c000
+            0x36, 0xc9, // ld [hl],$c9
+            0x3e, 0x0a, 0xea, 0x00, 0x00, 0x3e, 0xc9, 0xea, 0x00, 0xa0, // enable RAM; ld [$a000],$c9
+            0xcd, 0x00, 0xa0, 0xc3, 0x00, 0x40, // call $a000; jp $4000
+        ];
+        rom[0x150..0x150 + code.len()].copy_from_slice(&code);
+        rom[0x4000..0x4005].copy_from_slice(&[0x3e, 0x02, 0xea, 0x00, 0x20]);
+        rom[0x8005..0x8007].copy_from_slice(&[0x18, 0xfe]);
+        rom[0x147] = 0x03;
+        rom[0x148] = 0x01;
+        rom[0x149] = 0x02;
+
+        let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+        assert!(core.take_execution_warnings().is_empty());
+        core.enable_execution_diagnostics();
+        core.run_frame(JoypadInput::empty());
+        core.run_frame(JoypadInput::empty());
+
+        assert_eq!(core.take_execution_warnings(), [
+            ExecutionWarning::SelfModifyingCode { pc: 0x158, addr: 0xc000 },
+            ExecutionWarning::ExternalRamExecution { pc: 0xa000 },
+            ExecutionWarning::BankSwitchUnderPc { pc: 0x4002, from: 1, to: 2 },
+        ]);
+        assert!(core.take_execution_warnings().is_empty());
+    }
+
+    #[test]
+    fn developer_mode_catches_lcd_misuse() {
+        // Writes VRAM and OAM and bumps DE in OAM all through the visible lines, then turns the
+        // LCD off on line 16 and reads LY
+        let mut rom = looping_rom(&[0xc3, 0x50, 0x01]);
+        #[rustfmt::skip]
+        let code = [
+            0x21, 0x00, 0x80, 0x11, 0x00, 0xfe, // ld hl,$8000; ld de,$fe00
+            0x77, 0x12, 0x13, 0x1b, // ld [hl],a; ld [de],a; inc de; dec de
+            0xf0, 0x44, 0xfe, 0x90, 0x20, 0xf6, // loop until LY is 144
+            0xf0, 0x44, 0xfe, 0x10, 0x20, 0xfa, // wait for LY 16
+            0xaf, 0xe0, 0x40, 0xf0, 0x44, 0x18, 0xfe, // xor a; ldh [$40],a; ldh a,[$44]
+        ];
+        rom[0x150..0x150 + code.len()].copy_from_slice(&code);
+
+        let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+        core.enable_developer_mode();
+        core.run_frame(JoypadInput::empty());
+        core.run_frame(JoypadInput::empty());
+
+        let warnings = core.take_developer_warnings();
+        assert_eq!(warnings.len(), 6, "{:?}", warnings);
+        assert!(warnings.contains(&DeveloperWarning::VramWriteWhileDrawing { pc: 0x156, addr: 0x8000 }));
+        assert!(warnings.contains(&DeveloperWarning::OamWriteWhileBusy { pc: 0x157, addr: 0xfe00 }));
+        assert!(warnings.contains(&DeveloperWarning::OamBug { pc: 0x158 }));
+        assert!(warnings.contains(&DeveloperWarning::OamBug { pc: 0x159 }));
+        assert!(warnings.contains(&DeveloperWarning::LcdOffOutsideVblank { pc: 0x167, ly: 16 }));
+        assert!(warnings.contains(&DeveloperWarning::LyReadWhileLcdOff { pc: 0x169 }));
+        assert!(core.take_developer_warnings().is_empty());
+    }
+
+    #[test]
+    fn diagnostics_collect_what_isnt_emulated() {
+        // A 32K MBC3 ROM that touches an unused IO address, writes to sound registers with the APU
+        // off and latches the clock, twice over
+        let mut rom = looping_rom(&[0xc3, 0x50, 0x01]);
+        #[rustfmt::skip]
+        let code = [
+            0xf0, 0x03, 0xaf, 0xe0, 0x03, // ldh a,[$ff03]; xor a; ldh [$ff03],a
+            0xe0, 0x26, 0xe0, 0x12, // ldh [NR52],a; ldh [NR12],a
+            0xea, 0x00, 0x60, // ld [$6000],a
+            0x18, 0xf2, // jr to the start
+        ];
+        rom[0x150..0x150 + code.len()].copy_from_slice(&code);
+        rom[0x147] = 0x11;
+
+        let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+        core.read_byte(0xff03);
+        core.run_frame(JoypadInput::empty());
+
+        assert_eq!(core.take_diagnostics(), [
+            Diagnostic::UnknownIoRead { addr: 0xff03 },
+            Diagnostic::UnknownIoWrite { addr: 0xff03, value: 0 },
+            Diagnostic::ApuWriteWhileOff { addr: 0xff12, value: 0 },
+            Diagnostic::UnsupportedMbcWrite { addr: 0x6000, value: 0 },
+        ]);
+        core.run_frame(JoypadInput::empty());
+        assert!(core.take_diagnostics().is_empty());
+    }
+
+    #[test]
+    fn observations_see_color_numbers_before_the_palette() {
+        let mut core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        // Tile 0, shown all over the background, in color 3, which BGP maps to white
+        for addr in 0x8000..0x8010 {
+            core.write_byte(addr, 0xff);
+        }
+        core.write_byte(0xff47, 0x00);
+        core.set_memory_taps([0xff47, 0x8000]);
+        // The first frame starts after line 0 is drawn
+        core.run_frame(JoypadInput::empty());
+        core.run_frame(JoypadInput::empty());
+
+        let observation = core.observe();
+        assert_eq!(observation.frame, core.frames_rendered());
+        assert!(observation.pixels.iter().all(|&color| color == 3));
+        assert!(core.frame().iter().all(|&pixel| pixel == core.frame()[0]));
+        assert_eq!(observation.taps, [0x00, 0xff]);
+
+        core.reset();
+        assert_eq!(core.memory_taps(), [0xff47, 0x8000]);
+    }
+
+    #[test]
+    fn running_ahead_shows_a_later_frame_and_rolls_back() {
+        // A 32K ROM that scrolls the background down a line every VBlank
+        let mut rom = looping_rom(&[0xc3, 0x50, 0x01]);
+        rom[0x40] = 0xd9;
+        #[rustfmt::skip]
+        let code = [
+            0x3e, 0x01, 0xe0, 0xff, 0xfb, // ld a,1; ldh [IE],a; ei
+            0x76, 0x21, 0x42, 0xff, 0x34, 0x18, 0xf9, // halt; ld hl,SCY; inc [hl]; jr to the halt
+        ];
+        rom[0x150..0x150 + code.len()].copy_from_slice(&code);
+
+        let mut cores = [(); 2].map(|_| {
+            let mut core = CoreBuilder::new().game_rom_data(rom.clone()).deterministic(true).build().unwrap();
+            // Tile 0 black at the top half, in stripes which scrolling moves
+            for addr in 0x8000..0x8008 {
+                core.write_byte(addr, 0xff);
+            }
+            core
+        });
+        let [ahead, plain] = &mut cores;
+        let frames_ready = Arc::new(Mutex::new(0));
+        let counter = frames_ready.clone();
+        ahead.subscribe(move |event| {
+            if let Event::FrameReady = event {
+                *counter.lock().unwrap() += 1;
+            }
+        });
+
+        plain.run_frame(JoypadInput::empty());
+        plain.run_frame(JoypadInput::empty());
+        for _ in 0..3 {
+            let predicted = ahead.run_frame_ahead(JoypadInput::empty(), 2).pixels.clone();
+            assert_eq!(predicted, plain.run_frame(JoypadInput::empty()).pixels);
+            assert_ne!(predicted[..], ahead.frame()[..]);
+        }
+
+        assert_eq!(ahead.frames_rendered(), 3);
+        assert_eq!(*frames_ready.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn illegal_opcodes_lock_up_with_a_crash_report() {
+        let rom = looping_rom(&[0x3c, 0x3c, 0xd3]); // inc a; inc a; illegal
+        let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+
+        core.run_frame(JoypadInput::empty());
+        core.run_frame(JoypadInput::empty());
+
+        let report = core.take_crash().expect("no crash reported");
+        assert_eq!(report.reason, CrashReason::IllegalOpcode(0xd3));
+        assert_eq!(report.registers.pc, 0x102);
+        assert_eq!(report.opcode, 0xd3);
+        assert_eq!(report.rom_bank, 1);
+        assert_eq!(report.trace.len(), 3);
+        assert_eq!(report.rom_hash, core.rom_hash());
+        assert!(core.take_crash().is_none());
+
+        // The PPU carries on while the CPU stays put
+        assert_eq!(core.frames_rendered(), 2);
+        assert_eq!(core.registers().pc, 0x103);
+
+        core.reset();
+        assert!(core.take_crash().is_none());
+        assert_eq!(core.registers().pc, 0x100);
+    }
+}
@@ -0,0 +1,71 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Set in `Coverage::cdl` for ROM bytes executed as part of an instruction, the code flag of the
+/// code/data log (CDL) files written by Mesen and FCEUX.
+pub const CDL_CODE: u8 = 0x01;
+
+const BANK_SIZE: usize = 0x4000;
+
+/// Which ROM bytes the CPU has executed, tracked by offset into the ROM so each bank is told
+/// apart. Instructions run from the boot ROM, RAM or HRAM aren't counted.
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    flags: Vec<u8>,
+}
+
+impl Coverage {
+    pub fn new(rom_len: usize) -> Self {
+        Self { flags: vec![0; rom_len] }
+    }
+
+    pub(crate) fn mark_executed(&mut self, rom_offset: usize, len: usize) {
+        let end = (rom_offset + len).min(self.flags.len());
+        if let Some(flags) = self.flags.get_mut(rom_offset..end) {
+            flags.iter_mut().for_each(|flag| *flag |= CDL_CODE);
+        }
+    }
+
+    /// Whether the byte at `addr` in ROM bank `bank` has been executed. `addr` is taken within the
+    /// bank, so 0x4150 in bank 3 and 0x0150 in bank 3 are the same byte.
+    pub fn is_executed(&self, bank: usize, addr: u16) -> bool {
+        self.flags.get(bank * BANK_SIZE + (addr as usize % BANK_SIZE)).is_some_and(|flag| flag & CDL_CODE != 0)
+    }
+
+    /// Number of executed bytes in each ROM bank.
+    pub fn executed_per_bank(&self) -> Vec<usize> {
+        self.flags.chunks(BANK_SIZE).map(|bank| bank.iter().filter(|&&flag| flag & CDL_CODE != 0).count()).collect()
+    }
+
+    /// One flag byte per ROM byte, in the layout of a CDL file.
+    pub fn cdl(&self) -> &[u8] {
+        &self.flags
+    }
+
+    /// Writes `cdl` to `path`, for loading into a disassembler.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, &self.flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::coverage::{Coverage, CDL_CODE};
+
+    #[test]
+    fn tracks_executed_bytes_per_bank() {
+        let mut coverage = Coverage::new(0x10000);
+        coverage.mark_executed(0x0100, 1);
+        coverage.mark_executed(0xc150, 3);
+        coverage.mark_executed(0xfffe, 3);
+
+        assert!(coverage.is_executed(0, 0x0100));
+        assert!(coverage.is_executed(3, 0x4152));
+        assert!(coverage.is_executed(3, 0x0150));
+        assert!(!coverage.is_executed(1, 0x4150));
+        assert!(!coverage.is_executed(4, 0x4000));
+        assert_eq!(coverage.executed_per_bank(), [1, 0, 0, 5]);
+        assert_eq!(coverage.cdl()[0xc151], CDL_CODE);
+    }
+}
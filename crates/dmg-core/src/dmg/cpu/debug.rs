@@ -0,0 +1,45 @@
+use crate::dmg::annotations::io_register_name;
+use crate::dmg::mem::MemoryBus;
+use crate::dmg::symbols::Symbols;
+
+use super::opcodes::{decode, OpCode, OPCODES};
+
+/// Length in bytes of the instruction starting with `op_code`, operands and CB prefix included.
+pub fn instruction_len(op_code: u8) -> u16 {
+    OPCODES[op_code as usize].len
+}
+
+/// Disassembles the instruction at `addr`, returning its text and length in bytes. Jump, call and
+/// memory addresses found in `symbols` are shown as their labels, and hardware registers without
+/// one by their names.
+pub fn disassemble(bus: &MemoryBus, addr: u16, symbols: Option<&Symbols>) -> (String, u16) {
+    let OpCode { mnemonic, len, .. } = *decode(bus, addr);
+    let n = bus.read_byte(addr.wrapping_add(1));
+    let nn = u16::from_le_bytes([n, bus.read_byte(addr.wrapping_add(2))]);
+    let label = |target: u16| {
+        symbols
+            .and_then(|symbols| symbols.get(bus.bank_of(target), target))
+            .or_else(|| io_register_name(target))
+    };
+
+    let text = if mnemonic.contains("16") {
+        let operand = format!("${:04X}", nn);
+        let address = label(nn).unwrap_or(&operand);
+        mnemonic.replace("d16", &operand).replace("a16", address)
+    } else if mnemonic.starts_with("JR") {
+        let target = addr.wrapping_add(2).wrapping_add(n as i8 as u16);
+        let operand = format!("${:04X}", target);
+        mnemonic.replace("r8", label(target).unwrap_or(&operand))
+    } else if mnemonic.contains("r8") {
+        mnemonic.replace("r8", &format!("{}", n as i8))
+    } else if mnemonic.contains("a8") {
+        let operand = format!("$FF{:02X}", n);
+        mnemonic.replace("a8", label(0xff00 | n as u16).unwrap_or(&operand))
+    } else if mnemonic.contains("d8") {
+        mnemonic.replace("d8", &format!("${:02X}", n))
+    } else {
+        mnemonic.to_string()
+    };
+
+    (text, len)
+}
@@ -1,13 +1,23 @@
+use std::mem;
+use std::sync::Arc;
+use std::thread;
+
 use bit_field::BitField;
 use bitflags::bitflags;
+use log::error;
 use serde::{Serialize, Deserialize};
 
+use super::debug::{DoctorLog, Registers, TraceBuffer, TraceEntry};
 use super::mem::MemoryBus;
+use super::model::{Model, Quirks};
+use super::symbols::Symbols;
 
 mod step;
 mod debug;
+mod opcodes;
 
-use debug::{lookup_cb_prefix_op_code, lookup_op_code};
+pub use debug::{disassemble, instruction_len};
+use opcodes::decode;
 
 bitflags! {
     #[derive(Serialize, Deserialize)]
@@ -19,7 +29,7 @@ bitflags! {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProcessingUnit {
     a: u8,
     b: u8,
@@ -34,26 +44,53 @@ pub struct ProcessingUnit {
 
     halted: bool,
     interrupt_master_enable: bool,
-    enable_debugging: bool,
+    // Stuck after an illegal opcode until powered off
+    #[serde(default)]
+    locked_up: bool,
+    #[serde(skip)]
+    doctor_log: DoctorLog,
+    #[serde(skip)]
+    trace: TraceBuffer,
+    #[serde(skip)]
+    symbols: Option<Arc<Symbols>>,
+}
+
+// Holds the instruction trace while an instruction runs, logging it if the instruction panics
+struct CrashTrace(TraceBuffer, Option<Arc<Symbols>>);
+
+impl Drop for CrashTrace {
+    fn drop(&mut self) {
+        if thread::panicking() && !self.0.is_empty() {
+            let mut trace = String::from("Last executed instructions, oldest first:");
+            for entry in self.0.iter() {
+                match self.1.as_ref().and_then(|symbols| symbols.describe(entry.bank, entry.registers.pc)) {
+                    Some(label) => trace.push_str(&format!("\n  {}  ; {}", entry, label)),
+                    None => trace.push_str(&format!("\n  {}", entry)),
+                }
+            }
+            error!(target: "cpu", "{}", trace);
+        }
+    }
 }
 
 impl ProcessingUnit {
-    pub fn skip_boot_rom(&mut self) {
-        self.a = 0x01;
-        self.f = Flags::CARRY | Flags::H | Flags::ZERO;
-        assert_eq!(self.f.bits, 0xb0);
-        self.b = 0x00;
-        self.c = 0x13;
-        self.d = 0x00;
-        self.e = 0xd8;
-        self.h = 0x01;
-        self.l = 0x4d;
+    pub fn skip_boot_rom(&mut self, model: Model, quirks: Quirks, cgb_mode: bool) {
+        let [a, f, b, c, d, e, h, l] = model.post_boot_registers(cgb_mode, quirks);
+        self.a = a;
+        self.f = Flags::from_bits_truncate(f);
+        self.b = b;
+        self.c = c;
+        self.d = d;
+        self.e = e;
+        self.h = h;
+        self.l = l;
         self.sp = 0xFFFE;
         self.pc = 0x0100;
     }
-    pub fn initialize_gameboy_doctor(&mut self) {
-        self.enable_debugging = true;
-        self.skip_boot_rom();
+    pub fn initialize_gameboy_doctor(&mut self, log: DoctorLog) {
+        self.doctor_log = log;
+        // gameboy-doctor logs are recorded on a DMG
+        self.skip_boot_rom(Model::Dmg, Model::Dmg.quirks(), false);
     }
 
     pub fn new() -> ProcessingUnit {
@@ -70,7 +107,58 @@ impl ProcessingUnit {
             sp: 0xFFFE,
             halted: false,
             interrupt_master_enable: false,
-            enable_debugging: false,
+            locked_up: false,
+            doctor_log: DoctorLog::default(),
+            trace: TraceBuffer::default(),
+            symbols: None,
+        }
+    }
+
+    pub fn next(&mut self, bus: &mut MemoryBus) -> u32 {
+        let mut trace = CrashTrace(mem::take(&mut self.trace), self.symbols.take());
+        let cycles = self.execute_next(bus, &mut trace.0);
+        self.trace = mem::take(&mut trace.0);
+        self.symbols = trace.1.take();
+        cycles
+    }
+
+    /// Labels shown in the trace printed on panics.
+    pub fn set_symbols(&mut self, symbols: Option<Arc<Symbols>>) {
+        self.symbols = symbols;
+    }
+
+    pub fn symbols(&self) -> Option<&Arc<Symbols>> {
+        self.symbols.as_ref()
+    }
+
+    /// The last executed instructions, oldest first.
+    pub fn trace(&self) -> Vec<TraceEntry> {
+        self.trace.iter().copied().collect()
+    }
+
+    fn trace_entry(&self, pc: u16, bus: &MemoryBus) -> TraceEntry {
+        TraceEntry {
+            registers: self.registers(),
+            bank: bus.bank_of(pc),
+            opcode: self.read_byte(bus, pc),
+            mnemonic: decode(bus, pc).mnemonic,
+        }
+    }
+
+    pub fn registers(&self) -> Registers {
+        Registers {
+            a: self.a,
+            f: self.f.bits,
+            b: self.b,
+            c: self.c,
+            d: self.d,
+            e: self.e,
+            h: self.h,
+            l: self.l,
+            sp: self.sp,
+            pc: self.pc,
+            ime: self.interrupt_master_enable,
+            halted: self.halted,
         }
     }
 
@@ -139,7 +227,7 @@ impl ProcessingUnit {
     }
 
     fn write_byte(&mut self, bus: &mut MemoryBus, addr: u16, value: u8) {
-        bus.write_byte(addr, value);
+        bus.cpu_write(addr, value);
     }
 
     fn daa(&mut self) {
@@ -161,20 +249,14 @@ impl ProcessingUnit {
     }
 
     fn read_byte(&self, bus: &MemoryBus, addr: u16) -> u8 {
-        bus.read_byte(addr)
+        bus.cpu_read(addr)
     }
 
-    pub fn debug_print(&self, pc: u16, bus: &MemoryBus) {
-        if self.enable_debugging {
-            println!("A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}", self.a, self.f, self.b, self.c, self.d, self.e, self.h, self.l, self.sp, pc, self.read_byte(bus, pc), self.read_byte(bus, pc + 1), self.read_byte(bus, pc + 2), self.read_byte(bus, pc + 3));
-        }
-    }
-
-    fn lookup_op_code_for_pc(&self, bus: &MemoryBus, pc: u16) -> (&str, u32) {
-        if self.read_byte(bus, pc) != 0xCB {
-            lookup_op_code(self.read_byte(bus, pc))
-        } else {
-            lookup_cb_prefix_op_code(self.read_byte(bus, pc + 1))
+    pub fn debug_print(&mut self, pc: u16, bus: &MemoryBus) {
+        // gameboy-doctor diffs the log line by line, so this bypasses the logger
+        if self.doctor_log.is_enabled() {
+            let line = format!("A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}", self.a, self.f, self.b, self.c, self.d, self.e, self.h, self.l, self.sp, pc, self.read_byte(bus, pc), self.read_byte(bus, pc + 1), self.read_byte(bus, pc + 2), self.read_byte(bus, pc + 3));
+            self.doctor_log.write_line(&line);
         }
     }
 
@@ -223,6 +305,7 @@ impl ProcessingUnit {
                     let triggered = interrupt_flags.highest_prio_bit();
 
                     bus.ppu.interrupt_flag.remove(triggered);
+                    bus.stats.interrupts[triggered.bits().trailing_zeros() as usize] += 1;
 
                     self.call(addr, bus);
 
@@ -476,6 +559,8 @@ impl ProcessingUnit {
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use crate::dmg::cpu::{Flags, ProcessingUnit};
     use crate::dmg::mem::MemoryBus;
 
@@ -702,4 +787,99 @@ mod tests {
 
         assert!(!cpu.f.contains(Flags::ZERO));
     }
+
+    // ALU flags, compared against a reference that works on wider integers
+
+    // (result, Z, N, H, C)
+    type AluResult = (u8, bool, bool, bool, bool);
+
+    fn cpu_with(a: u8, flags: u8) -> ProcessingUnit {
+        let mut cpu = ProcessingUnit::new();
+        cpu.a = a;
+        cpu.f = Flags::from_bits_truncate(flags);
+        cpu
+    }
+
+    fn alu_result(cpu: &ProcessingUnit) -> AluResult {
+        (cpu.a, cpu.f.contains(Flags::ZERO), cpu.f.contains(Flags::N), cpu.f.contains(Flags::H), cpu.f.contains(Flags::CARRY))
+    }
+
+    fn reference_add(a: u8, n: u8, carry: bool) -> AluResult {
+        let sum = a as u32 + n as u32 + carry as u32;
+        let half_sum = (a & 0xf) as u32 + (n & 0xf) as u32 + carry as u32;
+        (sum as u8, sum as u8 == 0, false, half_sum > 0xf, sum > 0xff)
+    }
+
+    fn reference_sub(a: u8, n: u8, carry: bool) -> AluResult {
+        let difference = a as i32 - n as i32 - carry as i32;
+        let half_difference = (a & 0xf) as i32 - (n & 0xf) as i32 - carry as i32;
+        (difference as u8, difference as u8 == 0, true, half_difference < 0, difference < 0)
+    }
+
+    fn reference_daa(a: u8, n: bool, h: bool, c: bool) -> AluResult {
+        let mut result = a as i32;
+        let mut carry = c;
+        if n {
+            if c {
+                result -= 0x60;
+            }
+            if h {
+                result -= 0x06;
+            }
+        } else {
+            if c || a > 0x99 {
+                result += 0x60;
+                carry = true;
+            }
+            if h || a & 0x0f > 0x09 {
+                result += 0x06;
+            }
+        }
+        (result as u8, result as u8 == 0, n, false, carry)
+    }
+
+    proptest! {
+        #[test]
+        fn add_a_matches_reference(a: u8, n: u8, flags: u8) {
+            let mut cpu = cpu_with(a, flags);
+            cpu.add_a(n);
+            prop_assert_eq!(alu_result(&cpu), reference_add(a, n, false));
+        }
+
+        #[test]
+        fn adc_matches_reference(a: u8, n: u8, flags: u8) {
+            let mut cpu = cpu_with(a, flags);
+            cpu.adc(n);
+            prop_assert_eq!(alu_result(&cpu), reference_add(a, n, flags & 0x10 != 0));
+        }
+
+        #[test]
+        fn sub_a_matches_reference(a: u8, n: u8, flags: u8) {
+            let mut cpu = cpu_with(a, flags);
+            cpu.sub_a(n);
+            prop_assert_eq!(alu_result(&cpu), reference_sub(a, n, false));
+        }
+
+        #[test]
+        fn sbc_matches_reference(a: u8, n: u8, flags: u8) {
+            let mut cpu = cpu_with(a, flags);
+            cpu.sbc(n);
+            prop_assert_eq!(alu_result(&cpu), reference_sub(a, n, flags & 0x10 != 0));
+        }
+
+        #[test]
+        fn compare_matches_reference(a: u8, n: u8, flags: u8) {
+            let mut cpu = cpu_with(a, flags);
+            cpu.compare_a_with(n);
+            let (_, z, n, h, c) = reference_sub(a, n, false);
+            prop_assert_eq!(alu_result(&cpu), (a, z, n, h, c));
+        }
+
+        #[test]
+        fn daa_matches_reference(a: u8, flags: u8) {
+            let mut cpu = cpu_with(a, flags);
+            cpu.daa();
+            prop_assert_eq!(alu_result(&cpu), reference_daa(a, flags & 0x40 != 0, flags & 0x20 != 0, flags & 0x10 != 0));
+        }
+    }
 }
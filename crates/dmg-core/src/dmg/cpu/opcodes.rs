@@ -0,0 +1,622 @@
+use bit_field::BitField;
+
+use crate::dmg::mem::MemoryBus;
+
+use super::{Flags, ProcessingUnit};
+
+/// Runs an instruction whose opcode has been read, returning whether a conditional jump, call or
+/// return was taken.
+pub(super) type Handler = fn(&mut ProcessingUnit, &mut MemoryBus) -> bool;
+
+/// Everything known about an opcode ahead of running it: how it's disassembled, its length, its
+/// timing and the code that runs it.
+#[derive(Clone, Copy)]
+pub struct OpCode {
+    /// Operands are written as in the Pan Docs opcode tables: d8/d16 for immediates, a8/a16 for
+    /// addresses and r8 for relative jumps.
+    pub mnemonic: &'static str,
+    /// Length in bytes, operands and CB prefix included.
+    pub len: u16,
+    /// Clock cycles taken, for conditional instructions when the condition doesn't hold.
+    pub cycles: u32,
+    /// Clock cycles taken by a conditional instruction when the condition holds.
+    pub branch_cycles: u32,
+    pub(super) execute: Handler,
+}
+
+macro_rules! op {
+    ($mnemonic:literal, $len:literal, $cycles:literal, |$cpu:pat_param, $bus:pat_param| $execute:expr) => {
+        OpCode {
+            mnemonic: $mnemonic,
+            len: $len,
+            cycles: $cycles,
+            branch_cycles: $cycles,
+            execute: |$cpu: &mut ProcessingUnit, $bus: &mut MemoryBus| {
+                $execute;
+                false
+            },
+        }
+    };
+    ($mnemonic:literal, $len:literal, $cycles:literal / $branch_cycles:literal, |$cpu:pat_param, $bus:pat_param| $execute:expr) => {
+        OpCode {
+            mnemonic: $mnemonic,
+            len: $len,
+            cycles: $cycles,
+            branch_cycles: $branch_cycles,
+            execute: |$cpu: &mut ProcessingUnit, $bus: &mut MemoryBus| $execute,
+        }
+    };
+}
+
+/// The opcode at `addr`, looked up in `CB_OPCODES` by the following byte for the CB prefix.
+pub fn decode(bus: &MemoryBus, addr: u16) -> &'static OpCode {
+    match bus.read_byte(addr) {
+        0xCB => &CB_OPCODES[bus.read_byte(addr.wrapping_add(1)) as usize],
+        op => &OPCODES[op as usize],
+    }
+}
+
+// `decode` looks up CB prefixed opcodes with the byte after the prefix, so this never runs
+fn prefix(_: &mut ProcessingUnit, _: &mut MemoryBus) -> bool {
+    unreachable!("CB prefix executed without the byte after it")
+}
+
+#[rustfmt::skip]
+pub static OPCODES: [OpCode; 256] = [
+    /* 00 */ op!("NOP", 1, 4, |_, _| {}),
+    /* 01 */ op!("LD BC,d16", 3, 12, |cpu, bus| { let nn = cpu.get_immediate_u16(bus); cpu.set_bc(nn) }),
+    /* 02 */ op!("LD BC,A", 1, 8, |cpu, bus| cpu.write_byte(bus, cpu.get_bc(), cpu.a)),
+    /* 03 */ op!("INC BC", 1, 8, |cpu, _| cpu.set_bc(cpu.get_bc().wrapping_add(1))),
+    /* 04 */ op!("INC B", 1, 4, |cpu, _| cpu.b = cpu.inc_8(cpu.b)),
+    /* 05 */ op!("DEC B", 1, 4, |cpu, _| cpu.b = cpu.dec_8(cpu.b)),
+    /* 06 */ op!("LD B,d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.ld_b(n) }),
+    /* 07 */ op!("RLCA", 1, 4, |cpu, _| { cpu.a = cpu.rlc_8(cpu.a); cpu.f.remove(Flags::ZERO) }),
+    /* 08 */ op!("LD a16,SP", 3, 20, |cpu, bus| cpu.ld_a16_sp(bus)),
+    /* 09 */ op!("ADD HL,BC", 1, 8, |cpu, _| cpu.add_hl_16(cpu.get_bc())),
+    /* 0A */ op!("LD A,BC", 1, 8, |cpu, bus| cpu.ld_a(cpu.read_byte(bus, cpu.get_bc()))),
+    /* 0B */ op!("DEC BC", 1, 8, |cpu, _| cpu.set_bc(cpu.get_bc().wrapping_sub(1))),
+    /* 0C */ op!("INC C", 1, 4, |cpu, _| cpu.c = cpu.inc_8(cpu.c)),
+    /* 0D */ op!("DEC C", 1, 4, |cpu, _| cpu.c = cpu.dec_8(cpu.c)),
+    /* 0E */ op!("LD C,d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.ld_c(n) }),
+    /* 0F */ op!("RRCA", 1, 4, |cpu, _| { cpu.a = cpu.rrc_8(cpu.a); cpu.f.remove(Flags::ZERO) }),
+    /* 10 */ op!("STOP d8", 2, 4, |_, _| {}),
+    /* 11 */ op!("LD DE,d16", 3, 12, |cpu, bus| { let nn = cpu.get_immediate_u16(bus); cpu.set_de(nn) }),
+    /* 12 */ op!("LD DE,A", 1, 8, |cpu, bus| cpu.write_byte(bus, cpu.get_de(), cpu.a)),
+    /* 13 */ op!("INC DE", 1, 8, |cpu, _| cpu.set_de(cpu.get_de().wrapping_add(1))),
+    /* 14 */ op!("INC D", 1, 4, |cpu, _| cpu.d = cpu.inc_8(cpu.d)),
+    /* 15 */ op!("DEC D", 1, 4, |cpu, _| cpu.d = cpu.dec_8(cpu.d)),
+    /* 16 */ op!("LD D,d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.ld_d(n) }),
+    /* 17 */ op!("RLA", 1, 4, |cpu, _| { cpu.a = cpu.rl_8(cpu.a); cpu.f.remove(Flags::ZERO) }),
+    /* 18 */ op!("JR r8", 2, 12, |cpu, bus| { let n = cpu.get_immediate_i8(bus); cpu.jr(n) }),
+    /* 19 */ op!("ADD HL,DE", 1, 8, |cpu, _| cpu.add_hl_16(cpu.get_de())),
+    /* 1A */ op!("LD A,DE", 1, 8, |cpu, bus| cpu.ld_a(cpu.read_byte(bus, cpu.get_de()))),
+    /* 1B */ op!("DEC DE", 1, 8, |cpu, _| cpu.set_de(cpu.get_de().wrapping_sub(1))),
+    /* 1C */ op!("INC E", 1, 4, |cpu, _| cpu.e = cpu.inc_8(cpu.e)),
+    /* 1D */ op!("DEC E", 1, 4, |cpu, _| cpu.e = cpu.dec_8(cpu.e)),
+    /* 1E */ op!("LD E,d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.ld_e(n) }),
+    /* 1F */ op!("RRA", 1, 4, |cpu, _| { cpu.a = cpu.rr_8(cpu.a); cpu.f.remove(Flags::ZERO) }),
+    /* 20 */ op!("JR NZ,r8", 2, 8 / 12, |cpu, bus| cpu.jr_if(!cpu.f.contains(Flags::ZERO), bus)),
+    /* 21 */ op!("LD HL,d16", 3, 12, |cpu, bus| { let nn = cpu.get_immediate_u16(bus); cpu.set_hl(nn) }),
+    /* 22 */ op!("LD HL,A", 1, 8, |cpu, bus| cpu.ldi_hla(bus)),
+    /* 23 */ op!("INC HL", 1, 8, |cpu, _| cpu.set_hl(cpu.get_hl().wrapping_add(1))),
+    /* 24 */ op!("INC H", 1, 4, |cpu, _| cpu.h = cpu.inc_8(cpu.h)),
+    /* 25 */ op!("DEC H", 1, 4, |cpu, _| cpu.h = cpu.dec_8(cpu.h)),
+    /* 26 */ op!("LD H,d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.ld_h(n) }),
+    /* 27 */ op!("DAA", 1, 4, |cpu, _| cpu.daa()),
+    /* 28 */ op!("JR Z,r8", 2, 8 / 12, |cpu, bus| cpu.jr_if(cpu.f.contains(Flags::ZERO), bus)),
+    /* 29 */ op!("ADD HL,HL", 1, 8, |cpu, _| cpu.add_hl_16(cpu.get_hl())),
+    /* 2A */ op!("LD A,HL", 1, 8, |cpu, bus| cpu.lda_hli(bus)),
+    /* 2B */ op!("DEC HL", 1, 8, |cpu, _| cpu.set_hl(cpu.get_hl().wrapping_sub(1))),
+    /* 2C */ op!("INC L", 1, 4, |cpu, _| cpu.l = cpu.inc_8(cpu.l)),
+    /* 2D */ op!("DEC L", 1, 4, |cpu, _| cpu.l = cpu.dec_8(cpu.l)),
+    /* 2E */ op!("LD L,d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.ld_l(n) }),
+    /* 2F */ op!("CPL", 1, 4, |cpu, _| cpu.cpl()),
+    /* 30 */ op!("JR NC,r8", 2, 8 / 12, |cpu, bus| cpu.jr_if(!cpu.f.contains(Flags::CARRY), bus)),
+    /* 31 */ op!("LD SP,d16", 3, 12, |cpu, bus| cpu.sp = cpu.get_immediate_u16(bus)),
+    /* 32 */ op!("LD HL,A", 1, 8, |cpu, bus| { let hl = cpu.hld(); cpu.write_byte(bus, hl, cpu.a) }),
+    /* 33 */ op!("INC SP", 1, 8, |cpu, _| cpu.sp = cpu.sp.wrapping_add(1)),
+    /* 34 */ op!("INC HL", 1, 12, |cpu, bus| cpu.modify_hl(bus, ProcessingUnit::inc_8)),
+    /* 35 */ op!("DEC HL", 1, 12, |cpu, bus| cpu.modify_hl(bus, ProcessingUnit::dec_8)),
+    /* 36 */ op!("LD HL,d8", 2, 12, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.ld_hl(n, bus) }),
+    /* 37 */ op!("SCF", 1, 4, |cpu, _| cpu.scf()),
+    /* 38 */ op!("JR C,r8", 2, 8 / 12, |cpu, bus| cpu.jr_if(cpu.f.contains(Flags::CARRY), bus)),
+    /* 39 */ op!("ADD HL,SP", 1, 8, |cpu, _| cpu.add_hl_16(cpu.sp)),
+    /* 3A */ op!("LD A,HL", 1, 8, |cpu, bus| { let hl = cpu.hld(); cpu.a = cpu.read_byte(bus, hl) }),
+    /* 3B */ op!("DEC SP", 1, 8, |cpu, _| cpu.sp = cpu.sp.wrapping_sub(1)),
+    /* 3C */ op!("INC A", 1, 4, |cpu, _| cpu.a = cpu.inc_8(cpu.a)),
+    /* 3D */ op!("DEC A", 1, 4, |cpu, _| cpu.a = cpu.dec_8(cpu.a)),
+    /* 3E */ op!("LD A,d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.ld_a(n) }),
+    /* 3F */ op!("CCF", 1, 4, |cpu, _| cpu.ccf()),
+    /* 40 */ op!("LD B,B", 1, 4, |cpu, _| cpu.ld_b(cpu.b)),
+    /* 41 */ op!("LD B,C", 1, 4, |cpu, _| cpu.ld_b(cpu.c)),
+    /* 42 */ op!("LD B,D", 1, 4, |cpu, _| cpu.ld_b(cpu.d)),
+    /* 43 */ op!("LD B,E", 1, 4, |cpu, _| cpu.ld_b(cpu.e)),
+    /* 44 */ op!("LD B,H", 1, 4, |cpu, _| cpu.ld_b(cpu.h)),
+    /* 45 */ op!("LD B,L", 1, 4, |cpu, _| cpu.ld_b(cpu.l)),
+    /* 46 */ op!("LD B,HL", 1, 8, |cpu, bus| cpu.ld_b(cpu.read_byte(bus, cpu.get_hl()))),
+    /* 47 */ op!("LD B,A", 1, 4, |cpu, _| cpu.ld_b(cpu.a)),
+    /* 48 */ op!("LD C,B", 1, 4, |cpu, _| cpu.ld_c(cpu.b)),
+    /* 49 */ op!("LD C,C", 1, 4, |cpu, _| cpu.ld_c(cpu.c)),
+    /* 4A */ op!("LD C,D", 1, 4, |cpu, _| cpu.ld_c(cpu.d)),
+    /* 4B */ op!("LD C,E", 1, 4, |cpu, _| cpu.ld_c(cpu.e)),
+    /* 4C */ op!("LD C,H", 1, 4, |cpu, _| cpu.ld_c(cpu.h)),
+    /* 4D */ op!("LD C,L", 1, 4, |cpu, _| cpu.ld_c(cpu.l)),
+    /* 4E */ op!("LD C,HL", 1, 8, |cpu, bus| cpu.ld_c(cpu.read_byte(bus, cpu.get_hl()))),
+    /* 4F */ op!("LD C,A", 1, 4, |cpu, _| cpu.ld_c(cpu.a)),
+    /* 50 */ op!("LD D,B", 1, 4, |cpu, _| cpu.ld_d(cpu.b)),
+    /* 51 */ op!("LD D,C", 1, 4, |cpu, _| cpu.ld_d(cpu.c)),
+    /* 52 */ op!("LD D,D", 1, 4, |cpu, _| cpu.ld_d(cpu.d)),
+    /* 53 */ op!("LD D,E", 1, 4, |cpu, _| cpu.ld_d(cpu.e)),
+    /* 54 */ op!("LD D,H", 1, 4, |cpu, _| cpu.ld_d(cpu.h)),
+    /* 55 */ op!("LD D,L", 1, 4, |cpu, _| cpu.ld_d(cpu.l)),
+    /* 56 */ op!("LD D,HL", 1, 8, |cpu, bus| cpu.ld_d(cpu.read_byte(bus, cpu.get_hl()))),
+    /* 57 */ op!("LD D,A", 1, 4, |cpu, _| cpu.ld_d(cpu.a)),
+    /* 58 */ op!("LD E,B", 1, 4, |cpu, _| cpu.ld_e(cpu.b)),
+    /* 59 */ op!("LD E,C", 1, 4, |cpu, _| cpu.ld_e(cpu.c)),
+    /* 5A */ op!("LD E,D", 1, 4, |cpu, _| cpu.ld_e(cpu.d)),
+    /* 5B */ op!("LD E,E", 1, 4, |cpu, _| cpu.ld_e(cpu.e)),
+    /* 5C */ op!("LD E,H", 1, 4, |cpu, _| cpu.ld_e(cpu.h)),
+    /* 5D */ op!("LD E,L", 1, 4, |cpu, _| cpu.ld_e(cpu.l)),
+    /* 5E */ op!("LD E,HL", 1, 8, |cpu, bus| cpu.ld_e(cpu.read_byte(bus, cpu.get_hl()))),
+    /* 5F */ op!("LD E,A", 1, 4, |cpu, _| cpu.ld_e(cpu.a)),
+    /* 60 */ op!("LD H,B", 1, 4, |cpu, _| cpu.ld_h(cpu.b)),
+    /* 61 */ op!("LD H,C", 1, 4, |cpu, _| cpu.ld_h(cpu.c)),
+    /* 62 */ op!("LD H,D", 1, 4, |cpu, _| cpu.ld_h(cpu.d)),
+    /* 63 */ op!("LD H,E", 1, 4, |cpu, _| cpu.ld_h(cpu.e)),
+    /* 64 */ op!("LD H,H", 1, 4, |cpu, _| cpu.ld_h(cpu.h)),
+    /* 65 */ op!("LD H,L", 1, 4, |cpu, _| cpu.ld_h(cpu.l)),
+    /* 66 */ op!("LD H,HL", 1, 8, |cpu, bus| cpu.ld_h(cpu.read_byte(bus, cpu.get_hl()))),
+    /* 67 */ op!("LD H,A", 1, 4, |cpu, _| cpu.ld_h(cpu.a)),
+    /* 68 */ op!("LD L,B", 1, 4, |cpu, _| cpu.ld_l(cpu.b)),
+    /* 69 */ op!("LD L,C", 1, 4, |cpu, _| cpu.ld_l(cpu.c)),
+    /* 6A */ op!("LD L,D", 1, 4, |cpu, _| cpu.ld_l(cpu.d)),
+    /* 6B */ op!("LD L,E", 1, 4, |cpu, _| cpu.ld_l(cpu.e)),
+    /* 6C */ op!("LD L,H", 1, 4, |cpu, _| cpu.ld_l(cpu.h)),
+    /* 6D */ op!("LD L,L", 1, 4, |cpu, _| cpu.ld_l(cpu.l)),
+    /* 6E */ op!("LD L,HL", 1, 8, |cpu, bus| cpu.ld_l(cpu.read_byte(bus, cpu.get_hl()))),
+    /* 6F */ op!("LD L,A", 1, 4, |cpu, _| cpu.ld_l(cpu.a)),
+    /* 70 */ op!("LD HL,B", 1, 8, |cpu, bus| cpu.ld_hl(cpu.b, bus)),
+    /* 71 */ op!("LD HL,C", 1, 8, |cpu, bus| cpu.ld_hl(cpu.c, bus)),
+    /* 72 */ op!("LD HL,D", 1, 8, |cpu, bus| cpu.ld_hl(cpu.d, bus)),
+    /* 73 */ op!("LD HL,E", 1, 8, |cpu, bus| cpu.ld_hl(cpu.e, bus)),
+    /* 74 */ op!("LD HL,H", 1, 8, |cpu, bus| cpu.ld_hl(cpu.h, bus)),
+    /* 75 */ op!("LD HL,L", 1, 8, |cpu, bus| cpu.ld_hl(cpu.l, bus)),
+    /* 76 */ op!("HALT", 1, 4, |cpu, _| cpu.halted = true),
+    /* 77 */ op!("LD HL,A", 1, 8, |cpu, bus| cpu.ld_hl(cpu.a, bus)),
+    /* 78 */ op!("LD A,B", 1, 4, |cpu, _| cpu.ld_a(cpu.b)),
+    /* 79 */ op!("LD A,C", 1, 4, |cpu, _| cpu.ld_a(cpu.c)),
+    /* 7A */ op!("LD A,D", 1, 4, |cpu, _| cpu.ld_a(cpu.d)),
+    /* 7B */ op!("LD A,E", 1, 4, |cpu, _| cpu.ld_a(cpu.e)),
+    /* 7C */ op!("LD A,H", 1, 4, |cpu, _| cpu.ld_a(cpu.h)),
+    /* 7D */ op!("LD A,L", 1, 4, |cpu, _| cpu.ld_a(cpu.l)),
+    /* 7E */ op!("LD A,HL", 1, 8, |cpu, bus| cpu.ld_a(cpu.read_byte(bus, cpu.get_hl()))),
+    /* 7F */ op!("LD A,A", 1, 4, |cpu, _| cpu.ld_a(cpu.a)),
+    /* 80 */ op!("ADD A,B", 1, 4, |cpu, _| cpu.add_a(cpu.b)),
+    /* 81 */ op!("ADD A,C", 1, 4, |cpu, _| cpu.add_a(cpu.c)),
+    /* 82 */ op!("ADD A,D", 1, 4, |cpu, _| cpu.add_a(cpu.d)),
+    /* 83 */ op!("ADD A,E", 1, 4, |cpu, _| cpu.add_a(cpu.e)),
+    /* 84 */ op!("ADD A,H", 1, 4, |cpu, _| cpu.add_a(cpu.h)),
+    /* 85 */ op!("ADD A,L", 1, 4, |cpu, _| cpu.add_a(cpu.l)),
+    /* 86 */ op!("ADD A,HL", 1, 8, |cpu, bus| cpu.add_a(cpu.read_byte(bus, cpu.get_hl()))),
+    /* 87 */ op!("ADD A,A", 1, 4, |cpu, _| cpu.add_a(cpu.a)),
+    /* 88 */ op!("ADC A,B", 1, 4, |cpu, _| cpu.adc(cpu.b)),
+    /* 89 */ op!("ADC A,C", 1, 4, |cpu, _| cpu.adc(cpu.c)),
+    /* 8A */ op!("ADC A,D", 1, 4, |cpu, _| cpu.adc(cpu.d)),
+    /* 8B */ op!("ADC A,E", 1, 4, |cpu, _| cpu.adc(cpu.e)),
+    /* 8C */ op!("ADC A,H", 1, 4, |cpu, _| cpu.adc(cpu.h)),
+    /* 8D */ op!("ADC A,L", 1, 4, |cpu, _| cpu.adc(cpu.l)),
+    /* 8E */ op!("ADC A,HL", 1, 8, |cpu, bus| cpu.adc(cpu.read_byte(bus, cpu.get_hl()))),
+    /* 8F */ op!("ADC A,A", 1, 4, |cpu, _| cpu.adc(cpu.a)),
+    /* 90 */ op!("SUB B", 1, 4, |cpu, _| cpu.sub_a(cpu.b)),
+    /* 91 */ op!("SUB C", 1, 4, |cpu, _| cpu.sub_a(cpu.c)),
+    /* 92 */ op!("SUB D", 1, 4, |cpu, _| cpu.sub_a(cpu.d)),
+    /* 93 */ op!("SUB E", 1, 4, |cpu, _| cpu.sub_a(cpu.e)),
+    /* 94 */ op!("SUB H", 1, 4, |cpu, _| cpu.sub_a(cpu.h)),
+    /* 95 */ op!("SUB L", 1, 4, |cpu, _| cpu.sub_a(cpu.l)),
+    /* 96 */ op!("SUB HL", 1, 8, |cpu, bus| cpu.sub_a(cpu.read_byte(bus, cpu.get_hl()))),
+    /* 97 */ op!("SUB A", 1, 4, |cpu, _| cpu.sub_a(cpu.a)),
+    /* 98 */ op!("SBC A,B", 1, 4, |cpu, _| cpu.sbc(cpu.b)),
+    /* 99 */ op!("SBC A,C", 1, 4, |cpu, _| cpu.sbc(cpu.c)),
+    /* 9A */ op!("SBC A,D", 1, 4, |cpu, _| cpu.sbc(cpu.d)),
+    /* 9B */ op!("SBC A,E", 1, 4, |cpu, _| cpu.sbc(cpu.e)),
+    /* 9C */ op!("SBC A,H", 1, 4, |cpu, _| cpu.sbc(cpu.h)),
+    /* 9D */ op!("SBC A,L", 1, 4, |cpu, _| cpu.sbc(cpu.l)),
+    /* 9E */ op!("SBC A,HL", 1, 8, |cpu, bus| cpu.sbc(cpu.read_byte(bus, cpu.get_hl()))),
+    /* 9F */ op!("SBC A,A", 1, 4, |cpu, _| cpu.sbc(cpu.a)),
+    /* A0 */ op!("AND B", 1, 4, |cpu, _| cpu.and(cpu.b)),
+    /* A1 */ op!("AND C", 1, 4, |cpu, _| cpu.and(cpu.c)),
+    /* A2 */ op!("AND D", 1, 4, |cpu, _| cpu.and(cpu.d)),
+    /* A3 */ op!("AND E", 1, 4, |cpu, _| cpu.and(cpu.e)),
+    /* A4 */ op!("AND H", 1, 4, |cpu, _| cpu.and(cpu.h)),
+    /* A5 */ op!("AND L", 1, 4, |cpu, _| cpu.and(cpu.l)),
+    /* A6 */ op!("AND HL", 1, 8, |cpu, bus| cpu.and(cpu.read_byte(bus, cpu.get_hl()))),
+    /* A7 */ op!("AND A", 1, 4, |cpu, _| cpu.and(cpu.a)),
+    /* A8 */ op!("XOR B", 1, 4, |cpu, _| cpu.xor_a(cpu.b)),
+    /* A9 */ op!("XOR C", 1, 4, |cpu, _| cpu.xor_a(cpu.c)),
+    /* AA */ op!("XOR D", 1, 4, |cpu, _| cpu.xor_a(cpu.d)),
+    /* AB */ op!("XOR E", 1, 4, |cpu, _| cpu.xor_a(cpu.e)),
+    /* AC */ op!("XOR H", 1, 4, |cpu, _| cpu.xor_a(cpu.h)),
+    /* AD */ op!("XOR L", 1, 4, |cpu, _| cpu.xor_a(cpu.l)),
+    /* AE */ op!("XOR HL", 1, 8, |cpu, bus| cpu.xor_a(cpu.read_byte(bus, cpu.get_hl()))),
+    /* AF */ op!("XOR A", 1, 4, |cpu, _| cpu.xor_a(cpu.a)),
+    /* B0 */ op!("OR B", 1, 4, |cpu, _| cpu.or(cpu.b)),
+    /* B1 */ op!("OR C", 1, 4, |cpu, _| cpu.or(cpu.c)),
+    /* B2 */ op!("OR D", 1, 4, |cpu, _| cpu.or(cpu.d)),
+    /* B3 */ op!("OR E", 1, 4, |cpu, _| cpu.or(cpu.e)),
+    /* B4 */ op!("OR H", 1, 4, |cpu, _| cpu.or(cpu.h)),
+    /* B5 */ op!("OR L", 1, 4, |cpu, _| cpu.or(cpu.l)),
+    /* B6 */ op!("OR HL", 1, 8, |cpu, bus| cpu.or(cpu.read_byte(bus, cpu.get_hl()))),
+    /* B7 */ op!("OR A", 1, 4, |cpu, _| cpu.or(cpu.a)),
+    /* B8 */ op!("CP B", 1, 4, |cpu, _| cpu.compare_a_with(cpu.b)),
+    /* B9 */ op!("CP C", 1, 4, |cpu, _| cpu.compare_a_with(cpu.c)),
+    /* BA */ op!("CP D", 1, 4, |cpu, _| cpu.compare_a_with(cpu.d)),
+    /* BB */ op!("CP E", 1, 4, |cpu, _| cpu.compare_a_with(cpu.e)),
+    /* BC */ op!("CP H", 1, 4, |cpu, _| cpu.compare_a_with(cpu.h)),
+    /* BD */ op!("CP L", 1, 4, |cpu, _| cpu.compare_a_with(cpu.l)),
+    /* BE */ op!("CP HL", 1, 8, |cpu, bus| cpu.compare_a_with(cpu.read_byte(bus, cpu.get_hl()))),
+    /* BF */ op!("CP A", 1, 4, |cpu, _| cpu.compare_a_with(cpu.a)),
+    /* C0 */ op!("RET NZ", 1, 8 / 20, |cpu, bus| cpu.ret_if(!cpu.f.contains(Flags::ZERO), bus)),
+    /* C1 */ op!("POP BC", 1, 12, |cpu, bus| { cpu.c = cpu.read_sp_u8(bus); cpu.b = cpu.read_sp_u8(bus) }),
+    /* C2 */ op!("JP NZ,a16", 3, 12 / 16, |cpu, bus| cpu.jp_if(!cpu.f.contains(Flags::ZERO), bus)),
+    /* C3 */ op!("JP a16", 3, 16, |cpu, bus| cpu.pc = cpu.get_immediate_u16(bus)),
+    /* C4 */ op!("CALL NZ,a16", 3, 12 / 24, |cpu, bus| cpu.call_if(!cpu.f.contains(Flags::ZERO), bus)),
+    /* C5 */ op!("PUSH BC", 1, 16, |cpu, bus| cpu.push_u16(cpu.get_bc(), bus)),
+    /* C6 */ op!("ADD A,d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.add_a(n) }),
+    /* C7 */ op!("RST 00H", 1, 16, |cpu, bus| cpu.rst(0x00, bus)),
+    /* C8 */ op!("RET Z", 1, 8 / 20, |cpu, bus| cpu.ret_if(cpu.f.contains(Flags::ZERO), bus)),
+    /* C9 */ op!("RET", 1, 16, |cpu, bus| cpu.ret(bus)),
+    /* CA */ op!("JP Z,a16", 3, 12 / 16, |cpu, bus| cpu.jp_if(cpu.f.contains(Flags::ZERO), bus)),
+    /* CB */ OpCode { mnemonic: "PREFIX", len: 2, cycles: 4, branch_cycles: 4, execute: prefix },
+    /* CC */ op!("CALL Z,a16", 3, 12 / 24, |cpu, bus| cpu.call_if(cpu.f.contains(Flags::ZERO), bus)),
+    /* CD */ op!("CALL a16", 3, 24, |cpu, bus| { let nn = cpu.get_immediate_u16(bus); cpu.call(nn, bus) }),
+    /* CE */ op!("ADC A,d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.adc(n) }),
+    /* CF */ op!("RST 08H", 1, 16, |cpu, bus| cpu.rst(0x08, bus)),
+    /* D0 */ op!("RET NC", 1, 8 / 20, |cpu, bus| cpu.ret_if(!cpu.f.contains(Flags::CARRY), bus)),
+    /* D1 */ op!("POP DE", 1, 12, |cpu, bus| { cpu.e = cpu.read_sp_u8(bus); cpu.d = cpu.read_sp_u8(bus) }),
+    /* D2 */ op!("JP NC,a16", 3, 12 / 16, |cpu, bus| cpu.jp_if(!cpu.f.contains(Flags::CARRY), bus)),
+    /* D3 */ op!("ILLEGAL_D3", 1, 4, |cpu, bus| cpu.illegal(bus)),
+    /* D4 */ op!("CALL NC,a16", 3, 12 / 24, |cpu, bus| cpu.call_if(!cpu.f.contains(Flags::CARRY), bus)),
+    /* D5 */ op!("PUSH DE", 1, 16, |cpu, bus| cpu.push_u16(cpu.get_de(), bus)),
+    /* D6 */ op!("SUB d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.sub_a(n) }),
+    /* D7 */ op!("RST 10H", 1, 16, |cpu, bus| cpu.rst(0x10, bus)),
+    /* D8 */ op!("RET C", 1, 8 / 20, |cpu, bus| cpu.ret_if(cpu.f.contains(Flags::CARRY), bus)),
+    /* D9 */ op!("RETI", 1, 16, |cpu, bus| { cpu.ret(bus); cpu.interrupt_master_enable = true }),
+    /* DA */ op!("JP C,a16", 3, 12 / 16, |cpu, bus| cpu.jp_if(cpu.f.contains(Flags::CARRY), bus)),
+    /* DB */ op!("ILLEGAL_DB", 1, 4, |cpu, bus| cpu.illegal(bus)),
+    /* DC */ op!("CALL C,a16", 3, 12 / 24, |cpu, bus| cpu.call_if(cpu.f.contains(Flags::CARRY), bus)),
+    /* DD */ op!("ILLEGAL_DD", 1, 4, |cpu, bus| cpu.illegal(bus)),
+    /* DE */ op!("SBC A,d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.sbc(n) }),
+    /* DF */ op!("RST 18H", 1, 16, |cpu, bus| cpu.rst(0x18, bus)),
+    /* E0 */ op!("LDH a8,A", 2, 12, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.write_byte(bus, 0xff00 + n as u16, cpu.a) }),
+    /* E1 */ op!("POP HL", 1, 12, |cpu, bus| { cpu.l = cpu.read_sp_u8(bus); cpu.h = cpu.read_sp_u8(bus) }),
+    /* E2 */ op!("LD C,A", 1, 8, |cpu, bus| cpu.write_byte(bus, 0xff00 + cpu.c as u16, cpu.a)),
+    /* E3 */ op!("ILLEGAL_E3", 1, 4, |cpu, bus| cpu.illegal(bus)),
+    /* E4 */ op!("ILLEGAL_E4", 1, 4, |cpu, bus| cpu.illegal(bus)),
+    /* E5 */ op!("PUSH HL", 1, 16, |cpu, bus| cpu.push_u16(cpu.get_hl(), bus)),
+    /* E6 */ op!("AND d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.and(n) }),
+    /* E7 */ op!("RST 20H", 1, 16, |cpu, bus| cpu.rst(0x20, bus)),
+    /* E8 */ op!("ADD SP,r8", 2, 16, |cpu, bus| cpu.sp = cpu.add_16_imm(cpu.sp, bus)),
+    /* E9 */ op!("JP HL", 1, 4, |cpu, _| cpu.pc = cpu.get_hl()),
+    /* EA */ op!("LD a16,A", 3, 16, |cpu, bus| { let nn = cpu.get_immediate_u16(bus); cpu.write_byte(bus, nn, cpu.a) }),
+    /* EB */ op!("ILLEGAL_EB", 1, 4, |cpu, bus| cpu.illegal(bus)),
+    /* EC */ op!("ILLEGAL_EC", 1, 4, |cpu, bus| cpu.illegal(bus)),
+    /* ED */ op!("ILLEGAL_ED", 1, 4, |cpu, bus| cpu.illegal(bus)),
+    /* EE */ op!("XOR d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.xor_a(n) }),
+    /* EF */ op!("RST 28H", 1, 16, |cpu, bus| cpu.rst(0x28, bus)),
+    /* F0 */ op!("LDH A,a8", 2, 12, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.a = cpu.read_byte(bus, 0xff00 + n as u16) }),
+    /* F1 */ op!("POP AF", 1, 12, |cpu, bus| { cpu.f.bits = cpu.read_sp_u8(bus) & 0xf0; cpu.a = cpu.read_sp_u8(bus) }),
+    /* F2 */ op!("LD A,C", 1, 8, |cpu, bus| cpu.a = cpu.read_byte(bus, 0xff00 + cpu.c as u16)),
+    /* F3 */ op!("DI", 1, 4, |cpu, _| cpu.interrupt_master_enable = false),
+    /* F4 */ op!("ILLEGAL_F4", 1, 4, |cpu, bus| cpu.illegal(bus)),
+    /* F5 */ op!("PUSH AF", 1, 16, |cpu, bus| cpu.push_u16(cpu.get_af(), bus)),
+    /* F6 */ op!("OR d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.or(n) }),
+    /* F7 */ op!("RST 30H", 1, 16, |cpu, bus| cpu.rst(0x30, bus)),
+    /* F8 */ op!("LD HL,SP,r8", 2, 12, |cpu, bus| { let r = cpu.add_16_imm(cpu.sp, bus); cpu.set_hl(r) }),
+    /* F9 */ op!("LD SP,HL", 1, 8, |cpu, _| cpu.sp = cpu.get_hl()),
+    /* FA */ op!("LD A,a16", 3, 16, |cpu, bus| { let nn = cpu.get_immediate_u16(bus); cpu.ld_a(cpu.read_byte(bus, nn)) }),
+    /* FB */ op!("EI", 1, 4, |cpu, _| cpu.interrupt_master_enable = true),
+    /* FC */ op!("ILLEGAL_FC", 1, 4, |cpu, bus| cpu.illegal(bus)),
+    /* FD */ op!("ILLEGAL_FD", 1, 4, |cpu, bus| cpu.illegal(bus)),
+    /* FE */ op!("CP d8", 2, 8, |cpu, bus| { let n = cpu.get_immediate_u8(bus); cpu.compare_a_with(n) }),
+    /* FF */ op!("RST 38H", 1, 16, |cpu, bus| cpu.rst(0x38, bus)),
+];
+
+#[rustfmt::skip]
+pub static CB_OPCODES: [OpCode; 256] = [
+    /* 00 */ op!("RLC B", 2, 8, |cpu, _| cpu.b = cpu.rlc_8(cpu.b)),
+    /* 01 */ op!("RLC C", 2, 8, |cpu, _| cpu.c = cpu.rlc_8(cpu.c)),
+    /* 02 */ op!("RLC D", 2, 8, |cpu, _| cpu.d = cpu.rlc_8(cpu.d)),
+    /* 03 */ op!("RLC E", 2, 8, |cpu, _| cpu.e = cpu.rlc_8(cpu.e)),
+    /* 04 */ op!("RLC H", 2, 8, |cpu, _| cpu.h = cpu.rlc_8(cpu.h)),
+    /* 05 */ op!("RLC L", 2, 8, |cpu, _| cpu.l = cpu.rlc_8(cpu.l)),
+    /* 06 */ op!("RLC HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, ProcessingUnit::rlc_8)),
+    /* 07 */ op!("RLC A", 2, 8, |cpu, _| cpu.a = cpu.rlc_8(cpu.a)),
+    /* 08 */ op!("RRC B", 2, 8, |cpu, _| cpu.b = cpu.rrc_8(cpu.b)),
+    /* 09 */ op!("RRC C", 2, 8, |cpu, _| cpu.c = cpu.rrc_8(cpu.c)),
+    /* 0A */ op!("RRC D", 2, 8, |cpu, _| cpu.d = cpu.rrc_8(cpu.d)),
+    /* 0B */ op!("RRC E", 2, 8, |cpu, _| cpu.e = cpu.rrc_8(cpu.e)),
+    /* 0C */ op!("RRC H", 2, 8, |cpu, _| cpu.h = cpu.rrc_8(cpu.h)),
+    /* 0D */ op!("RRC L", 2, 8, |cpu, _| cpu.l = cpu.rrc_8(cpu.l)),
+    /* 0E */ op!("RRC HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, ProcessingUnit::rrc_8)),
+    /* 0F */ op!("RRC A", 2, 8, |cpu, _| cpu.a = cpu.rrc_8(cpu.a)),
+    /* 10 */ op!("RL B", 2, 8, |cpu, _| cpu.b = cpu.rl_8(cpu.b)),
+    /* 11 */ op!("RL C", 2, 8, |cpu, _| cpu.c = cpu.rl_8(cpu.c)),
+    /* 12 */ op!("RL D", 2, 8, |cpu, _| cpu.d = cpu.rl_8(cpu.d)),
+    /* 13 */ op!("RL E", 2, 8, |cpu, _| cpu.e = cpu.rl_8(cpu.e)),
+    /* 14 */ op!("RL H", 2, 8, |cpu, _| cpu.h = cpu.rl_8(cpu.h)),
+    /* 15 */ op!("RL L", 2, 8, |cpu, _| cpu.l = cpu.rl_8(cpu.l)),
+    /* 16 */ op!("RL HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, ProcessingUnit::rl_8)),
+    /* 17 */ op!("RL A", 2, 8, |cpu, _| cpu.a = cpu.rl_8(cpu.a)),
+    /* 18 */ op!("RR B", 2, 8, |cpu, _| cpu.b = cpu.rr_8(cpu.b)),
+    /* 19 */ op!("RR C", 2, 8, |cpu, _| cpu.c = cpu.rr_8(cpu.c)),
+    /* 1A */ op!("RR D", 2, 8, |cpu, _| cpu.d = cpu.rr_8(cpu.d)),
+    /* 1B */ op!("RR E", 2, 8, |cpu, _| cpu.e = cpu.rr_8(cpu.e)),
+    /* 1C */ op!("RR H", 2, 8, |cpu, _| cpu.h = cpu.rr_8(cpu.h)),
+    /* 1D */ op!("RR L", 2, 8, |cpu, _| cpu.l = cpu.rr_8(cpu.l)),
+    /* 1E */ op!("RR HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, ProcessingUnit::rr_8)),
+    /* 1F */ op!("RR A", 2, 8, |cpu, _| cpu.a = cpu.rr_8(cpu.a)),
+    /* 20 */ op!("SLA B", 2, 8, |cpu, _| cpu.b = cpu.sla_8(cpu.b)),
+    /* 21 */ op!("SLA C", 2, 8, |cpu, _| cpu.c = cpu.sla_8(cpu.c)),
+    /* 22 */ op!("SLA D", 2, 8, |cpu, _| cpu.d = cpu.sla_8(cpu.d)),
+    /* 23 */ op!("SLA E", 2, 8, |cpu, _| cpu.e = cpu.sla_8(cpu.e)),
+    /* 24 */ op!("SLA H", 2, 8, |cpu, _| cpu.h = cpu.sla_8(cpu.h)),
+    /* 25 */ op!("SLA L", 2, 8, |cpu, _| cpu.l = cpu.sla_8(cpu.l)),
+    /* 26 */ op!("SLA HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, ProcessingUnit::sla_8)),
+    /* 27 */ op!("SLA A", 2, 8, |cpu, _| cpu.a = cpu.sla_8(cpu.a)),
+    /* 28 */ op!("SRA B", 2, 8, |cpu, _| cpu.b = cpu.sra_8(cpu.b)),
+    /* 29 */ op!("SRA C", 2, 8, |cpu, _| cpu.c = cpu.sra_8(cpu.c)),
+    /* 2A */ op!("SRA D", 2, 8, |cpu, _| cpu.d = cpu.sra_8(cpu.d)),
+    /* 2B */ op!("SRA E", 2, 8, |cpu, _| cpu.e = cpu.sra_8(cpu.e)),
+    /* 2C */ op!("SRA H", 2, 8, |cpu, _| cpu.h = cpu.sra_8(cpu.h)),
+    /* 2D */ op!("SRA L", 2, 8, |cpu, _| cpu.l = cpu.sra_8(cpu.l)),
+    /* 2E */ op!("SRA HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, ProcessingUnit::sra_8)),
+    /* 2F */ op!("SRA A", 2, 8, |cpu, _| cpu.a = cpu.sra_8(cpu.a)),
+    /* 30 */ op!("SWAP B", 2, 8, |cpu, _| cpu.b = cpu.swap(cpu.b)),
+    /* 31 */ op!("SWAP C", 2, 8, |cpu, _| cpu.c = cpu.swap(cpu.c)),
+    /* 32 */ op!("SWAP D", 2, 8, |cpu, _| cpu.d = cpu.swap(cpu.d)),
+    /* 33 */ op!("SWAP E", 2, 8, |cpu, _| cpu.e = cpu.swap(cpu.e)),
+    /* 34 */ op!("SWAP H", 2, 8, |cpu, _| cpu.h = cpu.swap(cpu.h)),
+    /* 35 */ op!("SWAP L", 2, 8, |cpu, _| cpu.l = cpu.swap(cpu.l)),
+    /* 36 */ op!("SWAP HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, ProcessingUnit::swap)),
+    /* 37 */ op!("SWAP A", 2, 8, |cpu, _| cpu.a = cpu.swap(cpu.a)),
+    /* 38 */ op!("SRL B", 2, 8, |cpu, _| cpu.b = cpu.srl_8(cpu.b)),
+    /* 39 */ op!("SRL C", 2, 8, |cpu, _| cpu.c = cpu.srl_8(cpu.c)),
+    /* 3A */ op!("SRL D", 2, 8, |cpu, _| cpu.d = cpu.srl_8(cpu.d)),
+    /* 3B */ op!("SRL E", 2, 8, |cpu, _| cpu.e = cpu.srl_8(cpu.e)),
+    /* 3C */ op!("SRL H", 2, 8, |cpu, _| cpu.h = cpu.srl_8(cpu.h)),
+    /* 3D */ op!("SRL L", 2, 8, |cpu, _| cpu.l = cpu.srl_8(cpu.l)),
+    /* 3E */ op!("SRL HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, ProcessingUnit::srl_8)),
+    /* 3F */ op!("SRL A", 2, 8, |cpu, _| cpu.a = cpu.srl_8(cpu.a)),
+    /* 40 */ op!("BIT 0,B", 2, 8, |cpu, _| ProcessingUnit::bit(0, cpu.b, &mut cpu.f)),
+    /* 41 */ op!("BIT 0,C", 2, 8, |cpu, _| ProcessingUnit::bit(0, cpu.c, &mut cpu.f)),
+    /* 42 */ op!("BIT 0,D", 2, 8, |cpu, _| ProcessingUnit::bit(0, cpu.d, &mut cpu.f)),
+    /* 43 */ op!("BIT 0,E", 2, 8, |cpu, _| ProcessingUnit::bit(0, cpu.e, &mut cpu.f)),
+    /* 44 */ op!("BIT 0,H", 2, 8, |cpu, _| ProcessingUnit::bit(0, cpu.h, &mut cpu.f)),
+    /* 45 */ op!("BIT 0,L", 2, 8, |cpu, _| ProcessingUnit::bit(0, cpu.l, &mut cpu.f)),
+    /* 46 */ op!("BIT 0,HL", 2, 12, |cpu, bus| ProcessingUnit::bit(0, cpu.read_byte(bus, cpu.get_hl()), &mut cpu.f)),
+    /* 47 */ op!("BIT 0,A", 2, 8, |cpu, _| ProcessingUnit::bit(0, cpu.a, &mut cpu.f)),
+    /* 48 */ op!("BIT 1,B", 2, 8, |cpu, _| ProcessingUnit::bit(1, cpu.b, &mut cpu.f)),
+    /* 49 */ op!("BIT 1,C", 2, 8, |cpu, _| ProcessingUnit::bit(1, cpu.c, &mut cpu.f)),
+    /* 4A */ op!("BIT 1,D", 2, 8, |cpu, _| ProcessingUnit::bit(1, cpu.d, &mut cpu.f)),
+    /* 4B */ op!("BIT 1,E", 2, 8, |cpu, _| ProcessingUnit::bit(1, cpu.e, &mut cpu.f)),
+    /* 4C */ op!("BIT 1,H", 2, 8, |cpu, _| ProcessingUnit::bit(1, cpu.h, &mut cpu.f)),
+    /* 4D */ op!("BIT 1,L", 2, 8, |cpu, _| ProcessingUnit::bit(1, cpu.l, &mut cpu.f)),
+    /* 4E */ op!("BIT 1,HL", 2, 12, |cpu, bus| ProcessingUnit::bit(1, cpu.read_byte(bus, cpu.get_hl()), &mut cpu.f)),
+    /* 4F */ op!("BIT 1,A", 2, 8, |cpu, _| ProcessingUnit::bit(1, cpu.a, &mut cpu.f)),
+    /* 50 */ op!("BIT 2,B", 2, 8, |cpu, _| ProcessingUnit::bit(2, cpu.b, &mut cpu.f)),
+    /* 51 */ op!("BIT 2,C", 2, 8, |cpu, _| ProcessingUnit::bit(2, cpu.c, &mut cpu.f)),
+    /* 52 */ op!("BIT 2,D", 2, 8, |cpu, _| ProcessingUnit::bit(2, cpu.d, &mut cpu.f)),
+    /* 53 */ op!("BIT 2,E", 2, 8, |cpu, _| ProcessingUnit::bit(2, cpu.e, &mut cpu.f)),
+    /* 54 */ op!("BIT 2,H", 2, 8, |cpu, _| ProcessingUnit::bit(2, cpu.h, &mut cpu.f)),
+    /* 55 */ op!("BIT 2,L", 2, 8, |cpu, _| ProcessingUnit::bit(2, cpu.l, &mut cpu.f)),
+    /* 56 */ op!("BIT 2,HL", 2, 12, |cpu, bus| ProcessingUnit::bit(2, cpu.read_byte(bus, cpu.get_hl()), &mut cpu.f)),
+    /* 57 */ op!("BIT 2,A", 2, 8, |cpu, _| ProcessingUnit::bit(2, cpu.a, &mut cpu.f)),
+    /* 58 */ op!("BIT 3,B", 2, 8, |cpu, _| ProcessingUnit::bit(3, cpu.b, &mut cpu.f)),
+    /* 59 */ op!("BIT 3,C", 2, 8, |cpu, _| ProcessingUnit::bit(3, cpu.c, &mut cpu.f)),
+    /* 5A */ op!("BIT 3,D", 2, 8, |cpu, _| ProcessingUnit::bit(3, cpu.d, &mut cpu.f)),
+    /* 5B */ op!("BIT 3,E", 2, 8, |cpu, _| ProcessingUnit::bit(3, cpu.e, &mut cpu.f)),
+    /* 5C */ op!("BIT 3,H", 2, 8, |cpu, _| ProcessingUnit::bit(3, cpu.h, &mut cpu.f)),
+    /* 5D */ op!("BIT 3,L", 2, 8, |cpu, _| ProcessingUnit::bit(3, cpu.l, &mut cpu.f)),
+    /* 5E */ op!("BIT 3,HL", 2, 12, |cpu, bus| ProcessingUnit::bit(3, cpu.read_byte(bus, cpu.get_hl()), &mut cpu.f)),
+    /* 5F */ op!("BIT 3,A", 2, 8, |cpu, _| ProcessingUnit::bit(3, cpu.a, &mut cpu.f)),
+    /* 60 */ op!("BIT 4,B", 2, 8, |cpu, _| ProcessingUnit::bit(4, cpu.b, &mut cpu.f)),
+    /* 61 */ op!("BIT 4,C", 2, 8, |cpu, _| ProcessingUnit::bit(4, cpu.c, &mut cpu.f)),
+    /* 62 */ op!("BIT 4,D", 2, 8, |cpu, _| ProcessingUnit::bit(4, cpu.d, &mut cpu.f)),
+    /* 63 */ op!("BIT 4,E", 2, 8, |cpu, _| ProcessingUnit::bit(4, cpu.e, &mut cpu.f)),
+    /* 64 */ op!("BIT 4,H", 2, 8, |cpu, _| ProcessingUnit::bit(4, cpu.h, &mut cpu.f)),
+    /* 65 */ op!("BIT 4,L", 2, 8, |cpu, _| ProcessingUnit::bit(4, cpu.l, &mut cpu.f)),
+    /* 66 */ op!("BIT 4,HL", 2, 12, |cpu, bus| ProcessingUnit::bit(4, cpu.read_byte(bus, cpu.get_hl()), &mut cpu.f)),
+    /* 67 */ op!("BIT 4,A", 2, 8, |cpu, _| ProcessingUnit::bit(4, cpu.a, &mut cpu.f)),
+    /* 68 */ op!("BIT 5,B", 2, 8, |cpu, _| ProcessingUnit::bit(5, cpu.b, &mut cpu.f)),
+    /* 69 */ op!("BIT 5,C", 2, 8, |cpu, _| ProcessingUnit::bit(5, cpu.c, &mut cpu.f)),
+    /* 6A */ op!("BIT 5,D", 2, 8, |cpu, _| ProcessingUnit::bit(5, cpu.d, &mut cpu.f)),
+    /* 6B */ op!("BIT 5,E", 2, 8, |cpu, _| ProcessingUnit::bit(5, cpu.e, &mut cpu.f)),
+    /* 6C */ op!("BIT 5,H", 2, 8, |cpu, _| ProcessingUnit::bit(5, cpu.h, &mut cpu.f)),
+    /* 6D */ op!("BIT 5,L", 2, 8, |cpu, _| ProcessingUnit::bit(5, cpu.l, &mut cpu.f)),
+    /* 6E */ op!("BIT 5,HL", 2, 12, |cpu, bus| ProcessingUnit::bit(5, cpu.read_byte(bus, cpu.get_hl()), &mut cpu.f)),
+    /* 6F */ op!("BIT 5,A", 2, 8, |cpu, _| ProcessingUnit::bit(5, cpu.a, &mut cpu.f)),
+    /* 70 */ op!("BIT 6,B", 2, 8, |cpu, _| ProcessingUnit::bit(6, cpu.b, &mut cpu.f)),
+    /* 71 */ op!("BIT 6,C", 2, 8, |cpu, _| ProcessingUnit::bit(6, cpu.c, &mut cpu.f)),
+    /* 72 */ op!("BIT 6,D", 2, 8, |cpu, _| ProcessingUnit::bit(6, cpu.d, &mut cpu.f)),
+    /* 73 */ op!("BIT 6,E", 2, 8, |cpu, _| ProcessingUnit::bit(6, cpu.e, &mut cpu.f)),
+    /* 74 */ op!("BIT 6,H", 2, 8, |cpu, _| ProcessingUnit::bit(6, cpu.h, &mut cpu.f)),
+    /* 75 */ op!("BIT 6,L", 2, 8, |cpu, _| ProcessingUnit::bit(6, cpu.l, &mut cpu.f)),
+    /* 76 */ op!("BIT 6,HL", 2, 12, |cpu, bus| ProcessingUnit::bit(6, cpu.read_byte(bus, cpu.get_hl()), &mut cpu.f)),
+    /* 77 */ op!("BIT 6,A", 2, 8, |cpu, _| ProcessingUnit::bit(6, cpu.a, &mut cpu.f)),
+    /* 78 */ op!("BIT 7,B", 2, 8, |cpu, _| ProcessingUnit::bit(7, cpu.b, &mut cpu.f)),
+    /* 79 */ op!("BIT 7,C", 2, 8, |cpu, _| ProcessingUnit::bit(7, cpu.c, &mut cpu.f)),
+    /* 7A */ op!("BIT 7,D", 2, 8, |cpu, _| ProcessingUnit::bit(7, cpu.d, &mut cpu.f)),
+    /* 7B */ op!("BIT 7,E", 2, 8, |cpu, _| ProcessingUnit::bit(7, cpu.e, &mut cpu.f)),
+    /* 7C */ op!("BIT 7,H", 2, 8, |cpu, _| ProcessingUnit::bit(7, cpu.h, &mut cpu.f)),
+    /* 7D */ op!("BIT 7,L", 2, 8, |cpu, _| ProcessingUnit::bit(7, cpu.l, &mut cpu.f)),
+    /* 7E */ op!("BIT 7,HL", 2, 12, |cpu, bus| ProcessingUnit::bit(7, cpu.read_byte(bus, cpu.get_hl()), &mut cpu.f)),
+    /* 7F */ op!("BIT 7,A", 2, 8, |cpu, _| ProcessingUnit::bit(7, cpu.a, &mut cpu.f)),
+    /* 80 */ op!("RES 0,B", 2, 8, |cpu, _| { cpu.b.set_bit(0, false); }),
+    /* 81 */ op!("RES 0,C", 2, 8, |cpu, _| { cpu.c.set_bit(0, false); }),
+    /* 82 */ op!("RES 0,D", 2, 8, |cpu, _| { cpu.d.set_bit(0, false); }),
+    /* 83 */ op!("RES 0,E", 2, 8, |cpu, _| { cpu.e.set_bit(0, false); }),
+    /* 84 */ op!("RES 0,H", 2, 8, |cpu, _| { cpu.h.set_bit(0, false); }),
+    /* 85 */ op!("RES 0,L", 2, 8, |cpu, _| { cpu.l.set_bit(0, false); }),
+    /* 86 */ op!("RES 0,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(0, false))),
+    /* 87 */ op!("RES 0,A", 2, 8, |cpu, _| { cpu.a.set_bit(0, false); }),
+    /* 88 */ op!("RES 1,B", 2, 8, |cpu, _| { cpu.b.set_bit(1, false); }),
+    /* 89 */ op!("RES 1,C", 2, 8, |cpu, _| { cpu.c.set_bit(1, false); }),
+    /* 8A */ op!("RES 1,D", 2, 8, |cpu, _| { cpu.d.set_bit(1, false); }),
+    /* 8B */ op!("RES 1,E", 2, 8, |cpu, _| { cpu.e.set_bit(1, false); }),
+    /* 8C */ op!("RES 1,H", 2, 8, |cpu, _| { cpu.h.set_bit(1, false); }),
+    /* 8D */ op!("RES 1,L", 2, 8, |cpu, _| { cpu.l.set_bit(1, false); }),
+    /* 8E */ op!("RES 1,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(1, false))),
+    /* 8F */ op!("RES 1,A", 2, 8, |cpu, _| { cpu.a.set_bit(1, false); }),
+    /* 90 */ op!("RES 2,B", 2, 8, |cpu, _| { cpu.b.set_bit(2, false); }),
+    /* 91 */ op!("RES 2,C", 2, 8, |cpu, _| { cpu.c.set_bit(2, false); }),
+    /* 92 */ op!("RES 2,D", 2, 8, |cpu, _| { cpu.d.set_bit(2, false); }),
+    /* 93 */ op!("RES 2,E", 2, 8, |cpu, _| { cpu.e.set_bit(2, false); }),
+    /* 94 */ op!("RES 2,H", 2, 8, |cpu, _| { cpu.h.set_bit(2, false); }),
+    /* 95 */ op!("RES 2,L", 2, 8, |cpu, _| { cpu.l.set_bit(2, false); }),
+    /* 96 */ op!("RES 2,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(2, false))),
+    /* 97 */ op!("RES 2,A", 2, 8, |cpu, _| { cpu.a.set_bit(2, false); }),
+    /* 98 */ op!("RES 3,B", 2, 8, |cpu, _| { cpu.b.set_bit(3, false); }),
+    /* 99 */ op!("RES 3,C", 2, 8, |cpu, _| { cpu.c.set_bit(3, false); }),
+    /* 9A */ op!("RES 3,D", 2, 8, |cpu, _| { cpu.d.set_bit(3, false); }),
+    /* 9B */ op!("RES 3,E", 2, 8, |cpu, _| { cpu.e.set_bit(3, false); }),
+    /* 9C */ op!("RES 3,H", 2, 8, |cpu, _| { cpu.h.set_bit(3, false); }),
+    /* 9D */ op!("RES 3,L", 2, 8, |cpu, _| { cpu.l.set_bit(3, false); }),
+    /* 9E */ op!("RES 3,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(3, false))),
+    /* 9F */ op!("RES 3,A", 2, 8, |cpu, _| { cpu.a.set_bit(3, false); }),
+    /* A0 */ op!("RES 4,B", 2, 8, |cpu, _| { cpu.b.set_bit(4, false); }),
+    /* A1 */ op!("RES 4,C", 2, 8, |cpu, _| { cpu.c.set_bit(4, false); }),
+    /* A2 */ op!("RES 4,D", 2, 8, |cpu, _| { cpu.d.set_bit(4, false); }),
+    /* A3 */ op!("RES 4,E", 2, 8, |cpu, _| { cpu.e.set_bit(4, false); }),
+    /* A4 */ op!("RES 4,H", 2, 8, |cpu, _| { cpu.h.set_bit(4, false); }),
+    /* A5 */ op!("RES 4,L", 2, 8, |cpu, _| { cpu.l.set_bit(4, false); }),
+    /* A6 */ op!("RES 4,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(4, false))),
+    /* A7 */ op!("RES 4,A", 2, 8, |cpu, _| { cpu.a.set_bit(4, false); }),
+    /* A8 */ op!("RES 5,B", 2, 8, |cpu, _| { cpu.b.set_bit(5, false); }),
+    /* A9 */ op!("RES 5,C", 2, 8, |cpu, _| { cpu.c.set_bit(5, false); }),
+    /* AA */ op!("RES 5,D", 2, 8, |cpu, _| { cpu.d.set_bit(5, false); }),
+    /* AB */ op!("RES 5,E", 2, 8, |cpu, _| { cpu.e.set_bit(5, false); }),
+    /* AC */ op!("RES 5,H", 2, 8, |cpu, _| { cpu.h.set_bit(5, false); }),
+    /* AD */ op!("RES 5,L", 2, 8, |cpu, _| { cpu.l.set_bit(5, false); }),
+    /* AE */ op!("RES 5,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(5, false))),
+    /* AF */ op!("RES 5,A", 2, 8, |cpu, _| { cpu.a.set_bit(5, false); }),
+    /* B0 */ op!("RES 6,B", 2, 8, |cpu, _| { cpu.b.set_bit(6, false); }),
+    /* B1 */ op!("RES 6,C", 2, 8, |cpu, _| { cpu.c.set_bit(6, false); }),
+    /* B2 */ op!("RES 6,D", 2, 8, |cpu, _| { cpu.d.set_bit(6, false); }),
+    /* B3 */ op!("RES 6,E", 2, 8, |cpu, _| { cpu.e.set_bit(6, false); }),
+    /* B4 */ op!("RES 6,H", 2, 8, |cpu, _| { cpu.h.set_bit(6, false); }),
+    /* B5 */ op!("RES 6,L", 2, 8, |cpu, _| { cpu.l.set_bit(6, false); }),
+    /* B6 */ op!("RES 6,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(6, false))),
+    /* B7 */ op!("RES 6,A", 2, 8, |cpu, _| { cpu.a.set_bit(6, false); }),
+    /* B8 */ op!("RES 7,B", 2, 8, |cpu, _| { cpu.b.set_bit(7, false); }),
+    /* B9 */ op!("RES 7,C", 2, 8, |cpu, _| { cpu.c.set_bit(7, false); }),
+    /* BA */ op!("RES 7,D", 2, 8, |cpu, _| { cpu.d.set_bit(7, false); }),
+    /* BB */ op!("RES 7,E", 2, 8, |cpu, _| { cpu.e.set_bit(7, false); }),
+    /* BC */ op!("RES 7,H", 2, 8, |cpu, _| { cpu.h.set_bit(7, false); }),
+    /* BD */ op!("RES 7,L", 2, 8, |cpu, _| { cpu.l.set_bit(7, false); }),
+    /* BE */ op!("RES 7,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(7, false))),
+    /* BF */ op!("RES 7,A", 2, 8, |cpu, _| { cpu.a.set_bit(7, false); }),
+    /* C0 */ op!("SET 0,B", 2, 8, |cpu, _| { cpu.b.set_bit(0, true); }),
+    /* C1 */ op!("SET 0,C", 2, 8, |cpu, _| { cpu.c.set_bit(0, true); }),
+    /* C2 */ op!("SET 0,D", 2, 8, |cpu, _| { cpu.d.set_bit(0, true); }),
+    /* C3 */ op!("SET 0,E", 2, 8, |cpu, _| { cpu.e.set_bit(0, true); }),
+    /* C4 */ op!("SET 0,H", 2, 8, |cpu, _| { cpu.h.set_bit(0, true); }),
+    /* C5 */ op!("SET 0,L", 2, 8, |cpu, _| { cpu.l.set_bit(0, true); }),
+    /* C6 */ op!("SET 0,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(0, true))),
+    /* C7 */ op!("SET 0,A", 2, 8, |cpu, _| { cpu.a.set_bit(0, true); }),
+    /* C8 */ op!("SET 1,B", 2, 8, |cpu, _| { cpu.b.set_bit(1, true); }),
+    /* C9 */ op!("SET 1,C", 2, 8, |cpu, _| { cpu.c.set_bit(1, true); }),
+    /* CA */ op!("SET 1,D", 2, 8, |cpu, _| { cpu.d.set_bit(1, true); }),
+    /* CB */ op!("SET 1,E", 2, 8, |cpu, _| { cpu.e.set_bit(1, true); }),
+    /* CC */ op!("SET 1,H", 2, 8, |cpu, _| { cpu.h.set_bit(1, true); }),
+    /* CD */ op!("SET 1,L", 2, 8, |cpu, _| { cpu.l.set_bit(1, true); }),
+    /* CE */ op!("SET 1,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(1, true))),
+    /* CF */ op!("SET 1,A", 2, 8, |cpu, _| { cpu.a.set_bit(1, true); }),
+    /* D0 */ op!("SET 2,B", 2, 8, |cpu, _| { cpu.b.set_bit(2, true); }),
+    /* D1 */ op!("SET 2,C", 2, 8, |cpu, _| { cpu.c.set_bit(2, true); }),
+    /* D2 */ op!("SET 2,D", 2, 8, |cpu, _| { cpu.d.set_bit(2, true); }),
+    /* D3 */ op!("SET 2,E", 2, 8, |cpu, _| { cpu.e.set_bit(2, true); }),
+    /* D4 */ op!("SET 2,H", 2, 8, |cpu, _| { cpu.h.set_bit(2, true); }),
+    /* D5 */ op!("SET 2,L", 2, 8, |cpu, _| { cpu.l.set_bit(2, true); }),
+    /* D6 */ op!("SET 2,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(2, true))),
+    /* D7 */ op!("SET 2,A", 2, 8, |cpu, _| { cpu.a.set_bit(2, true); }),
+    /* D8 */ op!("SET 3,B", 2, 8, |cpu, _| { cpu.b.set_bit(3, true); }),
+    /* D9 */ op!("SET 3,C", 2, 8, |cpu, _| { cpu.c.set_bit(3, true); }),
+    /* DA */ op!("SET 3,D", 2, 8, |cpu, _| { cpu.d.set_bit(3, true); }),
+    /* DB */ op!("SET 3,E", 2, 8, |cpu, _| { cpu.e.set_bit(3, true); }),
+    /* DC */ op!("SET 3,H", 2, 8, |cpu, _| { cpu.h.set_bit(3, true); }),
+    /* DD */ op!("SET 3,L", 2, 8, |cpu, _| { cpu.l.set_bit(3, true); }),
+    /* DE */ op!("SET 3,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(3, true))),
+    /* DF */ op!("SET 3,A", 2, 8, |cpu, _| { cpu.a.set_bit(3, true); }),
+    /* E0 */ op!("SET 4,B", 2, 8, |cpu, _| { cpu.b.set_bit(4, true); }),
+    /* E1 */ op!("SET 4,C", 2, 8, |cpu, _| { cpu.c.set_bit(4, true); }),
+    /* E2 */ op!("SET 4,D", 2, 8, |cpu, _| { cpu.d.set_bit(4, true); }),
+    /* E3 */ op!("SET 4,E", 2, 8, |cpu, _| { cpu.e.set_bit(4, true); }),
+    /* E4 */ op!("SET 4,H", 2, 8, |cpu, _| { cpu.h.set_bit(4, true); }),
+    /* E5 */ op!("SET 4,L", 2, 8, |cpu, _| { cpu.l.set_bit(4, true); }),
+    /* E6 */ op!("SET 4,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(4, true))),
+    /* E7 */ op!("SET 4,A", 2, 8, |cpu, _| { cpu.a.set_bit(4, true); }),
+    /* E8 */ op!("SET 5,B", 2, 8, |cpu, _| { cpu.b.set_bit(5, true); }),
+    /* E9 */ op!("SET 5,C", 2, 8, |cpu, _| { cpu.c.set_bit(5, true); }),
+    /* EA */ op!("SET 5,D", 2, 8, |cpu, _| { cpu.d.set_bit(5, true); }),
+    /* EB */ op!("SET 5,E", 2, 8, |cpu, _| { cpu.e.set_bit(5, true); }),
+    /* EC */ op!("SET 5,H", 2, 8, |cpu, _| { cpu.h.set_bit(5, true); }),
+    /* ED */ op!("SET 5,L", 2, 8, |cpu, _| { cpu.l.set_bit(5, true); }),
+    /* EE */ op!("SET 5,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(5, true))),
+    /* EF */ op!("SET 5,A", 2, 8, |cpu, _| { cpu.a.set_bit(5, true); }),
+    /* F0 */ op!("SET 6,B", 2, 8, |cpu, _| { cpu.b.set_bit(6, true); }),
+    /* F1 */ op!("SET 6,C", 2, 8, |cpu, _| { cpu.c.set_bit(6, true); }),
+    /* F2 */ op!("SET 6,D", 2, 8, |cpu, _| { cpu.d.set_bit(6, true); }),
+    /* F3 */ op!("SET 6,E", 2, 8, |cpu, _| { cpu.e.set_bit(6, true); }),
+    /* F4 */ op!("SET 6,H", 2, 8, |cpu, _| { cpu.h.set_bit(6, true); }),
+    /* F5 */ op!("SET 6,L", 2, 8, |cpu, _| { cpu.l.set_bit(6, true); }),
+    /* F6 */ op!("SET 6,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(6, true))),
+    /* F7 */ op!("SET 6,A", 2, 8, |cpu, _| { cpu.a.set_bit(6, true); }),
+    /* F8 */ op!("SET 7,B", 2, 8, |cpu, _| { cpu.b.set_bit(7, true); }),
+    /* F9 */ op!("SET 7,C", 2, 8, |cpu, _| { cpu.c.set_bit(7, true); }),
+    /* FA */ op!("SET 7,D", 2, 8, |cpu, _| { cpu.d.set_bit(7, true); }),
+    /* FB */ op!("SET 7,E", 2, 8, |cpu, _| { cpu.e.set_bit(7, true); }),
+    /* FC */ op!("SET 7,H", 2, 8, |cpu, _| { cpu.h.set_bit(7, true); }),
+    /* FD */ op!("SET 7,L", 2, 8, |cpu, _| { cpu.l.set_bit(7, true); }),
+    /* FE */ op!("SET 7,HL", 2, 16, |cpu, bus| cpu.modify_hl(bus, |_, mut v| *v.set_bit(7, true))),
+    /* FF */ op!("SET 7,A", 2, 8, |cpu, _| { cpu.a.set_bit(7, true); }),
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::cpu::opcodes::{CB_OPCODES, OPCODES};
+    use crate::dmg::cpu::{Flags, ProcessingUnit};
+    use crate::dmg::mem::MemoryBus;
+
+    #[test]
+    fn lengths_match_operands() {
+        for opcode in &OPCODES {
+            let expected = if opcode.mnemonic.contains("16") {
+                3
+            } else if ["r8", "a8", "d8"].iter().any(|operand| opcode.mnemonic.contains(operand)) || opcode.mnemonic == "PREFIX" {
+                2
+            } else {
+                1
+            };
+            assert_eq!(opcode.len, expected, "{}", opcode.mnemonic);
+        }
+        assert!(CB_OPCODES.iter().all(|opcode| opcode.len == 2));
+    }
+
+    #[test]
+    fn conditional_jumps_take_longer_when_taken() {
+        let mut bus = MemoryBus::default();
+        // jr nz, +0
+        bus.write_byte(0xc000, 0x20);
+        bus.write_byte(0xc001, 0x00);
+
+        let mut cpu = ProcessingUnit::new();
+        cpu.pc = 0xc000;
+        cpu.f = Flags::ZERO;
+        assert_eq!(cpu.next(&mut bus), 8);
+
+        cpu.pc = 0xc000;
+        cpu.f = Flags::empty();
+        assert_eq!(cpu.next(&mut bus), 12);
+        assert_eq!(cpu.pc, 0xc002);
+    }
+}
@@ -0,0 +1,175 @@
+use log::error;
+
+use super::opcodes::decode;
+use crate::dmg::crash::CrashReason;
+use crate::dmg::debug::TraceBuffer;
+use crate::dmg::mem::MemoryBus;
+
+use super::Flags;
+use super::ProcessingUnit;
+
+// Keeps frame and cycle budgets from overshooting much with the LCD and timer off
+const MAX_HALT_SKIP: u32 = 456;
+
+impl ProcessingUnit {
+    pub(super) fn execute_next(&mut self, bus: &mut MemoryBus, trace: &mut TraceBuffer) -> u32 {
+        // Not even interrupts get through, but the rest of the hardware keeps running
+        if self.locked_up {
+            return 4;
+        }
+
+        if self.check_and_execute_interrupts(bus) {
+            return 4;
+        }
+
+        if self.halted {
+            // Nothing can wake us before the PPU, timer or serial port's next event, so skip straight to it
+            let until_event = bus.ppu.cycles_until_event().min(bus.serial.cycles_until_event());
+            let cycles = until_event.clamp(4, MAX_HALT_SKIP).next_multiple_of(4);
+            bus.stats.halt_cycles += cycles;
+            return cycles;
+        }
+
+        let pc = self.pc;
+
+        self.debug_print(pc, bus);
+        trace.push(self.trace_entry(pc, bus));
+        bus.mark_executed(pc);
+
+        let opcode = decode(bus, pc);
+        self.pc = pc.wrapping_add(if self.read_byte(bus, pc) == 0xCB { 2 } else { 1 });
+
+        if (opcode.execute)(self, bus) {
+            opcode.branch_cycles
+        } else {
+            opcode.cycles
+        }
+    }
+
+    pub(super) fn inc_8(&mut self, v: u8) -> u8 {
+        let r = v.wrapping_add(1);
+        self.reset_and_set_carry_zero(v, r);
+        r
+    }
+
+    pub(super) fn dec_8(&mut self, v: u8) -> u8 {
+        let r = v.wrapping_sub(1);
+        self.dec_flags(v, r);
+        r
+    }
+
+    /// Replaces the byte at (HL) with `f` applied to it.
+    pub(super) fn modify_hl(&mut self, bus: &mut MemoryBus, f: impl FnOnce(&mut Self, u8) -> u8) {
+        let hl = self.get_hl();
+        let v = self.read_byte(bus, hl);
+        let r = f(self, v);
+        self.write_byte(bus, hl, r);
+    }
+
+    pub(super) fn ld_a16_sp(&mut self, bus: &mut MemoryBus) {
+        let lsb_addr = self.get_immediate_u16(bus);
+        let msb_addr = lsb_addr.wrapping_add(1);
+        let (sp_msb, sp_lsb) = Self::get_bits(self.sp);
+
+        self.write_byte(bus, lsb_addr, sp_lsb);
+        self.write_byte(bus, msb_addr, sp_msb);
+    }
+
+    pub(super) fn cpl(&mut self) {
+        self.a = !self.a;
+
+        self.f.insert(Flags::N);
+        self.f.insert(Flags::H);
+    }
+
+    pub(super) fn ccf(&mut self) {
+        self.f.remove(Flags::N);
+        self.f.remove(Flags::H);
+        self.f.toggle(Flags::CARRY);
+    }
+
+    pub(super) fn scf(&mut self) {
+        self.f.remove(Flags::N);
+        self.f.remove(Flags::H);
+        self.f.insert(Flags::CARRY);
+    }
+
+    pub(super) fn jr(&mut self, n: i8) {
+        self.pc = ((self.pc as i16) + n as i16) as u16;
+    }
+
+    // The conditional instructions read their operands whether or not they're taken, and return
+    // whether they were
+
+    pub(super) fn jr_if(&mut self, condition: bool, bus: &MemoryBus) -> bool {
+        let n = self.get_immediate_i8(bus);
+        if condition {
+            self.jr(n);
+        }
+        condition
+    }
+
+    pub(super) fn jp_if(&mut self, condition: bool, bus: &MemoryBus) -> bool {
+        let nn = self.get_immediate_u16(bus);
+        if condition {
+            self.pc = nn;
+        }
+        condition
+    }
+
+    pub(super) fn call_if(&mut self, condition: bool, bus: &mut MemoryBus) -> bool {
+        let nn = self.get_immediate_u16(bus);
+        if condition {
+            self.call(nn, bus);
+        }
+        condition
+    }
+
+    pub(super) fn ret_if(&mut self, condition: bool, bus: &MemoryBus) -> bool {
+        if condition {
+            self.ret(bus);
+        }
+        condition
+    }
+
+    /// Opcodes the CPU doesn't have, which lock up the hardware.
+    pub(super) fn illegal(&mut self, bus: &MemoryBus) {
+        let pc = self.pc.wrapping_sub(1);
+        let opcode = self.read_byte(bus, pc);
+        error!(target: "cpu", "Illegal opcode at pc={:x}, op={:x}", pc, opcode);
+        self.locked_up = true;
+        bus.report_crash(CrashReason::IllegalOpcode(opcode));
+    }
+
+    fn set_slr_flags(&mut self, c: bool, r: u8) {
+        self.f.set(Flags::ZERO, r == 0);
+        self.f.remove(Flags::N);
+        self.f.remove(Flags::H);
+        self.f.set(Flags::CARRY, c);
+    }
+
+
+    pub(super) fn sla_8(&mut self, v: u8) -> u8 {
+        let c = (0x80 & v) == 0x80;
+        let r = v << 1;
+        self.set_slr_flags(c, r);
+
+        r
+    }
+
+    pub(super) fn sra_8(&mut self, v: u8) -> u8 {
+        let c = v & 0x01 == 0x01;
+        let r = (v >> 1) | (v & 0x80);
+        self.set_slr_flags(c, r);
+
+        r
+    }
+
+    pub(super) fn srl_8(&mut self, v: u8) -> u8 {
+        let c = v & 0x01 == 0x01;
+        let r = v >> 1;
+        self.set_slr_flags(c, r);
+
+        r
+    }
+}
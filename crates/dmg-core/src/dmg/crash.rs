@@ -0,0 +1,109 @@
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::dmg::debug::{Registers, TraceEntry};
+
+/// What stopped the emulated CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrashReason {
+    /// One of the opcodes the CPU doesn't have, D3, DB, DD, E3, E4, EB, EC, ED, F4 and FC, which
+    /// lock up the hardware until it's powered off.
+    IllegalOpcode(u8),
+    /// A read the memory bus has nothing mapped at.
+    UnmappedRead(u16),
+    /// A write the memory bus has nothing mapped at.
+    UnmappedWrite(u16),
+}
+
+impl CrashReason {
+    /// The bus address involved, for reads and writes.
+    pub fn address(self) -> Option<u16> {
+        match self {
+            CrashReason::IllegalOpcode(_) => None,
+            CrashReason::UnmappedRead(addr) | CrashReason::UnmappedWrite(addr) => Some(addr),
+        }
+    }
+}
+
+impl fmt::Display for CrashReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrashReason::IllegalOpcode(opcode) => write!(f, "illegal opcode ${:02X}", opcode),
+            CrashReason::UnmappedRead(addr) => write!(f, "read from unmapped address ${:04X}", addr),
+            CrashReason::UnmappedWrite(addr) => write!(f, "write to unmapped address ${:04X}", addr),
+        }
+    }
+}
+
+/// Everything needed to reproduce a crash from a bug report, see `Core::take_crash`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashReport {
+    pub reason: CrashReason,
+    /// The registers before the crashing instruction ran, with its address in `pc`.
+    pub registers: Registers,
+    pub opcode: u8,
+    /// The switchable ROM bank at 0x4000-0x7FFF and WRAM bank at 0xD000-0xDFFF.
+    pub rom_bank: u16,
+    pub wram_bank: u16,
+    /// The last executed instructions, oldest first, ending with the crashing one.
+    pub trace: Vec<TraceEntry>,
+    /// `Core::rom_hash` of the cartridge.
+    pub rom_hash: u32,
+}
+
+impl CrashReport {
+    /// Writes the report as text, the summary and banks first and the trace last.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "Crashed: {}", self)?;
+        writeln!(writer, "Opcode: {:02X}", self.opcode)?;
+        match self.reason.address() {
+            Some(addr) => writeln!(writer, "Address: {:04X}", addr)?,
+            None => writeln!(writer, "Address: -")?,
+        }
+        writeln!(writer, "ROM bank: {}", self.rom_bank)?;
+        writeln!(writer, "WRAM bank: {}", self.wram_bank)?;
+        writeln!(writer, "ROM CRC32: {:08X}", self.rom_hash)?;
+        writeln!(writer)?;
+        writeln!(writer, "Last executed instructions, oldest first:")?;
+        for entry in &self.trace {
+            writeln!(writer, "  {:02X}:{}", entry.bank, entry)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CrashReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at PC:{:04X}", self.reason, self.registers.pc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::crash::{CrashReason, CrashReport};
+    use crate::dmg::debug::{Registers, TraceEntry};
+
+    #[test]
+    fn reports_list_the_trace_last() {
+        let registers = Registers { pc: 0x4123, ..Registers::default() };
+        let report = CrashReport {
+            reason: CrashReason::UnmappedWrite(0xfea0),
+            registers,
+            opcode: 0x77,
+            rom_bank: 3,
+            wram_bank: 1,
+            trace: vec![TraceEntry { registers, bank: 3, opcode: 0x77, mnemonic: "LD (HL),A" }],
+            rom_hash: 0xdeadbeef,
+        };
+
+        let mut text = Vec::new();
+        report.write_to(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[0], "Crashed: write to unmapped address $FEA0 at PC:4123");
+        assert_eq!(lines[2], "Address: FEA0");
+        assert_eq!(lines[5], "ROM CRC32: DEADBEEF");
+        assert!(lines.last().unwrap().starts_with("  03:PC:4123 77 LD (HL),A"));
+    }
+}
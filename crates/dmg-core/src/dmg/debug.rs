@@ -0,0 +1,332 @@
+use std::fmt;
+use std::io::Write;
+
+use log::error;
+
+use crate::dmg::annotations::annotation;
+
+/// Size of `Core::render_vram`'s image: 128 x 192 pixels of tile data next to the 256 x 256
+/// pixel background map.
+pub const VRAM_VIEW_WIDTH: usize = 128 + 256;
+pub const VRAM_VIEW_HEIGHT: usize = 256;
+
+/// CPU registers at an instruction boundary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Registers {
+    pub a: u8,
+    pub f: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+    pub sp: u16,
+    pub pc: u16,
+    pub ime: bool,
+    pub halted: bool,
+}
+
+/// Where a paused core stopped: the instruction it runs next and the clock cycle it stopped at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PausePoint {
+    pub pc: u16,
+    /// `Core::cycles_elapsed` when paused
+    pub cycle: u64,
+}
+
+/// One disassembled instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub addr: u16,
+    pub len: u16,
+    pub text: String,
+}
+
+/// LCD registers as the CPU would read them, and the window line counter, which it can't read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PpuState {
+    pub lcdc: u8,
+    pub stat: u8,
+    pub ly: u8,
+    pub lyc: u8,
+    pub scy: u8,
+    pub scx: u8,
+    pub wy: u8,
+    pub wx: u8,
+    /// The line of the window drawn next
+    pub window_line: u8,
+}
+
+impl PpuState {
+    pub fn mode(&self) -> PpuMode {
+        match self.stat & 0b11 {
+            0 => PpuMode::HBlank,
+            1 => PpuMode::VBlank,
+            2 => PpuMode::OamScan,
+            _ => PpuMode::Drawing,
+        }
+    }
+
+    pub fn lcd_control(&self) -> LcdControl {
+        LcdControl::from(self.lcdc)
+    }
+}
+
+/// The PPU mode in the low bits of STAT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PpuMode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+impl fmt::Display for PpuMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PpuMode::HBlank => "HBlank",
+            PpuMode::VBlank => "VBlank",
+            PpuMode::OamScan => "OAM scan",
+            PpuMode::Drawing => "drawing",
+        };
+        write!(f, "{} {}", *self as u8, name)
+    }
+}
+
+/// The LCDC bits, decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LcdControl {
+    pub lcd_enabled: bool,
+    /// Tile map the window is drawn from, 0x9800 or 0x9C00
+    pub window_tile_map: u16,
+    pub window_enabled: bool,
+    /// Tile data of the background and window, 0x8000 with unsigned or 0x8800 with signed tile numbers
+    pub tile_data: u16,
+    /// Tile map the background is drawn from, 0x9800 or 0x9C00
+    pub bg_tile_map: u16,
+    /// 8 or 16 pixels
+    pub sprite_height: u8,
+    pub sprites_enabled: bool,
+    /// Whether the background and window are drawn at all
+    pub bg_enabled: bool,
+}
+
+impl From<u8> for LcdControl {
+    fn from(lcdc: u8) -> Self {
+        let bit = |n: u8| lcdc & (1 << n) != 0;
+        let tile_map = |n: u8| if bit(n) { 0x9c00 } else { 0x9800 };
+
+        LcdControl {
+            lcd_enabled: bit(7),
+            window_tile_map: tile_map(6),
+            window_enabled: bit(5),
+            tile_data: if bit(4) { 0x8000 } else { 0x8800 },
+            bg_tile_map: tile_map(3),
+            sprite_height: if bit(2) { 16 } else { 8 },
+            sprites_enabled: bit(1),
+            bg_enabled: bit(0),
+        }
+    }
+}
+
+/// Events counted while emulating a frame, see `Core::frame_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Interrupts serviced, in IF bit order: VBlank, STAT, timer, serial, joypad
+    pub interrupts: [u32; 5],
+    /// Clock cycles the CPU spent in HALT
+    pub halt_cycles: u32,
+    /// OAM DMA transfers started
+    pub dma_transfers: u32,
+    /// Times the LCD was switched on or off
+    pub lcd_toggles: u32,
+}
+
+impl FrameStats {
+    pub fn total_interrupts(&self) -> u32 {
+        self.interrupts.iter().sum()
+    }
+}
+
+/// Memory a debugger can edit through `Core::memory_mut`. Each region is the whole backing store,
+/// all banks included, so offsets don't depend on the banks currently mapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryRegion {
+    Vram,
+    Wram,
+    Oam,
+    Hram,
+    CartridgeRam,
+}
+
+impl MemoryRegion {
+    pub const ALL: [MemoryRegion; 5] = [
+        MemoryRegion::Vram,
+        MemoryRegion::Wram,
+        MemoryRegion::Oam,
+        MemoryRegion::Hram,
+        MemoryRegion::CartridgeRam,
+    ];
+
+    /// Where the region's first byte is mapped for the CPU, for labelling rows in an editor.
+    pub fn base_addr(self) -> u16 {
+        match self {
+            MemoryRegion::Vram => 0x8000,
+            MemoryRegion::Wram => 0xc000,
+            MemoryRegion::Oam => 0xfe00,
+            MemoryRegion::Hram => 0xff80,
+            MemoryRegion::CartridgeRam => 0xa000,
+        }
+    }
+}
+
+impl fmt::Display for MemoryRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            MemoryRegion::Vram => "VRAM",
+            MemoryRegion::Wram => "WRAM",
+            MemoryRegion::Oam => "OAM",
+            MemoryRegion::Hram => "HRAM",
+            MemoryRegion::CartridgeRam => "Cartridge RAM",
+        })
+    }
+}
+
+/// Number of executed instructions the CPU keeps for crash reports.
+pub const TRACE_LENGTH: usize = 32;
+
+/// An executed instruction and the registers before it ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub registers: Registers,
+    /// Bank the instruction ran from, see `Symbols`
+    pub bank: u16,
+    pub opcode: u8,
+    pub mnemonic: &'static str,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let r = &self.registers;
+        write!(
+            f,
+            "PC:{:04X} {:02X} {:<12} A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X}",
+            r.pc, self.opcode, self.mnemonic.trim_end(), r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l, r.sp
+        )
+    }
+}
+
+/// The last `TRACE_LENGTH` executed instructions, overwriting the oldest.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TraceBuffer {
+    entries: Vec<TraceEntry>,
+    next: usize,
+}
+
+impl TraceBuffer {
+    pub(crate) fn push(&mut self, entry: TraceEntry) {
+        if self.entries.len() < TRACE_LENGTH {
+            self.entries.push(entry);
+        } else {
+            self.entries[self.next] = entry;
+        }
+        self.next = (self.next + 1) % TRACE_LENGTH;
+    }
+
+    /// Oldest first.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &TraceEntry> {
+        let (newer, older) = self.entries.split_at(self.next);
+        older.iter().chain(newer)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Formats `bytes`, read from `addr` on, like `hexdump -C`: 16 bytes per line with the address,
+/// the bytes in hex and printable ASCII, followed by the `annotation` of the line's first address
+/// if it has one.
+pub fn format_hexdump(addr: u16, bytes: &[u8]) -> String {
+    let mut out = String::new();
+
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+        let row_addr = addr.wrapping_add(row as u16 * 16);
+        let hex: Vec<String> = (0..16)
+            .map(|i| chunk.get(i).map_or("  ".to_string(), |b| format!("{:02X}", b)))
+            .collect();
+        let ascii: String = chunk.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+
+        out.push_str(&format!("{:04X}  {}  {}  |{}|", row_addr, hex[..8].join(" "), hex[8..].join(" "), ascii));
+        if let Some(annotation) = annotation(row_addr) {
+            out.push_str("  ");
+            out.push_str(&annotation);
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Where the CPU writes its gameboy-doctor trace, one line per executed instruction. Cloned cores
+/// (rewind snapshots and the like) don't log.
+#[derive(Default)]
+pub(crate) struct DoctorLog(Option<Box<dyn Write + Send>>);
+
+impl DoctorLog {
+    pub(crate) fn new(writer: impl Write + Send + 'static) -> Self {
+        DoctorLog(Some(Box::new(writer)))
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Logging stops at the first failed write, rather than failing emulation.
+    pub(crate) fn write_line(&mut self, line: &str) {
+        if let Some(writer) = &mut self.0 {
+            if let Err(e) = writeln!(writer, "{}", line) {
+                error!("Failed writing gameboy-doctor log: {}", e);
+                self.0 = None;
+            }
+        }
+    }
+}
+
+impl Clone for DoctorLog {
+    fn clone(&self) -> Self {
+        DoctorLog(None)
+    }
+}
+
+impl fmt::Debug for DoctorLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DoctorLog").field(&self.is_enabled()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::debug::{Registers, TraceBuffer, TraceEntry, TRACE_LENGTH};
+
+    fn entry_at(pc: u16) -> TraceEntry {
+        let registers = Registers { a: 0, f: 0, b: 0, c: 0, d: 0, e: 0, h: 0, l: 0, sp: 0xfffe, pc, ime: false, halted: false };
+        TraceEntry { registers, bank: 0, opcode: 0x00, mnemonic: "NOP " }
+    }
+
+    #[test]
+    fn trace_keeps_the_latest_instructions_oldest_first() {
+        let mut trace = TraceBuffer::default();
+        for pc in 0..TRACE_LENGTH as u16 + 5 {
+            trace.push(entry_at(pc));
+        }
+
+        let pcs: Vec<u16> = trace.iter().map(|e| e.registers.pc).collect();
+        assert_eq!(pcs, (5..TRACE_LENGTH as u16 + 5).collect::<Vec<_>>());
+        assert_eq!(
+            entry_at(0x150).to_string(),
+            "PC:0150 00 NOP          A:00 F:00 B:00 C:00 D:00 E:00 H:00 L:00 SP:FFFE"
+        );
+    }
+}
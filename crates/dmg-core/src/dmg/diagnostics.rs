@@ -0,0 +1,293 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::mem;
+
+use log::{debug, warn};
+
+use crate::dmg::annotations::describe_address;
+use crate::dmg::debug::{PpuMode, Registers};
+use crate::dmg::gpu::GPU;
+
+// RAM from VRAM on, where code can be copied to and modified
+const RAM_START: usize = 0x8000;
+
+/// Something a game did that works on hardware but is a common source of confusing emulator bugs,
+/// worth mentioning when reporting one. See `Core::enable_execution_diagnostics`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExecutionWarning {
+    /// Code ran from cartridge RAM.
+    ExternalRamExecution { pc: u16 },
+    /// The instruction at `pc` wrote to `addr`, in RAM that code has run from.
+    SelfModifyingCode { pc: u16, addr: u16 },
+    /// The instruction at `pc` switched the ROM or WRAM bank it was running from.
+    BankSwitchUnderPc { pc: u16, from: u16, to: u16 },
+}
+
+impl ExecutionWarning {
+    pub fn pc(&self) -> u16 {
+        match *self {
+            ExecutionWarning::ExternalRamExecution { pc }
+            | ExecutionWarning::SelfModifyingCode { pc, .. }
+            | ExecutionWarning::BankSwitchUnderPc { pc, .. } => pc,
+        }
+    }
+}
+
+impl fmt::Display for ExecutionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ExecutionWarning::ExternalRamExecution { pc } => write!(f, "{:04X}: executing from cartridge RAM", pc),
+            ExecutionWarning::SelfModifyingCode { pc, addr } => {
+                write!(f, "{:04X}: wrote to {}, which has been executed", pc, describe_address(addr))
+            }
+            ExecutionWarning::BankSwitchUnderPc { pc, from, to } => {
+                write!(f, "{:04X}: switched the bank it runs from, {} to {}", pc, from, to)
+            }
+        }
+    }
+}
+
+/// Something a game did that the emulator handled without emulating it exactly, for frontends to
+/// show as a warning. See `Core::take_diagnostics`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Diagnostic {
+    /// A read from an IO address nothing answers at on this model, which reads as 0xFF.
+    UnknownIoRead { addr: u16 },
+    /// A write to an IO address nothing answers at on this model, which is dropped.
+    UnknownIoWrite { addr: u16, value: u8 },
+    /// A write to the cartridge's mapper that isn't emulated.
+    UnsupportedMbcWrite { addr: u16, value: u8 },
+    /// A write to a sound register while the APU is powered off, which is dropped.
+    ApuWriteWhileOff { addr: u16, value: u8 },
+}
+
+impl Diagnostic {
+    pub fn addr(&self) -> u16 {
+        match *self {
+            Diagnostic::UnknownIoRead { addr }
+            | Diagnostic::UnknownIoWrite { addr, .. }
+            | Diagnostic::UnsupportedMbcWrite { addr, .. }
+            | Diagnostic::ApuWriteWhileOff { addr, .. } => addr,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Diagnostic::UnknownIoRead { addr } => write!(f, "read from unused IO address {:04X}", addr),
+            Diagnostic::UnknownIoWrite { addr, value } => {
+                write!(f, "wrote {:02X} to unused IO address {:04X}", value, addr)
+            }
+            Diagnostic::UnsupportedMbcWrite { addr, value } => {
+                write!(f, "wrote {:02X} to the mapper at {:04X}, which isn't emulated", value, addr)
+            }
+            Diagnostic::ApuWriteWhileOff { addr, value } => {
+                write!(f, "wrote {:02X} to {} while sound is off", value, describe_address(addr))
+            }
+        }
+    }
+}
+
+/// Collects `Diagnostic`s until they're taken. Each kind is reported once per address, so a game
+/// doing the same thing every frame doesn't flood the queue.
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticQueue {
+    reported: HashSet<(mem::Discriminant<Diagnostic>, u16)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl DiagnosticQueue {
+    /// Returns the diagnostics since the last call, oldest first.
+    pub fn take(&mut self) -> Vec<Diagnostic> {
+        mem::take(&mut self.diagnostics)
+    }
+
+    pub(crate) fn report(&mut self, diagnostic: Diagnostic) {
+        if self.reported.insert((mem::discriminant(&diagnostic), diagnostic.addr())) {
+            debug!(target: "diagnostics", "{}", diagnostic);
+            self.diagnostics.push(diagnostic);
+        }
+    }
+}
+
+/// Watches execution for `ExecutionWarning`s. Each kind of warning is reported once per address,
+/// logged with the target `cpu` and kept for `take_warnings`.
+#[derive(Debug, Clone)]
+pub struct ExecutionDiagnostics {
+    // One flag per address from `RAM_START` on
+    executed_ram: Vec<bool>,
+    // Writes to executed RAM by the instruction running now
+    pending_writes: Vec<u16>,
+    reported: HashSet<(mem::Discriminant<ExecutionWarning>, u16)>,
+    warnings: Vec<ExecutionWarning>,
+}
+
+impl Default for ExecutionDiagnostics {
+    fn default() -> Self {
+        Self {
+            executed_ram: vec![false; 0x10000 - RAM_START],
+            pending_writes: vec![],
+            reported: HashSet::new(),
+            warnings: vec![],
+        }
+    }
+}
+
+impl ExecutionDiagnostics {
+    /// Returns the warnings since the last call, oldest first.
+    pub fn take_warnings(&mut self) -> Vec<ExecutionWarning> {
+        mem::take(&mut self.warnings)
+    }
+
+    pub(crate) fn mark_executed(&mut self, pc: u16, len: u16) {
+        if (0xa000..=0xbfff).contains(&pc) {
+            self.report(ExecutionWarning::ExternalRamExecution { pc });
+        }
+        for addr in (pc..pc.saturating_add(len)).map(usize::from).filter(|&addr| addr >= RAM_START) {
+            self.executed_ram[addr - RAM_START] = true;
+        }
+    }
+
+    pub(crate) fn record_write(&mut self, addr: u16) {
+        if addr as usize >= RAM_START && self.executed_ram[addr as usize - RAM_START] {
+            self.pending_writes.push(addr);
+        }
+    }
+
+    /// Reports what the instruction at `pc` did, given the bank it ran from before and after.
+    pub(crate) fn finish_instruction(&mut self, pc: u16, bank_before: u16, bank_after: u16) {
+        for addr in mem::take(&mut self.pending_writes) {
+            self.report(ExecutionWarning::SelfModifyingCode { pc, addr });
+        }
+        if bank_before != bank_after {
+            self.report(ExecutionWarning::BankSwitchUnderPc { pc, from: bank_before, to: bank_after });
+        }
+    }
+
+    fn report(&mut self, warning: ExecutionWarning) {
+        if self.reported.insert((mem::discriminant(&warning), warning.pc())) {
+            warn!(target: "cpu", "{}", warning);
+            self.warnings.push(warning);
+        }
+    }
+}
+
+/// A mistake in how a game uses the LCD that the emulator lets pass but hardware doesn't, for
+/// homebrew developers checking their games. See `Core::enable_developer_mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeveloperWarning {
+    /// The instruction at `pc` wrote to VRAM while the PPU was drawing, which hardware ignores.
+    VramWriteWhileDrawing { pc: u16, addr: u16 },
+    /// The instruction at `pc` wrote to OAM during OAM scan or drawing, which hardware ignores.
+    /// OAM DMA is fine.
+    OamWriteWhileBusy { pc: u16, addr: u16 },
+    /// The instruction at `pc` turned the LCD off outside VBlank, which can damage a DMG's screen.
+    LcdOffOutsideVblank { pc: u16, ly: u8 },
+    /// The instruction at `pc` read LY while the LCD is off, where it stays 0, e.g. waiting for
+    /// VBlank after turning the LCD off, which never comes.
+    LyReadWhileLcdOff { pc: u16 },
+    /// The instruction at `pc` increased or decreased a register pair pointing at FE00-FEFF during
+    /// OAM scan, which corrupts OAM on the DMG, MGB and SGB.
+    OamBug { pc: u16 },
+}
+
+impl DeveloperWarning {
+    pub fn pc(&self) -> u16 {
+        match *self {
+            DeveloperWarning::VramWriteWhileDrawing { pc, .. }
+            | DeveloperWarning::OamWriteWhileBusy { pc, .. }
+            | DeveloperWarning::LcdOffOutsideVblank { pc, .. }
+            | DeveloperWarning::LyReadWhileLcdOff { pc }
+            | DeveloperWarning::OamBug { pc } => pc,
+        }
+    }
+}
+
+impl fmt::Display for DeveloperWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DeveloperWarning::VramWriteWhileDrawing { pc, addr } => {
+                write!(f, "{:04X}: wrote to VRAM at {:04X} while the PPU was drawing", pc, addr)
+            }
+            DeveloperWarning::OamWriteWhileBusy { pc, addr } => {
+                write!(f, "{:04X}: wrote to OAM at {:04X} while the PPU was using it", pc, addr)
+            }
+            DeveloperWarning::LcdOffOutsideVblank { pc, ly } => {
+                write!(f, "{:04X}: turned the LCD off outside VBlank, on line {}", pc, ly)
+            }
+            DeveloperWarning::LyReadWhileLcdOff { pc } => write!(f, "{:04X}: read LY while the LCD is off", pc),
+            DeveloperWarning::OamBug { pc } => write!(f, "{:04X}: triggered the OAM bug", pc),
+        }
+    }
+}
+
+/// Watches the CPU's accesses for `DeveloperWarning`s. Each kind of warning is reported once per
+/// address, logged with the target `developer` and kept for `take_warnings`. The checks go by the
+/// PPU mode at the start of each instruction.
+#[derive(Debug, Clone, Default)]
+pub struct DeveloperChecks {
+    // The instruction running now
+    pc: u16,
+    // The DMG CPU, which has the OAM bug
+    dmg_cpu: bool,
+    reported: HashSet<(mem::Discriminant<DeveloperWarning>, u16)>,
+    warnings: Vec<DeveloperWarning>,
+}
+
+impl DeveloperChecks {
+    pub fn new(dmg_cpu: bool) -> Self {
+        Self { dmg_cpu, ..Self::default() }
+    }
+
+    /// Returns the warnings since the last call, oldest first.
+    pub fn take_warnings(&mut self) -> Vec<DeveloperWarning> {
+        mem::take(&mut self.warnings)
+    }
+
+    /// Checks the instruction `opcode` about to run with `registers`, and blames it for the
+    /// accesses until the next one.
+    pub(crate) fn start_instruction(&mut self, registers: &Registers, opcode: u8, ppu: &GPU) {
+        self.pc = registers.pc;
+
+        // INC rr and DEC rr, LD (HL+/-) both ways, and PUSH and POP, which go through SP
+        let pair = match opcode {
+            0x03 | 0x0b => Some(u16::from_be_bytes([registers.b, registers.c])),
+            0x13 | 0x1b => Some(u16::from_be_bytes([registers.d, registers.e])),
+            0x23 | 0x2b | 0x22 | 0x2a | 0x32 | 0x3a => Some(u16::from_be_bytes([registers.h, registers.l])),
+            0x33 | 0x3b | 0xc1 | 0xd1 | 0xe1 | 0xf1 | 0xc5 | 0xd5 | 0xe5 | 0xf5 => Some(registers.sp),
+            _ => None,
+        };
+        if self.dmg_cpu && ppu.mode() == PpuMode::OamScan && pair.is_some_and(|pair| pair >> 8 == 0xfe) {
+            self.report(DeveloperWarning::OamBug { pc: self.pc });
+        }
+    }
+
+    pub(crate) fn check_read(&mut self, addr: u16, ppu: &GPU) {
+        if addr == 0xff44 && !ppu.lcd_enabled() {
+            self.report(DeveloperWarning::LyReadWhileLcdOff { pc: self.pc });
+        }
+    }
+
+    pub(crate) fn check_write(&mut self, addr: u16, value: u8, ppu: &GPU) {
+        let pc = self.pc;
+        match (addr, ppu.mode()) {
+            (0x8000..=0x9fff, PpuMode::Drawing) => self.report(DeveloperWarning::VramWriteWhileDrawing { pc, addr }),
+            (0xfe00..=0xfe9f, PpuMode::OamScan | PpuMode::Drawing) => {
+                self.report(DeveloperWarning::OamWriteWhileBusy { pc, addr })
+            }
+            (0xff40, mode) if value & 0x80 == 0 && ppu.lcd_enabled() && mode != PpuMode::VBlank => {
+                let ly = ppu.read_register(0xff44);
+                self.report(DeveloperWarning::LcdOffOutsideVblank { pc, ly })
+            }
+            _ => {}
+        }
+    }
+
+    fn report(&mut self, warning: DeveloperWarning) {
+        if self.reported.insert((mem::discriminant(&warning), warning.pc())) {
+            warn!(target: "developer", "{}", warning);
+            self.warnings.push(warning);
+        }
+    }
+}
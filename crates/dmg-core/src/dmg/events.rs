@@ -0,0 +1,72 @@
+use std::sync::{Arc, Mutex};
+
+use crate::dmg::debug::PausePoint;
+
+/// Something that happened while emulating, delivered to the subscribers added with
+/// `Core::subscribe` as soon as the instruction causing it has run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The last line of a frame has been drawn, see `Core::frame`.
+    FrameReady,
+    /// The game started a link port transfer sending this byte.
+    SerialByte(u8),
+    /// Battery-backed cartridge RAM changed after having been saved, so there is something new to
+    /// write to the `.sav` file. Sent again on the next change once `take_battery_ram_written` has
+    /// been called.
+    BatteryRamDirty,
+    /// The CPU is about to run the instruction at a breakpoint set with `Core::add_breakpoint`.
+    BreakpointHit(u16),
+    /// The game turned the LCD on (true) or off (false).
+    LcdToggled(bool),
+    /// The boot ROM unmapped itself by writing 0xFF50 and handed over to the game, which starts at
+    /// 0x100 next. Not sent for cores starting from the post-boot state.
+    BootRomFinished,
+    /// The core paused between two instructions, after `Core::set_paused` or a pause request.
+    Paused(PausePoint),
+}
+
+/// Identifies a subscriber for `Core::unsubscribe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+type Subscriber = Arc<Mutex<dyn FnMut(&Event) + Send>>;
+
+/// The subscribers of a core. Clones of the core share them.
+#[derive(Clone, Default)]
+pub(crate) struct Subscribers {
+    next_id: u64,
+    subscribers: Vec<(SubscriptionId, Subscriber)>,
+    // Whether `BatteryRamDirty` has been sent for the unsaved changes
+    battery_ram_dirty: bool,
+}
+
+impl Subscribers {
+    pub fn add(&mut self, subscriber: impl FnMut(&Event) + Send + 'static) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.subscribers.push((id, Arc::new(Mutex::new(subscriber))));
+        id
+    }
+
+    pub fn remove(&mut self, id: SubscriptionId) {
+        self.subscribers.retain(|(subscriber_id, _)| *subscriber_id != id);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscribers.is_empty()
+    }
+
+    pub fn emit(&self, event: Event) {
+        for (_, subscriber) in &self.subscribers {
+            (subscriber.lock().unwrap())(&event);
+        }
+    }
+
+    /// Sends `BatteryRamDirty` when unsaved changes first show up.
+    pub fn update_battery_ram_dirty(&mut self, dirty: bool) {
+        if dirty && !self.battery_ram_dirty {
+            self.emit(Event::BatteryRamDirty);
+        }
+        self.battery_ram_dirty = dirty;
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt::Write;
+
+// VRAM from 0x8000 up to the tile maps at 0x9800: 384 tiles of 16 bytes per bank
+const TILE_DATA_SIZE: usize = 0x1800;
+const VRAM_BANK_SIZE: usize = 0x2000;
+
+/// Memory in the formats other tools open, for ROM hacking alongside the emulator. See
+/// `Core::export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Export {
+    /// WRAM as raw bytes from 0xC000 on: 8K on a DMG, all eight 4K banks in order in CGB mode.
+    Wram,
+    /// Cartridge RAM as raw bytes, all banks in order, the same as a `.sav` file. Empty for
+    /// cartridges without RAM.
+    CartridgeRam,
+    /// The tile data at 0x8000-0x97FF, 384 tiles of 16 bytes in the Game Boy's 2bpp format,
+    /// which YY-CHR and other tile editors open as "GB" graphics. Bank 1's tiles follow bank 0's
+    /// in CGB mode.
+    VramTiles,
+    /// OAM as CSV, a header and a row per sprite with its raw position and tile and its flags
+    /// split up.
+    OamCsv,
+}
+
+impl Export {
+    pub const ALL: [Export; 4] = [Export::Wram, Export::CartridgeRam, Export::VramTiles, Export::OamCsv];
+
+    /// A file name with the extension tools expect, e.g. `vram.chr`.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Export::Wram => "wram.bin",
+            Export::CartridgeRam => "cartridge_ram.sav",
+            Export::VramTiles => "vram.chr",
+            Export::OamCsv => "oam.csv",
+        }
+    }
+}
+
+/// The tile data of the first `banks` banks of `vram`, leaving out the tile maps.
+pub fn vram_tiles(vram: &[u8], banks: usize) -> Vec<u8> {
+    vram.chunks(VRAM_BANK_SIZE)
+        .take(banks)
+        .flat_map(|bank| &bank[..TILE_DATA_SIZE.min(bank.len())])
+        .copied()
+        .collect()
+}
+
+/// `oam` as CSV. Y and X are as stored, 16 and 8 more than the sprite's top left on screen.
+pub fn oam_csv(oam: &[u8]) -> String {
+    let mut csv = String::from("sprite,y,x,tile,behind_bg,y_flip,x_flip,dmg_palette,vram_bank,cgb_palette\n");
+    for (sprite, entry) in oam.chunks_exact(4).enumerate() {
+        let [y, x, tile, flags] = [entry[0], entry[1], entry[2], entry[3]];
+        // Writing to a String can't fail
+        writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{},{},{}",
+            sprite,
+            y,
+            x,
+            tile,
+            flags >> 7 & 1,
+            flags >> 6 & 1,
+            flags >> 5 & 1,
+            flags >> 4 & 1,
+            flags >> 3 & 1,
+            flags & 0x07
+        )
+        .unwrap();
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::export::{oam_csv, vram_tiles};
+
+    #[test]
+    fn tiles_leave_out_the_maps() {
+        let mut vram = vec![0; 0x4000];
+        vram[0x17ff] = 1;
+        vram[0x1800] = 2;
+        vram[0x2000] = 3;
+
+        let tiles = vram_tiles(&vram, 1);
+        assert_eq!(tiles.len(), 0x1800);
+        assert_eq!(tiles[0x17ff], 1);
+
+        let tiles = vram_tiles(&vram, 2);
+        assert_eq!(tiles.len(), 0x3000);
+        assert_eq!(tiles[0x1800], 3);
+    }
+
+    #[test]
+    fn oam_has_a_row_per_sprite() {
+        let mut oam = vec![0; 0xa0];
+        oam[4..8].copy_from_slice(&[16, 8, 0x42, 0b1010_1011]);
+
+        let csv = oam_csv(&oam);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 41);
+        assert_eq!(lines[0], "sprite,y,x,tile,behind_bg,y_flip,x_flip,dmg_palette,vram_bank,cgb_palette");
+        assert_eq!(lines[2], "1,16,8,66,1,0,1,0,1,3");
+    }
+}
@@ -0,0 +1,324 @@
+use std::fmt;
+
+use crate::dmg::core::Core;
+use crate::error::{Error, Result};
+
+/// An expression over registers and memory for debuggers to watch, e.g. `[HL+1]*256+[HL]`.
+///
+/// Numbers are decimal, or hex with a `$` or `0x` prefix. Register names (`A`, `HL`, `SP`, ...)
+/// read the register, `[expr]` reads the byte at that address, and the usual C operators work
+/// on unsigned 32-bit values, with comparisons and `&&`/`||`/`!` giving 0 or 1. Arithmetic
+/// wraps, and dividing by zero gives 0, so evaluating never fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr {
+    source: String,
+    node: Node,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Number(u32),
+    Register(Register),
+    Memory(Box<Node>),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    A, F, B, C, D, E, H, L, AF, BC, DE, HL, SP, PC,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnaryOp {
+    Neg,
+    Not,
+    Complement,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Mul, Div, Rem,
+    Add, Sub,
+    Shl, Shr,
+    Lt, Le, Gt, Ge,
+    Eq, Ne,
+    And,
+    Xor,
+    Or,
+    LogicalAnd,
+    LogicalOr,
+}
+
+impl BinaryOp {
+    // Tokens longest first, so `<<` isn't read as `<`, with C's precedence, higher binding tighter
+    #[rustfmt::skip]
+    const TABLE: [(&'static str, BinaryOp, u8); 18] = [
+        ("<<", BinaryOp::Shl, 7), (">>", BinaryOp::Shr, 7),
+        ("<=", BinaryOp::Le, 6), (">=", BinaryOp::Ge, 6),
+        ("==", BinaryOp::Eq, 5), ("!=", BinaryOp::Ne, 5),
+        ("&&", BinaryOp::LogicalAnd, 1), ("||", BinaryOp::LogicalOr, 0),
+        ("*", BinaryOp::Mul, 9), ("/", BinaryOp::Div, 9), ("%", BinaryOp::Rem, 9),
+        ("+", BinaryOp::Add, 8), ("-", BinaryOp::Sub, 8),
+        ("<", BinaryOp::Lt, 6), (">", BinaryOp::Gt, 6),
+        ("&", BinaryOp::And, 4), ("^", BinaryOp::Xor, 3), ("|", BinaryOp::Or, 2),
+    ];
+
+    fn apply(self, a: u32, b: u32) -> u32 {
+        match self {
+            BinaryOp::Mul => a.wrapping_mul(b),
+            BinaryOp::Div => a.checked_div(b).unwrap_or(0),
+            BinaryOp::Rem => a.checked_rem(b).unwrap_or(0),
+            BinaryOp::Add => a.wrapping_add(b),
+            BinaryOp::Sub => a.wrapping_sub(b),
+            BinaryOp::Shl => a.checked_shl(b).unwrap_or(0),
+            BinaryOp::Shr => a.checked_shr(b).unwrap_or(0),
+            BinaryOp::Lt => (a < b) as u32,
+            BinaryOp::Le => (a <= b) as u32,
+            BinaryOp::Gt => (a > b) as u32,
+            BinaryOp::Ge => (a >= b) as u32,
+            BinaryOp::Eq => (a == b) as u32,
+            BinaryOp::Ne => (a != b) as u32,
+            BinaryOp::And => a & b,
+            BinaryOp::Xor => a ^ b,
+            BinaryOp::Or => a | b,
+            BinaryOp::LogicalAnd => (a != 0 && b != 0) as u32,
+            BinaryOp::LogicalOr => (a != 0 || b != 0) as u32,
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser { text, rest: text };
+        let node = parser.expression(0)?;
+        parser.skip_whitespace();
+        if !parser.rest.is_empty() {
+            return Err(parser.error("unexpected"));
+        }
+        Ok(Self { source: text.trim().to_string(), node })
+    }
+
+    /// The value of the expression in `core`'s current state. Memory is read as the CPU would
+    /// see it, without side effects.
+    pub fn eval(&self, core: &Core) -> u32 {
+        eval(&self.node, core)
+    }
+
+    /// The address read when the whole expression is a byte at a fixed address, like `[$FF40]`,
+    /// for labelling it with `annotations::describe_address`.
+    pub fn address(&self) -> Option<u16> {
+        match &self.node {
+            Node::Memory(addr) => match **addr {
+                Node::Number(addr) => Some(addr as u16),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// True when the expression is non-zero, for conditions.
+    pub fn is_true(&self, core: &Core) -> bool {
+        self.eval(core) != 0
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn eval(node: &Node, core: &Core) -> u32 {
+    match node {
+        Node::Number(n) => *n,
+        Node::Register(register) => {
+            let r = core.registers();
+            let pair = |hi: u8, lo: u8| u16::from_be_bytes([hi, lo]) as u32;
+            match register {
+                Register::A => r.a as u32,
+                Register::F => r.f as u32,
+                Register::B => r.b as u32,
+                Register::C => r.c as u32,
+                Register::D => r.d as u32,
+                Register::E => r.e as u32,
+                Register::H => r.h as u32,
+                Register::L => r.l as u32,
+                Register::AF => pair(r.a, r.f),
+                Register::BC => pair(r.b, r.c),
+                Register::DE => pair(r.d, r.e),
+                Register::HL => pair(r.h, r.l),
+                Register::SP => r.sp as u32,
+                Register::PC => r.pc as u32,
+            }
+        }
+        Node::Memory(addr) => core.read_byte(eval(addr, core) as u16) as u32,
+        Node::Unary(op, operand) => {
+            let value = eval(operand, core);
+            match op {
+                UnaryOp::Neg => value.wrapping_neg(),
+                UnaryOp::Not => (value == 0) as u32,
+                UnaryOp::Complement => !value,
+            }
+        }
+        Node::Binary(op, a, b) => op.apply(eval(a, core), eval(b, core)),
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    rest: &'a str,
+}
+
+impl Parser<'_> {
+    fn error(&self, what: &str) -> Error {
+        let position = self.text.len() - self.rest.len();
+        match self.rest.chars().next() {
+            Some(c) => Error::InvalidExpression(format!("{} {:?} at column {}", what, c, position + 1)),
+            None => Error::InvalidExpression("unexpected end".to_string()),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Binary operators binding at least as tightly as `min_precedence`, all left-associative.
+    fn expression(&mut self, min_precedence: u8) -> Result<Node> {
+        let mut node = self.unary()?;
+
+        loop {
+            self.skip_whitespace();
+            let Some(&(token, op, precedence)) = BinaryOp::TABLE.iter().find(|(token, ..)| self.rest.starts_with(token)) else {
+                return Ok(node);
+            };
+            if precedence < min_precedence {
+                return Ok(node);
+            }
+            self.rest = &self.rest[token.len()..];
+            let rhs = self.expression(precedence + 1)?;
+            node = Node::Binary(op, Box::new(node), Box::new(rhs));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node> {
+        let op = if self.eat("-") {
+            UnaryOp::Neg
+        } else if self.eat("!") {
+            UnaryOp::Not
+        } else if self.eat("~") {
+            UnaryOp::Complement
+        } else {
+            return self.primary();
+        };
+        Ok(Node::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Node> {
+        if self.eat("(") {
+            let node = self.expression(0)?;
+            return if self.eat(")") { Ok(node) } else { Err(self.error("expected ')', found")) };
+        }
+        if self.eat("[") {
+            let node = self.expression(0)?;
+            return if self.eat("]") { Ok(Node::Memory(Box::new(node))) } else { Err(self.error("expected ']', found")) };
+        }
+
+        self.skip_whitespace();
+        let end = self.rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '$').unwrap_or(self.rest.len());
+        let (word, rest) = self.rest.split_at(end);
+        let node = if let Some(register) = parse_register(word) {
+            Node::Register(register)
+        } else if let Some(hex) = word.strip_prefix('$').or_else(|| word.strip_prefix("0x")).or_else(|| word.strip_prefix("0X")) {
+            Node::Number(u32::from_str_radix(hex, 16).map_err(|_| Error::InvalidExpression(format!("invalid number {:?}", word)))?)
+        } else if word.starts_with(|c: char| c.is_ascii_digit()) {
+            Node::Number(word.parse().map_err(|_| Error::InvalidExpression(format!("invalid number {:?}", word)))?)
+        } else if word.is_empty() {
+            return Err(self.error("unexpected"));
+        } else {
+            return Err(Error::InvalidExpression(format!("unknown name {:?}", word)));
+        };
+
+        self.rest = rest;
+        Ok(node)
+    }
+}
+
+fn parse_register(name: &str) -> Option<Register> {
+    let register = match name.to_ascii_uppercase().as_str() {
+        "A" => Register::A,
+        "F" => Register::F,
+        "B" => Register::B,
+        "C" => Register::C,
+        "D" => Register::D,
+        "E" => Register::E,
+        "H" => Register::H,
+        "L" => Register::L,
+        "AF" => Register::AF,
+        "BC" => Register::BC,
+        "DE" => Register::DE,
+        "HL" => Register::HL,
+        "SP" => Register::SP,
+        "PC" => Register::PC,
+        _ => return None,
+    };
+    Some(register)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::core::Core;
+    use crate::dmg::expr::Expr;
+
+    fn eval(text: &str, core: &Core) -> u32 {
+        Expr::parse(text).unwrap().eval(core)
+    }
+
+    #[test]
+    fn evaluates_registers_memory_and_operators() {
+        let mut core = Core::load_without_boot_rom(None).unwrap();
+        core.write_byte(0xc0a0, 0x12);
+        core.write_byte(0xc0a1, 0x34);
+        let hl = {
+            let r = core.registers();
+            u16::from_be_bytes([r.h, r.l]) as u32
+        };
+
+        assert_eq!(eval("[0xC0A0]", &core), 0x12);
+        assert_eq!(eval("[$c0a1] * 256 + [$c0a0]", &core), 0x3412);
+        assert_eq!(eval("hl", &core), hl);
+        assert_eq!(eval("HL + 1", &core), hl + 1);
+        assert_eq!(eval("1 + 2 * 3", &core), 7);
+        assert_eq!(eval("(1 + 2) * 3", &core), 9);
+        assert_eq!(eval("1 << 4 | 1", &core), 0x11);
+        assert_eq!(eval("[0xc0a0] == 0x12 && !0", &core), 1);
+        assert_eq!(eval("10 - 3 - 2", &core), 5);
+        assert_eq!(eval("5 / 0", &core), 0);
+        assert_eq!(eval("-1", &core), u32::MAX);
+    }
+
+    #[test]
+    fn reads_of_fixed_addresses_have_an_address() {
+        assert_eq!(Expr::parse("[$FF40]").unwrap().address(), Some(0xff40));
+        assert_eq!(Expr::parse("[HL]").unwrap().address(), None);
+        assert_eq!(Expr::parse("[$FF40] & 1").unwrap().address(), None);
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for text in ["", "[c0a0", "1 +", "(1", "xyz", "0xzz", "1 2"] {
+            assert!(Expr::parse(text).is_err(), "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::dmg::sound::sampler::StereoSample;
+use crate::dmg::traits::VideoSink;
+
+/// The size of the screen in pixels.
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+/// A whole screen of 0RGB pixels, row by row.
+pub type FrameBuffer = [u32; SCREEN_WIDTH * SCREEN_HEIGHT];
+
+/// A whole screen of color numbers 0-3, row by row: the colors in the tiles, before the palettes
+/// map them to shades.
+pub type ColorIndices = [u8; SCREEN_WIDTH * SCREEN_HEIGHT];
+
+/// One rendered frame and the audio samples generated while it was emulated.
+#[derive(Clone)]
+pub struct Frame {
+    pub pixels: Box<FrameBuffer>,
+    pub audio: Vec<StereoSample>,
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self {
+            pixels: Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT]),
+            audio: vec![],
+        }
+    }
+}
+
+/// Converts an 0RGB pixel to RGB565.
+pub fn rgb565(pixel: u32) -> u16 {
+    let r = (pixel >> 16) & 0xff;
+    let g = (pixel >> 8) & 0xff;
+    let b = pixel & 0xff;
+    ((r >> 3) << 11 | (g >> 2) << 5 | b >> 3) as u16
+}
+
+/// Video sink that hands each line to `push_line` as RGB565, for displays that are written a line
+/// at a time and have no room for a full framebuffer.
+pub struct Rgb565Lines<F: FnMut(usize, &[u16; SCREEN_WIDTH])> {
+    pub push_line: F,
+}
+
+impl<F: FnMut(usize, &[u16; SCREEN_WIDTH])> VideoSink for Rgb565Lines<F> {
+    fn push_line(&mut self, y: usize, line: &[u32; SCREEN_WIDTH]) {
+        (self.push_line)(y, &line.map(rgb565));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::frame::rgb565;
+
+    #[test]
+    fn converts_to_rgb565() {
+        assert_eq!(rgb565(0xffffffff), 0xffff);
+        assert_eq!(rgb565(0xff000000), 0x0000);
+        assert_eq!(rgb565(0x00ff0000), 0xf800);
+        assert_eq!(rgb565(0x0000ff00), 0x07e0);
+        assert_eq!(rgb565(0x000000ff), 0x001f);
+    }
+}
@@ -0,0 +1,971 @@
+use std::fmt::Debug;
+use std::usize;
+use bit_field::BitField;
+
+use bitflags::bitflags;
+use log::debug;
+use serde::{Serialize, Deserialize};
+
+use crate::dmg::colorization::Colorization;
+use crate::dmg::debug::PpuMode;
+use crate::dmg::frame::{ColorIndices, FrameBuffer, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::dmg::intf::InterruptFlag;
+use crate::dmg::traits::VideoSink;
+
+pub const VRAM_BEGIN: usize = 0x8000;
+pub const VRAM_END: usize = 0x9fff;
+pub const VRAM_SIZE: usize = VRAM_END - VRAM_BEGIN + 1;
+const VRAM_BANKS: usize = 2;
+pub const OAM_SIZE: usize = 0xA0;
+
+bitflags! {
+    #[derive(Serialize, Deserialize)]
+    pub struct Lcdc: u8 {
+        const LCD_DISPLAY_ENABLE = 0b1000_0000;
+        const WINDOW_TILE_MAP_DISPLAY_SELECT = 0b0100_0000;
+        const WINDOW_DISPLAY_ENABLE = 0b0010_0000;
+        const BG_AND_WINDOW_TILE_DATA_SELECT = 0b0001_0000;
+        const BG_TILE_MAP_DISPLAY_SELECT = 0b0000_1000;
+        const OBJ_SIZE = 0b0000_0100;
+        const OBJ_DISPLAY_ENABLE = 0b0000_0010;
+        const BG_AND_WINDOW_DISPLAY = 0b0000_0001;
+    }
+}
+
+impl Lcdc {
+    fn new() -> Self {
+        Self { bits: 0b0100_1000 }
+    }
+
+    fn lcd_display_enable(&self) -> bool {
+        self.contains(Lcdc::LCD_DISPLAY_ENABLE)
+    }
+
+    fn window_display_enable(&self) -> bool { self.contains(Lcdc::WINDOW_DISPLAY_ENABLE) }
+
+    fn bg_and_window_tile_data_select(&self) -> u16 {
+        if self.contains(Lcdc::BG_AND_WINDOW_TILE_DATA_SELECT) {
+            0x8000
+        } else {
+            0x8800
+        }
+    }
+
+    fn bg_and_window_display_enable(&self) -> bool {
+        self.contains(Lcdc::BG_AND_WINDOW_DISPLAY)
+    }
+
+    fn window_tile_map_display_select(&self) -> u16 {
+        if self.contains(Lcdc::WINDOW_TILE_MAP_DISPLAY_SELECT) {
+            0x9c00
+        } else {
+            0x9800
+        }
+    }
+
+    fn bg_tile_map_display_select(&self) -> u16 {
+        if self.contains(Lcdc::BG_TILE_MAP_DISPLAY_SELECT) {
+            0x9c00
+        } else {
+            0x9800
+        }
+    }
+
+    fn obj_display_enable(&self) -> bool {
+        self.contains(Lcdc::OBJ_DISPLAY_ENABLE)
+    }
+
+    fn obj_size(&self) -> u16 {
+        if self.contains(Lcdc::OBJ_SIZE) {
+            16
+        } else {
+            8
+        }
+    }
+}
+
+/// Which palette a pixel was drawn with, for colorization.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Layer {
+    Background,
+    Obj0,
+    Obj1,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[repr(u8)]
+enum TilePixelValue {
+    Black = 0b11,
+    LightGray = 0b01,
+    DarkGray = 0b10,
+    White = 0b00,
+}
+
+impl TilePixelValue {
+    fn from_palette_and_u8(palette: u8, value: u8) -> TilePixelValue {
+        match palette >> (2 * value) & 0x03 {
+            0b00 => TilePixelValue::White,
+            0b01 => TilePixelValue::LightGray,
+            0b10 => TilePixelValue::DarkGray,
+            _ => TilePixelValue::Black,
+        }
+    }
+
+    fn to_rgb(self, colorization: Option<&Colorization>, layer: Layer) -> u32 {
+        let Some(colorization) = colorization else {
+            return self.to_dmg_rgb();
+        };
+
+        let palette = match layer {
+            Layer::Background => colorization.bg,
+            Layer::Obj0 => colorization.obj0,
+            Layer::Obj1 => colorization.obj1,
+        };
+        palette[self as usize]
+    }
+
+    fn to_dmg_rgb(self) -> u32 {
+        match self {
+            TilePixelValue::Black => 0xff091820,
+            TilePixelValue::LightGray => 0xff88C070,
+            TilePixelValue::DarkGray => 0xff356856,
+            TilePixelValue::White => 0xffE0F8D0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct Stat {
+    // Bit 6 - LYC=LY Coincidence Interrupt (1=Enable) (Read/Write)
+    enable_ly_interrupt: bool,
+    // Bit 5 - Mode 2 OAM Interrupt         (1=Enable) (Read/Write)
+    enable_m2_interrupt: bool,
+    // Bit 4 - Mode 1 V-Blank Interrupt     (1=Enable) (Read/Write)
+    enable_m1_interrupt: bool,
+    // Bit 3 - Mode 0 H-Blank Interrupt     (1=Enable) (Read/Write)
+    enable_m0_interrupt: bool,
+    mode: StatMode,
+}
+
+impl Stat {
+    pub fn new() -> Self {
+        Self {
+            enable_ly_interrupt: false,
+            enable_m2_interrupt: false,
+            enable_m1_interrupt: false,
+            enable_m0_interrupt: false,
+            mode: StatMode::HBlank0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GPU {
+    lcdc: Lcdc,
+    stat: Stat,
+
+    // Both CGB banks, only the first is used outside CGB mode
+    #[serde(with = "serde_bytes")]
+    vram: Vec<u8>,
+
+    #[serde(with = "serde_bytes")]
+    oam: [u8; OAM_SIZE],
+
+    vram_bank: usize,
+    // A CGB game on a CGB, drawn with the CGB's priority rules
+    #[serde(default)]
+    cgb_mode: bool,
+    // See `Quirks::stat_write_bug`, restored from the core's quirks after loading
+    #[serde(skip)]
+    stat_write_bug: bool,
+
+    scy: u8,
+    scx: u8,
+
+    win_y_trigger: bool,
+    wc: i32,
+    wy: u8,
+    wx: u8,
+    ly: u8,
+    lc: u8,
+    bgp: u8,
+    pal0: u8,
+    pal1: u8,
+
+    /** FF05 - TIMA - Timer counter (R/W) */
+    tima_counter: u8,
+
+    /** FF06 - TMA - Timer Modulo (R/W) */
+    tma_modulo: u8,
+
+    /** FF07 - TAC - Timer Control (R/W) */
+    tac: u8,
+
+    cycles: u32,
+    /// Internal counter of every cycle, DIV is its upper byte and TIMA counts the falling edges
+    /// of the bit TAC selects
+    #[serde(default)]
+    system_counter: u16,
+    enable_debug_override: bool,
+    pub interrupt_flag: InterruptFlag,
+
+    // CGB colors for a DMG game, drawn in DMG green when unset
+    #[serde(default)]
+    pub colorization: Option<Colorization>,
+
+    // Frame skip: keep timing and the window line counter going without drawing
+    #[serde(skip)]
+    pub skip_render: bool,
+
+    // Lines are drawn here as well as pushed to the video sink
+    #[serde(skip, default = "blank_frame")]
+    frame: Box<FrameBuffer>,
+    // And their color numbers, before the palettes
+    #[serde(skip, default = "blank_color_indices")]
+    color_indices: Box<ColorIndices>,
+}
+
+fn blank_frame() -> Box<FrameBuffer> {
+    Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT])
+}
+
+fn blank_color_indices() -> Box<ColorIndices> {
+    Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT])
+}
+
+/// How a background pixel stacks up against the sprites on it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum BgPriority {
+    Under,
+    // In front of sprites with OAM attribute bit 7 set
+    OverFlagged,
+    // In front of all sprites, from CGB BG map attribute bit 7
+    Over,
+}
+
+#[repr(u8)]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+enum StatMode {
+    HBlank0 = 0x00,
+    VBlank1 = 0x01,
+    OamRead2 = 0x02,
+    Transfer3 = 0x03,
+}
+
+impl StatMode {
+    /// Cycles spent in the mode, per line for VBlank.
+    const fn duration(self) -> u32 {
+        match self {
+            StatMode::OamRead2 => 80,
+            StatMode::Transfer3 => 172,
+            StatMode::HBlank0 => 204,
+            StatMode::VBlank1 => 456,
+        }
+    }
+}
+
+impl GPU {
+    pub fn new() -> GPU {
+        GPU {
+            lcdc: Lcdc::new(),
+            stat: Stat::new(),
+            vram: vec![0; VRAM_BANKS * VRAM_SIZE],
+            oam: [0; OAM_SIZE],
+
+            vram_bank: 0,
+            cgb_mode: false,
+            stat_write_bug: false,
+
+            scy: 0x00,
+            scx: 0x00,
+
+            win_y_trigger: false,
+            wc: 0x00,
+            wx: 0x00,
+            wy: 0x00,
+
+            ly: 0x00,
+            lc: 0x00,
+            bgp: 0x00,
+            pal0: 0x00,
+            pal1: 0x00,
+
+            tima_counter: 0x00,
+            tma_modulo: 0x00,
+            tac: 0x00,
+            enable_debug_override: false,
+            skip_render: false,
+            colorization: None,
+            frame: blank_frame(),
+            color_indices: blank_color_indices(),
+
+            cycles: 0,
+            system_counter: 0,
+            interrupt_flag: InterruptFlag::empty(),
+        }
+    }
+
+    pub fn set_cgb_mode(&mut self, cgb_mode: bool) {
+        self.cgb_mode = cgb_mode;
+        // States saved before VRAM banking have only the first bank
+        self.vram.resize(VRAM_BANKS * VRAM_SIZE, 0);
+    }
+
+    /// Whether an enabled STAT interrupt source is active.
+    fn stat_line(&self) -> bool {
+        let mode = match self.stat.mode {
+            StatMode::HBlank0 => self.stat.enable_m0_interrupt,
+            StatMode::VBlank1 => self.stat.enable_m1_interrupt,
+            StatMode::OamRead2 => self.stat.enable_m2_interrupt,
+            StatMode::Transfer3 => false,
+        };
+        mode || (self.stat.enable_ly_interrupt && self.ly == self.lc)
+    }
+
+    pub fn set_stat_write_bug(&mut self, stat_write_bug: bool) {
+        self.stat_write_bug = stat_write_bug;
+    }
+
+    pub fn initialize_gameboy_doctor(&mut self) {
+        self.enable_debug_override = true;
+    }
+
+    /// Sets the timer state that registers can't be written with to where the boot ROM leaves it.
+    pub fn skip_boot_rom(&mut self) {
+        self.system_counter = 0xabcc;
+    }
+
+    /// Cycles per TIMA increment at the frequency selected in TAC, twice the value of the
+    /// counter bit it follows.
+    fn timer_step(&self) -> u32 {
+        match self.tac & 0b11 {
+            1 => 16,
+            2 => 64,
+            3 => 256,
+            _ => 1024
+        }
+    }
+
+    /// The signal TIMA is incremented on the falling edge of.
+    fn timer_input(&self) -> bool {
+        self.tac.get_bit(2) && self.system_counter as u32 & (self.timer_step() / 2) != 0
+    }
+
+    fn increment_tima(&mut self) {
+        self.tima_counter = self.tima_counter.wrapping_add(1);
+
+        if self.tima_counter == 0 {
+            self.tima_counter = self.tma_modulo;
+            self.interrupt_flag.insert(InterruptFlag::TIMER);
+        }
+    }
+
+    // Resetting the counter, or switching TAC away from a set bit, is a falling edge too
+    fn write_timer_input(&mut self, write: impl FnOnce(&mut Self)) {
+        let before = self.timer_input();
+        write(self);
+
+        if before && !self.timer_input() {
+            self.increment_tima();
+        }
+    }
+
+    fn handle_timer(&mut self, elapsed: u32) {
+        let before = self.system_counter as u32;
+        let after = before + elapsed;
+        self.system_counter = after as u16;
+
+        if self.tac.get_bit(2) {
+            // The selected bit falls every time the counter passes a multiple of the step
+            let step = self.timer_step();
+            for _ in 0..after / step - before / step {
+                self.increment_tima();
+            }
+        }
+    }
+
+    pub fn next(&mut self, elapsed: u32, video: &mut impl VideoSink) -> bool {
+        self.handle_timer(elapsed);
+
+        if !self.lcdc.lcd_display_enable() {
+            return false;
+        }
+
+        // Catch up on whole modes, carrying what's left over into the next one
+        self.cycles += elapsed;
+
+        let mut should_render = false;
+
+        while self.cycles >= self.stat.mode.duration() {
+            self.cycles -= self.stat.mode.duration();
+            should_render |= self.end_mode(video);
+        }
+        should_render
+    }
+
+    /// Cycles until the next mode change or timer overflow, which along with serial transfers are
+    /// the events that can raise an interrupt while the CPU is halted.
+    pub fn cycles_until_event(&self) -> u32 {
+        let mut cycles = u32::MAX;
+
+        if self.lcdc.lcd_display_enable() {
+            cycles = self.stat.mode.duration() - self.cycles;
+        }
+        if self.tac.get_bit(2) {
+            let step = self.timer_step();
+            let overflow = (0x100 - self.tima_counter as u32) * step - self.system_counter as u32 % step;
+            cycles = cycles.min(overflow);
+        }
+        cycles
+    }
+
+    /// Moves on from the current mode, returning whether a frame was finished.
+    fn end_mode(&mut self, video: &mut impl VideoSink) -> bool {
+        match self.stat.mode {
+            StatMode::OamRead2 => {
+                if self.ly >= self.wy {
+                    self.win_y_trigger = true;
+                }
+                self.stat.mode = StatMode::Transfer3;
+            }
+            StatMode::Transfer3 => {
+                self.stat.mode = StatMode::HBlank0;
+                if self.stat.enable_m0_interrupt {
+                    self.interrupt_flag.insert(InterruptFlag::LCD_STAT);
+                }
+                self.render_line(video);
+            }
+            StatMode::HBlank0 => {
+                self.ly += 1;
+
+                if self.stat.enable_ly_interrupt && self.ly == self.lc {
+                    self.interrupt_flag.insert(InterruptFlag::LCD_STAT);
+                }
+
+                if self.ly == 144 {
+                    self.stat.mode = StatMode::VBlank1;
+                    self.interrupt_flag.insert(InterruptFlag::V_BLANK);
+                    if self.stat.enable_m1_interrupt {
+                        self.interrupt_flag.insert(InterruptFlag::LCD_STAT);
+                    }
+                    return true;
+                }
+
+                self.stat.mode = StatMode::OamRead2;
+                if self.stat.enable_m2_interrupt {
+                    self.interrupt_flag.insert(InterruptFlag::LCD_STAT);
+                }
+            }
+            StatMode::VBlank1 => {
+                self.ly += 1;
+
+                if self.stat.enable_ly_interrupt && self.ly == self.lc {
+                    self.interrupt_flag.insert(InterruptFlag::LCD_STAT);
+                }
+
+                if self.ly > 153 {
+                    self.interrupt_flag.remove(InterruptFlag::V_BLANK);
+                    self.stat.mode = StatMode::OamRead2;
+                    if self.stat.enable_m2_interrupt {
+                        self.interrupt_flag.insert(InterruptFlag::LCD_STAT);
+                    }
+                    self.ly = 0;
+                    self.wc = 0;
+                    self.win_y_trigger = false;
+                }
+            }
+        }
+        false
+    }
+
+    /// LY should be set to 0 when the LCD is off.
+    fn read_ly(&self) -> u8 {
+        if self.enable_debug_override {
+            // Hard coded value for gameboy-doctor
+            0x90
+        } else if !self.lcdc.lcd_display_enable() {
+            0
+        } else {
+            self.ly
+        }
+    }
+
+    /// While the LCD is disabled, the mode flag (bits 1-0 of STAT) is set to 0, indicating that it is safe to write to all parts of RAM.
+    fn read_stat_mode(&self) -> StatMode {
+        if !self.lcdc.lcd_display_enable() {
+            StatMode::HBlank0
+        } else {
+            self.stat.mode
+        }
+    }
+
+    /// Reads VRAM or OAM.
+    pub fn read_vram(&self, adr: u16) -> u8 {
+        let address = adr as usize;
+
+        match address {
+            VRAM_BEGIN..=VRAM_END => self.vram[self.vram_offset(adr)],
+            0xfe00..=0xfe9f => self.oam[address - 0xfe00],
+            _ => unreachable!("PPU: Read from unmapped address: {:04X}", address)
+        }
+    }
+
+    /// Reads one of the LCD registers, FF40-FF4B but DMA, and VBK.
+    pub fn read_register(&self, addr: u16) -> u8 {
+        match addr {
+            0xff40 => self.lcdc.bits,
+            0xff41 => {
+                let bit6 = if self.stat.enable_ly_interrupt {
+                    0x40
+                } else {
+                    0x00
+                };
+                let bit5 = if self.stat.enable_m2_interrupt {
+                    0x20
+                } else {
+                    0x00
+                };
+                let bit4 = if self.stat.enable_m1_interrupt {
+                    0x10
+                } else {
+                    0x00
+                };
+                let bit3 = if self.stat.enable_m0_interrupt {
+                    0x08
+                } else {
+                    0x00
+                };
+                let bit2 = if self.ly == self.lc { 0x04 } else { 0x00 };
+                let mode = self.read_stat_mode() as u8;
+
+                bit6 | bit5 | bit4 | bit3 | bit2 | mode
+            }
+            0xff42 => self.scy,
+            0xff43 => self.scx,
+            0xff44 => self.read_ly(),
+            0xff45 => self.lc,
+            0xff47 => self.bgp,
+            0xff48 => self.pal0,
+            0xff49 => self.pal1,
+            0xff4a => self.wy,
+            0xff4b => self.wx,
+            0xff4f => self.vram_bank as u8 | 0xfe,
+            _ => unreachable!("PPU: Read from unmapped register: {:04X}", addr)
+        }
+    }
+
+    /// Reads one of the timer registers, FF04-FF07, which count along with the PPU.
+    pub fn read_timer(&self, addr: u16) -> u8 {
+        match addr {
+            0xff04 => (self.system_counter >> 8) as u8,
+            0xff05 => self.tima_counter,
+            0xff06 => self.tma_modulo,
+            0xff07 => self.tac,
+            _ => unreachable!("TIMER: Read from unmapped register: {:04X}", addr)
+        }
+    }
+
+    /// Writes VRAM or OAM.
+    pub fn write_vram(&mut self, adr: u16, value: u8) {
+        let address = adr as usize;
+
+        match address {
+            VRAM_BEGIN..=VRAM_END => {
+                let offset = self.vram_offset(adr);
+                self.vram[offset] = value;
+            }
+            0xfe00..=0xfe9f => self.oam[address - 0xfe00] = value,
+            _ => unreachable!("PPU: Write to unmapped address: {:04X}", address)
+        }
+    }
+
+    /// Writes one of the LCD registers, see `read_register`.
+    pub fn write_register(&mut self, addr: u16, value: u8) {
+        match addr {
+            0xff40 => {
+                let lcdc = Lcdc::from_bits_truncate(value);
+                if lcdc.lcd_display_enable() != self.lcdc.lcd_display_enable() {
+                    debug!(target: "ppu", "lcd display enable: {}", lcdc.lcd_display_enable());
+                }
+                self.lcdc = lcdc;
+            }
+            0xff41 => {
+                // The DMG briefly enables every source while writing, see `Quirks::stat_write_bug`.
+                // Mode 2 doesn't take part, and like any STAT interrupt it needs the line to rise.
+                let line_was_high = self.stat_line();
+                let sources_active = matches!(self.stat.mode, StatMode::HBlank0 | StatMode::VBlank1) || self.ly == self.lc;
+                if self.stat_write_bug && self.lcdc.lcd_display_enable() && sources_active && !line_was_high {
+                    self.interrupt_flag.insert(InterruptFlag::LCD_STAT);
+                }
+
+                self.stat.enable_ly_interrupt = value & 0x40 != 0x00;
+                self.stat.enable_m2_interrupt = value & 0x20 != 0x00;
+                self.stat.enable_m1_interrupt = value & 0x10 != 0x00;
+                self.stat.enable_m0_interrupt = value & 0x08 != 0x00;
+            }
+            0xff42 => self.scy = value,
+            0xff43 => self.scx = value,
+            0xff44 => self.ly = value,
+            0xff45 => self.lc = value,
+            0xff47 => self.bgp = value,
+            0xff48 => self.pal0 = value,
+            0xff49 => self.pal1 = value,
+            0xff4a => self.wy = value,
+            0xff4b => self.wx = value,
+            0xff4f => self.vram_bank = (value & 0x01) as usize,
+            _ => unreachable!("PPU: Write to unmapped register: {:04X}", addr)
+        }
+    }
+
+    /// Writes one of the timer registers, see `read_timer`.
+    pub fn write_timer(&mut self, addr: u16, value: u8) {
+        match addr {
+            0xff04 => self.write_timer_input(|ppu| ppu.system_counter = 0),
+            0xff05 => self.tima_counter = value,
+            0xff06 => self.tma_modulo = value,
+            0xff07 => self.write_timer_input(|ppu| ppu.tac = value),
+            _ => unreachable!("TIMER: Write to unmapped register: {:04X}", addr)
+        }
+    }
+
+    fn get_pixel_color(&self, tile_location: u16, tile_y: u8, tile_x: u8) -> TilePixelValue {
+        let tile_y_data: [u8; 2] = {
+            let a = self.read_vram(tile_location + (tile_y as u16 * 2));
+            let b = self.read_vram(tile_location + (tile_y as u16 * 2) + 1);
+            [a, b]
+        };
+
+        // Palettes
+        let color_l = if tile_y_data[0] & (0x80 >> tile_x) != 0 {
+            1
+        } else {
+            0
+        };
+        let color_h = if tile_y_data[1] & (0x80 >> tile_x) != 0 {
+            2
+        } else {
+            0
+        };
+        let color = (color_h | color_l) as u8;
+
+        TilePixelValue::from_palette_and_u8(self.bgp, color)
+    }
+
+    fn get_tile_location(&self, tx: u8, ty: u8, base: u16) -> u16 {
+        let tile_base = self.lcdc.bg_and_window_tile_data_select();
+
+
+        let title_addr = base + ty as u16 * 32 + tx as u16;
+
+        let tile_number = self.vram_in_bank(0, title_addr);
+
+        let tile_offset = if self.lcdc.contains(Lcdc::BG_AND_WINDOW_TILE_DATA_SELECT) {
+            i16::from(tile_number)
+        } else {
+            i16::from(tile_number as i8) + 128
+        } as u16
+            * 16;
+
+        tile_base + tile_offset
+    }
+
+    fn draw_tile_at(&self, x: u8, y: u8, base: u16) -> TilePixelValue {
+        let tile_location = self.get_tile_location(x / 8, y / 8, base);
+
+        let tile_x = x % 8;
+        let tile_y = y % 8;
+
+        self.get_pixel_color(tile_location, tile_y, tile_x)
+    }
+
+    /// The screen as drawn so far: the last frame, with the lines of the current one above LY.
+    pub fn frame(&self) -> &FrameBuffer {
+        &self.frame
+    }
+
+    /// The color numbers of `frame`'s pixels, before the palettes.
+    pub fn color_indices(&self) -> &ColorIndices {
+        &self.color_indices
+    }
+
+    pub fn lcd_enabled(&self) -> bool {
+        self.lcdc.lcd_display_enable()
+    }
+
+    /// The mode as STAT shows it, HBlank while the LCD is off.
+    pub fn mode(&self) -> PpuMode {
+        match self.read_stat_mode() {
+            StatMode::HBlank0 => PpuMode::HBlank,
+            StatMode::VBlank1 => PpuMode::VBlank,
+            StatMode::OamRead2 => PpuMode::OamScan,
+            StatMode::Transfer3 => PpuMode::Drawing,
+        }
+    }
+
+    /// The window line counter, which only counts lines the window was drawn on.
+    pub fn window_line(&self) -> u8 {
+        self.wc as u8
+    }
+
+    pub fn vram(&self) -> &[u8] {
+        &self.vram
+    }
+
+    pub fn vram_mut(&mut self) -> &mut [u8] {
+        &mut self.vram
+    }
+
+    pub fn oam(&self) -> &[u8] {
+        &self.oam
+    }
+
+    pub fn oam_mut(&mut self) -> &mut [u8] {
+        &mut self.oam
+    }
+
+    /// Offset into `vram_mut` of `addr` in the mapped VRAM bank.
+    pub fn vram_offset(&self, addr: u16) -> usize {
+        (self.vram_bank * 0x2000) | (addr as usize & 0x1fff)
+    }
+
+    /// The byte at `addr` in VRAM bank `bank`, whichever bank the CPU has mapped.
+    fn vram_in_bank(&self, bank: usize, addr: u16) -> u8 {
+        self.vram[(bank * 0x2000) | (addr as usize & 0x1fff)]
+    }
+
+    /// Draws the whole screen again from the current VRAM, OAM and registers, as the next frame
+    /// would show it, e.g. after memory was edited while paused. Timing state is left as it was.
+    pub fn redraw(&mut self, video: &mut impl VideoSink) {
+        let (ly, wc, win_y_trigger, skip_render) = (self.ly, self.wc, self.win_y_trigger, self.skip_render);
+        self.wc = 0;
+        self.win_y_trigger = false;
+        self.skip_render = false;
+
+        for line in 0..SCREEN_HEIGHT as u8 {
+            self.ly = line;
+            if self.ly >= self.wy {
+                self.win_y_trigger = true;
+            }
+            self.render_line(video);
+        }
+
+        (self.ly, self.wc, self.win_y_trigger, self.skip_render) = (ly, wc, win_y_trigger, skip_render);
+    }
+
+    /// Draws the 384 tiles of VRAM as a 16 x 24 tile grid at `buffer`'s top left, and the
+    /// background tile map to the right of it, both through the background palette.
+    pub fn render_vram(&self, buffer: &mut [u32], stride: usize) {
+        for tile in 0..384 {
+            let location = VRAM_BEGIN as u16 + tile * 16;
+            let (left, top) = (tile as usize % 16 * 8, tile as usize / 16 * 8);
+
+            for y in 0..8 {
+                for x in 0..8 {
+                    buffer[(top + y as usize) * stride + left + x as usize] = self.get_pixel_color(location, y, x).to_rgb(self.colorization.as_ref(), Layer::Background);
+                }
+            }
+        }
+
+        let base = self.lcdc.bg_tile_map_display_select();
+        for y in 0..=255u8 {
+            for x in 0..=255u8 {
+                buffer[y as usize * stride + 16 * 8 + x as usize] = self.draw_tile_at(x, y, base).to_rgb(self.colorization.as_ref(), Layer::Background);
+            }
+        }
+    }
+
+    fn render_line(&mut self, video: &mut impl VideoSink) {
+        if self.skip_render {
+            // Same as the per-pixel check below, which triggers at the first x >= WX - 7
+            if self.lcdc.window_display_enable() && self.win_y_trigger && (1..=166).contains(&self.wx) {
+                self.wc += 1;
+            }
+            return;
+        }
+
+        let y = self.ly;
+        let mut line = [0; SCREEN_WIDTH];
+        let mut color_numbers = [0; SCREEN_WIDTH];
+        let mut bg_priority = [BgPriority::Under; SCREEN_WIDTH];
+
+        let window_x = self.window_start_x();
+
+        // On a CGB, LCDC bit 0 doesn't turn the background off but puts all sprites in front of it
+        if self.lcdc.bg_and_window_display_enable() || self.cgb_mode {
+            let mut attributes = [0; SCREEN_WIDTH];
+            let bg_end = window_x.unwrap_or(SCREEN_WIDTH);
+            let (x, y) = (self.scx, y.wrapping_add(self.scy));
+            self.fetch_tile_row(&mut color_numbers[..bg_end], x, y, self.lcdc.bg_tile_map_display_select());
+            self.fetch_attribute_row(&mut attributes[..bg_end], x, y, self.lcdc.bg_tile_map_display_select());
+            if let Some(window_x) = window_x {
+                let (x, y) = ((window_x + 7 - self.wx as usize) as u8, self.wc as u8);
+                self.fetch_tile_row(&mut color_numbers[window_x..], x, y, self.lcdc.window_tile_map_display_select());
+                self.fetch_attribute_row(&mut attributes[window_x..], x, y, self.lcdc.window_tile_map_display_select());
+            }
+
+            let colors = self.palette_colors(self.bgp, Layer::Background);
+            for x in 0..SCREEN_WIDTH {
+                let color = color_numbers[x] as usize;
+                line[x] = colors[color];
+                bg_priority[x] = if self.cgb_mode {
+                    // Color 0 is always behind, the rest can be put in front by either the
+                    // sprite or the tile's BG map attributes
+                    match (self.lcdc.bg_and_window_display_enable(), color, attributes[x].get_bit(7)) {
+                        (false, _, _) | (_, 0, _) => BgPriority::Under,
+                        (true, _, true) => BgPriority::Over,
+                        (true, _, false) => BgPriority::OverFlagged,
+                    }
+                } else if self.bgp >> (2 * color) & 0x03 != TilePixelValue::White as u8 {
+                    // Sprites flagged to be drawn behind the background still show over white
+                    BgPriority::OverFlagged
+                } else {
+                    BgPriority::Under
+                };
+            }
+        } else {
+            line = [TilePixelValue::White.to_rgb(self.colorization.as_ref(), Layer::Background); SCREEN_WIDTH];
+        }
+
+        if self.lcdc.obj_display_enable() {
+            self.draw_sprites(&mut line, &mut color_numbers, &bg_priority, y);
+        }
+
+        self.frame[y as usize * SCREEN_WIDTH..][..SCREEN_WIDTH].copy_from_slice(&line);
+        self.color_indices[y as usize * SCREEN_WIDTH..][..SCREEN_WIDTH].copy_from_slice(&color_numbers);
+        video.push_line(y as usize, &line);
+
+        if window_x.is_some() {
+            self.wc += 1;
+        }
+    }
+
+    /// The first x the window is drawn from on this line, if it shows up on it at all.
+    fn window_start_x(&self) -> Option<usize> {
+        if !self.lcdc.window_display_enable() || !self.win_y_trigger || self.wx == 0 {
+            return None;
+        }
+        let x = (self.wx as usize).saturating_sub(7);
+        (x < SCREEN_WIDTH).then_some(x)
+    }
+
+    /// Fills `out` with the color numbers along row `y` of the tile map at `map`, starting at `x`,
+    /// decoding a whole tile row at a time.
+    fn fetch_tile_row(&self, out: &mut [u8], mut x: u8, y: u8, map: u16) {
+        let mut i = 0;
+        while i < out.len() {
+            let row = self.tile_row(self.get_tile_location(x / 8, y / 8, map), y % 8);
+            let skip = (x % 8) as usize;
+            let n = (8 - skip).min(out.len() - i);
+
+            out[i..i + n].copy_from_slice(&row[skip..skip + n]);
+            i += n;
+            x = x.wrapping_add(n as u8);
+        }
+    }
+
+    /// Fills `out` with the CGB BG map attributes of the tiles along row `y` of the tile map at
+    /// `map`, starting at `x`, one byte per pixel. All zero outside CGB mode.
+    fn fetch_attribute_row(&self, out: &mut [u8], x: u8, y: u8, map: u16) {
+        if !self.cgb_mode {
+            return;
+        }
+        for (i, attributes) in out.iter_mut().enumerate() {
+            let x = x.wrapping_add(i as u8);
+            *attributes = self.vram_in_bank(1, map + (y / 8) as u16 * 32 + (x / 8) as u16);
+        }
+    }
+
+    /// The color numbers of row `tile_y` of the tile at `tile_location`, leftmost first.
+    fn tile_row(&self, tile_location: u16, tile_y: u8) -> [u8; 8] {
+        let addr = tile_location + tile_y as u16 * 2;
+        let (lo, hi) = (self.vram_in_bank(0, addr), self.vram_in_bank(0, addr + 1));
+
+        std::array::from_fn(|i| (lo >> (7 - i) & 1) | (hi >> (7 - i) & 1) << 1)
+    }
+
+    /// The colors of color numbers 0-3 through `palette`.
+    fn palette_colors(&self, palette: u8, layer: Layer) -> [u32; 4] {
+        std::array::from_fn(|color| TilePixelValue::from_palette_and_u8(palette, color as u8).to_rgb(self.colorization.as_ref(), layer))
+    }
+
+    /// Draws the sprites on line `y` over the background a sprite row at a time. Where sprites
+    /// overlap, the first one in `populate_sprites_to_render`'s order with a visible pixel wins.
+    fn draw_sprites(&self, line: &mut [u32; SCREEN_WIDTH], color_numbers: &mut [u8; SCREEN_WIDTH], bg_priority: &[BgPriority; SCREEN_WIDTH], y: u8) {
+        let (sprites, len) = self.populate_sprites_to_render(y as u16);
+        let sprite_size = self.lcdc.obj_size();
+        let obj0 = self.palette_colors(self.pal0, Layer::Obj0);
+        let obj1 = self.palette_colors(self.pal1, Layer::Obj1);
+        let mut drawn = [false; SCREEN_WIDTH];
+
+        for &(sprite_x, sprite_y, i) in &sprites[..len] {
+            let addr = 0xfe00 + i * 4;
+            let tile_num = (self.read_vram(addr + 2) as u16) & (if sprite_size == 16 { 0xfe } else { 0xff } as u16);
+            let flags = self.read_vram(addr + 3);
+            let colors = if flags.get_bit(4) { &obj1 } else { &obj0 };
+            let x_flip = flags.get_bit(5);
+            let y_flip = flags.get_bit(6);
+            let behind_bg = flags.get_bit(7);
+
+            let tile_y = if y_flip {
+                (sprite_size - 1) - (y as i32 - sprite_y) as u16
+            } else {
+                (y as i32 - sprite_y) as u16
+            };
+
+            let mut row = self.tile_row(0x8000 + tile_num * 16, tile_y as u8);
+            if x_flip {
+                row.reverse();
+            }
+
+            for (tile_x, &color) in row.iter().enumerate() {
+                let Ok(x) = usize::try_from(sprite_x + tile_x as i32) else { continue };
+                if x >= SCREEN_WIDTH || drawn[x] || color == 0 {
+                    continue;
+                }
+                match bg_priority[x] {
+                    BgPriority::Over => continue,
+                    BgPriority::OverFlagged if behind_bg => continue,
+                    _ => {}
+                }
+
+                line[x] = colors[color as usize];
+                color_numbers[x] = color;
+                drawn[x] = true;
+            }
+        }
+    }
+
+    fn populate_sprites_to_render(&self, line: u16) -> ([(i32, i32, u16); 10], usize) {
+        let mut sprites = [(0, 0, 0); 10];
+        let mut index = 0usize;
+        let line = line as i32;
+        let sprite_size = self.lcdc.obj_size() as i32;
+
+
+        for i in 0..40u16 {
+            let addr = 0xfe00 + (i * 4);
+            let sprite_y = self.read_vram(addr + 0) as u16 as i32 - 16;
+
+            if line < sprite_y || line >= sprite_y + sprite_size {
+                continue;
+            }
+
+            let sprite_x = self.read_vram(addr + 1) as u16 as i32 - 8;
+            sprites[index] = (sprite_x, sprite_y, i);
+            index += 1;
+            if index >= 10 {
+                break;
+            }
+        }
+
+        if index > 0 {
+            sprites[..index].sort_by(|a, b| {
+                a.0.cmp(&b.0)
+            })
+        }
+
+
+        (sprites, index)
+    }
+}
+
@@ -0,0 +1,255 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::dmg::core::{Core, FRAME_DURATION};
+use crate::dmg::input::JoypadInput;
+use crate::dmg::traits::{NullAudioSink, NullVideoSink};
+use crate::{Error, Result};
+
+/// How one ROM of a `run_many` batch went.
+#[derive(Debug)]
+pub struct RomRun {
+    pub rom: PathBuf,
+    /// Time spent emulating its frames, or why the ROM couldn't be loaded.
+    pub result: Result<Duration>,
+}
+
+/// The ROMs of a `run_many` batch, in the order given, and the throughput across all of them.
+#[derive(Debug)]
+pub struct RunReport {
+    pub runs: Vec<RomRun>,
+    /// Frames emulated by the ROMs that loaded.
+    pub frames: u64,
+    /// Wall-clock time for the whole batch.
+    pub elapsed: Duration,
+    pub threads: usize,
+}
+
+impl RunReport {
+    pub fn frames_per_second(&self) -> f64 {
+        self.frames as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Emulated time over wall-clock time, summed over all threads.
+    pub fn speed(&self) -> f64 {
+        self.frames as f64 * FRAME_DURATION.as_secs_f64() / self.elapsed.as_secs_f64()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &RomRun> {
+        self.runs.iter().filter(|run| run.result.is_err())
+    }
+}
+
+/// Runs each of `roms` headless, without a boot ROM and with no keys held, for `frames` frames,
+/// spreading the ROMs over as many threads as there are cores.
+pub fn run_many(roms: &[impl AsRef<Path> + Sync], frames: u32) -> RunReport {
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get).min(roms.len()).max(1);
+    let next = AtomicUsize::new(0);
+    let start = Instant::now();
+
+    let mut runs: Vec<(usize, RomRun)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut runs = vec![];
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(rom) = roms.get(i) else { break };
+                        let rom = rom.as_ref();
+                        runs.push((i, RomRun { rom: rom.to_path_buf(), result: run_rom(rom, frames) }));
+                    }
+                    runs
+                })
+            })
+            .collect();
+
+        workers.into_iter().flat_map(|worker| worker.join().expect("harness thread panicked")).collect()
+    });
+    let elapsed = start.elapsed();
+
+    runs.sort_by_key(|&(i, _)| i);
+    let runs: Vec<RomRun> = runs.into_iter().map(|(_, run)| run).collect();
+    let loaded = runs.iter().filter(|run| run.result.is_ok()).count() as u64;
+
+    RunReport { runs, frames: loaded * frames as u64, elapsed, threads }
+}
+
+fn run_rom(rom: &Path, frames: u32) -> Result<Duration> {
+    let mut core = Core::load_without_boot_rom(Some(rom.to_string_lossy().into_owned()))?;
+    let start = Instant::now();
+
+    for _ in 0..frames {
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+    }
+    Ok(start.elapsed())
+}
+
+/// One line of a cycle log: an instruction and the clock cycles it took, including any
+/// interrupt dispatched after it. Written as `PC:0150 OP:3E CY:8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CycleLogEntry {
+    pub pc: u16,
+    pub opcode: u8,
+    pub cycles: u64,
+}
+
+impl fmt::Display for CycleLogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PC:{:04X} OP:{:02X} CY:{}", self.pc, self.opcode, self.cycles)
+    }
+}
+
+impl FromStr for CycleLogEntry {
+    type Err = String;
+
+    /// Reads the `PC:`, `OP:` and `CY:` fields in any order, ignoring anything else on the line,
+    /// so logs from other emulators only need those three added.
+    fn from_str(line: &str) -> std::result::Result<Self, String> {
+        let (mut pc, mut opcode, mut cycles) = (None, None, None);
+        for field in line.split_whitespace() {
+            let Some((name, value)) = field.split_once(':') else { continue };
+            match name {
+                "PC" => pc = Some(u16::from_str_radix(value, 16).map_err(|_| format!("invalid PC {:?}", value))?),
+                "OP" => opcode = Some(u8::from_str_radix(value, 16).map_err(|_| format!("invalid OP {:?}", value))?),
+                "CY" => cycles = Some(value.parse().map_err(|_| format!("invalid CY {:?}", value))?),
+                _ => {}
+            }
+        }
+
+        match (pc, opcode, cycles) {
+            (Some(pc), Some(opcode), Some(cycles)) => Ok(Self { pc, opcode, cycles }),
+            _ => Err("expected PC:, OP: and CY: fields".to_string()),
+        }
+    }
+}
+
+/// Runs one instruction of `core`, headless with no keys held, and returns its cycle log entry.
+pub fn step_logged(core: &mut Core) -> CycleLogEntry {
+    let pc = core.registers().pc;
+    let opcode = core.read_byte(pc);
+    let before = core.cycles_elapsed();
+
+    core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+
+    CycleLogEntry { pc, opcode, cycles: core.cycles_elapsed() - before }
+}
+
+/// Runs `instructions` instructions of `core`, writing a cycle log line for each to `out`.
+pub fn write_cycle_log(core: &mut Core, instructions: u64, mut out: impl Write) -> io::Result<()> {
+    for _ in 0..instructions {
+        writeln!(out, "{}", step_logged(core))?;
+    }
+    out.flush()
+}
+
+/// Where a core's cycle log first differed from a reference log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimingDivergence {
+    /// Line of the reference log, from 1.
+    pub line: usize,
+    pub expected: CycleLogEntry,
+    pub actual: CycleLogEntry,
+    /// The last instruction both logs agreed on, if any.
+    pub previous: Option<CycleLogEntry>,
+}
+
+impl fmt::Display for TimingDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "line {} differs", self.line)?;
+        if let Some(previous) = self.previous {
+            writeln!(f, "  after:    {}", previous)?;
+        }
+        writeln!(f, "  expected: {}", self.expected)?;
+        write!(f, "  actual:   {}", self.actual)
+    }
+}
+
+/// Steps `core` along a reference cycle log, e.g. one from another emulator, until the logs
+/// differ or the reference ends. Blank lines and `#` comments in the reference are skipped.
+pub fn compare_cycle_log(core: &mut Core, reference: impl BufRead) -> Result<Option<TimingDivergence>> {
+    let mut previous = None;
+
+    for (i, line) in reference.lines().enumerate() {
+        let line_error = |reason: String| Error::CycleLog { line: i + 1, reason };
+        let line = line.map_err(|e| line_error(e.to_string()))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let expected: CycleLogEntry = line.parse().map_err(line_error)?;
+        let actual = step_logged(core);
+        if actual != expected {
+            return Ok(Some(TimingDivergence { line: i + 1, expected, actual, previous }));
+        }
+        previous = Some(actual);
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::dmg::core::CoreBuilder;
+    use crate::dmg::harness::{compare_cycle_log, run_many, step_logged, write_cycle_log, CycleLogEntry};
+    use crate::test_support::looping_rom;
+    use crate::Error;
+
+    // `run_many` takes paths
+    fn looping_rom_file(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(name);
+        fs::write(&path, looping_rom(&[])).unwrap();
+        path
+    }
+
+    #[test]
+    fn run_many_reports_every_rom_in_order() {
+        let roms = vec![
+            looping_rom_file("run_many_1.gb"),
+            env::temp_dir().join("run_many_missing.gb"),
+            looping_rom_file("run_many_2.gb"),
+            looping_rom_file("run_many_3.gb"),
+        ];
+
+        let report = run_many(&roms, 5);
+
+        assert_eq!(report.runs.iter().map(|run| &run.rom).collect::<Vec<_>>(), roms.iter().collect::<Vec<_>>());
+        assert_eq!(report.failures().count(), 1);
+        assert_eq!(report.frames, 15);
+        assert!(report.threads >= 1);
+        assert!(report.speed() > 0.0);
+    }
+
+    #[test]
+    fn cycle_logs_round_trip_and_find_the_first_divergence() {
+        let new_core = || CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        let mut core = new_core();
+        let mut log = vec![];
+        write_cycle_log(&mut core, 3, &mut log).unwrap();
+
+        let log = String::from_utf8(log).unwrap();
+        assert_eq!(log.lines().next(), Some("PC:0100 OP:18 CY:12"));
+        assert_eq!(compare_cycle_log(&mut new_core(), log.as_bytes()).unwrap(), None);
+
+        // A reference with its own fields around the ones that matter, and a slower second JR
+        let reference = "# from elsewhere\nPC:0100 OP:18 CY:12 A:01\n\nA:01 CY:16 OP:18 PC:0100\n";
+        let divergence = compare_cycle_log(&mut new_core(), reference.as_bytes()).unwrap().unwrap();
+        assert_eq!(divergence.line, 4);
+        assert_eq!(divergence.expected.cycles, 16);
+        assert_eq!(divergence.actual, CycleLogEntry { pc: 0x100, opcode: 0x18, cycles: 12 });
+        assert_eq!(divergence.previous, Some(step_logged(&mut new_core())));
+
+        let bad = compare_cycle_log(&mut new_core(), "PC:0100 OP:18\n".as_bytes());
+        assert!(matches!(bad, Err(Error::CycleLog { line: 1, .. })));
+    }
+}
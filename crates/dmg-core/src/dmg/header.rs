@@ -0,0 +1,124 @@
+// The header lives at 0x100-0x14F, with the parts used here from 0x134 on
+const HEADER_START: usize = 0x100;
+const HEADER_END: usize = 0x150;
+
+const TITLE: usize = 0x134;
+const MANUFACTURER_CODE: usize = 0x13f;
+const CGB_FLAG: usize = 0x143;
+const CARTRIDGE_TYPE: usize = 0x147;
+const OLD_LICENSEE_CODE: usize = 0x14b;
+
+/// The cartridge header of a ROM, see the Pan Docs. Bytes past the end of a short ROM read as 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CartridgeHeader {
+    bytes: [u8; HEADER_END - HEADER_START],
+}
+
+impl CartridgeHeader {
+    pub fn from_rom(rom: &[u8]) -> Self {
+        let mut bytes = [0; HEADER_END - HEADER_START];
+        let header = rom.get(HEADER_START..HEADER_END.min(rom.len())).unwrap_or_default();
+        bytes[..header.len()].copy_from_slice(header);
+        Self { bytes }
+    }
+
+    fn byte(&self, addr: usize) -> u8 {
+        self.bytes[addr - HEADER_START]
+    }
+
+    fn range(&self, start: usize, end: usize) -> &[u8] {
+        &self.bytes[start - HEADER_START..end - HEADER_START]
+    }
+
+    /// 0x80 for games that also run on a DMG, 0xC0 for CGB-only games, part of the title
+    /// otherwise.
+    pub fn cgb_flag(&self) -> u8 {
+        self.byte(CGB_FLAG)
+    }
+
+    fn has_cgb_flag(&self) -> bool {
+        matches!(self.cgb_flag(), 0x80 | 0xc0)
+    }
+
+    pub fn cartridge_type(&self) -> u8 {
+        self.byte(CARTRIDGE_TYPE)
+    }
+
+    /// The four letter code at 0x13F-0x142 of later CGB games, e.g. `AAUE`. These share the
+    /// bytes with the end of the title of older games, so the code is only taken when the game
+    /// has a CGB flag, uses the new licensee code and all four bytes are capitals or digits.
+    pub fn manufacturer_code(&self) -> Option<&str> {
+        let code = self.range(MANUFACTURER_CODE, CGB_FLAG);
+        let is_code = self.has_cgb_flag()
+            && self.byte(OLD_LICENSEE_CODE) == 0x33
+            && code.iter().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit());
+        // Only ASCII has passed the check, so this can't fail
+        is_code.then(|| std::str::from_utf8(code).unwrap())
+    }
+
+    /// The game's title: 16 bytes on DMG games, 15 once 0x143 holds the CGB flag and 11 when a
+    /// manufacturer code follows. Ends at the first zero, with trailing spaces trimmed and
+    /// anything but printable ASCII shown as `?`.
+    pub fn title(&self) -> String {
+        let end = if self.manufacturer_code().is_some() {
+            MANUFACTURER_CODE
+        } else if self.has_cgb_flag() {
+            CGB_FLAG
+        } else {
+            CGB_FLAG + 1
+        };
+
+        self.range(TITLE, end)
+            .iter()
+            .take_while(|&&b| b != 0)
+            .map(|&b| if b == b' ' || b.is_ascii_graphic() { b as char } else { '?' })
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::header::CartridgeHeader;
+
+    fn rom_with(title: &[u8], cgb_flag: u8, old_licensee_code: u8) -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x134..0x134 + title.len()].copy_from_slice(title);
+        rom[0x143] = cgb_flag;
+        rom[0x14b] = old_licensee_code;
+        rom
+    }
+
+    #[test]
+    fn dmg_titles_use_all_16_bytes() {
+        let header = CartridgeHeader::from_rom(&rom_with(b"SUPER MARIOLAND", b'2', 0x01));
+
+        assert_eq!(header.title(), "SUPER MARIOLAND2");
+        assert_eq!(header.manufacturer_code(), None);
+    }
+
+    #[test]
+    fn cgb_flag_ends_the_title() {
+        assert_eq!(CartridgeHeader::from_rom(&rom_with(b"POKEMON YELLOW", 0x80, 0x33)).title(), "POKEMON YELLOW");
+        assert_eq!(CartridgeHeader::from_rom(&rom_with(b"SOME 15 CHAR GA", 0xc0, 0x01)).title(), "SOME 15 CHAR GA");
+    }
+
+    #[test]
+    fn manufacturer_code_is_split_off() {
+        let header = CartridgeHeader::from_rom(&rom_with(b"PM_CRYSTAL\0BYTE", 0xc0, 0x33));
+        assert_eq!(header.title(), "PM_CRYSTAL");
+        assert_eq!(header.manufacturer_code(), Some("BYTE"));
+
+        let header = CartridgeHeader::from_rom(&rom_with(b"ZELDA      AZ7E", 0x80, 0x33));
+        assert_eq!(header.title(), "ZELDA");
+        assert_eq!(header.manufacturer_code(), Some("AZ7E"));
+    }
+
+    #[test]
+    fn odd_bytes_and_short_roms_are_tolerated() {
+        assert_eq!(CartridgeHeader::from_rom(&rom_with(b"A\x01B  ", 0x00, 0x00)).title(), "A?B");
+        assert_eq!(CartridgeHeader::from_rom(&[0; 0x140]).title(), "");
+        assert_eq!(CartridgeHeader::from_rom(&[]).cartridge_type(), 0);
+    }
+}
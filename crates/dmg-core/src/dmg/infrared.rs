@@ -0,0 +1,134 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use bit_field::BitField;
+use serde::{Deserialize, Serialize};
+
+use crate::dmg::traits::IrTransceiver;
+
+/// The CGB infrared port, RP (0xFF56). Light goes out and comes in through the connected
+/// transceiver; without one the port never sees any light.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Infrared {
+    rp: u8,
+
+    #[serde(skip)]
+    transceiver: Option<Arc<dyn IrTransceiver>>,
+}
+
+impl Infrared {
+    pub fn connect(&mut self, transceiver: Option<Arc<dyn IrTransceiver>>) {
+        self.transceiver = transceiver;
+        self.update_led();
+    }
+
+    pub fn transceiver(&self) -> Option<Arc<dyn IrTransceiver>> {
+        self.transceiver.clone()
+    }
+
+    pub fn read_byte(&self) -> u8 {
+        let read_enabled = self.rp & 0xc0 == 0xc0;
+        let receiving = read_enabled && self.transceiver.as_ref().is_some_and(|t| t.receiving());
+
+        // Bit 1 is low while light is received, and reads high while reading is disabled
+        (self.rp & 0xc1) | 0x3c | if receiving { 0x00 } else { 0x02 }
+    }
+
+    pub fn write_byte(&mut self, value: u8) {
+        self.rp = value & 0xc1;
+        self.update_led();
+    }
+
+    fn update_led(&self) {
+        if let Some(transceiver) = &self.transceiver {
+            transceiver.set_emitting(self.rp.get_bit(0));
+        }
+    }
+}
+
+impl fmt::Debug for Infrared {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Infrared").field("rp", &self.rp).field("connected", &self.transceiver.is_some()).finish()
+    }
+}
+
+/// Receives the core's own light, as when a CGB faces a mirror. Games use this to test the port.
+#[derive(Debug, Default)]
+pub struct IrLoopback {
+    emitting: AtomicBool,
+}
+
+impl IrTransceiver for IrLoopback {
+    fn set_emitting(&self, on: bool) {
+        self.emitting.store(on, Ordering::Relaxed);
+    }
+
+    fn receiving(&self) -> bool {
+        self.emitting.load(Ordering::Relaxed)
+    }
+}
+
+/// One end of two cores facing each other, e.g. for Mystery Gift. Each end receives the light the
+/// other emits. Games time the signal in CPU cycles, so the two cores should be stepped in
+/// lockstep, a few cycles at a time.
+#[derive(Debug)]
+pub struct IrLink {
+    emitting: Arc<AtomicBool>,
+    other: Arc<AtomicBool>,
+}
+
+impl IrLink {
+    pub fn pair() -> (IrLink, IrLink) {
+        let a = Arc::new(AtomicBool::new(false));
+        let b = Arc::new(AtomicBool::new(false));
+
+        (IrLink { emitting: a.clone(), other: b.clone() }, IrLink { emitting: b, other: a })
+    }
+}
+
+impl IrTransceiver for IrLink {
+    fn set_emitting(&self, on: bool) {
+        self.emitting.store(on, Ordering::Relaxed);
+    }
+
+    fn receiving(&self) -> bool {
+        self.other.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::dmg::infrared::{Infrared, IrLink, IrLoopback};
+
+    #[test]
+    fn reads_light_only_while_reading_is_enabled() {
+        let mut port = Infrared::default();
+        port.connect(Some(Arc::new(IrLoopback::default())));
+
+        port.write_byte(0x01);
+        assert_eq!(port.read_byte(), 0x3f);
+
+        port.write_byte(0xc1);
+        assert_eq!(port.read_byte(), 0xfd);
+
+        port.write_byte(0xc0);
+        assert_eq!(port.read_byte(), 0xfe);
+    }
+
+    #[test]
+    fn linked_ports_receive_each_others_light() {
+        let (a, b) = IrLink::pair();
+        let mut port_a = Infrared::default();
+        let mut port_b = Infrared::default();
+        port_a.connect(Some(Arc::new(a)));
+        port_b.connect(Some(Arc::new(b)));
+
+        port_a.write_byte(0xc1);
+        port_b.write_byte(0xc0);
+        assert_eq!(port_a.read_byte() & 0x02, 0x02);
+        assert_eq!(port_b.read_byte() & 0x02, 0x00);
+    }
+}
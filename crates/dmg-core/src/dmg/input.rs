@@ -0,0 +1,262 @@
+use bit_field::BitField;
+use bitflags::bitflags;
+use serde::{Serialize, Deserialize};
+use crate::dmg::sgb::{command, PacketReceiver, MLT_REQ};
+use crate::dmg::traits::Mem;
+
+/// Most joypads an SGB reads, with a multitap.
+pub const MAX_PLAYERS: usize = 4;
+
+bitflags! {
+    #[derive(Serialize, Deserialize)]
+    pub struct JoypadInput: u8 {
+        const DOWN    = 1 << 0;
+        const LEFT    = 1 << 1;
+        const UP      = 1 << 2;
+        const RIGHT   = 1 << 3;
+        const START   = 1 << 4;
+        const SELECT  = 1 << 5;
+        const A       = 1 << 6;
+        const B       = 1 << 7;
+    }
+}
+
+bitflags! {
+    #[derive(Serialize, Deserialize)]
+    pub struct JoypadOutput: u8 {
+        const RIGHT_OR_A    = 1 << 0;
+        const LEFT_OR_B     = 1 << 1;
+        const UP_OR_SELECT  = 1 << 2;
+        const DOWN_OR_START = 1 << 3;
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Joypad {
+    // P14 and P15 are active low and can be selected at the same time
+    select_direction: bool,
+    select_action: bool,
+    input: JoypadInput,
+
+    // SGB only: joypads 2-4, which player is read, and how many players MLT_REQ enabled
+    #[serde(default)]
+    sgb: bool,
+    #[serde(default)]
+    sgb_packets: PacketReceiver,
+    #[serde(default = "other_players_idle")]
+    other_players: [JoypadInput; MAX_PLAYERS - 1],
+    #[serde(default)]
+    player: usize,
+    #[serde(default = "one_player")]
+    players: usize,
+}
+
+fn other_players_idle() -> [JoypadInput; MAX_PLAYERS - 1] {
+    [JoypadInput::empty(); MAX_PLAYERS - 1]
+}
+
+fn one_player() -> usize {
+    1
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Self {
+            select_direction: true,
+            select_action: true,
+            input: JoypadInput::empty(),
+            sgb: false,
+            sgb_packets: PacketReceiver::default(),
+            other_players: other_players_idle(),
+            player: 0,
+            players: one_player(),
+        }
+    }
+}
+
+impl Joypad {
+    pub fn update(&mut self, input: JoypadInput) {
+        self.input = input;
+    }
+
+    /// Listens for SGB command packets on P1, for multiplayer.
+    pub fn set_sgb(&mut self, sgb: bool) {
+        self.sgb = sgb;
+    }
+
+    /// Sets the buttons held on joypad `player`, counting from 0, for players after the first.
+    pub fn update_player(&mut self, player: usize, input: JoypadInput) {
+        if let Some(keys) = player.checked_sub(1).and_then(|i| self.other_players.get_mut(i)) {
+            *keys = input;
+        }
+    }
+
+    /// How many joypads the game reads: 1, or 2 or 4 after an SGB MLT_REQ.
+    pub fn players(&self) -> usize {
+        self.players
+    }
+
+    fn selected_input(&self) -> JoypadInput {
+        match self.player {
+            0 => self.input,
+            player => self.other_players[player - 1],
+        }
+    }
+
+    fn receive_sgb_packet(&mut self, value: u8) {
+        let Some(packet) = self.sgb_packets.write(value) else { return };
+
+        if command(&packet) == MLT_REQ {
+            self.players = match packet[1] & 0x03 {
+                1 => 2,
+                3 => 4,
+                _ => 1,
+            };
+            self.player = 0;
+        }
+    }
+}
+
+impl Mem for Joypad {
+    /// With both groups selected the lines are wired together, so a button in either group pulls
+    /// its bit low. With neither selected all lines read high.
+    fn read_byte(&self, _addr: u16) -> u8 {
+        if self.players > 1 && !self.select_action && !self.select_direction {
+            // The SGB answers with the ID of the joypad read next, 0xf for the first
+            return 0xf0 | (0x0f - self.player as u8);
+        }
+
+        let input = self.selected_input();
+        let mut output = JoypadOutput::all();
+        if self.select_action {
+            if input.contains(JoypadInput::START) {
+                output.remove(JoypadOutput::DOWN_OR_START);
+            }
+            if input.contains(JoypadInput::SELECT) {
+                output.remove(JoypadOutput::UP_OR_SELECT);
+            }
+            if input.contains(JoypadInput::A) {
+                output.remove(JoypadOutput::RIGHT_OR_A);
+            }
+            if input.contains(JoypadInput::B) {
+                output.remove(JoypadOutput::LEFT_OR_B);
+            }
+        }
+        if self.select_direction {
+            if input.contains(JoypadInput::DOWN) {
+                output.remove(JoypadOutput::DOWN_OR_START);
+            }
+            if input.contains(JoypadInput::UP) {
+                output.remove(JoypadOutput::UP_OR_SELECT);
+            }
+            if input.contains(JoypadInput::RIGHT) {
+                output.remove(JoypadOutput::RIGHT_OR_A);
+            }
+            if input.contains(JoypadInput::LEFT) {
+                output.remove(JoypadOutput::LEFT_OR_B);
+            }
+        }
+
+        let mut value = 0xc0 | output.bits;
+        value.set_bit(4, !self.select_direction);
+        value.set_bit(5, !self.select_action);
+        value
+    }
+
+    fn write_byte(&mut self, _addr: u16, value: u8) {
+        if self.sgb {
+            // The next joypad is selected when P15 goes high
+            if self.select_action && value.get_bit(5) {
+                self.player = (self.player + 1) % self.players;
+            }
+            self.receive_sgb_packet(value);
+        }
+
+        self.select_direction = !value.get_bit(4);
+        self.select_action = !value.get_bit(5);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::input::{Joypad, JoypadInput};
+    use crate::dmg::sgb::tests::packet_writes;
+    use crate::dmg::sgb::{MLT_REQ, PACKET_SIZE};
+    use crate::dmg::traits::Mem;
+
+    fn joypad_with(input: JoypadInput, select: u8) -> Joypad {
+        let mut joypad = Joypad::default();
+        joypad.update(input);
+        joypad.write_byte(0xff00, select);
+        joypad
+    }
+
+    #[test]
+    fn reads_selected_group_only() {
+        assert_eq!(joypad_with(JoypadInput::A | JoypadInput::UP, 0x10).read_byte(0xff00), 0xde);
+        assert_eq!(joypad_with(JoypadInput::A | JoypadInput::UP, 0x20).read_byte(0xff00), 0xeb);
+    }
+
+    #[test]
+    fn both_groups_selected_merges_buttons() {
+        assert_eq!(joypad_with(JoypadInput::A | JoypadInput::UP, 0x00).read_byte(0xff00), 0xca);
+    }
+
+    #[test]
+    fn no_group_selected_reads_idle() {
+        assert_eq!(joypad_with(JoypadInput::all(), 0x30).read_byte(0xff00), 0xff);
+    }
+
+    fn sgb_joypad_with_players(mode: u8) -> Joypad {
+        let mut packet = [0; PACKET_SIZE];
+        packet[0] = MLT_REQ << 3 | 1;
+        packet[1] = mode;
+
+        let mut joypad = Joypad::default();
+        joypad.set_sgb(true);
+        for p1 in packet_writes(packet) {
+            joypad.write_byte(0xff00, p1);
+        }
+        joypad
+    }
+
+    #[test]
+    fn sgb_multiplayer_cycles_through_joypad_ids() {
+        let mut joypad = sgb_joypad_with_players(0x03);
+        assert_eq!(joypad.players(), 4);
+
+        let ids: Vec<u8> = (0..5).map(|_| {
+            let id = joypad.read_byte(0xff00) & 0x0f;
+            joypad.write_byte(0xff00, 0x10);
+            joypad.write_byte(0xff00, 0x30);
+            id
+        }).collect();
+        assert_eq!(ids, [0xf, 0xe, 0xd, 0xc, 0xf]);
+    }
+
+    #[test]
+    fn sgb_multiplayer_reads_each_players_buttons() {
+        let mut joypad = sgb_joypad_with_players(0x01);
+        joypad.update(JoypadInput::A);
+        joypad.update_player(1, JoypadInput::B);
+
+        joypad.write_byte(0xff00, 0x10);
+        assert_eq!(joypad.read_byte(0xff00) & 0x0f, 0x0e);
+        joypad.write_byte(0xff00, 0x30);
+        joypad.write_byte(0xff00, 0x10);
+        assert_eq!(joypad.read_byte(0xff00) & 0x0f, 0x0d);
+
+        let joypad = sgb_joypad_with_players(0x00);
+        assert_eq!(joypad.players(), 1);
+        assert_eq!(joypad.read_byte(0xff00), 0xff);
+    }
+
+    #[test]
+    fn ignores_packets_outside_sgb() {
+        let mut joypad = Joypad::default();
+        for p1 in packet_writes([MLT_REQ << 3 | 1, 0x03, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]) {
+            joypad.write_byte(0xff00, p1);
+        }
+        assert_eq!(joypad.players(), 1);
+    }
+}
@@ -0,0 +1,157 @@
+/// The component that answers reads and writes of an IO register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Owner {
+    Joypad,
+    Serial,
+    Timer,
+    /// IF and IE
+    Interrupts,
+    Apu,
+    /// The LCD registers, VRAM bank included
+    Ppu,
+    /// The OAM DMA source, starting a transfer when written
+    Dma,
+    /// Unmaps the boot ROM when 1 is written
+    BootRom,
+    Infrared,
+    /// The CGB's WRAM bank
+    WramBank,
+    /// The CGB's undocumented FF72-FF75, which only hold what's written to them
+    Undocumented,
+    /// CGB registers that aren't emulated: they read as 0xFF and writes are dropped
+    Ignored,
+}
+
+/// Which consoles have a register. Elsewhere it reads as 0xFF and writes are dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Availability {
+    All,
+    /// On a CGB, also when running DMG games
+    CgbHardware,
+    /// On a CGB running a CGB game
+    CgbMode,
+}
+
+/// An IO register at 0xFF00-0xFF7F or 0xFFFF, see `IO_REGISTERS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoRegister {
+    pub addr: u16,
+    /// As in the Pan Docs, empty for the undocumented FF72-FF75 that have no name.
+    pub name: &'static str,
+    pub owner: Owner,
+    /// Bits that always read as 1: unused bits, write-only bits and write-only registers.
+    pub read_mask: u8,
+    pub availability: Availability,
+}
+
+const fn register(addr: u16, name: &'static str, owner: Owner, read_mask: u8) -> IoRegister {
+    IoRegister { addr, name, owner, read_mask, availability: Availability::All }
+}
+
+const fn cgb_register(addr: u16, name: &'static str, owner: Owner, read_mask: u8, availability: Availability) -> IoRegister {
+    IoRegister { addr, name, owner, read_mask, availability }
+}
+
+/// Every IO register of the DMG and CGB, sorted by address. Addresses missing here are unused,
+/// reading as 0xFF, apart from wave RAM at FF30-FF3F. The memory bus dispatches accesses by
+/// `owner`, applies `read_mask` to what is read, and the debugger shows the names.
+#[rustfmt::skip]
+pub const IO_REGISTERS: [IoRegister; 63] = {
+    use Availability::{CgbHardware, CgbMode};
+    use Owner::*;
+
+    [
+        register(0xff00, "P1", Joypad, 0xc0),
+        register(0xff01, "SB", Serial, 0x00),
+        register(0xff02, "SC", Serial, 0x7e),
+        register(0xff04, "DIV", Timer, 0x00),
+        register(0xff05, "TIMA", Timer, 0x00),
+        register(0xff06, "TMA", Timer, 0x00),
+        register(0xff07, "TAC", Timer, 0xf8),
+        register(0xff0f, "IF", Interrupts, 0xe0),
+        register(0xff10, "NR10", Apu, 0x80),
+        register(0xff11, "NR11", Apu, 0x3f),
+        register(0xff12, "NR12", Apu, 0x00),
+        register(0xff13, "NR13", Apu, 0xff),
+        register(0xff14, "NR14", Apu, 0xbf),
+        register(0xff16, "NR21", Apu, 0x3f),
+        register(0xff17, "NR22", Apu, 0x00),
+        register(0xff18, "NR23", Apu, 0xff),
+        register(0xff19, "NR24", Apu, 0xbf),
+        register(0xff1a, "NR30", Apu, 0x7f),
+        register(0xff1b, "NR31", Apu, 0xff),
+        register(0xff1c, "NR32", Apu, 0x9f),
+        register(0xff1d, "NR33", Apu, 0xff),
+        register(0xff1e, "NR34", Apu, 0xbf),
+        register(0xff20, "NR41", Apu, 0xff),
+        register(0xff21, "NR42", Apu, 0x00),
+        register(0xff22, "NR43", Apu, 0x00),
+        register(0xff23, "NR44", Apu, 0xbf),
+        register(0xff24, "NR50", Apu, 0x00),
+        register(0xff25, "NR51", Apu, 0x00),
+        register(0xff26, "NR52", Apu, 0x70),
+        register(0xff40, "LCDC", Ppu, 0x00),
+        register(0xff41, "STAT", Ppu, 0x80),
+        register(0xff42, "SCY", Ppu, 0x00),
+        register(0xff43, "SCX", Ppu, 0x00),
+        register(0xff44, "LY", Ppu, 0x00),
+        register(0xff45, "LYC", Ppu, 0x00),
+        register(0xff46, "DMA", Dma, 0x00),
+        register(0xff47, "BGP", Ppu, 0x00),
+        register(0xff48, "OBP0", Ppu, 0x00),
+        register(0xff49, "OBP1", Ppu, 0x00),
+        register(0xff4a, "WY", Ppu, 0x00),
+        register(0xff4b, "WX", Ppu, 0x00),
+        cgb_register(0xff4d, "KEY1", Ignored, 0x7e, CgbMode),
+        cgb_register(0xff4f, "VBK", Ppu, 0xfe, CgbMode),
+        register(0xff50, "BOOT", BootRom, 0xff),
+        cgb_register(0xff51, "HDMA1", Ignored, 0x00, CgbMode),
+        cgb_register(0xff52, "HDMA2", Ignored, 0x00, CgbMode),
+        cgb_register(0xff53, "HDMA3", Ignored, 0x00, CgbMode),
+        cgb_register(0xff54, "HDMA4", Ignored, 0x00, CgbMode),
+        cgb_register(0xff55, "HDMA5", Ignored, 0x00, CgbMode),
+        cgb_register(0xff56, "RP", Infrared, 0x3c, CgbMode),
+        cgb_register(0xff68, "BCPS", Ignored, 0x40, CgbMode),
+        cgb_register(0xff69, "BCPD", Ignored, 0x00, CgbMode),
+        cgb_register(0xff6a, "OCPS", Ignored, 0x40, CgbMode),
+        cgb_register(0xff6b, "OCPD", Ignored, 0x00, CgbMode),
+        cgb_register(0xff6c, "OPRI", Ignored, 0xfe, CgbMode),
+        cgb_register(0xff70, "SVBK", WramBank, 0xf8, CgbMode),
+        cgb_register(0xff72, "", Undocumented, 0x00, CgbHardware),
+        cgb_register(0xff73, "", Undocumented, 0x00, CgbHardware),
+        cgb_register(0xff74, "", Undocumented, 0x00, CgbMode),
+        // Only bits 4-6 are there
+        cgb_register(0xff75, "", Undocumented, 0x8f, CgbHardware),
+        cgb_register(0xff76, "PCM12", Apu, 0x00, CgbHardware),
+        cgb_register(0xff77, "PCM34", Apu, 0x00, CgbHardware),
+        register(0xffff, "IE", Interrupts, 0x00),
+    ]
+};
+
+/// The register at `addr`, `None` for unused addresses and anything outside the IO registers.
+pub fn io_register(addr: u16) -> Option<&'static IoRegister> {
+    IO_REGISTERS
+        .binary_search_by_key(&addr, |register| register.addr)
+        .ok()
+        .map(|i| &IO_REGISTERS[i])
+}
+
+/// The bits of the register at `addr` that always read as 1, all of them at unused addresses.
+pub fn read_mask(addr: u16) -> u8 {
+    io_register(addr).map_or(0xff, |register| register.read_mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::io_registers::{io_register, read_mask, Owner, IO_REGISTERS};
+
+    #[test]
+    fn registers_are_sorted_for_lookup() {
+        assert!(IO_REGISTERS.windows(2).all(|pair| pair[0].addr < pair[1].addr));
+        assert!(IO_REGISTERS.iter().all(|register| (0xff00..0xff80).contains(&register.addr) || register.addr == 0xffff));
+        assert_eq!(io_register(0xff05).map(|register| register.owner), Some(Owner::Timer));
+        assert_eq!(read_mask(0xff41), 0x80);
+        assert_eq!(read_mask(0xff03), 0xff);
+        assert_eq!(io_register(0xc000), None);
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::{Arc, Mutex};
+
+use crate::dmg::traits::SerialDevice;
+
+/// One end of a link cable between two cores, e.g. for two-player games. Whichever side clocks a
+/// transfer swaps bytes with the other, if that side is waiting on the external clock; otherwise it
+/// shifts in 0xFF as with nothing plugged in. A waiting transfer is only seen once the waiting core
+/// has been stepped, so the two cores should take turns a frame or less at a time.
+#[derive(Debug)]
+pub struct LinkCable {
+    side: usize,
+    ends: Arc<Mutex<[End; 2]>>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct End {
+    // The byte in SB while the game waits on the external clock
+    waiting: Option<u8>,
+    // The byte clocked in from the other side, finishing the waiting transfer
+    received: Option<u8>,
+}
+
+impl LinkCable {
+    pub fn pair() -> (LinkCable, LinkCable) {
+        let ends = Arc::new(Mutex::new([End::default(); 2]));
+
+        (LinkCable { side: 0, ends: ends.clone() }, LinkCable { side: 1, ends })
+    }
+}
+
+impl SerialDevice for LinkCable {
+    fn exchange(&self, sent: u8) -> u8 {
+        let mut ends = self.ends.lock().unwrap();
+        let other = &mut ends[1 - self.side];
+
+        match other.waiting.take() {
+            Some(byte) => {
+                other.received = Some(sent);
+                byte
+            }
+            None => 0xff,
+        }
+    }
+
+    fn clock_transfer(&self, sent: u8) -> Option<u8> {
+        let mut ends = self.ends.lock().unwrap();
+        let end = &mut ends[self.side];
+
+        let received = end.received.take();
+        end.waiting = if received.is_some() { None } else { Some(sent) };
+        received
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::link::LinkCable;
+    use crate::dmg::traits::SerialDevice;
+
+    #[test]
+    fn clocked_side_swaps_bytes_with_waiting_side() {
+        let (a, b) = LinkCable::pair();
+
+        // Nobody waiting on the other end
+        assert_eq!(a.exchange(0x42), 0xff);
+
+        assert_eq!(b.clock_transfer(0x24), None);
+        assert_eq!(a.exchange(0x42), 0x24);
+        assert_eq!(b.clock_transfer(0x24), Some(0x42));
+
+        // The finished transfer isn't swapped again
+        assert_eq!(a.exchange(0x43), 0xff);
+    }
+}
@@ -0,0 +1,531 @@
+use std::iter;
+
+use log::debug;
+use crate::dmg::rom::Rom;
+use crate::error::Error;
+use serde::{Serialize, Deserialize};
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+enum MBC {
+    NoMbc,
+    Mbc1,
+    Mbc1ExternalRam,
+    Mbc1BatteryExternalRam,
+    Mbc2,
+    Mbc2ExternalRam,
+    Mbc3,
+    RomExternatRam,
+    RomBatteryExternatRam,
+}
+
+impl Default for MBC {
+    fn default() -> Self {
+        MBC::NoMbc
+    }
+}
+
+impl TryFrom<u8> for MBC {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(MBC::NoMbc),
+            0x01 => Ok(MBC::Mbc1),
+            0x02 => Ok(MBC::Mbc1ExternalRam),
+            0x03 => Ok(MBC::Mbc1BatteryExternalRam),
+            0x05 => Ok(MBC::Mbc2),
+            0x06 => Ok(MBC::Mbc2ExternalRam),
+            0x08 => Ok(MBC::RomExternatRam),
+            0x09 => Ok(MBC::RomBatteryExternatRam),
+            0x0f..=0x13 => Ok(MBC::Mbc3),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct MBC0 {
+    rom: Rom,
+}
+
+impl MBC0 {
+    fn new(rom: Rom) -> Self {
+        Self {
+            rom
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq)]
+enum MBC1Mode {
+    RomMode,
+    RamMode,
+}
+
+
+impl TryFrom<u8> for MBC1Mode {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value & 0x01 {
+            0x00 => Ok(MBC1Mode::RomMode),
+            0x01 => Ok(MBC1Mode::RamMode),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct MBC1 {
+    rom: Rom,
+    #[serde(with = "serde_bytes")]
+    ram: Vec<u8>,
+
+    // Selected ROM bank
+    rom_bank: usize,
+
+    // Selected RAM bank
+    ram_bank: usize,
+
+    // RAM enable switch
+    ram_on: bool,
+
+    num_rom_banks: usize,
+    num_ram_banks: usize,
+
+    // ROM/RAM expansion mode
+    mode: MBC1Mode,
+
+    // Set when RAM changes, cleared when the frontend has seen it
+    #[serde(skip)]
+    ram_written: bool,
+}
+
+
+impl MBC1 {
+    fn new(rom: Rom) -> Self {
+        let num_rom_banks = rom_banks(*rom.get(0x148).unwrap_or(&0u8));
+
+        let num_ram_banks = ram_banks(*rom.get(0x0149).unwrap_or(&0u8));
+        let ram_size = num_ram_banks * 0x2000;
+
+        if num_ram_banks > 0 {
+            debug!(target: "mbc", "RAM banks: {}", num_ram_banks);
+        }
+
+        Self {
+            rom,
+            ram: iter::repeat(0u8).take(ram_size).collect(),
+            mode: MBC1Mode::RomMode,
+            ram_on: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            num_ram_banks,
+            num_rom_banks,
+            ram_written: false,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.mode = MBC1Mode::RomMode;
+        self.ram_on = false;
+        self.rom_bank = 1;
+        self.ram_bank = 0;
+    }
+
+    pub fn read_rom(&self, addr: usize) -> u8 {
+        *self.rom.get(self.rom_offset(addr)).unwrap_or(&0xff)
+    }
+
+    fn rom_offset(&self, addr: usize) -> usize {
+        let bank = if addr < 0x4000 {
+            if self.mode == MBC1Mode::RomMode {
+                self.rom_bank & 0xe0
+            } else { 0 }
+        } else {
+            self.rom_bank
+        };
+        (bank * 0x4000) | (addr & 0x3fff)
+    }
+
+    fn ram_offset(&self, addr: usize) -> usize {
+        let bank = if self.mode == MBC1Mode::RamMode { self.ram_bank } else { 0 };
+        (bank * 0x2000) | (addr & 0x1fff)
+    }
+
+    pub fn read_ram(&self, addr: usize) -> u8 {
+        if !self.ram_on { return 0xff; }
+
+        self.ram[self.ram_offset(addr)]
+    }
+
+    pub fn write_ram(&mut self, addr: usize, value: u8) {
+        if self.ram_on {
+            let idx = self.ram_offset(addr);
+
+            if idx < self.ram.len() && self.ram[idx] != value {
+                self.ram[idx] = value;
+                self.ram_written = true;
+            }
+        }
+    }
+
+    pub fn write_rom(&mut self, addr: usize, value: u8) {
+        match addr {
+            0x0000..=0x1fff => {
+                self.ram_on = value & 0xf == 0xa;
+            }
+            0x2000..=0x3fff => {
+                let lower_bits = match (value as usize) & 0x1f {
+                    0 => 1,
+                    n => n,
+                };
+                self.rom_bank = ((self.rom_bank & 0x60) | lower_bits) % self.num_rom_banks
+            }
+            0x4000..=0x5fff => {
+                if self.num_rom_banks > 0x20 {
+                    let upper_bits = (value as usize & 0x03) % (self.num_rom_banks >> 5);
+                    self.rom_bank = (self.rom_bank & 0x1f) | (upper_bits << 5);
+                }
+                if self.num_ram_banks > 1 {
+                    self.ram_bank = (value as usize) & 0x03;
+                }
+            }
+            0x6000..=0x7fff => {
+                self.mode = MBC1Mode::try_from(value).expect("Set unexpected bank_mode");
+            }
+            _ => unreachable!("MBC1 invalid address, {:04X}", addr)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct MBC3 {
+    rom: Rom,
+    #[serde(with = "serde_bytes")]
+    ram: Vec<u8>,
+
+    // Selected ROM bank
+    rom_bank: usize,
+
+    // Selected RAM bank, or RTC register from 0x08 to 0x0C
+    ram_bank: usize,
+
+    // RAM and RTC enable switch
+    ram_on: bool,
+
+    num_rom_banks: usize,
+
+    // MBC30, as in Pokémon Crystal (JP), has a whole byte of ROM bank and 8 RAM banks
+    mbc30: bool,
+
+    // Seconds, minutes, hours, day low and day high/halt/carry. The clock isn't emulated, so
+    // these only hold what the game writes
+    rtc: [u8; 5],
+
+    // Set when RAM changes, cleared when the frontend has seen it
+    #[serde(skip)]
+    ram_written: bool,
+}
+
+impl MBC3 {
+    fn new(rom: Rom) -> Self {
+        let num_rom_banks = rom_banks(*rom.get(0x148).unwrap_or(&0u8));
+        let num_ram_banks = ram_banks(*rom.get(0x0149).unwrap_or(&0u8));
+        let mbc30 = num_rom_banks > 0x80 || num_ram_banks > 4;
+
+        if mbc30 {
+            debug!(target: "mbc", "MBC30 with {} ROM banks and {} RAM banks", num_rom_banks, num_ram_banks);
+        }
+
+        Self {
+            rom,
+            ram: vec![0; num_ram_banks * 0x2000],
+            rom_bank: 1,
+            ram_bank: 0,
+            ram_on: false,
+            num_rom_banks,
+            mbc30,
+            rtc: [0; 5],
+            ram_written: false,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.rom_bank = 1;
+        self.ram_bank = 0;
+        self.ram_on = false;
+    }
+
+    pub fn read_rom(&self, addr: usize) -> u8 {
+        *self.rom.get(self.rom_offset(addr)).unwrap_or(&0xff)
+    }
+
+    fn rom_offset(&self, addr: usize) -> usize {
+        let bank = if addr < 0x4000 { 0 } else { self.rom_bank };
+        (bank * 0x4000) | (addr & 0x3fff)
+    }
+
+    fn ram_offset(&self, addr: usize) -> usize {
+        (self.ram_bank * 0x2000) | (addr & 0x1fff)
+    }
+
+    fn rtc_register(&self) -> Option<usize> {
+        (0x08..=0x0c).contains(&self.ram_bank).then(|| self.ram_bank - 0x08)
+    }
+
+    pub fn read_ram(&self, addr: usize) -> u8 {
+        if !self.ram_on { return 0xff; }
+
+        match self.rtc_register() {
+            Some(register) => self.rtc[register],
+            None => *self.ram.get(self.ram_offset(addr)).unwrap_or(&0xff),
+        }
+    }
+
+    pub fn write_ram(&mut self, addr: usize, value: u8) {
+        if !self.ram_on { return; }
+
+        if let Some(register) = self.rtc_register() {
+            self.rtc[register] = value;
+            return;
+        }
+
+        let idx = self.ram_offset(addr);
+        if idx < self.ram.len() && self.ram[idx] != value {
+            self.ram[idx] = value;
+            self.ram_written = true;
+        }
+    }
+
+    // False for the writes that aren't emulated
+    pub fn write_rom(&mut self, addr: usize, value: u8) -> bool {
+        match addr {
+            0x0000..=0x1fff => {
+                self.ram_on = value & 0xf == 0xa;
+            }
+            0x2000..=0x3fff => {
+                let mask = if self.mbc30 { 0xff } else { 0x7f };
+                let bank = match (value as usize) & mask {
+                    0 => 1,
+                    n => n,
+                };
+                self.rom_bank = bank % self.num_rom_banks.max(1);
+            }
+            0x4000..=0x5fff => {
+                let mask = if self.mbc30 { 0x07 } else { 0x03 };
+                self.ram_bank = match value as usize {
+                    rtc @ 0x08..=0x0c => rtc,
+                    bank => bank & mask,
+                };
+                // Neither a RAM bank nor a clock register
+                return self.ram_bank == value as usize;
+            }
+            0x6000..=0x7fff => {
+                // Latches the clock, which doesn't run
+                return false;
+            }
+            _ => unreachable!("MBC3 invalid address, {:04X}", addr)
+        }
+        true
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+enum MBCType {
+    Mbc0(MBC0),
+    Mbc1(MBC1),
+    Mbc3(MBC3),
+    // No cartridge inserted, where the pulled up data lines read 0xFF
+    Empty,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MBCWrapper {
+    variant: MBCType,
+}
+
+impl Default for MBCWrapper {
+    fn default() -> Self {
+        Self {
+            variant: MBCType::Empty
+        }
+    }
+}
+
+fn ram_banks(v: u8) -> usize {
+    match v {
+        1 | 2 => 1,
+        3 => 4,
+        4 => 16,
+        5 => 8,
+        _ => 0,
+    }
+}
+
+fn rom_banks(v: u8) -> usize {
+    if v <= 8 {
+        2 << v
+    } else {
+        0
+    }
+}
+
+impl MBCWrapper {
+    pub fn new(rom: Rom) -> Result<Self, Error> {
+        let cartridge_type = rom.get(0x147).copied().unwrap_or_default();
+        let mbc = MBC::try_from(cartridge_type).map_err(|_| Error::UnsupportedMapper(cartridge_type))?;
+
+        match mbc {
+            MBC::NoMbc => {
+                Ok(Self {
+                    variant: MBCType::Mbc0(MBC0::new(rom))
+                })
+            }
+            MBC::Mbc1 | MBC::Mbc1BatteryExternalRam | MBC::Mbc1ExternalRam => {
+                Ok(Self {
+                    variant: MBCType::Mbc1(MBC1::new(rom))
+                })
+            }
+            MBC::Mbc3 => {
+                Ok(Self {
+                    variant: MBCType::Mbc3(MBC3::new(rom))
+                })
+            }
+            _ => Err(Error::UnsupportedMapper(cartridge_type)),
+        }
+    }
+
+    /// Resets the banking registers, keeping the ROM and the contents of RAM.
+    pub fn reset(&mut self) {
+        match &mut self.variant {
+            MBCType::Mbc0(_) | MBCType::Empty => {}
+            MBCType::Mbc1(ref mut m) => m.reset(),
+            MBCType::Mbc3(ref mut m) => m.reset(),
+        }
+    }
+
+    /// Whether a cartridge is inserted.
+    pub fn is_inserted(&self) -> bool {
+        !matches!(self.variant, MBCType::Empty)
+    }
+
+    /// Whether the cartridge type has a battery keeping RAM contents while powered off.
+    pub fn has_battery(&self) -> bool {
+        matches!(self.rom().get(0x147), Some(0x03 | 0x06 | 0x09 | 0x0d | 0x0f | 0x10 | 0x13 | 0x1b | 0x1e | 0x22 | 0xff))
+    }
+
+    pub fn ram(&self) -> &[u8] {
+        match self.variant {
+            MBCType::Mbc0(_) | MBCType::Empty => &[],
+            MBCType::Mbc1(ref m) => &m.ram,
+            MBCType::Mbc3(ref m) => &m.ram,
+        }
+    }
+
+    pub fn ram_mut(&mut self) -> &mut [u8] {
+        match &mut self.variant {
+            MBCType::Mbc0(_) | MBCType::Empty => &mut [],
+            MBCType::Mbc1(ref mut m) => &mut m.ram,
+            MBCType::Mbc3(ref mut m) => &mut m.ram,
+        }
+    }
+
+    /// Offset into `ram` of `addr` in the mapped RAM bank, whether or not RAM is enabled. `None`
+    /// past the end of RAM, or with an RTC register mapped instead.
+    pub fn ram_offset(&self, addr: usize) -> Option<usize> {
+        match self.variant {
+            MBCType::Mbc0(_) | MBCType::Empty => None,
+            MBCType::Mbc1(ref m) => Some(m.ram_offset(addr)).filter(|&offset| offset < m.ram.len()),
+            MBCType::Mbc3(ref m) => match m.rtc_register() {
+                Some(_) => None,
+                None => Some(m.ram_offset(addr)).filter(|&offset| offset < m.ram.len()),
+            },
+        }
+    }
+
+    /// Flags RAM as changed, for edits made through `ram_mut`.
+    pub fn mark_ram_written(&mut self) {
+        match &mut self.variant {
+            MBCType::Mbc0(_) | MBCType::Empty => {}
+            MBCType::Mbc1(ref mut m) => m.ram_written = true,
+            MBCType::Mbc3(ref mut m) => m.ram_written = true,
+        }
+    }
+
+    /// Whether RAM has changed since `take_ram_written` was last called, leaving the flag set.
+    pub fn ram_written(&self) -> bool {
+        match &self.variant {
+            MBCType::Mbc0(_) | MBCType::Empty => false,
+            MBCType::Mbc1(ref m) => m.ram_written,
+            MBCType::Mbc3(ref m) => m.ram_written,
+        }
+    }
+
+    /// Returns whether RAM has changed since the last call.
+    pub fn take_ram_written(&mut self) -> bool {
+        match &mut self.variant {
+            MBCType::Mbc0(_) | MBCType::Empty => false,
+            MBCType::Mbc1(ref mut m) => std::mem::take(&mut m.ram_written),
+            MBCType::Mbc3(ref mut m) => std::mem::take(&mut m.ram_written),
+        }
+    }
+
+    pub fn rom(&self) -> &[u8] {
+        match self.variant {
+            MBCType::Mbc0(MBC0 { ref rom }) => rom,
+            MBCType::Mbc1(MBC1 { ref rom, .. }) => rom,
+            MBCType::Mbc3(MBC3 { ref rom, .. }) => rom,
+            MBCType::Empty => &[],
+        }
+    }
+
+    pub fn read_rom(&self, addr: usize) -> u8 {
+        match self.variant {
+            MBCType::Mbc0(MBC0 { ref rom }) => rom[addr],
+            MBCType::Mbc1(ref m) => m.read_rom(addr),
+            MBCType::Mbc3(ref m) => m.read_rom(addr),
+            MBCType::Empty => 0xff,
+        }
+    }
+
+    /// Where in the ROM the CPU address `addr` (0x0000-0x7FFF) is mapped with the current banks.
+    pub fn rom_offset(&self, addr: usize) -> usize {
+        match self.variant {
+            MBCType::Mbc0(_) | MBCType::Empty => addr,
+            MBCType::Mbc1(ref m) => m.rom_offset(addr),
+            MBCType::Mbc3(ref m) => m.rom_offset(addr),
+        }
+    }
+
+
+    pub fn read_ram(&self, addr: usize) -> u8 {
+        match self.variant {
+            MBCType::Mbc0(_) => 0x00,
+            MBCType::Mbc1(ref m) => m.read_ram(addr),
+            MBCType::Mbc3(ref m) => m.read_ram(addr),
+            MBCType::Empty => 0xff,
+        }
+    }
+
+    pub fn write_ram(&mut self, addr: usize, value: u8) {
+        match &mut self.variant {
+            MBCType::Mbc0(_) | MBCType::Empty => {}
+            MBCType::Mbc1(ref mut a) => a.write_ram(addr, value),
+            MBCType::Mbc3(ref mut a) => a.write_ram(addr, value),
+        }
+    }
+
+    /// Writes to the mapper's registers. False when the mapper doesn't emulate the write, e.g. an
+    /// MBC3 clock latch or a RAM bank the cartridge can't have.
+    pub fn write_rom(&mut self, addr: usize, value: u8) -> bool {
+        match &mut self.variant {
+            MBCType::Mbc0(_) | MBCType::Empty => true,
+            MBCType::Mbc1(ref mut a) => {
+                a.write_rom(addr, value);
+                true
+            }
+            MBCType::Mbc3(ref mut a) => a.write_rom(addr, value),
+        }
+    }
+}
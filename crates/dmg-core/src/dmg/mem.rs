@@ -0,0 +1,585 @@
+// 32K memory
+// 0x0000 - 0x7FFF
+
+// 0x0000 - 0x00FF: Destination address for RST instructions and starting address for interrupts
+// 0x0100 - 0x014F: ROM area for storing data such as the name of the game
+//          0x0150: Starting address of the user program
+
+// 0x8000 - 0x9FFF: RAM for LCD display
+//                  Only 8KB is used for DMG
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::mem;
+use std::ops::Range;
+
+use crate::dmg::coverage::Coverage;
+use crate::dmg::crash::CrashReason;
+use crate::dmg::diagnostics::{DeveloperChecks, Diagnostic, DiagnosticQueue, ExecutionDiagnostics};
+use crate::dmg::cpu::instruction_len;
+use crate::dmg::debug::{FrameStats, MemoryRegion};
+use crate::dmg::gpu::GPU;
+use crate::dmg::infrared::Infrared;
+use crate::dmg::input::Joypad;
+use crate::dmg::intf::InterruptFlag;
+use crate::dmg::io_registers::{io_register, Availability, IoRegister, Owner};
+use crate::dmg::mbc::MBCWrapper;
+use crate::dmg::model::PowerOnRam;
+use crate::dmg::rom::Rom;
+use crate::dmg::serial::Serial;
+use serde::{Serialize, Deserialize};
+use crate::dmg::sound::Apu;
+use crate::dmg::boot_rom::{CGB_BOOT_ROM_SIZE, DMG_BOOT_ROM_SIZE, DMG_POST_BOOT_IO};
+use crate::dmg::traits::Mem;
+use crate::error::Error;
+
+const WRAM_SIZE: usize = 0x8000;
+const ZRAM_SIZE: usize = 0x7F;
+
+
+/// Where a 256-byte page of the address space is mapped, so that the common ROM and WRAM accesses
+/// skip the full address decoding.
+#[derive(Debug, Copy, Clone)]
+enum Page {
+    /// At this offset into the ROM, with the current banks
+    Rom(usize),
+    /// At this offset into WRAM, with the current bank
+    Wram(usize),
+    /// Anything with side effects, registers or memory owned by another component
+    Decode,
+}
+
+fn unmapped_pages() -> Box<[Page; 0x100]> {
+    Box::new([Page::Decode; 0x100])
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MemoryBus {
+    // Kept on the heap so moving a MemoryBus (e.g. while deserializing a state) stays cheap
+    #[serde(with = "serde_bytes")]
+    wram: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    zram: [u8; ZRAM_SIZE],
+    boot_rom_disabled: bool,
+    mbc: MBCWrapper,
+    pub serial: Serial,
+    wram_bank: usize,
+    // Running a CGB game on a CGB, which makes the CGB registers available
+    #[serde(default)]
+    cgb_mode: bool,
+    // Running on a CGB in either mode, for the registers that are there even for DMG games
+    #[serde(default)]
+    cgb_hardware: bool,
+    // The undocumented FF72-FF75, which only hold what's written to them
+    #[serde(default)]
+    undocumented: [u8; 4],
+    // The last OAM DMA source written, which reads back
+    #[serde(default)]
+    dma_source: u8,
+    #[serde(default)]
+    pub infrared: Infrared,
+    #[serde(skip)]
+    pub coverage: Option<Coverage>,
+    #[serde(skip)]
+    pub diagnostics: Option<ExecutionDiagnostics>,
+    // Filled from reads too, which only borrow the bus
+    #[serde(skip)]
+    pub diagnostic_queue: RefCell<DiagnosticQueue>,
+    // Also checks reads, which only borrow the bus
+    #[serde(skip)]
+    pub developer_checks: Option<RefCell<DeveloperChecks>>,
+    // Set from reads too, and picked up by the core after the instruction
+    #[serde(skip)]
+    crash: Cell<Option<CrashReason>>,
+    // Events so far in the current frame
+    #[serde(skip)]
+    pub stats: FrameStats,
+
+    // 256 bytes for DMG, 2304 for CGB where 0x100-0x1ff is left to the cartridge header
+    #[serde(with = "serde_bytes")]
+    boot_rom: Vec<u8>,
+    pub input: Joypad,
+    pub ppu: GPU,
+
+    #[serde(default)]
+    pub apu: Apu,
+    pub interrupt_enable: InterruptFlag,
+
+    // Rebuilt by `update_pages` whenever the banks change
+    #[serde(skip, default = "unmapped_pages")]
+    pages: Box<[Page; 0x100]>,
+}
+
+impl Default for MemoryBus {
+    fn default() -> Self {
+        MemoryBus {
+            wram: vec![0x00; WRAM_SIZE],
+            zram: [0x00; ZRAM_SIZE],
+            wram_bank: 1,
+            cgb_mode: false,
+            cgb_hardware: false,
+            undocumented: [0; 4],
+            dma_source: 0,
+            infrared: Infrared::default(),
+            coverage: None,
+            diagnostics: None,
+            diagnostic_queue: RefCell::default(),
+            developer_checks: None,
+            crash: Cell::new(None),
+            stats: FrameStats::default(),
+            serial: Serial::default(),
+            mbc: MBCWrapper::default(),
+            ppu: GPU::new(),
+            apu: Apu::default(),
+            boot_rom: vec![0x00; DMG_BOOT_ROM_SIZE],
+            input: Joypad::default(),
+            boot_rom_disabled: false,
+            interrupt_enable: InterruptFlag::empty(),
+            pages: unmapped_pages(),
+        }
+    }
+}
+
+impl MemoryBus {
+    pub fn new_without_boot_rom(rom: Option<Rom>) -> Result<MemoryBus, Error> {
+        let mut bus = Self::new(None, rom)?;
+        bus.apply_post_boot_state();
+
+        Ok(bus)
+    }
+    pub fn new(bootloader: Option<Vec<u8>>, rom: Option<Rom>) -> Result<MemoryBus, Error> {
+        let boot_rom_disabled = bootloader.is_none();
+        let boot_rom = match bootloader {
+            Some(rom) if rom.len() == DMG_BOOT_ROM_SIZE || rom.len() == CGB_BOOT_ROM_SIZE => rom,
+            Some(rom) => return Err(Error::InvalidBootRom(rom.len())),
+            None => vec![0x00; DMG_BOOT_ROM_SIZE],
+        };
+
+        let mbc = rom.map(MBCWrapper::new).transpose()?.unwrap_or_default();
+
+
+        let mut bus = MemoryBus {
+            wram: vec![0x00; WRAM_SIZE],
+            zram: [0x00; ZRAM_SIZE],
+            wram_bank: 1,
+            cgb_mode: false,
+            cgb_hardware: false,
+            undocumented: [0; 4],
+            dma_source: 0,
+            infrared: Infrared::default(),
+            coverage: None,
+            diagnostics: None,
+            diagnostic_queue: RefCell::default(),
+            developer_checks: None,
+            crash: Cell::new(None),
+            stats: FrameStats::default(),
+            mbc,
+            serial: Serial::default(),
+            boot_rom,
+            boot_rom_disabled,
+            input: Joypad::default(),
+            ppu: GPU::new(),
+            apu: Apu::default(),
+            interrupt_enable: InterruptFlag::empty(),
+            pages: unmapped_pages(),
+        };
+        bus.update_pages();
+
+        Ok(bus)
+    }
+
+    fn dma_transfer(&mut self, addr: u8) {
+        self.dma_source = addr;
+        self.stats.dma_transfers += 1;
+        let address_block: u16 = (addr as u16) << 8;
+        for i in 0..=0x9f {
+            self.write_byte(0xfe00 + i, self.read_byte(address_block + i));
+        }
+    }
+
+    /// Powers everything but the cartridge back on in the post-boot state. The cartridge keeps its
+    /// ROM and RAM.
+    pub fn reset(&mut self) {
+        let mut mbc = mem::take(&mut self.mbc);
+        mbc.reset();
+        let mut infrared = Infrared::default();
+        infrared.connect(self.infrared.transceiver());
+        let mut serial = Serial::default();
+        serial.connect(self.serial.device());
+
+        *self = MemoryBus {
+            mbc,
+            boot_rom_disabled: true,
+            cgb_mode: self.cgb_mode,
+            cgb_hardware: self.cgb_hardware,
+            infrared,
+            serial,
+            coverage: self.coverage.take(),
+            diagnostics: self.diagnostics.take(),
+            developer_checks: self.developer_checks.take(),
+            ..MemoryBus::default()
+        };
+        self.ppu.set_cgb_mode(self.cgb_mode);
+        self.apu.set_cgb_hardware(self.cgb_hardware);
+        self.apply_post_boot_state();
+        self.update_pages();
+    }
+
+    /// Maps the ROM and WRAM pages for the current banks. Needed after anything that switches
+    /// banks or the boot ROM, and after deserializing, as the pages aren't saved.
+    pub fn update_pages(&mut self) {
+        self.map_pages(0x00..0x100);
+    }
+
+    fn map_pages(&mut self, pages: Range<usize>) {
+        let rom_len = self.mbc.rom().len();
+
+        for (mapping, page) in self.pages[pages.clone()].iter_mut().zip(pages) {
+            let address = page << 8;
+            let in_boot_rom = !self.boot_rom_disabled && (address < 0x100 || (0x200..self.boot_rom.len()).contains(&address));
+
+            *mapping = match address {
+                0x0000..=0x7fff if !in_boot_rom => {
+                    let offset = self.mbc.rom_offset(address);
+                    // Reads past the end of a short ROM go through the MBC
+                    if offset + 0x100 <= rom_len { Page::Rom(offset) } else { Page::Decode }
+                }
+                0xc000..=0xcfff | 0xe000..=0xefff => Page::Wram(address & 0x0fff),
+                0xd000..=0xdfff | 0xf000..=0xfdff => Page::Wram((self.wram_bank * 0x1000) | address & 0x0fff),
+                _ => Page::Decode,
+            };
+        }
+    }
+
+    fn apply_post_boot_state(&mut self) {
+        for (addr, value) in DMG_POST_BOOT_IO {
+            self.write_byte(addr, value);
+        }
+        self.ppu.skip_boot_rom();
+        self.stats = FrameStats::default();
+    }
+
+    /// Fills WRAM and HRAM as they are at power on, and VRAM too when a boot ROM is about to run
+    /// and clear it as on hardware. Starting from the post-boot state, VRAM stays as the boot ROM
+    /// leaves it.
+    pub fn fill_power_on_ram(&mut self, ram: PowerOnRam) {
+        ram.fill(&mut self.wram, 0);
+        ram.fill(&mut self.zram, 1);
+        if !self.boot_rom_disabled {
+            ram.fill(self.ppu.vram_mut(), 2);
+        }
+    }
+
+    /// Whether the boot ROM is still mapped over the start of the cartridge ROM.
+    pub fn boot_rom_mapped(&self) -> bool {
+        !self.boot_rom_disabled
+    }
+
+    /// Replaces the boot ROM, which the CPU runs from right away while it is mapped.
+    pub fn set_boot_rom(&mut self, boot_rom: Vec<u8>) {
+        self.boot_rom = boot_rom;
+        self.update_pages();
+    }
+
+    pub fn set_cgb_mode(&mut self, cgb_mode: bool) {
+        self.cgb_mode = cgb_mode;
+        self.ppu.set_cgb_mode(cgb_mode);
+    }
+
+    pub fn cgb_mode(&self) -> bool {
+        self.cgb_mode
+    }
+
+    pub fn set_cgb_hardware(&mut self, cgb_hardware: bool) {
+        self.cgb_hardware = cgb_hardware;
+        self.apu.set_cgb_hardware(cgb_hardware);
+    }
+
+    pub fn cgb_hardware(&self) -> bool {
+        self.cgb_hardware
+    }
+
+    /// The bank mapped at `addr`, as symbol files number them: the ROM bank in ROM and the WRAM
+    /// bank in banked WRAM. Other areas are bank 0.
+    pub fn bank_of(&self, addr: u16) -> u16 {
+        match addr {
+            0x0000..=0x7fff => (self.mbc.rom_offset(addr as usize) / 0x4000) as u16,
+            0xd000..=0xdfff => self.wram_bank as u16,
+            _ => 0,
+        }
+    }
+
+    /// Records the instruction at `pc` as executed when coverage is tracked and it runs from ROM,
+    /// and for diagnostics wherever it runs from.
+    pub fn mark_executed(&mut self, pc: u16) {
+        if self.diagnostics.is_some() {
+            let len = instruction_len(self.read_byte(pc));
+            if let Some(diagnostics) = &mut self.diagnostics {
+                diagnostics.mark_executed(pc, len);
+            }
+        }
+
+        let address = pc as usize;
+        let in_boot_rom = !self.boot_rom_disabled && (address < 0x100 || (0x200..self.boot_rom.len()).contains(&address));
+        if address >= 0x8000 || in_boot_rom {
+            return;
+        }
+
+        if let Some(coverage) = &mut self.coverage {
+            let len = instruction_len(self.mbc.read_rom(address));
+            coverage.mark_executed(self.mbc.rom_offset(address), len as usize);
+        }
+    }
+
+    pub fn take_serial_output(&mut self) -> Vec<u8> {
+        self.serial.take_output()
+    }
+
+    pub fn take_serial_sent(&mut self) -> Option<u8> {
+        self.serial.take_sent()
+    }
+
+    pub fn rom(&self) -> &[u8] {
+        self.mbc.rom()
+    }
+
+    pub fn mbc(&self) -> &MBCWrapper {
+        &self.mbc
+    }
+
+    pub fn mbc_mut(&mut self) -> &mut MBCWrapper {
+        &mut self.mbc
+    }
+
+    pub fn region(&self, region: MemoryRegion) -> &[u8] {
+        match region {
+            MemoryRegion::Vram => self.ppu.vram(),
+            MemoryRegion::Wram => &self.wram,
+            MemoryRegion::Oam => self.ppu.oam(),
+            MemoryRegion::Hram => &self.zram,
+            MemoryRegion::CartridgeRam => self.mbc.ram(),
+        }
+    }
+
+    pub fn region_mut(&mut self, region: MemoryRegion) -> &mut [u8] {
+        match region {
+            MemoryRegion::Vram => self.ppu.vram_mut(),
+            MemoryRegion::Wram => &mut self.wram,
+            MemoryRegion::Oam => self.ppu.oam_mut(),
+            MemoryRegion::Hram => &mut self.zram,
+            MemoryRegion::CartridgeRam => self.mbc.ram_mut(),
+        }
+    }
+
+    /// The region and offset `addr` is mapped to with the current banks. `None` for ROM, I/O
+    /// registers and unusable memory.
+    pub fn region_offset(&self, addr: u16) -> Option<(MemoryRegion, usize)> {
+        let address = addr as usize;
+
+        match address {
+            0x8000..=0x9fff => Some((MemoryRegion::Vram, self.ppu.vram_offset(addr))),
+            0xa000..=0xbfff => self.mbc.ram_offset(address).map(|offset| (MemoryRegion::CartridgeRam, offset)),
+            0xc000..=0xcfff | 0xe000..=0xefff => Some((MemoryRegion::Wram, address & 0x0fff)),
+            0xd000..=0xdfff | 0xf000..=0xfdff => Some((MemoryRegion::Wram, (self.wram_bank * 0x1000) | address & 0x0fff)),
+            0xfe00..=0xfe9f => Some((MemoryRegion::Oam, address - 0xfe00)),
+            0xff80..=0xfffe => Some((MemoryRegion::Hram, address & 0x007f)),
+            _ => None,
+        }
+    }
+
+    pub fn check_interrupt(&self) -> bool {
+        self.interrupt_enable
+            .intersects(self.ppu.interrupt_flag)
+    }
+}
+
+impl MemoryBus {
+    /// Reads `addr` for the CPU, which unlike a debugger peeking reports reading unused IO
+    /// addresses as a `Diagnostic`.
+    pub fn cpu_read(&self, addr: u16) -> u8 {
+        if matches!(addr, 0xff00..=0xff2f | 0xff40..=0xff7f) && self.available_io_register(addr).is_none() {
+            self.report(Diagnostic::UnknownIoRead { addr });
+        }
+        if let Some(checks) = &self.developer_checks {
+            checks.borrow_mut().check_read(addr, &self.ppu);
+        }
+        self.read_byte(addr)
+    }
+
+    /// Writes `addr` for the CPU, which unlike OAM DMA or a debugger poking is checked for
+    /// `DeveloperWarning`s.
+    pub fn cpu_write(&mut self, addr: u16, value: u8) {
+        if let Some(checks) = &mut self.developer_checks {
+            checks.get_mut().check_write(addr, value, &self.ppu);
+        }
+        self.write_byte(addr, value);
+    }
+
+    fn report(&self, diagnostic: Diagnostic) {
+        self.diagnostic_queue.borrow_mut().report(diagnostic);
+    }
+
+    /// Records what stopped the CPU, for the core to build a `CrashReport` from once the
+    /// instruction has run.
+    pub fn report_crash(&self, reason: CrashReason) {
+        self.crash.set(Some(reason));
+    }
+
+    pub fn take_crash(&mut self) -> Option<CrashReason> {
+        self.crash.take()
+    }
+
+    pub fn read_byte(&self, addr: u16) -> u8 {
+        let offset = addr as usize & 0xff;
+
+        match self.pages[addr as usize >> 8] {
+            Page::Rom(base) => self.mbc.rom()[base + offset],
+            Page::Wram(base) => self.wram[base + offset],
+            Page::Decode => self.decode_read(addr),
+        }
+    }
+
+    pub fn write_byte(&mut self, addr: u16, value: u8) {
+        if let Some(diagnostics) = &mut self.diagnostics {
+            diagnostics.record_write(addr);
+        }
+
+        match self.pages[addr as usize >> 8] {
+            Page::Wram(base) => self.wram[base + (addr as usize & 0xff)] = value,
+            Page::Rom(_) | Page::Decode => self.decode_write(addr, value),
+        }
+    }
+
+    fn decode_read(&self, addr: u16) -> u8 {
+        let address = addr as usize;
+
+        if !self.boot_rom_disabled && (address < 0x100 || (0x200..self.boot_rom.len()).contains(&address)) {
+            return self.boot_rom[address];
+        }
+
+
+        let val = match address {
+            0x0000..=0x7fff => self.mbc.read_rom(address),
+            0xa000..=0xbfff => self.mbc.read_ram(address),
+            0xc000..=0xcfff | 0xe000..=0xefff => self.wram[address & 0x0fff],
+            0xd000..=0xdfff | 0xf000..=0xfdff => self.wram[(self.wram_bank * 0x1000) | address & 0x0fff],
+            0x8000..=0x9fff => self.ppu.read_vram(addr),
+            0xfe00..=0xfe9f => self.ppu.read_vram(addr),
+            0xfea0..=0xfeff => { /* Unusable */ 0xff }
+            0xff30..=0xff3f => self.apu.read_byte(addr),
+            0xff00..=0xff7f | 0xffff => self.read_io(addr),
+            0xff80..=0xfffe => self.zram[address & 0x007f],
+            _ => {
+                self.report_crash(CrashReason::UnmappedRead(addr));
+                0xff
+            }
+        };
+
+        val
+    }
+
+    // The register at `addr` if this console has it
+    fn available_io_register(&self, addr: u16) -> Option<&'static IoRegister> {
+        io_register(addr).filter(|register| match register.availability {
+            Availability::All => true,
+            Availability::CgbHardware => self.cgb_hardware,
+            Availability::CgbMode => self.cgb_mode,
+        })
+    }
+
+    fn read_io(&self, addr: u16) -> u8 {
+        // Nothing answers at unused addresses
+        let Some(register) = self.available_io_register(addr) else {
+            return 0xff;
+        };
+
+        let value = match register.owner {
+            Owner::Joypad => self.input.read_byte(addr),
+            Owner::Serial => self.serial.read_byte(addr),
+            Owner::Timer => self.ppu.read_timer(addr),
+            Owner::Interrupts if addr == 0xff0f => self.ppu.interrupt_flag.bits(),
+            Owner::Interrupts => self.interrupt_enable.bits(),
+            Owner::Apu => self.apu.read_byte(addr),
+            Owner::Ppu => self.ppu.read_register(addr),
+            Owner::Dma => self.dma_source,
+            Owner::Infrared => self.infrared.read_byte(),
+            Owner::WramBank => self.wram_bank as u8,
+            Owner::Undocumented => self.undocumented[addr as usize - 0xff72],
+            Owner::BootRom | Owner::Ignored => 0xff,
+        };
+
+        value | register.read_mask
+    }
+
+    fn write_io(&mut self, addr: u16, value: u8) {
+        let Some(register) = self.available_io_register(addr) else {
+            self.report(Diagnostic::UnknownIoWrite { addr, value });
+            return;
+        };
+
+        match register.owner {
+            Owner::Joypad => self.input.write_byte(addr, value),
+            Owner::Serial => self.serial.write_byte(addr, value),
+            Owner::Timer => self.ppu.write_timer(addr, value),
+            Owner::Interrupts if addr == 0xff0f => self.ppu.interrupt_flag = InterruptFlag::from_bits_truncate(value),
+            Owner::Interrupts => self.interrupt_enable = InterruptFlag::from_bits_truncate(value),
+            Owner::Apu => {
+                if self.apu.drops_write(addr) {
+                    self.report(Diagnostic::ApuWriteWhileOff { addr, value });
+                }
+                self.apu.write_byte(addr, value)
+            }
+            Owner::Ppu => {
+                if addr == 0xff40 && (self.ppu.read_register(addr) ^ value) & 0x80 != 0 {
+                    self.stats.lcd_toggles += 1;
+                }
+                self.ppu.write_register(addr, value)
+            }
+            Owner::Dma => self.dma_transfer(value),
+            Owner::BootRom => {
+                self.boot_rom_disabled = value == 1;
+                self.update_pages();
+            }
+            Owner::Infrared => self.infrared.write_byte(value),
+            Owner::WramBank => {
+                self.wram_bank = (value as usize & 0x07).max(1);
+                self.update_pages();
+            }
+            // Bits that aren't there read back as set
+            Owner::Undocumented => self.undocumented[addr as usize - 0xff72] = value & !register.read_mask,
+            Owner::Ignored => {}
+        }
+    }
+
+    fn decode_write(&mut self, addr: u16, value: u8) {
+        let address = addr as usize;
+
+        match address {
+            0x0000..=0x7fff => {
+                if !self.mbc.write_rom(address, value) {
+                    self.report(Diagnostic::UnsupportedMbcWrite { addr, value });
+                }
+                self.map_pages(0x00..0x80);
+            }
+            0xc000..=0xcfff | 0xe000..=0xefff => self.wram[address & 0x0fff] = value,
+            0xd000..=0xdfff | 0xf000..=0xfdff => self.wram[(self.wram_bank * 0x1000) | address & 0x0fff] = value,
+            0xa000..=0xbfff => self.mbc.write_ram(address, value),
+            0x8000..=0x9fff => self.ppu.write_vram(addr, value),
+            0xfe00..=0xfe9f => self.ppu.write_vram(addr, value),
+            0xfea0..=0xfeff => { /* Unusable */ }
+            0xff30..=0xff3f => self.apu.write_byte(addr, value),
+            0xff00..=0xff7f | 0xffff => self.write_io(addr, value),
+            0xff80..=0xfffe => self.zram[address & 0x007f] = value,
+            _ => self.report_crash(CrashReason::UnmappedWrite(addr)),
+        }
+    }
+}
+
+impl fmt::Debug for MemoryBus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Bootloader: {:02x?}", &self.boot_rom[..256])
+    }
+}
+
+
+
@@ -0,0 +1,34 @@
+pub mod annotations;
+pub mod barcode;
+pub mod boot_rom;
+pub mod colorization;
+pub mod compatibility;
+pub mod core;
+pub mod coverage;
+pub mod crash;
+pub mod debug;
+pub mod diagnostics;
+pub mod events;
+pub mod export;
+pub mod expr;
+pub mod frame;
+pub mod harness;
+pub mod header;
+mod cpu;
+mod gpu;
+pub mod infrared;
+pub mod io_registers;
+mod intf;
+pub mod link;
+mod mem;
+mod mbc;
+pub mod model;
+pub mod observation;
+pub mod input;
+pub mod rom;
+mod serial;
+pub mod serial_bridge;
+mod sgb;
+mod sound;
+pub mod symbols;
+pub mod traits;
@@ -0,0 +1,116 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// The console being emulated. Games and test ROMs tell them apart by the registers the boot ROM
+/// leaves behind, so the model mostly matters when starting without a boot ROM.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Model {
+    /// The original Game Boy
+    #[default]
+    Dmg,
+    /// Game Boy Pocket and Light
+    Mgb,
+    /// Super Game Boy
+    Sgb,
+    /// Game Boy Color. Only the CGB registers needed to detect the console and bank WRAM and VRAM
+    /// are emulated, plus the undocumented FF72-FF77 and the CGB's wave RAM access; CGB games run
+    /// without color.
+    Cgb,
+}
+
+impl Model {
+    /// Whether a game runs with the CGB registers available rather than in DMG compatibility mode,
+    /// decided by the CGB flag in the cartridge header.
+    pub fn cgb_mode(self, rom: &[u8]) -> bool {
+        self == Model::Cgb && rom.get(0x143).is_some_and(|&flags| flags & 0x80 != 0)
+    }
+
+    /// The quirks of the model's usual revision.
+    pub fn quirks(self) -> Quirks {
+        Quirks {
+            stat_write_bug: self != Model::Cgb,
+            dmg0_registers: false,
+        }
+    }
+
+    /// A, F, B, C, D, E, H and L as the boot ROM leaves them.
+    pub(crate) fn post_boot_registers(self, cgb_mode: bool, quirks: Quirks) -> [u8; 8] {
+        match self {
+            Model::Dmg if quirks.dmg0_registers => [0x01, 0x00, 0xff, 0x13, 0x00, 0xc1, 0x84, 0x03],
+            Model::Dmg => [0x01, 0xb0, 0x00, 0x13, 0x00, 0xd8, 0x01, 0x4d],
+            Model::Mgb => [0xff, 0xb0, 0x00, 0x13, 0x00, 0xd8, 0x01, 0x4d],
+            Model::Sgb => [0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0xc0, 0x60],
+            Model::Cgb if cgb_mode => [0x11, 0x80, 0x00, 0x00, 0xff, 0x56, 0x00, 0x0d],
+            Model::Cgb => [0x11, 0x80, 0x00, 0x00, 0x00, 0x08, 0x00, 0x7c],
+        }
+    }
+}
+
+/// Behaviors that differ between revisions of a model, for the few games and test ROMs that tell
+/// them apart. `Model::quirks` gives those of each model's usual revision, and
+/// `CoreBuilder::quirks` picks others.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// Writing STAT during HBlank or VBlank, or while LY=LYC, raises a STAT interrupt as if all
+    /// its sources were enabled for a moment. Road Rash and Zerd no Densetsu rely on it; the CGB
+    /// fixed it.
+    pub stat_write_bug: bool,
+    /// Starting without a boot ROM leaves the registers as the first DMG revision's (DMG0) boot
+    /// ROM does, rather than the later revisions'. Only applies to `Model::Dmg`.
+    pub dmg0_registers: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Model::default().quirks()
+    }
+}
+
+/// What RAM holds at power on, see `CoreBuilder::power_on_ram`. Real hardware powers on with
+/// semi-random RAM, which some games read before writing by mistake and a few anti-emulator
+/// checks look for. The choice, seed included, is saved with the core, so states and movies
+/// start from the same RAM when replayed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PowerOnRam {
+    /// All zeros, as most emulators start
+    #[default]
+    Zeroed,
+    /// Every byte set to this value
+    Fill(u8),
+    /// Pseudo-random bytes, always the same for the same seed
+    Random(u64),
+}
+
+impl PowerOnRam {
+    /// `Random` with a seed from the host clock. Read it back from `Core::power_on_ram` to
+    /// reproduce a run.
+    pub fn random() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        PowerOnRam::Random(nanos as u64)
+    }
+
+    /// Fills `ram`, taking `region` into the seed so that regions of the same size differ.
+    pub(crate) fn fill(self, ram: &mut [u8], region: u64) {
+        match self {
+            PowerOnRam::Zeroed => ram.fill(0),
+            PowerOnRam::Fill(value) => ram.fill(value),
+            PowerOnRam::Random(seed) => {
+                let mut state = seed ^ region.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+                for chunk in ram.chunks_mut(8) {
+                    let bytes = splitmix64(&mut state).to_le_bytes();
+                    chunk.copy_from_slice(&bytes[..chunk.len()]);
+                }
+            }
+        }
+    }
+}
+
+// SplitMix64, small and good enough for RAM contents
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,49 @@
+use crate::dmg::frame::{ColorIndices, SCREEN_WIDTH};
+
+/// A compact view of the game for agents and machine learning: the screen as color numbers rather
+/// than RGB, the frame count and the bytes at chosen addresses. See `Core::observe`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Observation {
+    /// `Core::frames_rendered` when observed.
+    pub frame: u64,
+    /// The color number, 0-3, of each pixel row by row, before the palettes map them to shades.
+    /// Games fading by changing palettes keep the same numbers throughout.
+    pub pixels: Box<ColorIndices>,
+    /// The bytes at the addresses given to `Core::set_memory_taps`, in the same order.
+    pub taps: Vec<u8>,
+}
+
+impl Observation {
+    /// The color numbers of line `y`.
+    pub fn scanline(&self, y: usize) -> &[u8] {
+        &self.pixels[y * SCREEN_WIDTH..][..SCREEN_WIDTH]
+    }
+
+    /// The color numbers packed four pixels to a byte, the leftmost in the top two bits: 40
+    /// bytes per line and 5760 for the screen.
+    pub fn packed_pixels(&self) -> Vec<u8> {
+        self.pixels
+            .chunks_exact(4)
+            .map(|pixels| pixels.iter().fold(0, |byte, &pixel| byte << 2 | pixel & 0x03))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::frame::{SCREEN_HEIGHT, SCREEN_WIDTH};
+    use crate::dmg::observation::Observation;
+
+    #[test]
+    fn pixels_pack_four_to_a_byte() {
+        let mut pixels = Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT]);
+        pixels[SCREEN_WIDTH..SCREEN_WIDTH + 4].copy_from_slice(&[3, 0, 1, 2]);
+        let observation = Observation { frame: 0, pixels, taps: vec![] };
+
+        assert_eq!(observation.scanline(1)[..4], [3, 0, 1, 2]);
+        let packed = observation.packed_pixels();
+        assert_eq!(packed.len(), SCREEN_WIDTH * SCREEN_HEIGHT / 4);
+        assert_eq!(packed[SCREEN_WIDTH / 4], 0b11_00_01_10);
+        assert_eq!(packed[0], 0);
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+#[cfg(feature = "mmap")]
+use std::{fs::File, io, path::Path};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A cartridge's ROM, which clones of the core share rather than copy, e.g. the snapshots kept
+/// for rewinding or many cores running the same game in a harness. Read into memory, or with the
+/// `mmap` feature mapped from the file by `Rom::map`. Either way it's never written to.
+#[derive(Clone)]
+pub struct Rom(Arc<RomData>);
+
+enum RomData {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Rom {
+    /// Maps the file at `path` into memory, so the OS only reads the pages the game touches and
+    /// shares them between processes. The file must not change while mapped, as the game would see
+    /// it change too.
+    #[cfg(feature = "mmap")]
+    pub fn map(path: impl AsRef<Path>) -> io::Result<Rom> {
+        let file = File::open(path)?;
+        // SAFETY: the map is only ever read, and a ROM file isn't expected to be written to while
+        // a game runs from it
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Rom(Arc::new(RomData::Mapped(map))))
+    }
+
+    /// Whether this is mapped from a file rather than read into memory.
+    pub fn is_mapped(&self) -> bool {
+        match *self.0 {
+            RomData::Owned(_) => false,
+            #[cfg(feature = "mmap")]
+            RomData::Mapped(_) => true,
+        }
+    }
+}
+
+impl Default for Rom {
+    fn default() -> Self {
+        Rom::from(vec![])
+    }
+}
+
+impl From<Vec<u8>> for Rom {
+    fn from(bytes: Vec<u8>) -> Self {
+        Rom(Arc::new(RomData::Owned(bytes)))
+    }
+}
+
+impl Deref for Rom {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match *self.0 {
+            RomData::Owned(ref bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            RomData::Mapped(ref map) => map,
+        }
+    }
+}
+
+impl fmt::Debug for Rom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Rom({} bytes{})", self.len(), if self.is_mapped() { ", mapped" } else { "" })
+    }
+}
+
+// The same as the `Vec<u8>` it replaced, so older save states still load. Loading always reads
+// the ROM into memory.
+impl Serialize for Rom {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rom {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        serde_bytes::ByteBuf::deserialize(deserializer).map(|bytes| Rom::from(bytes.into_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::rom::Rom;
+
+    #[test]
+    fn clones_share_the_bytes() {
+        let rom = Rom::from(vec![1, 2, 3]);
+        let clone = rom.clone();
+
+        assert_eq!(&clone[..], [1, 2, 3]);
+        assert_eq!(clone.as_ptr(), rom.as_ptr());
+        assert!(!rom.is_mapped());
+    }
+
+    #[test]
+    fn serializes_as_bytes() {
+        let rom = Rom::from(vec![0xc3, 0x50, 0x01]);
+        let bytes = serde_cbor::to_vec(&rom).unwrap();
+
+        assert_eq!(bytes, serde_cbor::to_vec(&serde_bytes::Bytes::new(&rom)).unwrap());
+        assert_eq!(&serde_cbor::from_slice::<Rom>(&bytes).unwrap()[..], &rom[..]);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn maps_files() {
+        let path = std::env::temp_dir().join("maps_files.gb");
+        std::fs::write(&path, [0x18, 0xfe]).unwrap();
+
+        let rom = Rom::map(&path).unwrap();
+        assert!(rom.is_mapped());
+        assert_eq!(&rom[..], [0x18, 0xfe]);
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use bit_field::BitField;
+use log::debug;
+
+use serde::{Serialize, Deserialize};
+use crate::dmg::traits::{Mem, SerialDevice};
+
+// Bytes sent but never collected are dropped past this point
+const OUTPUT_LIMIT: usize = 4096;
+
+// A byte takes 8 bits at 8192 Hz with the internal clock
+const TRANSFER_CYCLES: u32 = 4096;
+
+/// The link port, SB (0xFF01) and SC (0xFF02). Bytes go out to the connected device; without one
+/// a transfer on the internal clock shifts in 0xFF and one on the external clock never finishes.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Serial {
+    #[serde(default)]
+    sb: u8,
+    #[serde(default)]
+    sc: u8,
+    // Until the transfer in progress finishes, or is first offered to the device on the external clock
+    #[serde(default)]
+    transfer_cycles: u32,
+
+    #[serde(skip)]
+    output: VecDeque<u8>,
+    // The byte of the last transfer started, until taken for `Event::SerialByte`
+    #[serde(skip)]
+    sent: Option<u8>,
+    #[serde(skip)]
+    device: Option<Arc<dyn SerialDevice>>,
+}
+
+impl Serial {
+    /// Returns the bytes sent since the last call, oldest first.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output).into()
+    }
+
+    /// Returns the byte sent by a transfer started since the last call. The CPU starts at most one
+    /// per instruction.
+    pub fn take_sent(&mut self) -> Option<u8> {
+        self.sent.take()
+    }
+
+    pub fn connect(&mut self, device: Option<Arc<dyn SerialDevice>>) {
+        self.device = device;
+    }
+
+    pub fn device(&self) -> Option<Arc<dyn SerialDevice>> {
+        self.device.clone()
+    }
+
+    /// Moves a transfer in progress along by `cycles`, returning whether it finished and raises
+    /// the serial interrupt.
+    pub fn step(&mut self, cycles: u32) -> bool {
+        if let Some(device) = &self.device {
+            device.advance(cycles);
+        }
+        if !self.sc.get_bit(7) {
+            return false;
+        }
+
+        self.transfer_cycles = self.transfer_cycles.saturating_sub(cycles);
+        if self.transfer_cycles > 0 {
+            return false;
+        }
+
+        let received = if self.sc.get_bit(0) {
+            Some(self.device.as_ref().map_or(0xff, |device| device.exchange(self.sb)))
+        } else {
+            self.device.as_ref().and_then(|device| device.clock_transfer(self.sb))
+        };
+
+        match received {
+            Some(received) => {
+                debug!(target: "serial", "received {:#04X}", received);
+                self.sb = received;
+                self.sc.set_bit(7, false);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cycles until a transfer on the internal clock finishes.
+    pub fn cycles_until_event(&self) -> u32 {
+        if self.sc.get_bit(7) && self.sc.get_bit(0) {
+            self.transfer_cycles
+        } else {
+            u32::MAX
+        }
+    }
+}
+
+impl Mem for Serial {
+    fn read_byte(&self, addr: u16) -> u8 {
+        match addr {
+            0xff01 => self.sb,
+            0xff02 => self.sc | 0x7e,
+            _ => unreachable!("SERIAL: Read from unmapped address: {:04X}", addr)
+        }
+    }
+
+    fn write_byte(&mut self, addr: u16, v: u8) {
+        match addr {
+            0xff01 => self.sb = v,
+            0xff02 => {
+                self.sc = v & 0x81;
+
+                if v.get_bit(7) {
+                    self.transfer_cycles = TRANSFER_CYCLES;
+                    if self.output.len() >= OUTPUT_LIMIT {
+                        self.output.pop_front();
+                    }
+                    self.output.push_back(self.sb);
+                    self.sent = Some(self.sb);
+                    debug!(target: "serial", "sent {:#04X} {:?}", self.sb, self.sb as char);
+                }
+            }
+            _ => unreachable!("SERIAL: Write to unmapped address: {:04X}", addr)
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use log::warn;
+
+use crate::dmg::traits::SerialDevice;
+
+/// Connects the link port to a pair of byte streams, e.g. the process's stdin and stdout or named
+/// pipes, so scripts can talk to homebrew over serial. Each byte the game sends is written out as
+/// its transfer finishes. Transfers the game clocks shift in the next byte read, or 0xFF when none
+/// has arrived yet; on the external clock the game waits until one does. Clones share the streams.
+#[derive(Clone)]
+pub struct SerialBridge {
+    incoming: Arc<Mutex<VecDeque<u8>>>,
+    output: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl SerialBridge {
+    /// Reads `input` on a thread of its own, so the emulator never waits on it.
+    pub fn new(input: impl Read + Send + 'static, output: impl Write + Send + 'static) -> Self {
+        let incoming = Arc::new(Mutex::new(VecDeque::new()));
+
+        {
+            let incoming = incoming.clone();
+            thread::spawn(move || read_into(input, &incoming));
+        }
+
+        Self { incoming, output: Arc::new(Mutex::new(Box::new(output))) }
+    }
+
+    pub fn stdio() -> Self {
+        Self::new(io::stdin(), io::stdout())
+    }
+
+    fn send(&self, byte: u8) {
+        let mut output = self.output.lock().unwrap();
+        if let Err(e) = output.write_all(&[byte]).and_then(|_| output.flush()) {
+            warn!(target: "serial", "Failed writing {:#04X} to the serial bridge: {}", byte, e);
+        }
+    }
+}
+
+fn read_into(mut input: impl Read, incoming: &Mutex<VecDeque<u8>>) {
+    let mut buffer = [0; 256];
+    loop {
+        match input.read(&mut buffer) {
+            Ok(0) => return,
+            Ok(n) => incoming.lock().unwrap().extend(&buffer[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => {
+                warn!(target: "serial", "Failed reading the serial bridge: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+impl SerialDevice for SerialBridge {
+    fn exchange(&self, sent: u8) -> u8 {
+        self.send(sent);
+        self.incoming.lock().unwrap().pop_front().unwrap_or(0xff)
+    }
+
+    fn clock_transfer(&self, sent: u8) -> Option<u8> {
+        let received = self.incoming.lock().unwrap().pop_front()?;
+        self.send(sent);
+        Some(received)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use crate::dmg::serial_bridge::SerialBridge;
+    use crate::dmg::traits::SerialDevice;
+
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn swaps_bytes_with_the_streams() {
+        let output = SharedOutput::default();
+        let bridge = SerialBridge::new(&b"\x01\x02"[..], output.clone());
+
+        // The input is read in the background
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while bridge.incoming.lock().unwrap().len() < 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(bridge.exchange(b'h'), 0x01);
+        assert_eq!(bridge.clock_transfer(b'i'), Some(0x02));
+        assert_eq!(bridge.clock_transfer(b'!'), None);
+        assert_eq!(bridge.exchange(b'?'), 0xff);
+        assert_eq!(*output.0.lock().unwrap(), b"hi?");
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+pub const PACKET_SIZE: usize = 16;
+
+/// Command number of MLT_REQ, which switches between one, two and four joypads.
+pub const MLT_REQ: u8 = 0x11;
+
+/// Collects the command packets an SGB game sends by pulsing P14 and P15. A packet starts with both
+/// lines pulled low, then each of its 128 bits, least significant first, is sent as P14 low for 0
+/// or P15 low for 1, followed by both lines high.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct PacketReceiver {
+    #[serde(with = "serde_bytes")]
+    packet: [u8; PACKET_SIZE],
+    bits: usize,
+    receiving: bool,
+    pending_bit: Option<bool>,
+    // Packets left of a command sent in several, which don't start with a command header
+    continuation_packets: u8,
+}
+
+impl PacketReceiver {
+    /// Takes the P14 (bit 4) and P15 (bit 5) lines written to P1 and returns the command packet they
+    /// complete, if any. Continuation packets of longer commands are skipped.
+    pub fn write(&mut self, p1: u8) -> Option<[u8; PACKET_SIZE]> {
+        match p1 & 0x30 {
+            0x00 => {
+                self.receiving = true;
+                self.bits = 0;
+                self.pending_bit = None;
+                self.packet = [0; PACKET_SIZE];
+            }
+            0x10 if self.receiving => self.pending_bit = Some(true),
+            0x20 if self.receiving => self.pending_bit = Some(false),
+            0x30 => {
+                if let Some(bit) = self.pending_bit.take() {
+                    self.packet[self.bits / 8] |= (bit as u8) << (self.bits % 8);
+                    self.bits += 1;
+                    if self.bits == PACKET_SIZE * 8 {
+                        self.receiving = false;
+                        return self.complete();
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn complete(&mut self) -> Option<[u8; PACKET_SIZE]> {
+        if self.continuation_packets > 0 {
+            self.continuation_packets -= 1;
+            return None;
+        }
+
+        self.continuation_packets = (self.packet[0] & 0x07).saturating_sub(1);
+        Some(self.packet)
+    }
+}
+
+/// The command number from a packet's header byte.
+pub fn command(packet: &[u8; PACKET_SIZE]) -> u8 {
+    packet[0] >> 3
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::dmg::sgb::{command, PacketReceiver, MLT_REQ, PACKET_SIZE};
+
+    /// The P1 writes that send `packet`, stop bit included.
+    pub(crate) fn packet_writes(packet: [u8; PACKET_SIZE]) -> Vec<u8> {
+        let mut writes = vec![0x00, 0x30];
+        for bit in 0..PACKET_SIZE * 8 {
+            let one = packet[bit / 8] & (1 << (bit % 8)) != 0;
+            writes.extend([if one { 0x10 } else { 0x20 }, 0x30]);
+        }
+        writes.extend([0x20, 0x30]);
+        writes
+    }
+
+    fn receive(receiver: &mut PacketReceiver, packet: [u8; PACKET_SIZE]) -> Option<[u8; PACKET_SIZE]> {
+        packet_writes(packet).into_iter().filter_map(|p1| receiver.write(p1)).last()
+    }
+
+    #[test]
+    fn decodes_packets_sent_over_p1() {
+        let mut packet = [0; PACKET_SIZE];
+        packet[0] = MLT_REQ << 3 | 1;
+        packet[1] = 0x03;
+        packet[15] = 0x80;
+
+        let received = receive(&mut PacketReceiver::default(), packet).unwrap();
+        assert_eq!(received, packet);
+        assert_eq!(command(&received), MLT_REQ);
+    }
+
+    #[test]
+    fn skips_continuation_packets() {
+        let mut receiver = PacketReceiver::default();
+        let mut header = [0; PACKET_SIZE];
+        header[0] = 0x0a << 3 | 2;
+
+        assert_eq!(receive(&mut receiver, header), Some(header));
+        assert_eq!(receive(&mut receiver, [MLT_REQ << 3 | 1; PACKET_SIZE]), None);
+        assert!(receive(&mut receiver, header).is_some());
+    }
+}
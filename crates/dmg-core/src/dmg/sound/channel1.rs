@@ -1,10 +1,12 @@
 use bit_field::BitField;
+use serde::{Deserialize, Serialize};
 
 use crate::dmg::sound::common::{ChannelCommon, DUTY_TABLE};
 use crate::dmg::sound::frequency_sweep::FrequencySweep;
 use crate::dmg::traits::{Mem, Tick};
 use crate::dmg::sound::volume_envelope::VolumeEnvelope;
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Channel1 {
     pub common: ChannelCommon,
     duty: u8,
@@ -1,8 +1,10 @@
 use bit_field::BitField;
+use serde::{Deserialize, Serialize};
 use crate::dmg::sound::common::{ChannelCommon, DUTY_TABLE};
 use crate::dmg::traits::{Mem, Tick};
 use crate::dmg::sound::volume_envelope::VolumeEnvelope;
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Channel2 {
     pub common: ChannelCommon,
     duty: u8,
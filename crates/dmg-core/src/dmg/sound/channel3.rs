@@ -1,8 +1,10 @@
 use bit_field::BitField;
+use serde::{Deserialize, Serialize};
 
 use crate::dmg::sound::common::ChannelCommon;
 use crate::dmg::traits::{Mem, Tick};
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Channel3 {
     pub common: ChannelCommon,
     timer: u32,
@@ -12,6 +14,9 @@ pub struct Channel3 {
     volume_code: u8,
     last_address: usize,
     wave_table: [u8; 16],
+    // On a CGB wave RAM stays accessible while playing and triggering doesn't corrupt it
+    #[serde(default)]
+    cgb: bool,
 }
 
 
@@ -26,6 +31,7 @@ impl Default for Channel3 {
             volume_code: 0,
             last_address: 0,
             wave_table: [0; 16],
+            cgb: false,
         }
     }
 }
@@ -34,8 +40,10 @@ impl Mem for Channel3 {
     fn read_byte(&self, addr: u16) -> u8 {
         match addr {
             0xff30..=0xff3f => {
+                // While playing, only the byte being played can be reached, and on a DMG only
+                // right as it's read
                 if self.common.is_channel_enabled() {
-                    if self.ticks_since_read < 2 {
+                    if self.cgb || self.ticks_since_read < 2 {
                         self.wave_table[self.last_address]
                     } else {
                         0xff
@@ -62,8 +70,10 @@ impl Mem for Channel3 {
     fn write_byte(&mut self, addr: u16, v: u8) {
         match addr {
             0xff30..=0xff3f => {
+                // While playing, only the byte being played can be reached, and on a DMG only
+                // right as it's read
                 if self.common.is_channel_enabled() {
-                    if self.ticks_since_read < 2 {
+                    if self.cgb || self.ticks_since_read < 2 {
                         self.wave_table[self.last_address] = v;
                     }
                 } else {
@@ -135,7 +145,8 @@ impl Tick for Channel3 {
 
 impl Channel3 {
     fn trigger(&mut self) {
-        if self.common.is_channel_enabled() && self.timer == 2 {
+        // Retriggering right as a byte is read corrupts the start of wave RAM on a DMG
+        if !self.cgb && self.common.is_channel_enabled() && self.timer == 2 {
             let mut pos = self.position >> 1;
 
             if pos < 4 {
@@ -154,6 +165,15 @@ impl Channel3 {
         self.common.ch_enabled = self.common.dac_enabled;
     }
 
+    pub fn set_cgb(&mut self, cgb: bool) {
+        self.cgb = cgb;
+    }
+
+    /// Wave RAM as stored, however reads of it behave while the channel plays.
+    pub fn wave_table(&self) -> [u8; 16] {
+        self.wave_table
+    }
+
     pub fn power_off(&mut self) {
         self.common.length_counter.power_off();
 
@@ -1,8 +1,10 @@
 use bit_field::BitField;
+use serde::{Deserialize, Serialize};
 use crate::dmg::sound::common::ChannelCommon;
 use crate::dmg::traits::{Mem, Tick};
 use crate::dmg::sound::volume_envelope::VolumeEnvelope;
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Channel4 {
     pub common: ChannelCommon,
 
@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
 use crate::dmg::sound::length_counter::LengthCounter;
 use crate::dmg::traits::Tick;
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ChannelCommon {
     pub channel_no: u8,
     pub ch_enabled: bool,
@@ -1,6 +1,8 @@
 use bit_field::BitField;
+use serde::{Deserialize, Serialize};
 use crate::dmg::traits::Tick;
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct FrequencySweep {
     enabled: bool,
     overflow: bool,
@@ -1,6 +1,8 @@
 use bit_field::BitField;
+use serde::{Deserialize, Serialize};
 use crate::dmg::traits::Tick;
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct LengthCounter {
     enabled: bool,
     length: u8,
@@ -48,11 +50,7 @@ impl LengthCounter {
     }
 
     pub fn set_length(&mut self, v: u8) {
-        if self.length == 0 {
-            self.length = self.full_length
-        } else {
-            self.length = self.full_length.saturating_sub(v);
-        }
+        self.length = self.full_length.saturating_sub(v);
     }
 
     pub fn power_off(&mut self) {
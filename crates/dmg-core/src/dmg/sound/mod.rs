@@ -1,10 +1,13 @@
 use bit_field::BitField;
+use serde::{Deserialize, Serialize};
 use bitflags::bitflags;
+use log::debug;
 use crate::dmg::sound::channel1::Channel1;
 use crate::dmg::sound::channel2::Channel2;
 use crate::dmg::sound::channel3::Channel3;
 use crate::dmg::sound::channel4::Channel4;
 
+use crate::dmg::io_registers::read_mask;
 use crate::dmg::traits::{Mem, Tick};
 
 mod channel1;
@@ -21,6 +24,7 @@ pub mod sampler;
 
 
 bitflags! {
+    #[derive(Serialize, Deserialize)]
     pub struct ChannelEnabled: u8 {
         const LEFT_1    = 1 << 7;
         const LEFT_2    = 1 << 6;
@@ -33,7 +37,17 @@ bitflags! {
     }
 }
 
+// Output gain at volume 1.0, leaving headroom for all four channels at full volume
+const DEFAULT_MASTER_VOLUME: f32 = 0.1;
+
+fn default_master_volume() -> f32 {
+    DEFAULT_MASTER_VOLUME
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Apu {
+    // A host setting, so save states don't carry it
+    #[serde(skip, default = "default_master_volume")]
     master_volume: f32,
     enabled: bool,
 
@@ -53,13 +67,17 @@ pub struct Apu {
     channel2: Channel2,
     channel3: Channel3,
     channel4: Channel4,
+
+    // A CGB drops the length loads while powered off too
+    #[serde(default)]
+    cgb_hardware: bool,
 }
 
 
 impl Default for Apu {
     fn default() -> Self {
         Self {
-            master_volume: 0.1,
+            master_volume: DEFAULT_MASTER_VOLUME,
             enabled: false,
 
             left_volume: 0,
@@ -77,6 +95,7 @@ impl Default for Apu {
             channel2: Channel2::default(),
             channel3: Channel3::default(),
             channel4: Channel4::default(),
+            cgb_hardware: false,
         }
     }
 }
@@ -144,6 +163,49 @@ impl Tick for Apu {
 
 
 impl Apu {
+    /// Scales the output, 1.0 being the default level.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.master_volume = DEFAULT_MASTER_VOLUME * volume;
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.master_volume / DEFAULT_MASTER_VOLUME
+    }
+
+    /// Switches to the CGB's wave RAM access while channel 3 plays, and its handling of length
+    /// loads while powered off.
+    pub fn set_cgb_hardware(&mut self, cgb_hardware: bool) {
+        self.cgb_hardware = cgb_hardware;
+        self.channel3.set_cgb(cgb_hardware);
+    }
+
+    /// NR10-NR52 and the unused registers up to FF2F as the CPU reads them, followed by wave RAM
+    /// as stored, for trackers and visualizers mirroring the sound state.
+    pub fn register_snapshot(&self) -> [u8; 0x30] {
+        let mut snapshot = [0; 0x30];
+        for (addr, byte) in (0xff10..0xff30).zip(&mut snapshot) {
+            *byte = self.read_byte(addr);
+        }
+        snapshot[0x20..].copy_from_slice(&self.channel3.wave_table());
+        snapshot
+    }
+
+    /// The CGB's undocumented PCM12 register: channel 1's current 4-bit output in the low
+    /// nibble and channel 2's in the high.
+    pub fn pcm12(&self) -> u8 {
+        self.channel1.common.output | self.channel2.common.output << 4
+    }
+
+    /// Whether a write to `addr` is dropped since the APU is powered off.
+    pub fn drops_write(&self, addr: u16) -> bool {
+        !self.enabled && matches!(addr, 0xff10..=0xff25) && (self.cgb_hardware || !matches!(addr, 0xff11 | 0xff16 | 0xff1b | 0xff20))
+    }
+
+    /// PCM34, the same for channels 3 and 4.
+    pub fn pcm34(&self) -> u8 {
+        self.channel3.common.output | self.channel4.common.output << 4
+    }
+
     fn clear_all_registers(&mut self) {
         self.vin_left_enable = false;
         self.vin_right_enable = false;
@@ -176,7 +238,12 @@ impl Apu {
 
 impl Mem for Apu {
     fn read_byte(&self, addr: u16) -> u8 {
-        match addr {
+        let mask = match addr {
+            0xff10..=0xff2f => read_mask(addr),
+            _ => 0,
+        };
+
+        mask | match addr {
             0xff10..=0xff14 => self.channel1.read_byte(addr),
             0xff15..=0xff19 => self.channel2.read_byte(addr),
             0xff1a..=0xff1e => self.channel3.read_byte(addr),
@@ -203,28 +270,37 @@ impl Mem for Apu {
             }
             0xff27..=0xff2f => 0xff,
             0xff30..=0xff3f => self.channel3.read_byte(addr),
+            0xff76 => self.pcm12(),
+            0xff77 => self.pcm34(),
             _ => unreachable!("APU: Read from unmapped address: {:04X}", addr)
         }
     }
 
+    /// While powered off, only NR52, wave RAM and, on everything but the CGB, the length loads take
+    /// writes.
     fn write_byte(&mut self, addr: u16, v: u8) {
         match addr {
             0xff26 => {
                 let enable_apu = v.get_bit(7);
 
                 if self.enabled && !enable_apu {
+                    debug!(target: "apu", "disable apu");
                     self.clear_all_registers()
                 } else if !self.enabled && enable_apu {
+                    debug!(target: "apu", "enable apu");
                     self.frame_sequencer = 0;
                 }
                 self.enabled = enable_apu;
             }
             0xff30..=0xff3f => self.channel3.write_byte(addr, v),
-
-            0xff11 if !self.enabled => self.channel1.write_byte(addr, v & 0x3f),
-            0xff16 if !self.enabled => self.channel2.write_byte(addr, v & 0x3f),
-            0xff1b if !self.enabled => self.channel3.write_byte(addr, v),
-            0xff20 if !self.enabled => self.channel4.write_byte(addr, v & 0x3f),
+            // PCM12 and PCM34 are read-only
+            0xff76 | 0xff77 => {}
+
+            0xff11 if !self.enabled && !self.cgb_hardware => self.channel1.write_byte(addr, v & 0x3f),
+            0xff16 if !self.enabled && !self.cgb_hardware => self.channel2.write_byte(addr, v & 0x3f),
+            0xff1b if !self.enabled && !self.cgb_hardware => self.channel3.write_byte(addr, v),
+            0xff20 if !self.enabled && !self.cgb_hardware => self.channel4.write_byte(addr, v & 0x3f),
+            _ if self.drops_write(addr) => {}
             0xff10..=0xff14 => self.channel1.write_byte(addr, v),
             0xff15..=0xff19 => self.channel2.write_byte(addr, v),
             0xff1a..=0xff1e => self.channel3.write_byte(addr, v),
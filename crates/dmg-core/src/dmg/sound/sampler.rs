@@ -0,0 +1,369 @@
+use std::f32::consts::PI;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use crate::dmg::traits::AudioSink;
+
+use super::{Apu, ChannelEnabled};
+
+pub type StereoSample = (f32, f32);
+
+// Clock cycles per sample at normal speed
+const SAMPLE_PERIOD: u32 = 95;
+
+// `clock` counts in fractions of a cycle, so that the period at any speed is exact enough for the
+// output to keep pace with the host's sample rate
+const CLOCK_FRACTION: u32 = 16;
+
+// How much charge the hardware's output capacitor keeps per clock cycle, from the Pan Docs
+const CHARGE_FACTOR: f32 = 0.999958;
+
+// The band-limited resampler's kernel: taps per step, twice the delay it adds in samples, and how
+// finely a step's position between two samples is told apart
+const TAPS: usize = 16;
+const PHASES: usize = 32;
+// Where the kernel cuts off, relative to the output's sample rate, leaving room for the window
+const CUTOFF: f32 = 0.9;
+
+/// How the emulated output, which can change every clock cycle, is brought down to the host's
+/// sample rate.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Resampler {
+    /// Takes the level at each sample. The cheapest, but edges snap to whole samples, which
+    /// aliases: square waves sound rougher the higher they go.
+    #[default]
+    Nearest,
+    /// Averages the level over each sample, so an edge lands in between by where it falls.
+    /// Mixes the channels every clock cycle.
+    Linear,
+    /// Draws every edge as a band-limited step from a windowed sinc, which keeps square waves
+    /// clean at 44.1 kHz. Costs the most CPU, and delays the output by 8 samples.
+    Sinc,
+}
+
+// The steps drawn by `Resampler::Sinc` for the samples to come. The output is the running sum
+// of `deltas`, each of which is a slice of a step's band-limited impulse.
+#[derive(Clone, Default)]
+struct BandLimited {
+    input: StereoSample,
+    level: StereoSample,
+    deltas: [StereoSample; TAPS],
+    head: usize,
+    // Samples since the last step, once the steps are all out the level is exactly the input
+    quiet: usize,
+}
+
+impl BandLimited {
+    // A step to `input` at `position`, from 0 right after the last sample to 1 at the next one
+    fn step_to(&mut self, input: StereoSample, position: f32) {
+        let delta = (input.0 - self.input.0, input.1 - self.input.1);
+        let phase = ((position * PHASES as f32) as usize).min(PHASES - 1);
+        for (k, tap) in kernel()[phase].iter().enumerate() {
+            let out = &mut self.deltas[(self.head + k) % TAPS];
+            out.0 += delta.0 * tap;
+            out.1 += delta.1 * tap;
+        }
+        self.input = input;
+        self.quiet = 0;
+    }
+
+    fn next_sample(&mut self) -> StereoSample {
+        let delta = std::mem::take(&mut self.deltas[self.head]);
+        self.head = (self.head + 1) % TAPS;
+        self.quiet += 1;
+
+        // Summing rounds a little each time, which this keeps from adding up
+        self.level = if self.quiet >= TAPS {
+            self.input
+        } else {
+            (self.level.0 + delta.0, self.level.1 + delta.1)
+        };
+        self.level
+    }
+}
+
+// A Blackman-windowed sinc per phase, each summing to 1 so a step ends exactly at its new level
+fn kernel() -> &'static [[f32; TAPS]; PHASES] {
+    static KERNEL: OnceLock<[[f32; TAPS]; PHASES]> = OnceLock::new();
+    KERNEL.get_or_init(|| {
+        let half = (TAPS / 2) as f32;
+        let mut kernel = [[0.0; TAPS]; PHASES];
+        for (phase, taps) in kernel.iter_mut().enumerate() {
+            let position = (phase as f32 + 0.5) / PHASES as f32;
+            for (k, tap) in taps.iter_mut().enumerate() {
+                let x = k as f32 + 1.0 - position - half;
+                let sinc = if x == 0.0 { 1.0 } else { (PI * CUTOFF * x).sin() / (PI * CUTOFF * x) };
+                let window = 0.42 + 0.5 * (PI * x / half).cos() + 0.08 * (2.0 * PI * x / half).cos();
+                *tap = sinc * window;
+            }
+            let sum: f32 = taps.iter().sum();
+            taps.iter_mut().for_each(|tap| *tap /= sum);
+        }
+        kernel
+    })
+}
+
+fn normal_speed() -> f32 {
+    1.0
+}
+
+fn normal_period() -> u32 {
+    SAMPLE_PERIOD * CLOCK_FRACTION
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AudioSampler {
+    clock: u32,
+    // Host settings like the volume, so save states don't carry them or the filter's charge
+    #[serde(skip, default = "normal_speed")]
+    speed: f32,
+    #[serde(skip, default = "normal_period")]
+    period: u32,
+    #[serde(skip)]
+    pop_filter: bool,
+    #[serde(skip)]
+    capacitor: StereoSample,
+    #[serde(skip)]
+    resampler: Resampler,
+    // The sum and count of the levels since the last sample, for `Resampler::Linear`
+    #[serde(skip)]
+    sum: StereoSample,
+    #[serde(skip)]
+    cycles: u32,
+    #[serde(skip)]
+    band_limited: BandLimited,
+}
+
+impl Default for AudioSampler {
+    fn default() -> Self {
+        Self {
+            clock: 0,
+            speed: normal_speed(),
+            period: normal_period(),
+            pop_filter: false,
+            capacitor: (0.0, 0.0),
+            resampler: Resampler::default(),
+            sum: (0.0, 0.0),
+            cycles: 0,
+            band_limited: BandLimited::default(),
+        }
+    }
+}
+
+impl AudioSampler {
+    pub fn tick(&mut self, apu: &Apu, audio_sink: &mut impl AudioSink)  {
+        self.clock += CLOCK_FRACTION;
+
+        if self.clock > self.period {
+            self.clock -= self.period;
+            let sample = match self.resampler {
+                Resampler::Nearest => apu.sample(),
+                Resampler::Linear => self.average(),
+                Resampler::Sinc => self.band_limited.next_sample(),
+            };
+            audio_sink.push_sample(if self.pop_filter { self.high_pass(sample) } else { sample });
+        }
+
+        match self.resampler {
+            Resampler::Nearest => {}
+            Resampler::Linear => {
+                let (left, right) = apu.sample();
+                self.sum = (self.sum.0 + left, self.sum.1 + right);
+                self.cycles += 1;
+            }
+            Resampler::Sinc => {
+                let sample = apu.sample();
+                if sample != self.band_limited.input {
+                    let position = self.clock as f32 / self.period as f32;
+                    self.band_limited.step_to(sample, position);
+                }
+            }
+        }
+    }
+
+    /// Starts over from power on, keeping the settings.
+    pub fn reset(&mut self) {
+        self.clock = 0;
+        self.capacitor = (0.0, 0.0);
+        self.clear_resampler();
+    }
+
+    /// Picks between cheaper and cleaner resampling, see `Resampler`.
+    pub fn set_resampler(&mut self, resampler: Resampler) {
+        if resampler != self.resampler {
+            self.resampler = resampler;
+            self.clear_resampler();
+        }
+    }
+
+    pub fn resampler(&self) -> Resampler {
+        self.resampler
+    }
+
+    fn clear_resampler(&mut self) {
+        self.sum = (0.0, 0.0);
+        self.cycles = 0;
+        self.band_limited = BandLimited::default();
+    }
+
+    fn average(&mut self) -> StereoSample {
+        let cycles = self.cycles.max(1) as f32;
+        let average = (self.sum.0 / cycles, self.sum.1 / cycles);
+        self.sum = (0.0, 0.0);
+        self.cycles = 0;
+        average
+    }
+
+    /// Resamples for running at `speed` times normal speed, so the output keeps the host's sample
+    /// rate: sped up audio plays higher and slowed down audio lower, as on a tape.
+    pub fn set_speed(&mut self, speed: f32) {
+        let period = (normal_period() as f32 * speed).round() as u32;
+        // Keep the way into the current sample, rather than owing a burst of samples
+        self.clock = (self.clock as u64 * period as u64 / self.period as u64) as u32;
+        self.speed = speed;
+        self.period = period;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Passes samples through a high-pass filter like the capacitor on the hardware's output.
+    /// Without it every channel or DAC switching on or off shifts the level for good, which is
+    /// heard as a click; with it the level settles back to silence within a few milliseconds.
+    pub fn set_pop_filter(&mut self, enabled: bool) {
+        if enabled != self.pop_filter {
+            self.pop_filter = enabled;
+            self.capacitor = (0.0, 0.0);
+        }
+    }
+
+    pub fn pop_filter(&self) -> bool {
+        self.pop_filter
+    }
+
+    fn high_pass(&mut self, (left, right): StereoSample) -> StereoSample {
+        let charge = CHARGE_FACTOR.powf(self.period as f32 / CLOCK_FRACTION as f32);
+        let out = (left - self.capacitor.0, right - self.capacitor.1);
+        self.capacitor = (left - out.0 * charge, right - out.1 * charge);
+        out
+    }
+}
+
+impl Apu {
+    pub fn sample(&self) -> StereoSample {
+        let mut left = 0.0;
+        let mut right = 0.0;
+
+        if self.channel_enabled.contains(ChannelEnabled::LEFT_1) {
+            left += self.channel1.common.output as f32;
+        }
+
+        if self.channel_enabled.contains(ChannelEnabled::LEFT_2) {
+            left += self.channel2.common.output as f32;
+        }
+
+        if self.channel_enabled.contains(ChannelEnabled::LEFT_3) {
+            left += self.channel3.common.output as f32;
+        }
+
+        if self.channel_enabled.contains(ChannelEnabled::LEFT_4) {
+            left += self.channel4.common.output as f32;
+        }
+
+        if self.channel_enabled.contains(ChannelEnabled::RIGHT_1) {
+            right += self.channel1.common.output as f32;
+        }
+
+        if self.channel_enabled.contains(ChannelEnabled::RIGHT_2) {
+            right += self.channel2.common.output as f32;
+        }
+
+        if self.channel_enabled.contains(ChannelEnabled::RIGHT_3) {
+            right += self.channel3.common.output as f32;
+        }
+
+        if self.channel_enabled.contains(ChannelEnabled::RIGHT_4) {
+            right += self.channel4.common.output as f32;
+        }
+
+        (
+            (left / 16.0) * self.master_volume,
+            (right / 16.0) * self.master_volume
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::sound::sampler::{AudioSampler, Resampler, StereoSample};
+    use crate::dmg::sound::Apu;
+    use crate::dmg::traits::{Mem, Tick};
+
+    // The samples of channel 3 playing a flat wave at full volume from the moment its DAC is on
+    fn flat_wave_samples(mut sampler: AudioSampler) -> Vec<StereoSample> {
+        let mut apu = Apu::default();
+        apu.write_byte(0xff26, 0x80);
+        apu.write_byte(0xff25, 0xff);
+        for addr in 0xff30..=0xff3f {
+            apu.write_byte(addr, 0xff);
+        }
+        apu.write_byte(0xff1a, 0x80);
+        apu.write_byte(0xff1c, 0x20);
+        apu.write_byte(0xff1e, 0x80);
+
+        let mut samples = vec![];
+        for _ in 0..95 * 2000 {
+            apu.tick();
+            sampler.tick(&apu, &mut samples);
+        }
+        samples
+    }
+
+    #[test]
+    fn raw_output_keeps_the_dc_step() {
+        let samples = flat_wave_samples(AudioSampler::default());
+        let last = samples.last().unwrap();
+
+        assert!(last.0 > 0.0);
+        assert_eq!(samples[samples.len() - 100], *last);
+    }
+
+    #[test]
+    fn pop_filter_settles_back_to_silence() {
+        let mut sampler = AudioSampler::default();
+        sampler.set_pop_filter(true);
+        let samples = flat_wave_samples(sampler);
+        let peak = samples.iter().map(|s| s.0).fold(0.0, f32::max);
+        let last = samples.last().unwrap();
+
+        assert!(peak > 0.0);
+        assert!(last.0.abs() < peak / 100.0);
+        assert!(last.1.abs() < peak / 100.0);
+    }
+
+    #[test]
+    fn resamplers_agree_on_the_level() {
+        let nearest = flat_wave_samples(AudioSampler::default());
+        for resampler in [Resampler::Linear, Resampler::Sinc] {
+            let mut sampler = AudioSampler::default();
+            sampler.set_resampler(resampler);
+            let samples = flat_wave_samples(sampler);
+
+            assert_eq!(samples.len(), nearest.len());
+            assert_eq!(samples.last(), nearest.last(), "{:?}", resampler);
+        }
+    }
+
+    #[test]
+    fn sinc_resampler_rounds_off_edges() {
+        let mut sampler = AudioSampler::default();
+        sampler.set_resampler(Resampler::Sinc);
+        let samples = flat_wave_samples(sampler);
+        let level = samples.last().unwrap().0;
+
+        // The DAC switching on is a step, which no longer jumps from silence to the level at once
+        assert!(samples.iter().any(|s| s.0 > 0.0 && s.0 < level * 0.9));
+    }
+}
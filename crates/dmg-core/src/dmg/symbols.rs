@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::error::{Error, Result};
+
+/// Labels from an RGBDS-style `.sym` file, keyed by bank and address. Each line reads
+/// `BB:AAAA Label`; comments after `;` and lines in other formats are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct Symbols {
+    labels: BTreeMap<(u16, u16), String>,
+}
+
+impl Symbols {
+    pub fn parse(text: &str) -> Self {
+        let labels = text
+            .lines()
+            .filter_map(|line| {
+                let line = line.split(';').next()?.trim();
+                let (location, label) = line.split_once(char::is_whitespace)?;
+                let (bank, addr) = location.split_once(':')?;
+                let bank = u16::from_str_radix(bank, 16).ok()?;
+                let addr = u16::from_str_radix(addr, 16).ok()?;
+                Some(((bank, addr), label.trim().to_string()))
+            })
+            .collect();
+
+        Self { labels }
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|source| Error::SymbolLoad { path: path.to_string(), source })?;
+        Ok(Self::parse(&text))
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// The label at exactly `addr` in `bank`.
+    pub fn get(&self, bank: u16, addr: u16) -> Option<&str> {
+        self.labels.get(&(bank, addr)).map(String::as_str)
+    }
+
+    /// The closest label at or before `addr` in the same bank and memory region, with the offset
+    /// from it, e.g. `UpdateOAM+3`.
+    pub fn describe(&self, bank: u16, addr: u16) -> Option<String> {
+        let (&(label_bank, label_addr), label) = self.labels.range(..=(bank, addr)).next_back()?;
+        if label_bank != bank || region(label_addr) != region(addr) {
+            return None;
+        }
+
+        Some(match addr - label_addr {
+            0 => label.clone(),
+            offset => format!("{}+{}", label, offset),
+        })
+    }
+}
+
+// Labels only extend to the end of the memory area they're in
+fn region(addr: u16) -> u8 {
+    match addr {
+        0x0000..=0x3fff => 0,
+        0x4000..=0x7fff => 1,
+        0x8000..=0x9fff => 2,
+        0xa000..=0xbfff => 3,
+        0xc000..=0xcfff => 4,
+        0xd000..=0xdfff => 5,
+        0xe000..=0xff7f => 6,
+        0xff80..=0xffff => 7,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::symbols::Symbols;
+
+    const SYM: &str = "; File generated by rgblink\n\
+                       00:0150 Main\n\
+                       00:2817 UpdateOAM\n\
+                       03:4000 Bank3Start ; comment\n\
+                       00:c000 wBuffer\n\
+                       not a symbol\n";
+
+    #[test]
+    fn parses_labels_by_bank_and_address() {
+        let symbols = Symbols::parse(SYM);
+
+        assert_eq!(symbols.len(), 4);
+        assert_eq!(symbols.get(0, 0x2817), Some("UpdateOAM"));
+        assert_eq!(symbols.get(3, 0x4000), Some("Bank3Start"));
+        assert_eq!(symbols.get(1, 0x4000), None);
+    }
+
+    #[test]
+    fn describes_addresses_relative_to_the_closest_label() {
+        let symbols = Symbols::parse(SYM);
+
+        assert_eq!(symbols.describe(0, 0x0150).as_deref(), Some("Main"));
+        assert_eq!(symbols.describe(0, 0x0153).as_deref(), Some("Main+3"));
+        assert_eq!(symbols.describe(3, 0x4010).as_deref(), Some("Bank3Start+16"));
+        assert_eq!(symbols.describe(0, 0x0100), None);
+        assert_eq!(symbols.describe(0, 0x4010), None);
+        assert_eq!(symbols.describe(0, 0xff80), None);
+    }
+}
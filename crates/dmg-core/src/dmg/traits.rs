@@ -0,0 +1,139 @@
+use crate::dmg::frame::SCREEN_WIDTH;
+use crate::dmg::input::JoypadInput;
+use crate::dmg::sound::sampler::StereoSample;
+
+pub trait Tick {
+    fn tick(&mut self);
+}
+
+
+pub trait Mem {
+    fn read_byte(&self, addr: u16) -> u8;
+    fn write_byte(&mut self, addr: u16, value: u8);
+}
+
+/// Destination for rendered video, receiving the 144 lines of a frame one at a time as they are
+/// drawn. Pixels are 0RGB.
+pub trait VideoSink {
+    fn push_line(&mut self, y: usize, line: &[u32; SCREEN_WIDTH]);
+
+    /// Called once the last line of a frame has been drawn.
+    fn frame_ready(&mut self) {}
+}
+
+/// Destination for the stereo samples produced by the APU.
+pub trait AudioSink {
+    fn push_sample(&mut self, sample: StereoSample);
+
+    /// Samples pushed that haven't been played yet, for pacing emulation by the audio device with
+    /// `AudioPacer`. `None` for sinks that don't play in real time.
+    fn queued_samples(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Source of the buttons held down, polled once per emulation step.
+pub trait InputSource {
+    fn poll(&mut self) -> JoypadInput;
+}
+
+/// The other side of the CGB infrared port, e.g. `IrLoopback` or an `IrLink` to another core.
+/// Shared with clones of the core, so it's called through `&self`.
+pub trait IrTransceiver: Send + Sync {
+    /// Called when the game turns the port's LED on or off.
+    fn set_emitting(&self, on: bool);
+
+    /// Whether light reaches the port's sensor.
+    fn receiving(&self) -> bool;
+}
+
+/// Something plugged into the link port, e.g. a `BarcodeBoy`. Shared with clones of the core, so
+/// it's called through `&self`.
+pub trait SerialDevice: Send + Sync {
+    /// Swaps a byte in a transfer the Game Boy clocks, returning the byte shifted in for `sent`.
+    fn exchange(&self, sent: u8) -> u8;
+
+    /// Called while the Game Boy waits for the device to clock a transfer. Returns the byte shifted
+    /// in for `sent`, or `None` to keep it waiting.
+    fn clock_transfer(&self, _sent: u8) -> Option<u8> {
+        None
+    }
+
+    /// Called as emulation runs with the clock cycles passed since the last call, for devices
+    /// that keep time.
+    fn advance(&self, _cycles: u32) {}
+}
+
+/// Hooks called by `Core::run`. Frames are delivered after the audio and serial output generated
+/// while emulating them.
+pub trait EmuCallbacks {
+    /// Receives a finished 160x144 frame. Return false to stop the emulation loop.
+    fn on_frame(&mut self, pixels: &[u32]) -> bool;
+
+    fn on_audio(&mut self, _samples: &[StereoSample]) {}
+
+    /// Receives each byte the game sends over the link port.
+    fn on_serial(&mut self, _byte: u8) {}
+
+    /// Buttons to hold down for the next frame.
+    fn input(&mut self) -> JoypadInput {
+        JoypadInput::empty()
+    }
+}
+
+/// Discards all lines, for frontends that read the finished frame from `Core::frame` instead.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullVideoSink;
+
+impl VideoSink for NullVideoSink {
+    fn push_line(&mut self, _y: usize, _line: &[u32; SCREEN_WIDTH]) {}
+}
+
+/// Discards all audio, for headless use such as tests and benchmarks.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullAudioSink;
+
+impl AudioSink for NullAudioSink {
+    fn push_sample(&mut self, _sample: StereoSample) {}
+}
+
+impl AudioSink for Vec<StereoSample> {
+    fn push_sample(&mut self, sample: StereoSample) {
+        self.push(sample);
+    }
+}
+
+impl<T: AudioSink + ?Sized> AudioSink for &mut T {
+    fn push_sample(&mut self, sample: StereoSample) {
+        (**self).push_sample(sample);
+    }
+
+    fn queued_samples(&self) -> Option<usize> {
+        (**self).queued_samples()
+    }
+}
+
+/// Sends every sample to both sinks, e.g. the audio device and a recorder.
+impl<A: AudioSink, B: AudioSink> AudioSink for (A, B) {
+    fn push_sample(&mut self, sample: StereoSample) {
+        self.0.push_sample(sample);
+        self.1.push_sample(sample);
+    }
+
+    fn queued_samples(&self) -> Option<usize> {
+        self.0.queued_samples().or_else(|| self.1.queued_samples())
+    }
+}
+
+impl InputSource for JoypadInput {
+    fn poll(&mut self) -> JoypadInput {
+        *self
+    }
+}
+
+/// Calls the closure for each poll, e.g. to script input by frame number.
+impl<F: FnMut() -> JoypadInput> InputSource for F {
+    fn poll(&mut self) -> JoypadInput {
+        self()
+    }
+}
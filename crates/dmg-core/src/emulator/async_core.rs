@@ -0,0 +1,130 @@
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, watch};
+use tokio::task::{self, JoinHandle};
+
+use crate::dmg::core::{Core, FRAME_DURATION};
+use crate::dmg::frame::Frame;
+use crate::dmg::input::JoypadInput;
+
+// Queued ahead of a consumer that has fallen behind before it starts missing some. Audio gets more
+// slack since a gap in it is more noticeable than a skipped frame
+const FRAME_CAPACITY: usize = 2;
+const AUDIO_CAPACITY: usize = 6;
+
+/// Runs a core on a tokio blocking task at the Game Boy's frame rate, for embedding the emulator
+/// in async servers. Frames and their audio arrive on separate channels so they can be consumed
+/// by different tasks; either is dropped when its consumer has not kept up.
+pub struct AsyncCore {
+    input: watch::Sender<JoypadInput>,
+    frames: mpsc::Receiver<Frame>,
+    audio: mpsc::Receiver<Vec<(f32, f32)>>,
+    running: Arc<AtomicBool>,
+    handle: JoinHandle<Core>,
+}
+
+impl AsyncCore {
+    /// Starts emulating `core`. Must be called from within a tokio runtime.
+    pub fn spawn(core: Core) -> Self {
+        let (input, input_rx) = watch::channel(JoypadInput::empty());
+        let (frames_tx, frames) = mpsc::channel(FRAME_CAPACITY);
+        let (audio_tx, audio) = mpsc::channel(AUDIO_CAPACITY);
+        let running = Arc::new(AtomicBool::new(true));
+
+        let handle = {
+            let running = running.clone();
+            task::spawn_blocking(move || emulate(core, input_rx, frames_tx, audio_tx, running))
+        };
+
+        Self { input, frames, audio, running, handle }
+    }
+
+    /// Holds `keys` from the next frame on.
+    pub fn set_input(&self, keys: JoypadInput) {
+        self.input.send_replace(keys);
+    }
+
+    /// Waits for the oldest frame not yet picked up. Its audio is sent to `recv_audio` instead, so
+    /// `Frame::audio` is empty. `None` once emulation has stopped.
+    pub async fn recv_frame(&mut self) -> Option<Frame> {
+        self.frames.recv().await
+    }
+
+    /// Waits for the samples of the oldest frame whose audio has not been picked up.
+    pub async fn recv_audio(&mut self) -> Option<Vec<(f32, f32)>> {
+        self.audio.recv().await
+    }
+
+    /// Stops emulation and hands back the core, e.g. for saving its state.
+    pub async fn stop(self) -> Core {
+        self.running.store(false, Ordering::Relaxed);
+        drop(self.frames);
+        drop(self.audio);
+        self.handle.await.expect("emulation task panicked")
+    }
+}
+
+fn emulate(
+    mut core: Core,
+    input: watch::Receiver<JoypadInput>,
+    frames: mpsc::Sender<Frame>,
+    audio: mpsc::Sender<Vec<(f32, f32)>>,
+    running: Arc<AtomicBool>,
+) -> Core {
+    let mut next_frame = Instant::now();
+
+    while running.load(Ordering::Relaxed) {
+        let keys_pressed = *input.borrow();
+
+        let mut frame = core.run_frame(keys_pressed).clone();
+        let samples = mem::take(&mut frame.audio);
+        if let Err(TrySendError::Closed(_)) = audio.try_send(samples) {
+            break;
+        }
+        if let Err(TrySendError::Closed(_)) = frames.try_send(frame) {
+            break;
+        }
+
+        next_frame += FRAME_DURATION;
+        let now = Instant::now();
+        if next_frame > now {
+            thread::sleep(next_frame - now);
+        } else {
+            // Fell behind, don't try to catch up with a burst of frames
+            next_frame = now;
+        }
+    }
+
+    core
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::core::CoreBuilder;
+    use crate::dmg::frame::{SCREEN_HEIGHT, SCREEN_WIDTH};
+    use crate::dmg::input::JoypadInput;
+    use crate::emulator::async_core::AsyncCore;
+    use crate::test_support::looping_rom;
+
+    #[tokio::test]
+    async fn emulates_on_blocking_task() {
+        let core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        let rom_hash = core.rom_hash();
+
+        let mut emulator = AsyncCore::spawn(core);
+        emulator.set_input(JoypadInput::A);
+        let frame = emulator.recv_frame().await.unwrap();
+        let samples = emulator.recv_audio().await.unwrap();
+        let core = emulator.stop().await;
+
+        assert_eq!(frame.pixels.len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+        assert!(frame.audio.is_empty());
+        assert!(!samples.is_empty());
+        assert_eq!(core.rom_hash(), rom_hash);
+    }
+}
@@ -0,0 +1,172 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::dmg::core::Core;
+use crate::emulator::save_location::SaveLocation;
+
+// Wait for writes to settle before saving, so a game saving byte by byte is written out once
+const FLUSH_DELAY: Duration = Duration::from_secs(3);
+
+/// Keeps battery-backed cartridge RAM in a `.sav` file, next to the ROM with `for_rom` or wherever
+/// a `SaveLocation` puts it with `at`. RAM is written back a few seconds after the game last
+/// changed it, and on `flush`, so a crash loses at most those seconds without rewriting the file
+/// every frame.
+pub struct BatterySave {
+    path: PathBuf,
+    last_write: Option<Instant>,
+}
+
+impl BatterySave {
+    pub fn for_rom(rom_path: impl AsRef<Path>) -> Self {
+        Self::at(SaveLocation::AlongsideRom.battery_path(rom_path))
+    }
+
+    /// Keeps RAM in the file at `path`, e.g. for a second copy of a game that shouldn't share the
+    /// first one's save.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), last_write: None }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Loads the `.sav` file into the cartridge, if there is one and the cartridge has a battery.
+    pub fn load(&self, core: &mut Core) -> io::Result<()> {
+        if core.battery_ram().is_none() {
+            return Ok(());
+        }
+
+        match fs::read(&self.path) {
+            Ok(data) => {
+                core.load_battery_ram(&data);
+                Ok(())
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Call once per frame. Returns true when RAM was written to disk.
+    pub fn update(&mut self, core: &mut Core) -> io::Result<bool> {
+        self.update_at(core, Instant::now())
+    }
+
+    fn update_at(&mut self, core: &mut Core, now: Instant) -> io::Result<bool> {
+        if core.take_battery_ram_written() {
+            self.last_write = Some(now);
+        }
+
+        match self.last_write {
+            Some(last_write) if now.saturating_duration_since(last_write) >= FLUSH_DELAY => self.flush(core).map(|_| true),
+            _ => Ok(false),
+        }
+    }
+
+    /// Writes RAM to disk now if it has changed since it was last written, e.g. before exiting.
+    pub fn flush(&mut self, core: &mut Core) -> io::Result<()> {
+        if core.take_battery_ram_written() {
+            self.last_write = Some(Instant::now());
+        }
+        if self.last_write.take().is_none() {
+            return Ok(());
+        }
+
+        match core.battery_ram() {
+            Some(ram) => {
+                // The directory may not exist yet with a `SaveLocation::PerRom`
+                if let Some(dir) = self.path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(&self.path, ram)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::time::{Duration, Instant};
+
+    use crate::dmg::core::Core;
+    use crate::emulator::battery::{BatterySave, FLUSH_DELAY};
+    use crate::test_support::looping_rom;
+
+    // A 32K MBC1+RAM+BATTERY ROM with 8K of RAM, written next to its save
+    fn battery_rom(name: &str) -> String {
+        let mut rom = looping_rom(&[]);
+        rom[0x147] = 0x03;
+        rom[0x149] = 0x02;
+
+        let path = env::temp_dir().join(name);
+        fs::write(&path, rom).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn ram_is_written_after_writes_settle() {
+        let rom = battery_rom("ram_is_written_after_writes_settle.gb");
+        let mut core = Core::load_without_boot_rom(Some(rom.clone())).unwrap();
+        let mut battery = BatterySave::for_rom(&rom);
+        let _ = fs::remove_file(battery.path());
+        let start = Instant::now();
+
+        core.write_byte(0x0000, 0x0a);
+        core.write_byte(0xa000, 0x42);
+
+        assert!(!battery.update_at(&mut core, start).unwrap());
+        assert!(!battery.update_at(&mut core, start + FLUSH_DELAY - Duration::from_millis(1)).unwrap());
+        assert!(battery.update_at(&mut core, start + FLUSH_DELAY).unwrap());
+        assert!(!battery.update_at(&mut core, start + FLUSH_DELAY * 2).unwrap());
+
+        let mut reloaded = Core::load_without_boot_rom(Some(rom)).unwrap();
+        battery.load(&mut reloaded).unwrap();
+        reloaded.write_byte(0x0000, 0x0a);
+        assert_eq!(reloaded.read_byte(0xa000), 0x42);
+    }
+
+    #[test]
+    fn flush_writes_pending_ram() {
+        let rom = battery_rom("flush_writes_pending_ram.gb");
+        let mut core = Core::load_without_boot_rom(Some(rom.clone())).unwrap();
+        let mut battery = BatterySave::for_rom(&rom);
+        let _ = fs::remove_file(battery.path());
+
+        battery.flush(&mut core).unwrap();
+        assert!(!battery.path().exists());
+
+        core.write_byte(0x0000, 0x0a);
+        core.write_byte(0xa001, 0x17);
+        battery.flush(&mut core).unwrap();
+
+        assert_eq!(fs::read(battery.path()).unwrap()[1], 0x17);
+    }
+
+    #[test]
+    fn loading_a_state_rewrites_the_battery_save() {
+        let rom = battery_rom("loading_a_state_rewrites_the_battery_save.gb");
+        let mut core = Core::load_without_boot_rom(Some(rom.clone())).unwrap();
+        let mut battery = BatterySave::for_rom(&rom);
+        let _ = fs::remove_file(battery.path());
+
+        core.write_byte(0x0000, 0x0a);
+        core.write_byte(0xa000, 0x01);
+        let mut state = vec![];
+        core.save_state_to(&mut state).unwrap();
+
+        // Saved in game after the state was made, then the state loaded again
+        core.write_byte(0xa000, 0x02);
+        battery.flush(&mut core).unwrap();
+        assert_eq!(fs::read(battery.path()).unwrap()[0], 0x02);
+
+        core.load_state_from(state.as_slice()).unwrap();
+        assert!(!battery.update_at(&mut core, Instant::now()).unwrap());
+        battery.flush(&mut core).unwrap();
+        assert_eq!(fs::read(battery.path()).unwrap()[0], 0x01);
+    }
+}
@@ -0,0 +1,98 @@
+use std::fs::File;
+use std::io;
+use std::io::Read;
+
+use crate::dmg::input::JoypadInput;
+use crate::emulator::movie::Movie;
+
+/// Reads the Gameboy input log out of a BizHawk .bk2 archive.
+///
+/// BizHawk movies don't carry a CRC32 of the ROM, so the caller supplies the hash of the ROM the
+/// movie is meant to be played back against. Frame 0 of the log is mapped to the first frame
+/// rendered by this core, which may be offset from BizHawk's frame counter for some cores.
+pub fn import_bk2(path: &str, rom_hash: u32) -> io::Result<Movie> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+    let mut log = String::new();
+    archive.by_name("Input Log.txt")?.read_to_string(&mut log)?;
+
+    parse_input_log(&log, rom_hash)
+}
+
+fn button_for_name(name: &str) -> Option<JoypadInput> {
+    let name = name.trim_start_matches("P1 ");
+
+    match name {
+        "Up" => Some(JoypadInput::UP),
+        "Down" => Some(JoypadInput::DOWN),
+        "Left" => Some(JoypadInput::LEFT),
+        "Right" => Some(JoypadInput::RIGHT),
+        "Start" => Some(JoypadInput::START),
+        "Select" => Some(JoypadInput::SELECT),
+        "B" => Some(JoypadInput::B),
+        "A" => Some(JoypadInput::A),
+        _ => None,
+    }
+}
+
+fn parse_input_log(log: &str, rom_hash: u32) -> io::Result<Movie> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    // LogKey:#Up|Down|Left|Right|Start|Select|B|A|Power|
+    let columns: Vec<Option<JoypadInput>> = log
+        .lines()
+        .find_map(|line| line.strip_prefix("LogKey:"))
+        .ok_or_else(|| invalid("bk2: missing LogKey in input log"))?
+        .split('|')
+        .map(|name| name.trim_start_matches('#'))
+        .filter(|name| !name.is_empty())
+        .map(button_for_name)
+        .collect();
+
+    let mut movie = Movie::new(rom_hash, None);
+
+    for line in log.lines().filter(|line| line.starts_with('|')) {
+        let mut keys = JoypadInput::empty();
+        let pressed = line.chars().filter(|&c| c != '|');
+
+        for (column, c) in columns.iter().zip(pressed) {
+            if let Some(button) = column {
+                if c != '.' && c != ' ' {
+                    keys |= *button;
+                }
+            }
+        }
+
+        movie.record_frame(keys);
+    }
+
+    Ok(movie)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::input::JoypadInput;
+    use crate::emulator::bk2::parse_input_log;
+
+    #[test]
+    fn parses_gameboy_input_log() {
+        let log = "[Input]\n\
+                   LogKey:#Up|Down|Left|Right|Start|Select|B|A|Power|\n\
+                   |.........|\n\
+                   |U......A.|\n\
+                   |....S....|\n\
+                   [/Input]\n";
+
+        let movie = parse_input_log(log, 0x1234).unwrap();
+
+        assert_eq!(movie.len(), 3);
+        assert_eq!(movie.rom_hash, 0x1234);
+        assert_eq!(movie.input_at(0), Some(JoypadInput::empty()));
+        assert_eq!(movie.input_at(1), Some(JoypadInput::UP | JoypadInput::A));
+        assert_eq!(movie.input_at(2), Some(JoypadInput::START));
+    }
+
+    #[test]
+    fn missing_log_key_is_an_error() {
+        assert!(parse_input_log("|....|\n", 0).is_err());
+    }
+}
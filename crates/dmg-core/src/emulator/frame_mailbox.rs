@@ -0,0 +1,148 @@
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::dmg::frame::Frame;
+
+// The shared state is the index of the buffer in the middle, with this bit set while it holds a
+// frame the reader hasn't picked up yet
+const NEW_FRAME: usize = 0b100;
+const INDEX: usize = 0b011;
+
+// Three buffers: the one the publisher writes, the one the reader renders and the latest
+// complete frame in between. Each is only ever touched by whichever side holds its index.
+struct Buffers {
+    frames: [UnsafeCell<Frame>; 3],
+    middle: AtomicUsize,
+}
+
+// Safe since the publisher and reader only access the buffer whose index they hold, and trade
+// buffers through `middle`
+unsafe impl Sync for Buffers {}
+
+impl Buffers {
+    fn swap_middle(&self, index: usize) -> usize {
+        // AcqRel: the frame written before handing a buffer over is seen by the side taking it
+        self.middle.swap(index, Ordering::AcqRel)
+    }
+}
+
+/// Creates a triple-buffered mailbox for handing frames from the emulation thread to the UI
+/// thread. Neither side ever waits for the other: the publisher overwrites frames the reader
+/// hasn't picked up, and the reader keeps rendering the last frame until a newer one is complete.
+pub fn frame_mailbox() -> (FramePublisher, FrameReader) {
+    let buffers = Arc::new(Buffers {
+        frames: Default::default(),
+        middle: AtomicUsize::new(1),
+    });
+
+    let publisher = FramePublisher { buffers: buffers.clone(), back: 0 };
+    let reader = FrameReader { buffers, front: 2 };
+    (publisher, reader)
+}
+
+/// The emulation thread's end of a `frame_mailbox`.
+pub struct FramePublisher {
+    buffers: Arc<Buffers>,
+    back: usize,
+}
+
+impl FramePublisher {
+    /// Makes `frame` the latest one, replacing a previous frame the reader hasn't picked up. Its
+    /// audio goes along with it, so audio is better sent elsewhere when frames may be skipped.
+    pub fn publish(&mut self, frame: &Frame) {
+        // SAFETY: only the publisher holds the back buffer's index
+        let back = unsafe { &mut *self.buffers.frames[self.back].get() };
+        back.pixels.copy_from_slice(&frame.pixels[..]);
+        back.audio.clone_from(&frame.audio);
+
+        self.back = self.buffers.swap_middle(self.back | NEW_FRAME) & INDEX;
+    }
+}
+
+/// The UI thread's end of a `frame_mailbox`.
+pub struct FrameReader {
+    buffers: Arc<Buffers>,
+    front: usize,
+}
+
+impl FrameReader {
+    /// Whether a frame has been published since the last one was picked up.
+    pub fn has_new_frame(&self) -> bool {
+        self.buffers.middle.load(Ordering::Relaxed) & NEW_FRAME != 0
+    }
+
+    /// The latest complete frame, if one has been published since the last call.
+    pub fn new_frame(&mut self) -> Option<&Frame> {
+        if !self.has_new_frame() {
+            return None;
+        }
+
+        self.front = self.buffers.swap_middle(self.front) & INDEX;
+        Some(self.front())
+    }
+
+    /// The latest complete frame, which is the one returned before when nothing new has been
+    /// published, and a blank frame before the first one.
+    pub fn latest(&mut self) -> &Frame {
+        self.new_frame();
+        self.front()
+    }
+
+    fn front(&self) -> &Frame {
+        // SAFETY: only the reader holds the front buffer's index
+        unsafe { &*self.buffers.frames[self.front].get() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::dmg::frame::Frame;
+    use crate::emulator::frame_mailbox::frame_mailbox;
+
+    fn frame_filled_with(color: u32) -> Frame {
+        let mut frame = Frame::default();
+        frame.pixels.fill(color);
+        frame
+    }
+
+    #[test]
+    fn reader_gets_the_latest_complete_frame() {
+        let (mut publisher, mut reader) = frame_mailbox();
+        assert!(reader.new_frame().is_none());
+        assert_eq!(reader.latest().pixels[0], 0);
+
+        publisher.publish(&frame_filled_with(1));
+        publisher.publish(&frame_filled_with(2));
+        assert!(reader.has_new_frame());
+        assert_eq!(reader.new_frame().map(|frame| frame.pixels[0]), Some(2));
+        assert!(reader.new_frame().is_none());
+        assert_eq!(reader.latest().pixels[0], 2);
+
+        publisher.publish(&frame_filled_with(3));
+        assert_eq!(reader.latest().pixels[0], 3);
+    }
+
+    #[test]
+    fn frames_are_never_torn_across_threads() {
+        let (mut publisher, mut reader) = frame_mailbox();
+
+        let emulation = thread::spawn(move || {
+            for color in 1..=2000 {
+                publisher.publish(&frame_filled_with(color));
+            }
+        });
+
+        let mut last = 0;
+        while last < 2000 {
+            let frame = reader.latest();
+            let color = frame.pixels[0];
+            assert!(frame.pixels.iter().all(|&pixel| pixel == color));
+            assert!(color >= last);
+            last = color;
+        }
+        emulation.join().unwrap();
+    }
+}
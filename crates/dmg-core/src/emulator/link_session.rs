@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use log::debug;
+
+use crate::dmg::traits::SerialDevice;
+use crate::error::{Error, Result};
+
+/// Which side clocked a link transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferClock {
+    /// The recorded game clocked it.
+    Internal,
+    /// The peer did, while the recorded game waited.
+    External,
+}
+
+/// A byte swapped over the link port, as seen by the recorded game. Written one per line as
+/// `CY:4096 CLK:INT TX:3C RX:7F`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkTransfer {
+    /// Clock cycles from the start of the session to the end of the transfer.
+    pub cycles: u64,
+    pub clock: TransferClock,
+    pub sent: u8,
+    pub received: u8,
+}
+
+impl fmt::Display for LinkTransfer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let clock = match self.clock {
+            TransferClock::Internal => "INT",
+            TransferClock::External => "EXT",
+        };
+        write!(f, "CY:{} CLK:{} TX:{:02X} RX:{:02X}", self.cycles, clock, self.sent, self.received)
+    }
+}
+
+impl FromStr for LinkTransfer {
+    type Err = String;
+
+    fn from_str(line: &str) -> std::result::Result<Self, String> {
+        let (mut cycles, mut clock, mut sent, mut received) = (None, None, None, None);
+        for field in line.split_whitespace() {
+            let Some((name, value)) = field.split_once(':') else { continue };
+            match name {
+                "CY" => cycles = Some(value.parse().map_err(|_| format!("invalid CY {:?}", value))?),
+                "CLK" => {
+                    clock = Some(match value {
+                        "INT" => TransferClock::Internal,
+                        "EXT" => TransferClock::External,
+                        _ => return Err(format!("invalid CLK {:?}", value)),
+                    })
+                }
+                "TX" => sent = Some(u8::from_str_radix(value, 16).map_err(|_| format!("invalid TX {:?}", value))?),
+                "RX" => received = Some(u8::from_str_radix(value, 16).map_err(|_| format!("invalid RX {:?}", value))?),
+                _ => {}
+            }
+        }
+
+        match (cycles, clock, sent, received) {
+            (Some(cycles), Some(clock), Some(sent), Some(received)) => Ok(Self { cycles, clock, sent, received }),
+            _ => Err("expected CY:, CLK:, TX: and RX: fields".to_string()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Session {
+    cycles: u64,
+    transfers: Vec<LinkTransfer>,
+}
+
+/// Passes transfers through to `device`, e.g. one end of a `LinkCable`, recording each one for
+/// `LinkReplay`. The session starts when the recorder is first connected. Clones share the
+/// recording.
+#[derive(Clone)]
+pub struct LinkRecorder {
+    device: Arc<dyn SerialDevice>,
+    session: Arc<Mutex<Session>>,
+}
+
+impl LinkRecorder {
+    pub fn new(device: impl SerialDevice + 'static) -> Self {
+        Self { device: Arc::new(device), session: Arc::default() }
+    }
+
+    pub fn transfers(&self) -> Vec<LinkTransfer> {
+        self.session.lock().unwrap().transfers.clone()
+    }
+
+    /// Writes the session so far, one transfer per line.
+    pub fn save(&self, mut out: impl Write) -> io::Result<()> {
+        for transfer in &self.session.lock().unwrap().transfers {
+            writeln!(out, "{}", transfer)?;
+        }
+        out.flush()
+    }
+
+    fn record(&self, clock: TransferClock, sent: u8, received: u8) {
+        let mut session = self.session.lock().unwrap();
+        let cycles = session.cycles;
+        session.transfers.push(LinkTransfer { cycles, clock, sent, received });
+    }
+}
+
+impl SerialDevice for LinkRecorder {
+    fn exchange(&self, sent: u8) -> u8 {
+        let received = self.device.exchange(sent);
+        self.record(TransferClock::Internal, sent, received);
+        received
+    }
+
+    fn clock_transfer(&self, sent: u8) -> Option<u8> {
+        let received = self.device.clock_transfer(sent)?;
+        self.record(TransferClock::External, sent, received);
+        Some(received)
+    }
+
+    fn advance(&self, cycles: u32) {
+        self.session.lock().unwrap().cycles += cycles as u64;
+        self.device.advance(cycles);
+    }
+}
+
+struct Replay {
+    cycles: u64,
+    transfers: VecDeque<LinkTransfer>,
+}
+
+/// Stands in for the peer of a recorded session, answering the game's transfers with the bytes
+/// received in the recording. Transfers the peer clocked are replayed no earlier than they were
+/// recorded, counting from when the replay is first connected, so start it from the same point as
+/// the recording. Clones share the replay.
+#[derive(Clone)]
+pub struct LinkReplay {
+    replay: Arc<Mutex<Replay>>,
+}
+
+impl LinkReplay {
+    pub fn new(transfers: Vec<LinkTransfer>) -> Self {
+        Self { replay: Arc::new(Mutex::new(Replay { cycles: 0, transfers: transfers.into() })) }
+    }
+
+    /// Reads a session written by `LinkRecorder::save`. Blank lines and `#` comments are skipped.
+    pub fn load(session: impl BufRead) -> Result<Self> {
+        let mut transfers = vec![];
+        for (i, line) in session.lines().enumerate() {
+            let line_error = |reason: String| Error::LinkSession { line: i + 1, reason };
+            let line = line.map_err(|e| line_error(e.to_string()))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            transfers.push(line.parse().map_err(line_error)?);
+        }
+        Ok(Self::new(transfers))
+    }
+
+    /// Transfers not yet replayed.
+    pub fn remaining(&self) -> usize {
+        self.replay.lock().unwrap().transfers.len()
+    }
+}
+
+impl SerialDevice for LinkReplay {
+    fn exchange(&self, sent: u8) -> u8 {
+        let mut replay = self.replay.lock().unwrap();
+
+        // The peer was meant to clock next, so the game has gone its own way
+        let Some(transfer) = replay.transfers.front().filter(|t| t.clock == TransferClock::Internal).copied() else {
+            return 0xff;
+        };
+        replay.transfers.pop_front();
+
+        if sent != transfer.sent {
+            debug!(target: "serial", "replay sent {:#04X} where the recording sent {:#04X}", sent, transfer.sent);
+        }
+        transfer.received
+    }
+
+    fn clock_transfer(&self, _sent: u8) -> Option<u8> {
+        let mut replay = self.replay.lock().unwrap();
+
+        let transfer = *replay.transfers.front()?;
+        if transfer.clock != TransferClock::External || replay.cycles < transfer.cycles {
+            return None;
+        }
+        replay.transfers.pop_front();
+        Some(transfer.received)
+    }
+
+    fn advance(&self, cycles: u32) {
+        self.replay.lock().unwrap().cycles += cycles as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::link::LinkCable;
+    use crate::dmg::traits::SerialDevice;
+    use crate::emulator::link_session::{LinkRecorder, LinkReplay, LinkTransfer, TransferClock};
+
+    #[test]
+    fn replays_a_recorded_session_as_the_peer() {
+        let (a, b) = LinkCable::pair();
+        let recorder = LinkRecorder::new(a);
+
+        recorder.advance(100);
+        assert_eq!(b.clock_transfer(0x24), None);
+        assert_eq!(recorder.exchange(0x42), 0x24);
+        assert_eq!(b.clock_transfer(0x24), Some(0x42));
+
+        recorder.advance(4096);
+        assert_eq!(recorder.clock_transfer(0x01), None);
+        assert_eq!(b.exchange(0x99), 0x01);
+        assert_eq!(recorder.clock_transfer(0x01), Some(0x99));
+
+        let mut saved = vec![];
+        recorder.save(&mut saved).unwrap();
+        assert_eq!(String::from_utf8_lossy(&saved), "CY:100 CLK:INT TX:42 RX:24\nCY:4196 CLK:EXT TX:01 RX:99\n");
+
+        let replay = LinkReplay::load(&saved[..]).unwrap();
+        assert_eq!(replay.exchange(0x42), 0x24);
+        // Not before the peer clocked it in the recording
+        assert_eq!(replay.clock_transfer(0x01), None);
+        replay.advance(4196);
+        assert_eq!(replay.clock_transfer(0x01), Some(0x99));
+        assert_eq!(replay.remaining(), 0);
+        assert_eq!(replay.exchange(0x42), 0xff);
+    }
+
+    #[test]
+    fn parses_transfers_and_rejects_bad_lines() {
+        let transfer: LinkTransfer = "CY:8 CLK:EXT TX:0a RX:FF".parse().unwrap();
+        assert_eq!(transfer, LinkTransfer { cycles: 8, clock: TransferClock::External, sent: 0x0a, received: 0xff });
+
+        assert!(LinkReplay::load(&b"# comment\n\nCY:8 CLK:INT TX:00 RX:00\n"[..]).is_ok());
+        assert!(LinkReplay::load(&b"CY:8 CLK:SIDEWAYS TX:00 RX:00\n"[..]).is_err());
+        assert!(LinkReplay::load(&b"CY:8 TX:00\n"[..]).is_err());
+    }
+}
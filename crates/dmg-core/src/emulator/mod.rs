@@ -0,0 +1,15 @@
+#[cfg(feature = "tokio")]
+pub mod async_core;
+pub mod battery;
+pub mod bk2;
+pub mod frame_mailbox;
+pub mod link_session;
+pub mod movie;
+pub mod rewind;
+pub mod save_location;
+pub mod scale;
+pub mod state;
+pub mod test_rom;
+pub mod timing;
+pub mod turbo;
+pub mod worker;
@@ -0,0 +1,129 @@
+use std::fs::File;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dmg::core::Core;
+use crate::dmg::input::JoypadInput;
+use crate::dmg::traits::{NullAudioSink, NullVideoSink};
+
+/// Recorded joypad input, one entry per rendered frame.
+///
+/// Playback starts either from power-on or from `initial_state` (a serialized Core) when
+/// the recording was started from a restored save state.
+#[derive(Serialize, Deserialize)]
+pub struct Movie {
+    pub rom_hash: u32,
+    pub initial_state: Option<Vec<u8>>,
+    pub frames: Vec<JoypadInput>,
+}
+
+impl Movie {
+    pub fn new(rom_hash: u32, initial_state: Option<Vec<u8>>) -> Self {
+        Self {
+            rom_hash,
+            initial_state,
+            frames: vec![],
+        }
+    }
+
+    /// A movie that plays back from `core` as it is now.
+    pub fn starting_from(core: &Core) -> Self {
+        Self::new(core.rom_hash(), serde_cbor::to_vec(core).ok())
+    }
+
+    pub fn record_frame(&mut self, keys: JoypadInput) {
+        self.frames.push(keys);
+    }
+
+    pub fn input_at(&self, frame: usize) -> Option<JoypadInput> {
+        self.frames.get(frame).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Runs every recorded frame on `core` without video or audio output. `core` should be the
+    /// power-on core or `restore_initial_state`, matching how the movie was recorded.
+    pub fn replay(&self, core: &mut Core) {
+        for &(mut keys) in &self.frames {
+            core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut keys);
+        }
+    }
+
+    pub fn restore_initial_state(&self) -> Option<Core> {
+        let state = self.initial_state.as_ref()?;
+        let mut core: Core = serde_cbor::from_slice(state).ok()?;
+        core.restore_unsaved();
+        Some(core)
+    }
+}
+
+pub fn load_movie(path: &str) -> serde_cbor::Result<Movie> {
+    let mut f = File::open(path)?;
+    serde_cbor::from_reader(&mut f)
+}
+
+pub fn save_movie(path: &str, movie: &Movie) -> serde_cbor::Result<()> {
+    let mut f = File::create(path)?;
+    serde_cbor::to_writer(&mut f, movie)
+}
+
+/// Feeds recorded input back frame by frame.
+pub struct MoviePlayer {
+    movie: Movie,
+    frame: usize,
+}
+
+impl MoviePlayer {
+    pub fn new(movie: Movie) -> Self {
+        Self { movie, frame: 0 }
+    }
+
+    /// Input for the frame currently being emulated, or None once the movie has ended.
+    pub fn current_input(&self) -> Option<JoypadInput> {
+        self.movie.input_at(self.frame)
+    }
+
+    pub fn next_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.movie.len()
+    }
+
+    /// Frames of input still to come.
+    pub fn remaining_frames(&self) -> usize {
+        self.movie.len().saturating_sub(self.frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::core::CoreBuilder;
+    use crate::dmg::input::JoypadInput;
+    use crate::emulator::movie::Movie;
+    use crate::test_support::looping_rom;
+
+    #[test]
+    fn replay_runs_every_frame_reproducibly() {
+        let rom = looping_rom(&[]);
+        let mut movie = Movie::new(0, None);
+        for keys in [JoypadInput::A, JoypadInput::empty(), JoypadInput::START] {
+            movie.record_frame(keys);
+        }
+
+        let mut first = CoreBuilder::new().game_rom_data(rom.clone()).build().unwrap();
+        let mut second = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+        movie.replay(&mut first);
+        movie.replay(&mut second);
+
+        assert_eq!(first.frames_rendered(), 3);
+        assert_eq!(first.state_hash(), second.state_hash());
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use crate::dmg::core::Core;
+
+/// History of snapshots for rewinding.
+///
+/// Only the newest snapshot is kept in full. Older snapshots are stored as the XOR between two
+/// consecutive snapshots, run-length encoded, which is mostly zeros since little state changes
+/// between frames. The oldest history is dropped once `capacity` bytes are used.
+pub struct RewindBuffer {
+    current: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>,
+    capacity: usize,
+    used: usize,
+}
+
+impl RewindBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            current: None,
+            deltas: VecDeque::new(),
+            capacity,
+            used: 0,
+        }
+    }
+
+    pub fn push(&mut self, core: &Core) {
+        let snapshot = match serde_cbor::to_vec(core) {
+            Ok(snapshot) => snapshot,
+            Err(_) => return,
+        };
+
+        if let Some(previous) = self.current.replace(snapshot) {
+            let delta = encode_delta(self.current.as_ref().unwrap(), &previous);
+            self.used += delta.len();
+            self.deltas.push_back(delta);
+        }
+
+        while self.used > self.capacity {
+            match self.deltas.pop_front() {
+                Some(delta) => self.used -= delta.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Steps back to the previous snapshot, or returns None when the history is exhausted.
+    pub fn rewind(&mut self) -> Option<Core> {
+        let delta = self.deltas.pop_back()?;
+        self.used -= delta.len();
+
+        let previous = apply_delta(self.current.as_ref()?, &delta);
+        let core = serde_cbor::from_slice(&previous).ok().map(|mut core: Core| {
+            core.restore_unsaved();
+            core.mark_battery_ram_restored();
+            core
+        });
+        self.current = Some(previous);
+
+        core
+    }
+
+    /// Number of snapshots that can be rewound to.
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.current = None;
+        self.deltas.clear();
+        self.used = 0;
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut v: usize) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> usize {
+    let mut v = 0;
+    let mut shift = 0;
+    loop {
+        let b = data[*pos];
+        *pos += 1;
+        v |= ((b & 0x7f) as usize) << shift;
+        if b & 0x80 == 0 {
+            return v;
+        }
+        shift += 7;
+    }
+}
+
+/// Encodes `to XOR from` as: target length, then pairs of (zero run, literal run, literal bytes).
+fn encode_delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+    let len = from.len().max(to.len());
+    let xor = |i: usize| from.get(i).unwrap_or(&0) ^ to.get(i).unwrap_or(&0);
+
+    let mut out = vec![];
+    write_varint(&mut out, to.len());
+
+    let mut i = 0;
+    while i < len {
+        let zeros_start = i;
+        while i < len && xor(i) == 0 {
+            i += 1;
+        }
+        let literal_start = i;
+        while i < len && xor(i) != 0 {
+            i += 1;
+        }
+
+        write_varint(&mut out, literal_start - zeros_start);
+        write_varint(&mut out, i - literal_start);
+        out.extend((literal_start..i).map(xor));
+    }
+
+    out
+}
+
+fn apply_delta(from: &[u8], delta: &[u8]) -> Vec<u8> {
+    let mut pos = 0;
+    let target_len = read_varint(delta, &mut pos);
+
+    let mut out = from.to_vec();
+    out.resize(from.len().max(target_len), 0);
+
+    let mut i = 0;
+    while pos < delta.len() {
+        i += read_varint(delta, &mut pos);
+        let literals = read_varint(delta, &mut pos);
+        for b in &delta[pos..pos + literals] {
+            out[i] ^= b;
+            i += 1;
+        }
+        pos += literals;
+    }
+
+    out.truncate(target_len);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::rewind::{apply_delta, encode_delta};
+
+    #[test]
+    fn delta_restores_previous_snapshot() {
+        let previous = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let current = vec![1, 2, 9, 4, 5, 6, 7, 8, 10, 11];
+
+        let delta = encode_delta(&current, &previous);
+
+        assert_eq!(apply_delta(&current, &delta), previous);
+    }
+
+    #[test]
+    fn unchanged_snapshots_compress_to_a_few_bytes() {
+        let snapshot = vec![0xaa; 0x10000];
+
+        assert!(encode_delta(&snapshot, &snapshot).len() < 8);
+    }
+}
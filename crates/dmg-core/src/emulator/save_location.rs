@@ -0,0 +1,70 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+// The directory inside the user's data directory
+const DATA_DIR_NAME: &str = "gameboy-rust";
+
+/// Where a ROM's battery save and save states are written, for when the directory holding the ROM
+/// is read-only or shouldn't fill up with saves.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum SaveLocation {
+    /// `game.sav` next to `game.gb`, with save states in a `states` directory beside it.
+    #[default]
+    AlongsideRom,
+    /// A subdirectory per ROM, named after the ROM file: `<dir>/game/game.sav` and the states.
+    PerRom(PathBuf),
+}
+
+impl SaveLocation {
+    /// Per-ROM subdirectories of the user's data directory, `$XDG_DATA_HOME/gameboy-rust` or
+    /// `~/.local/share/gameboy-rust` when that isn't set. `None` when neither can be found.
+    pub fn data_dir() -> Option<Self> {
+        let data_home = env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+        Some(SaveLocation::PerRom(data_home.join(DATA_DIR_NAME)))
+    }
+
+    /// The directory for the saves of the ROM at `rom_path`.
+    pub fn directory(&self, rom_path: impl AsRef<Path>) -> PathBuf {
+        let rom_path = rom_path.as_ref();
+        match self {
+            SaveLocation::AlongsideRom => rom_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            SaveLocation::PerRom(dir) => dir.join(rom_path.file_stem().unwrap_or_default()),
+        }
+    }
+
+    /// Where `BatterySave` keeps cartridge RAM, `game.sav` for `game.gb`.
+    pub fn battery_path(&self, rom_path: impl AsRef<Path>) -> PathBuf {
+        let file_name = Path::new(rom_path.as_ref().file_name().unwrap_or_default()).with_extension("sav");
+        self.directory(rom_path).join(file_name)
+    }
+
+    /// Where `SaveStateManager` keeps save states.
+    pub fn state_directory(&self, rom_path: impl AsRef<Path>) -> PathBuf {
+        match self {
+            SaveLocation::AlongsideRom => self.directory(rom_path).join("states"),
+            SaveLocation::PerRom(_) => self.directory(rom_path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::emulator::save_location::SaveLocation;
+
+    #[test]
+    fn saves_go_next_to_the_rom_or_in_a_directory_per_rom() {
+        let alongside = SaveLocation::AlongsideRom;
+        assert_eq!(alongside.battery_path("roms/tetris.gb"), Path::new("roms/tetris.sav"));
+        assert_eq!(alongside.state_directory("roms/tetris.gb"), Path::new("roms/states"));
+        assert_eq!(alongside.battery_path("tetris.gb"), Path::new("tetris.sav"));
+
+        let per_rom = SaveLocation::PerRom(PathBuf::from("/saves"));
+        assert_eq!(per_rom.battery_path("/media/cd/tetris.gb"), Path::new("/saves/tetris/tetris.sav"));
+        assert_eq!(per_rom.state_directory("/media/cd/tetris.gb"), Path::new("/saves/tetris"));
+    }
+}
@@ -0,0 +1,44 @@
+use crate::dmg::frame::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Scales a frame into a `width` x `height` window buffer by the largest whole factor that fits,
+/// centered with black borders, so pixels stay square and the 10:9 aspect ratio is kept.
+pub fn scale_to_window(frame: &[u32], width: usize, height: usize, out: &mut Vec<u32>) {
+    out.clear();
+    out.resize(width * height, 0);
+
+    let scale = (width / SCREEN_WIDTH).min(height / SCREEN_HEIGHT).max(1);
+    let scaled_width = (SCREEN_WIDTH * scale).min(width);
+    let scaled_height = (SCREEN_HEIGHT * scale).min(height);
+    let left = (width - scaled_width) / 2;
+    let top = (height - scaled_height) / 2;
+
+    for y in 0..scaled_height {
+        let src = &frame[(y / scale) * SCREEN_WIDTH..][..SCREEN_WIDTH];
+        let dst = &mut out[(top + y) * width + left..][..scaled_width];
+        for (x, pixel) in dst.iter_mut().enumerate() {
+            *pixel = src[x / scale];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::frame::{SCREEN_HEIGHT, SCREEN_WIDTH};
+    use crate::emulator::scale::scale_to_window;
+
+    #[test]
+    fn scales_by_whole_factor_with_borders() {
+        let frame = vec![0xffffff; SCREEN_WIDTH * SCREEN_HEIGHT];
+        let (width, height) = (1920, 1080);
+        let mut out = vec![];
+
+        scale_to_window(&frame, width, height, &mut out);
+
+        // 7x fits 1080 lines, leaving 36 rows and 400 columns of border on each side
+        assert_eq!(out[36 * width + 400], 0xffffff);
+        assert_eq!(out[35 * width + 400], 0);
+        assert_eq!(out[36 * width + 399], 0);
+        assert_eq!(out[(height - 37) * width + width - 401], 0xffffff);
+        assert_eq!(out[(height - 36) * width + width - 401], 0);
+    }
+}
@@ -0,0 +1,301 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::dmg::core::Core;
+
+const STATE_MAGIC: [u8; 4] = *b"GBST";
+const STATE_VERSION: u16 = 2;
+
+/// Upgrade steps for states saved by older versions, run in order after the payload has been
+/// decoded. Fields added since then are filled in by their serde defaults, the steps fix up
+/// anything a default can't express. `UPGRADES[n]` upgrades a version `n + 1` state.
+const UPGRADES: [fn(&mut Core); STATE_VERSION as usize - 1] = [upgrade_v1];
+
+// Version 1 did not store the APU, so it comes back powered off and the game stays silent
+fn upgrade_v1(core: &mut Core) {
+    core.write_byte(0xff26, 0x80);
+}
+
+// magic, version, rom hash, payload length, payload crc32
+const HEADER_SIZE: usize = 4 + 2 + 4 + 4 + 4;
+
+#[derive(Debug, Error)]
+pub enum StateError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("not a save state file")]
+    NotAState,
+    #[error("unsupported save state version {0}")]
+    UnsupportedVersion(u16),
+    #[error("save state belongs to another ROM (expected {expected:08X}, found {found:08X})")]
+    RomMismatch { expected: u32, found: u32 },
+    #[error("save state is corrupt (checksum mismatch)")]
+    ChecksumMismatch,
+    #[error("failed decoding save state: {0}")]
+    Decode(#[from] serde_cbor::Error),
+}
+
+/// Serializes the core behind a header of magic bytes, format version, ROM hash and checksum.
+pub fn encode_state(core: &Core) -> Result<Vec<u8>, StateError> {
+    let payload = serde_cbor::to_vec(core)?;
+    Ok(with_header(STATE_VERSION, core.rom_hash(), &payload))
+}
+
+fn with_header(version: u16, rom_hash: u32, payload: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(HEADER_SIZE + payload.len());
+    data.extend_from_slice(&STATE_MAGIC);
+    data.extend_from_slice(&version.to_le_bytes());
+    data.extend_from_slice(&rom_hash.to_le_bytes());
+    data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    data.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+    data.extend_from_slice(payload);
+    data
+}
+
+/// Validates the header and checksum before deserializing, upgrading states saved by older
+/// versions. When `rom_hash` is given the state must have been saved with that ROM loaded. States
+/// include cartridge RAM, which counts as written afterwards so that the `.sav` file follows it.
+pub fn decode_state(data: &[u8], rom_hash: Option<u32>) -> Result<Core, StateError> {
+    if data.len() < HEADER_SIZE || data[0..4] != STATE_MAGIC {
+        return Err(StateError::NotAState);
+    }
+
+    let read_u32 = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+
+    let version = u16::from_le_bytes([data[4], data[5]]);
+    if version == 0 || version > STATE_VERSION {
+        return Err(StateError::UnsupportedVersion(version));
+    }
+
+    let found = read_u32(6);
+    if let Some(expected) = rom_hash {
+        if expected != found {
+            return Err(StateError::RomMismatch { expected, found });
+        }
+    }
+
+    let payload = &data[HEADER_SIZE..];
+    if payload.len() != read_u32(10) as usize || crc32fast::hash(payload) != read_u32(14) {
+        return Err(StateError::ChecksumMismatch);
+    }
+
+    let mut core: Core = serde_cbor::from_slice(payload)?;
+    for upgrade in &UPGRADES[version as usize - 1..] {
+        upgrade(&mut core);
+    }
+    core.restore_unsaved();
+    core.mark_battery_ram_restored();
+
+    Ok(core)
+}
+
+fn read_state_file(path: &Path, rom_hash: Option<u32>) -> Result<Core, StateError> {
+    let mut data = vec![];
+    File::open(path)?.read_to_end(&mut data)?;
+    decode_state(&data, rom_hash)
+}
+
+fn write_state_file(path: &Path, core: &Core) -> Result<(), StateError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&encode_state(core)?)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Numbered save state slots, stored per ROM as `<directory>/<title>-<crc32>.ss<slot>`, plus an
+/// autosave written when the emulator exits.
+pub struct SaveStateManager {
+    directory: PathBuf,
+    rom_hash: u32,
+    rom_key: String,
+    // The key from before titles were read with `CartridgeHeader`, for loading older states
+    legacy_rom_key: String,
+}
+
+impl SaveStateManager {
+    pub fn new(directory: impl Into<PathBuf>, core: &Core) -> Self {
+        // The first 15 bytes up to a zero, whatever the header says they are
+        let legacy_title: String = (0x134..0x143)
+            .map(|addr| core.read_byte(addr))
+            .take_while(|&b| b != 0)
+            .map(|b| b as char)
+            .collect();
+
+        Self {
+            directory: directory.into(),
+            rom_hash: core.rom_hash(),
+            rom_key: rom_key(&core.cartridge_header().title(), core.rom_hash()),
+            legacy_rom_key: rom_key(&legacy_title, core.rom_hash()),
+        }
+    }
+
+    pub fn slot_path(&self, slot: u8) -> PathBuf {
+        self.directory.join(format!("{}.ss{}", self.rom_key, slot))
+    }
+
+    pub fn save_slot(&self, slot: u8, core: &Core) -> Result<(), StateError> {
+        fs::create_dir_all(&self.directory)?;
+        write_state_file(&self.slot_path(slot), core)
+    }
+
+    pub fn load_slot(&self, slot: u8) -> Result<Core, StateError> {
+        read_state_file(&self.existing_path(&format!("ss{}", slot)), Some(self.rom_hash))
+    }
+
+    pub fn autosave_path(&self) -> PathBuf {
+        self.directory.join(format!("{}.auto", self.rom_key))
+    }
+
+    pub fn save_autosave(&self, core: &Core) -> Result<(), StateError> {
+        fs::create_dir_all(&self.directory)?;
+        write_state_file(&self.autosave_path(), core)
+    }
+
+    /// Fails with `StateError::RomMismatch` unless the autosave was made with the same ROM.
+    pub fn load_autosave(&self) -> Result<Core, StateError> {
+        read_state_file(&self.existing_path("auto"), Some(self.rom_hash))
+    }
+
+    // The file with this extension, falling back on the legacy name when only that exists
+    fn existing_path(&self, extension: &str) -> PathBuf {
+        let path = self.directory.join(format!("{}.{}", self.rom_key, extension));
+        let legacy = self.directory.join(format!("{}.{}", self.legacy_rom_key, extension));
+        if !path.exists() && legacy.exists() {
+            legacy
+        } else {
+            path
+        }
+    }
+}
+
+fn rom_key(title: &str, rom_hash: u32) -> String {
+    let title: String = title.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    format!("{}-{:08x}", title, rom_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::core::{Core, CoreBuilder};
+    use std::{env, fs};
+
+    use crate::emulator::state::{decode_state, encode_state, with_header, SaveStateManager, StateError, HEADER_SIZE, STATE_VERSION};
+    use crate::error::Error;
+
+    #[test]
+    fn state_round_trips() {
+        let core = Core::load_without_boot_rom(None).unwrap();
+        let data = encode_state(&core).unwrap();
+
+        assert!(decode_state(&data, Some(core.rom_hash())).is_ok());
+    }
+
+    #[test]
+    fn state_round_trips_through_reader_and_writer() {
+        let mut core = Core::load_without_boot_rom(None).unwrap();
+        let mut data = vec![];
+
+        core.save_state_to(&mut data).unwrap();
+
+        assert!(core.load_state_from(data.as_slice()).is_ok());
+    }
+
+    #[test]
+    fn corrupt_state_is_rejected() {
+        let core = Core::load_without_boot_rom(None).unwrap();
+        let mut data = encode_state(&core).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0xff;
+
+        assert!(matches!(decode_state(&data, None), Err(StateError::ChecksumMismatch)));
+    }
+
+    #[test]
+    fn corrupt_state_leaves_the_core_untouched() {
+        let mut core = Core::load_without_boot_rom(None).unwrap();
+        core.enable_coverage();
+        let mut data = encode_state(&core).unwrap();
+        data[HEADER_SIZE] ^= 0xff;
+        core.write_byte(0xc000, 0x42);
+        let hash = core.state_hash();
+
+        let result = core.load_state_from(data.as_slice());
+
+        assert!(matches!(result, Err(Error::StateLoad(StateError::ChecksumMismatch))));
+        assert_eq!(core.state_hash(), hash);
+        assert!(core.coverage().is_some());
+    }
+
+    #[test]
+    fn state_restores_battery_ram_as_written() {
+        // MBC1+RAM+BATTERY with 8K of RAM
+        let mut rom = vec![0; 0x8000];
+        rom[0x147] = 0x03;
+        rom[0x149] = 0x02;
+        let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+        core.write_byte(0x0000, 0x0a);
+        core.write_byte(0xa000, 0x42);
+        assert!(core.take_battery_ram_written());
+
+        let mut decoded = decode_state(&encode_state(&core).unwrap(), None).unwrap();
+
+        assert_eq!(decoded.battery_ram().unwrap()[0], 0x42);
+        assert!(decoded.take_battery_ram_written());
+    }
+
+    #[test]
+    fn state_for_other_rom_is_rejected() {
+        let core = Core::load_without_boot_rom(None).unwrap();
+        let data = encode_state(&core).unwrap();
+
+        assert!(matches!(decode_state(&data, Some(!core.rom_hash())), Err(StateError::RomMismatch { .. })));
+        assert!(matches!(decode_state(b"not a state", None), Err(StateError::NotAState)));
+    }
+
+    #[test]
+    fn version_1_state_without_apu_is_upgraded() {
+        let core = Core::load_without_boot_rom(None).unwrap();
+
+        let mut value = serde_cbor::value::to_value(&core).unwrap();
+        if let serde_cbor::Value::Map(fields) = &mut value {
+            if let Some(serde_cbor::Value::Map(bus)) = fields.get_mut(&serde_cbor::Value::Text("bus".into())) {
+                bus.remove(&serde_cbor::Value::Text("apu".into()));
+            }
+        }
+        let data = with_header(1, core.rom_hash(), &serde_cbor::to_vec(&value).unwrap());
+
+        let upgraded = decode_state(&data, None).unwrap();
+        assert_eq!(upgraded.read_byte(0xff26) & 0x80, 0x80);
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let core = Core::load_without_boot_rom(None).unwrap();
+        let data = with_header(STATE_VERSION + 1, core.rom_hash(), &serde_cbor::to_vec(&core).unwrap());
+
+        assert!(matches!(decode_state(&data, None), Err(StateError::UnsupportedVersion(v)) if v == STATE_VERSION + 1));
+    }
+
+    #[test]
+    fn states_under_the_legacy_name_still_load() {
+        // A DMG game with a 16 byte title, which used to lose its last letter
+        let mut rom = vec![0; 0x8000];
+        rom[0x134..0x144].copy_from_slice(b"SUPER MARIOLAND2");
+        let core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+        let directory = env::temp_dir().join("states_under_the_legacy_name_still_load");
+        let _ = fs::remove_dir_all(&directory);
+        let states = SaveStateManager::new(&directory, &core);
+
+        let legacy = directory.join(format!("SUPER_MARIOLAND-{:08x}.ss1", core.rom_hash()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(&legacy, encode_state(&core).unwrap()).unwrap();
+
+        assert_eq!(states.slot_path(1), directory.join(format!("SUPER_MARIOLAND2-{:08x}.ss1", core.rom_hash())));
+        assert!(states.load_slot(1).is_ok());
+        assert!(states.load_slot(2).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::dmg::core::Core;
+use crate::dmg::input::JoypadInput;
+use crate::dmg::traits::{NullAudioSink, NullVideoSink};
+
+// Registers the mooneye test ROMs set before their final `ld b, b`
+const MOONEYE_PASSED: [u8; 6] = [3, 5, 8, 13, 21, 34];
+const MOONEYE_FAILED: [u8; 6] = [0x42; 6];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestResult {
+    /// "Passed" over serial, as Blargg's ROMs print, or the mooneye pass register signature.
+    Passed,
+    /// "Failed" over serial or the mooneye failure signature, with what was printed so far.
+    Failed(String),
+    /// Spinning on `jr -2` without either signal, as ROMs that only report on screen do.
+    Finished,
+    /// No completion signal within the timeout, with what was printed so far.
+    TimedOut(String),
+}
+
+/// Runs the test ROM at `path` without a boot ROM until it signals completion, checking once
+/// per frame for up to `timeout_frames` frames.
+pub fn run_test_rom(path: &str, timeout_frames: u32) -> crate::Result<TestResult> {
+    let mut core = Core::load_without_boot_rom(Some(path.to_string()))?;
+    let mut serial = String::new();
+
+    for _ in 0..timeout_frames {
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+        serial.extend(core.take_serial_output().into_iter().map(char::from));
+
+        if serial.contains("Passed") {
+            return Ok(TestResult::Passed);
+        }
+        if serial.contains("Failed") {
+            return Ok(TestResult::Failed(serial));
+        }
+
+        let pc = core.registers().pc;
+        if (core.read_byte(pc), core.read_byte(pc.wrapping_add(1))) == (0x18, 0xfe) {
+            let r = core.registers();
+            return Ok(match [r.b, r.c, r.d, r.e, r.h, r.l] {
+                MOONEYE_PASSED => TestResult::Passed,
+                MOONEYE_FAILED => TestResult::Failed(serial),
+                _ => TestResult::Finished,
+            });
+        }
+    }
+
+    Ok(TestResult::TimedOut(serial))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use crate::emulator::test_rom::{run_test_rom, TestResult};
+    use crate::test_support::looping_rom;
+
+    // `looping_rom(program)` written to a file for `run_test_rom`
+    fn rom_with(name: &str, program: &[u8]) -> String {
+        let path = env::temp_dir().join(name);
+        fs::write(&path, looping_rom(program)).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    // Sends `text` over serial
+    fn serial_program(text: &str) -> Vec<u8> {
+        text.bytes().flat_map(|b| [0x3e, b, 0xe0, 0x01, 0x3e, 0x81, 0xe0, 0x02]).collect()
+    }
+
+    // Loads B, C, D, E, H and L, then does `ld b, b`
+    fn register_program(values: [u8; 6]) -> Vec<u8> {
+        let mut program: Vec<u8> = [0x06, 0x0e, 0x16, 0x1e, 0x26, 0x2e].into_iter().zip(values).flat_map(|(op, v)| [op, v]).collect();
+        program.push(0x40);
+        program
+    }
+
+    #[test]
+    fn detects_serial_results() {
+        let passed = rom_with("detects_serial_results_passed.gb", &serial_program("cpu_instrs\nPassed\n"));
+        let failed = rom_with("detects_serial_results_failed.gb", &serial_program("Failed #3\n"));
+
+        assert_eq!(run_test_rom(&passed, 10).unwrap(), TestResult::Passed);
+        assert_eq!(run_test_rom(&failed, 10).unwrap(), TestResult::Failed("Failed #3\n".to_string()));
+    }
+
+    #[test]
+    fn detects_mooneye_signatures() {
+        let passed = rom_with("detects_mooneye_signatures_passed.gb", &register_program([3, 5, 8, 13, 21, 34]));
+        let failed = rom_with("detects_mooneye_signatures_failed.gb", &register_program([0x42; 6]));
+
+        assert_eq!(run_test_rom(&passed, 10).unwrap(), TestResult::Passed);
+        assert_eq!(run_test_rom(&failed, 10).unwrap(), TestResult::Failed(String::new()));
+    }
+
+    #[test]
+    fn detects_loops_and_timeouts() {
+        let finished = rom_with("detects_loops_and_timeouts_finished.gb", &[]);
+        // nop, then `jr -3` back to it
+        let running = rom_with("detects_loops_and_timeouts_running.gb", &[0x00, 0x18, 0xfd]);
+
+        assert_eq!(run_test_rom(&finished, 10).unwrap(), TestResult::Finished);
+        assert_eq!(run_test_rom(&running, 10).unwrap(), TestResult::TimedOut(String::new()));
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::VecDeque;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::dmg::traits::AudioSink;
+
+// Average over about half a second of frames
+const WINDOW: usize = 30;
+
+// How often the host frame rate is recalculated
+const FPS_INTERVAL: Duration = Duration::from_secs(1);
+
+// Falling further behind than this starts pacing over rather than catching up in a burst
+const MAX_LAG: Duration = Duration::from_millis(100);
+
+// Audio kept queued ahead of the device when pacing by it: enough to cover a late frame without
+// running dry, too little to hear as latency
+const AUDIO_AHEAD: Duration = Duration::from_millis(25);
+
+/// Host time spent per emulated frame, averaged over the last few frames, and the rate frames
+/// are shown at on the host.
+pub struct TimingStats {
+    // Host and emulated time of each frame
+    frame_times: VecDeque<(Duration, Duration)>,
+    last_frame: Option<(Instant, Duration)>,
+    presented_frames: u32,
+    fps_interval_start: Instant,
+    host_fps: f64,
+}
+
+impl Default for TimingStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimingStats {
+    pub fn new() -> Self {
+        Self {
+            frame_times: VecDeque::with_capacity(WINDOW),
+            last_frame: None,
+            presented_frames: 0,
+            fps_interval_start: Instant::now(),
+            host_fps: 0.0,
+        }
+    }
+
+    /// Call once per emulated frame with `Core::emulated_time`; the host and emulated time
+    /// between calls are recorded.
+    pub fn frame_finished(&mut self, emulated_time: Duration) {
+        let now = Instant::now();
+        if let Some((last, last_emulated)) = self.last_frame.replace((now, emulated_time)) {
+            // Rewinding and loading states move emulated time backwards
+            self.record_frame(now - last, emulated_time.saturating_sub(last_emulated));
+        }
+    }
+
+    pub fn record_frame(&mut self, host_time: Duration, emulated_time: Duration) {
+        if self.frame_times.len() == WINDOW {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back((host_time, emulated_time));
+    }
+
+    /// Call once per frame shown on the host. Returns true about once a second, when `host_fps`
+    /// has been updated.
+    pub fn frame_presented(&mut self) -> bool {
+        self.frame_presented_at(Instant::now())
+    }
+
+    fn frame_presented_at(&mut self, now: Instant) -> bool {
+        self.presented_frames += 1;
+
+        let elapsed = now.saturating_duration_since(self.fps_interval_start);
+        if elapsed < FPS_INTERVAL {
+            return false;
+        }
+
+        self.host_fps = self.presented_frames as f64 / elapsed.as_secs_f64();
+        self.presented_frames = 0;
+        self.fps_interval_start = now;
+        true
+    }
+
+    /// Frames shown per second on the host over the last full second.
+    pub fn host_fps(&self) -> f64 {
+        self.host_fps
+    }
+
+    pub fn host_ms_per_frame(&self) -> f64 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+        let total: Duration = self.frame_times.iter().map(|&(host, _)| host).sum();
+        total.as_secs_f64() * 1000.0 / self.frame_times.len() as f64
+    }
+
+    /// Emulation speed relative to real hardware, 100 meaning full speed.
+    pub fn speed_percent(&self) -> f64 {
+        let host: Duration = self.frame_times.iter().map(|&(host, _)| host).sum();
+        if host.is_zero() {
+            return 0.0;
+        }
+        let emulated: Duration = self.frame_times.iter().map(|&(_, emulated)| emulated).sum();
+        emulated.as_secs_f64() / host.as_secs_f64() * 100.0
+    }
+}
+
+/// Keeps emulation in step with the host's monotonic clock by the emulated time that has passed,
+/// so the game runs at the true 59.73 Hz rather than whatever rate the display or a frame rate cap
+/// gives. Pacing starts over after a pause, a jump back in emulated time or a change of speed.
+#[derive(Debug, Default)]
+pub struct FramePacer {
+    // Host and emulated time pacing is counted from
+    origin: Option<(Instant, Duration)>,
+    speed: f64,
+}
+
+impl FramePacer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sleeps until the host clock has caught up with `emulated_time` at `speed` times normal
+    /// speed. Call after each batch of emulated frames with `Core::emulated_time`.
+    pub fn wait(&mut self, emulated_time: Duration, speed: f64) {
+        let now = Instant::now();
+        if let Some(deadline) = self.deadline(now, emulated_time, speed) {
+            thread::sleep(deadline - now);
+        }
+    }
+
+    /// When the host should be at `emulated_time`, if that's still to come.
+    fn deadline(&mut self, now: Instant, emulated_time: Duration, speed: f64) -> Option<Instant> {
+        let target = match self.origin {
+            Some((host, emulated)) if speed == self.speed && emulated_time >= emulated => {
+                Some(host + (emulated_time - emulated).div_f64(speed))
+            }
+            _ => None,
+        };
+
+        match target {
+            Some(target) if target + MAX_LAG >= now => (target > now).then_some(target),
+            _ => {
+                self.origin = Some((now, emulated_time));
+                self.speed = speed;
+                None
+            }
+        }
+    }
+}
+
+/// Paces emulation by the audio device rather than the host clock, by waiting for the samples
+/// queued ahead of it to drain to a few milliseconds' worth. The device's clock sets the speed, so
+/// the sound neither runs dry and crackles nor backs up, whatever the display's refresh rate.
+#[derive(Debug)]
+pub struct AudioPacer {
+    sample_rate: u32,
+}
+
+impl AudioPacer {
+    /// A pacer for a device playing `sample_rate` samples per second.
+    pub fn new(sample_rate: u32) -> Self {
+        Self { sample_rate }
+    }
+
+    /// Sleeps until `sink` has played its queue down to `AUDIO_AHEAD`. Call after each batch of
+    /// emulated frames. Returns false without waiting for sinks that don't report
+    /// `AudioSink::queued_samples`, which need pacing some other way.
+    pub fn wait(&self, sink: &(impl AudioSink + ?Sized)) -> bool {
+        let Some(queued) = sink.queued_samples() else {
+            return false;
+        };
+
+        if let Some(duration) = self.wait_time(queued) {
+            thread::sleep(duration);
+        }
+        true
+    }
+
+    // How long `queued` samples take to drain to `AUDIO_AHEAD`, if they're above it
+    fn wait_time(&self, queued: usize) -> Option<Duration> {
+        let ahead = (AUDIO_AHEAD.as_secs_f64() * self.sample_rate as f64) as usize;
+        (queued > ahead).then(|| Duration::from_secs_f64((queued - ahead) as f64 / self.sample_rate as f64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::dmg::core::FRAME_DURATION;
+    use crate::emulator::timing::{AudioPacer, FramePacer, TimingStats};
+
+    #[test]
+    fn pacer_follows_emulated_time() {
+        let mut pacer = FramePacer::new();
+        let start = Instant::now();
+
+        assert_eq!(pacer.deadline(start, FRAME_DURATION, 1.0), None);
+        assert_eq!(pacer.deadline(start, FRAME_DURATION * 2, 1.0), Some(start + FRAME_DURATION));
+        // Running late, but not so late as to start over
+        let late = start + FRAME_DURATION * 3 + Duration::from_millis(1);
+        assert_eq!(pacer.deadline(late, FRAME_DURATION * 4, 1.0), None);
+        assert_eq!(pacer.deadline(late, FRAME_DURATION * 5, 1.0), Some(start + FRAME_DURATION * 4));
+
+        // Twice as fast counts from where the speed changed
+        assert_eq!(pacer.deadline(late, FRAME_DURATION * 5, 2.0), None);
+        assert_eq!(pacer.deadline(late, FRAME_DURATION * 7, 2.0), Some(late + FRAME_DURATION));
+    }
+
+    #[test]
+    fn pacer_starts_over_after_pauses_and_rewinds() {
+        let mut pacer = FramePacer::new();
+        let start = Instant::now();
+        pacer.deadline(start, FRAME_DURATION * 10, 1.0);
+
+        let resumed = start + Duration::from_secs(5);
+        assert_eq!(pacer.deadline(resumed, FRAME_DURATION * 11, 1.0), None);
+        assert_eq!(pacer.deadline(resumed, FRAME_DURATION * 12, 1.0), Some(resumed + FRAME_DURATION));
+
+        assert_eq!(pacer.deadline(resumed, FRAME_DURATION * 2, 1.0), None);
+        assert_eq!(pacer.deadline(resumed, FRAME_DURATION * 3, 1.0), Some(resumed + FRAME_DURATION));
+    }
+
+    #[test]
+    fn audio_pacer_waits_for_the_queue_to_drain() {
+        let pacer = AudioPacer::new(40_000);
+
+        // 25 ms at 40 kHz is 1000 samples
+        assert_eq!(pacer.wait_time(1000), None);
+        assert_eq!(pacer.wait_time(500), None);
+        assert_eq!(pacer.wait_time(1400), Some(Duration::from_millis(10)));
+
+        assert!(!pacer.wait(&Vec::new()));
+    }
+
+    #[test]
+    fn full_speed_is_100_percent() {
+        let mut stats = TimingStats::new();
+        for _ in 0..10 {
+            stats.record_frame(FRAME_DURATION, FRAME_DURATION);
+        }
+
+        assert!((stats.speed_percent() - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn speed_follows_emulated_time() {
+        let mut stats = TimingStats::new();
+        // Frames with the LCD off can run longer than a frame
+        for _ in 0..10 {
+            stats.record_frame(FRAME_DURATION, FRAME_DURATION * 2);
+        }
+
+        assert!((stats.speed_percent() - 200.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn averages_over_recent_frames() {
+        let mut stats = TimingStats::new();
+        for _ in 0..100 {
+            stats.record_frame(Duration::from_millis(40), FRAME_DURATION);
+        }
+        for _ in 0..30 {
+            stats.record_frame(Duration::from_millis(10), FRAME_DURATION);
+        }
+
+        assert!((stats.host_ms_per_frame() - 10.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn host_fps_updates_once_a_second() {
+        let mut stats = TimingStats::new();
+        let start = stats.fps_interval_start;
+
+        for frame in 1..60 {
+            assert!(!stats.frame_presented_at(start + Duration::from_millis(frame * 16)));
+        }
+        assert!(stats.frame_presented_at(start + Duration::from_millis(1000)));
+
+        assert!((stats.host_fps() - 60.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::dmg::input::JoypadInput;
+
+/// Auto-fire for held buttons. Turbo buttons are pressed for `frames_on` frames
+/// and released for `frames_off` frames, repeating for as long as they are held.
+pub struct Turbo {
+    frames_on: u32,
+    frames_off: u32,
+    frame: u32,
+}
+
+impl Default for Turbo {
+    fn default() -> Self {
+        Self::new(2, 2)
+    }
+}
+
+impl Turbo {
+    pub fn new(frames_on: u32, frames_off: u32) -> Self {
+        Self {
+            frames_on: frames_on.max(1),
+            frames_off,
+            frame: 0,
+        }
+    }
+
+    fn is_pressed(&self) -> bool {
+        self.frame < self.frames_on
+    }
+
+    /// Merges the turbo buttons into the regularly held buttons for the current frame.
+    pub fn apply(&self, keys: JoypadInput, turbo_keys: JoypadInput) -> JoypadInput {
+        if self.is_pressed() {
+            keys | turbo_keys
+        } else {
+            keys
+        }
+    }
+
+    pub fn next_frame(&mut self) {
+        self.frame = (self.frame + 1) % (self.frames_on + self.frames_off);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::input::JoypadInput;
+    use crate::emulator::turbo::Turbo;
+
+    #[test]
+    fn turbo_toggles_at_configured_rate() {
+        let mut turbo = Turbo::new(2, 1);
+        let mut pressed = vec![];
+
+        for _ in 0..6 {
+            pressed.push(turbo.apply(JoypadInput::empty(), JoypadInput::A).contains(JoypadInput::A));
+            turbo.next_frame();
+        }
+
+        assert_eq!(pressed, vec![true, true, false, true, true, false]);
+    }
+
+    #[test]
+    fn turbo_keeps_held_keys() {
+        let turbo = Turbo::new(1, 1);
+
+        assert_eq!(turbo.apply(JoypadInput::B, JoypadInput::A), JoypadInput::A | JoypadInput::B);
+    }
+}
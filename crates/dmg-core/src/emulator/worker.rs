@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use crate::dmg::core::{Core, FRAME_DURATION};
+use crate::dmg::frame::Frame;
+use crate::dmg::input::JoypadInput;
+
+/// Runs a core on its own thread at the Game Boy's frame rate, independent of how fast the UI
+/// thread presents frames. Frames the UI has not picked up in time are dropped.
+pub struct EmulationThread {
+    input: Sender<JoypadInput>,
+    frames: Receiver<Frame>,
+    running: Arc<AtomicBool>,
+    handle: JoinHandle<Core>,
+}
+
+impl EmulationThread {
+    pub fn spawn(core: Core) -> Self {
+        let (input, input_rx) = mpsc::channel();
+        let (frames_tx, frames) = mpsc::sync_channel(2);
+        let running = Arc::new(AtomicBool::new(true));
+
+        let handle = {
+            let running = running.clone();
+            thread::spawn(move || emulate(core, input_rx, frames_tx, running))
+        };
+
+        Self { input, frames, running, handle }
+    }
+
+    /// Holds `keys` from the next frame on.
+    pub fn set_input(&self, keys: JoypadInput) {
+        let _ = self.input.send(keys);
+    }
+
+    /// Returns the oldest frame not yet picked up, if any.
+    pub fn try_recv_frame(&self) -> Option<Frame> {
+        self.frames.try_recv().ok()
+    }
+
+    pub fn recv_frame(&self) -> Option<Frame> {
+        self.frames.recv().ok()
+    }
+
+    /// Stops emulation and hands back the core, e.g. for saving its state.
+    pub fn stop(self) -> Core {
+        self.running.store(false, Ordering::Relaxed);
+        drop(self.frames);
+        self.handle.join().expect("emulation thread panicked")
+    }
+}
+
+fn emulate(mut core: Core, input: Receiver<JoypadInput>, frames: SyncSender<Frame>, running: Arc<AtomicBool>) -> Core {
+    let mut keys_pressed = JoypadInput::empty();
+    let mut next_frame = Instant::now();
+
+    while running.load(Ordering::Relaxed) {
+        loop {
+            match input.try_recv() {
+                Ok(keys) => keys_pressed = keys,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return core,
+            }
+        }
+
+        let frame = core.run_frame(keys_pressed).clone();
+        match frames.try_send(frame) {
+            Ok(_) | Err(TrySendError::Full(_)) => {}
+            Err(TrySendError::Disconnected(_)) => break,
+        }
+
+        next_frame += FRAME_DURATION;
+        let now = Instant::now();
+        if next_frame > now {
+            thread::sleep(next_frame - now);
+        } else {
+            // Fell behind, don't try to catch up with a burst of frames
+            next_frame = now;
+        }
+    }
+
+    core
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dmg::core::CoreBuilder;
+    use crate::dmg::frame::{SCREEN_HEIGHT, SCREEN_WIDTH};
+    use crate::emulator::worker::EmulationThread;
+    use crate::test_support::looping_rom;
+
+    #[test]
+    fn emulates_on_worker_thread() {
+        let core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+        let rom_hash = core.rom_hash();
+
+        let worker = EmulationThread::spawn(core);
+        let frame = worker.recv_frame().unwrap();
+        let core = worker.stop();
+
+        assert_eq!(frame.pixels.len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+        assert_eq!(core.rom_hash(), rom_hash);
+    }
+}
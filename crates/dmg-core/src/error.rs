@@ -0,0 +1,39 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::emulator::state::StateError;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed reading ROM {path}: {source}")]
+    RomLoad { path: String, source: io::Error },
+    #[error("a boot ROM is required but none was given")]
+    BootRomRequired,
+    #[error("boot ROM must be 256 (DMG) or 2304 (CGB) bytes, found {0}")]
+    InvalidBootRom(usize),
+    #[error("unsupported cartridge type {0:#04X}")]
+    UnsupportedMapper(u8),
+    #[error("failed initializing audio: {0}")]
+    AudioInit(String),
+    #[error("failed reading symbols {path}: {source}")]
+    SymbolLoad { path: String, source: io::Error },
+    #[error("failed reading palette {path}: {source}")]
+    PaletteLoad { path: String, source: io::Error },
+    #[error("invalid palette: {0}")]
+    InvalidPalette(String),
+    #[error("barcode must be 13 digits, found {0:?}")]
+    InvalidBarcode(String),
+    #[error("invalid expression: {0}")]
+    InvalidExpression(String),
+    #[error("bad cycle log line {line}: {reason}")]
+    CycleLog { line: usize, reason: String },
+    #[error("bad link session line {line}: {reason}")]
+    LinkSession { line: usize, reason: String },
+    #[error("failed loading save state: {0}")]
+    StateLoad(#[from] StateError),
+    #[error("failed writing save state: {0}")]
+    StateSave(StateError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,162 @@
+//! C API for embedding the emulator. `build.rs` generates the matching declarations in
+//! `include/gameboy.h` from this file with cbindgen.
+
+use std::ffi::{c_char, c_int, CStr};
+use std::fs::{self, File};
+use std::ptr;
+
+use crate::dmg::core::Core;
+use crate::dmg::frame::Frame;
+use crate::dmg::input::JoypadInput;
+
+pub const GB_SCREEN_WIDTH: u32 = 160;
+pub const GB_SCREEN_HEIGHT: u32 = 144;
+
+// The `JoypadInput` bits
+pub const GB_BUTTON_DOWN: u8 = 1 << 0;
+pub const GB_BUTTON_LEFT: u8 = 1 << 1;
+pub const GB_BUTTON_UP: u8 = 1 << 2;
+pub const GB_BUTTON_RIGHT: u8 = 1 << 3;
+pub const GB_BUTTON_START: u8 = 1 << 4;
+pub const GB_BUTTON_SELECT: u8 = 1 << 5;
+pub const GB_BUTTON_A: u8 = 1 << 6;
+pub const GB_BUTTON_B: u8 = 1 << 7;
+
+pub struct GbHandle {
+    core: Core,
+    input: JoypadInput,
+    frame: Frame,
+}
+
+fn path_from(path: *const c_char) -> Option<String> {
+    if path.is_null() {
+        return None;
+    }
+    unsafe { CStr::from_ptr(path) }.to_str().ok().map(str::to_string)
+}
+
+/// Creates an emulator without a cartridge. Returns null if it could not be created.
+#[no_mangle]
+pub extern "C" fn gb_create() -> *mut GbHandle {
+    match Core::load_without_boot_rom(None) {
+        Ok(core) => Box::into_raw(Box::new(GbHandle { core, input: JoypadInput::empty(), frame: Frame::default() })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `gb` must come from `gb_create` and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn gb_destroy(gb: *mut GbHandle) {
+    if !gb.is_null() {
+        drop(Box::from_raw(gb));
+    }
+}
+
+/// Inserts the cartridge in a ROM file and restarts emulation. Returns 0 on success and -1 on
+/// failure.
+///
+/// # Safety
+/// `gb` must come from `gb_create` and `path` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn gb_load_rom(gb: *mut GbHandle, path: *const c_char) -> c_int {
+    let (Some(gb), Some(path)) = (gb.as_mut(), path_from(path)) else {
+        return -1;
+    };
+
+    match fs::read(path).map(|rom| gb.core.insert_cartridge(rom)) {
+        Ok(Ok(())) => 0,
+        _ => -1,
+    }
+}
+
+/// Sets the buttons held down from the next frame on, using the `GB_BUTTON_*` bits.
+///
+/// # Safety
+/// `gb` must come from `gb_create`.
+#[no_mangle]
+pub unsafe extern "C" fn gb_set_input(gb: *mut GbHandle, buttons: u8) {
+    if let Some(gb) = gb.as_mut() {
+        gb.input = JoypadInput::from_bits_truncate(buttons);
+    }
+}
+
+/// Emulates one frame.
+///
+/// # Safety
+/// `gb` must come from `gb_create`.
+#[no_mangle]
+pub unsafe extern "C" fn gb_run_frame(gb: *mut GbHandle) {
+    if let Some(gb) = gb.as_mut() {
+        gb.frame.clone_from(gb.core.run_frame(gb.input));
+    }
+}
+
+/// Returns the last frame as 160x144 0RGB pixels. The pointer is valid until the next
+/// `gb_run_frame` or `gb_destroy`.
+///
+/// # Safety
+/// `gb` must come from `gb_create`.
+#[no_mangle]
+pub unsafe extern "C" fn gb_get_framebuffer(gb: *const GbHandle) -> *const u32 {
+    match gb.as_ref() {
+        Some(gb) => gb.frame.pixels.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Copies up to `max_samples` interleaved stereo samples from the last frame into `out` and
+/// returns how many samples were copied.
+///
+/// # Safety
+/// `gb` must come from `gb_create` and `out` must have room for `max_samples * 2` floats.
+#[no_mangle]
+pub unsafe extern "C" fn gb_get_audio(gb: *const GbHandle, out: *mut f32, max_samples: usize) -> usize {
+    let Some(gb) = gb.as_ref() else {
+        return 0;
+    };
+    if out.is_null() {
+        return 0;
+    }
+
+    let count = gb.frame.audio.len().min(max_samples);
+    let out = std::slice::from_raw_parts_mut(out, count * 2);
+    for (i, (left, right)) in gb.frame.audio[..count].iter().enumerate() {
+        out[i * 2] = *left;
+        out[i * 2 + 1] = *right;
+    }
+    count
+}
+
+/// Writes a save state to `path`. Returns 0 on success and -1 on failure.
+///
+/// # Safety
+/// `gb` must come from `gb_create` and `path` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn gb_save_state(gb: *const GbHandle, path: *const c_char) -> c_int {
+    let (Some(gb), Some(path)) = (gb.as_ref(), path_from(path)) else {
+        return -1;
+    };
+
+    match File::create(path).map(|f| gb.core.save_state_to(f)) {
+        Ok(Ok(())) => 0,
+        _ => -1,
+    }
+}
+
+/// Restores a save state written by `gb_save_state` for the same ROM. Returns 0 on success and
+/// -1 on failure.
+///
+/// # Safety
+/// `gb` must come from `gb_create` and `path` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn gb_load_state(gb: *mut GbHandle, path: *const c_char) -> c_int {
+    let (Some(gb), Some(path)) = (gb.as_mut(), path_from(path)) else {
+        return -1;
+    };
+
+    match File::open(path).map(|f| gb.core.load_state_from(f)) {
+        Ok(Ok(())) => 0,
+        _ => -1,
+    }
+}
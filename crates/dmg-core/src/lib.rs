@@ -0,0 +1,12 @@
+pub mod dmg;
+pub mod emulator;
+pub mod error;
+pub mod ffi;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+
+pub use error::{Error, Result};
+
+// What every frontend needs to size its window and pace its frames
+pub use dmg::core::{CLOCK_SPEED, CYCLES_PER_FRAME, FRAME_DURATION, REFRESH_RATE};
+pub use dmg::frame::{Frame, FrameBuffer, SCREEN_HEIGHT, SCREEN_WIDTH};
@@ -0,0 +1,11 @@
+//! Helpers shared by the tests, and by the frontend's tests with the `test-support` feature.
+
+/// A 32K ROM that runs `program` from the entry point, then spins on `jr -2`. For
+/// `CoreBuilder::game_rom_data`; header fields can be set on the result.
+pub fn looping_rom(program: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0; 0x8000];
+    let end = 0x100 + program.len();
+    rom[0x100..end].copy_from_slice(program);
+    rom[end..end + 2].copy_from_slice(&[0x18, 0xfe]);
+    rom
+}
@@ -0,0 +1,35 @@
+//! Comparison of rendered frames with reference screenshots from other projects' test suites.
+
+use std::path::Path;
+
+use dmg::dmg::frame::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// The DMG shade (0 is lightest) closest to a pixel's luma, so that frames compare the same no
+/// matter which colors the palette uses.
+fn shade(r: u8, g: u8, b: u8) -> u8 {
+    let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+    3 - ((luma + 32) / 85).min(3) as u8
+}
+
+/// Compares a frame of 0RGB pixels with the reference image at `path` by shade, and describes
+/// where they differ.
+pub fn compare_with_reference(pixels: &[u32], path: &Path) -> Result<(), String> {
+    let reference = image::open(path).map_err(|e| format!("Failed reading {}: {}", path.display(), e))?.to_rgb8();
+    if reference.dimensions() != (SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32) {
+        return Err(format!("{} is {:?}, not the screen size", path.display(), reference.dimensions()));
+    }
+
+    let mismatches: Vec<(u32, u32)> = reference
+        .enumerate_pixels()
+        .filter(|&(x, y, expected)| {
+            let [_, r, g, b] = pixels[y as usize * SCREEN_WIDTH + x as usize].to_be_bytes();
+            shade(r, g, b) != shade(expected[0], expected[1], expected[2])
+        })
+        .map(|(x, y, _)| (x, y))
+        .collect();
+
+    match mismatches.first() {
+        Some(first) => Err(format!("{} pixels differ from {}, first at {:?}", mismatches.len(), path.display(), first)),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,37 @@
+//! Renders dmg-acid2 (https://github.com/mattcurrie/dmg-acid2) and compares the result with the
+//! reference image from the same repository. Copy `dmg-acid2.gb` to `tests/roms/` and
+//! `img/reference-dmg.png` to `tests/golden/dmg-acid2.png`, then run it with
+//! `cargo test --test dmg_acid2 -- --ignored`.
+
+use std::path::Path;
+
+use dmg::dmg::core::Core;
+use dmg::dmg::input::JoypadInput;
+use dmg::dmg::traits::{NullAudioSink, NullVideoSink};
+
+use common::compare_with_reference;
+
+mod common;
+
+const ROM: &str = "tests/roms/dmg-acid2.gb";
+const GOLDEN: &str = "tests/golden/dmg-acid2.png";
+
+// The test has drawn its final image and halted well before this
+const FRAMES: usize = 60;
+
+#[test]
+#[ignore = "needs tests/roms/dmg-acid2.gb and tests/golden/dmg-acid2.png"]
+fn dmg_acid2_matches_reference_image() {
+    for path in [ROM, GOLDEN] {
+        assert!(Path::new(path).exists(), "{} is missing", path);
+    }
+
+    let mut core = Core::load_without_boot_rom(Some(ROM.to_string())).unwrap();
+    for _ in 0..FRAMES {
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+    }
+
+    if let Err(e) = compare_with_reference(core.frame(), Path::new(GOLDEN)) {
+        panic!("{}", e);
+    }
+}
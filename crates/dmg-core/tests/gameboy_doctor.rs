@@ -0,0 +1,134 @@
+//! Runs the Blargg cpu_instrs ROMs in gameboy-doctor mode and compares the CPU log with the known
+//! good logs from gameboy-doctor (https://github.com/robert/gameboy-doctor), failing on the first
+//! line that differs. Copy the individual ROMs to `tests/roms/cpu_instrs/individual/` and unzip
+//! gameboy-doctor's `truth/zipped/cpu_instrs/<n>.zip` to `tests/golden/gameboy-doctor/cpu_instrs/`
+//! and run it with `cargo test --test gameboy_doctor -- --ignored`.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Lines, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use dmg::dmg::core::{Core, CLOCK_SPEED, CYCLES_PER_FRAME};
+use dmg::dmg::input::JoypadInput;
+use dmg::dmg::traits::{NullAudioSink, NullVideoSink};
+
+const ROM_DIR: &str = "tests/roms/cpu_instrs/individual";
+const LOG_DIR: &str = "tests/golden/gameboy-doctor/cpu_instrs";
+
+// The slowest ROM finishes in well under a minute of emulated time
+const MAX_CYCLES: u64 = 120 * CLOCK_SPEED;
+
+struct Divergence {
+    line: usize,
+    expected: String,
+    actual: String,
+}
+
+/// Compares log lines against the known good log as the core writes them.
+struct Comparison {
+    expected: Lines<BufReader<File>>,
+    line: usize,
+    partial_line: Vec<u8>,
+    divergence: Option<Divergence>,
+    finished: bool,
+}
+
+impl Comparison {
+    fn compare(&mut self, actual: String) {
+        if self.finished || self.divergence.is_some() {
+            return;
+        }
+
+        self.line += 1;
+        match self.expected.next() {
+            Some(Ok(expected)) if expected == actual => {}
+            Some(Ok(expected)) => self.divergence = Some(Divergence { line: self.line, expected, actual }),
+            Some(Err(e)) => panic!("Failed reading known good log: {}", e),
+            None => self.finished = true,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.finished || self.divergence.is_some()
+    }
+}
+
+#[derive(Clone)]
+struct DoctorChecker(Arc<Mutex<Comparison>>);
+
+impl Write for DoctorChecker {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut comparison = self.0.lock().unwrap();
+        comparison.partial_line.extend_from_slice(buf);
+
+        while let Some(end) = comparison.partial_line.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = comparison.partial_line.drain(..=end).collect();
+            comparison.compare(String::from_utf8_lossy(&line[..end]).into_owned());
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The ROM whose name starts with the number of the log, e.g. `03-op sp,hl.gb` for `3.log`.
+fn rom_for_log(log: &Path) -> Option<PathBuf> {
+    let number: u32 = log.file_stem()?.to_str()?.parse().ok()?;
+    let prefix = format!("{:02}-", number);
+
+    fs::read_dir(ROM_DIR)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with(&prefix)))
+}
+
+/// Runs `rom` until its log either matches all of `log` or differs from it.
+fn check_rom(rom: &Path, log: &Path) -> Result<(), String> {
+    let expected = BufReader::new(File::open(log).unwrap()).lines();
+    let checker = DoctorChecker(Arc::new(Mutex::new(Comparison { expected, line: 0, partial_line: vec![], divergence: None, finished: false })));
+
+    let mut core = Core::load_without_boot_rom(Some(rom.to_string_lossy().into_owned())).unwrap();
+    core.initialize_gameboy_doctor_with_log(checker.clone());
+
+    while !checker.0.lock().unwrap().is_done() && core.cycles_elapsed() < MAX_CYCLES {
+        core.run_cycles(CYCLES_PER_FRAME as u64, &mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+    }
+
+    let comparison = checker.0.lock().unwrap();
+    match &comparison.divergence {
+        Some(d) => Err(format!("line {} differs\n  expected: {}\n  actual:   {}", d.line, d.expected, d.actual)),
+        None if !comparison.finished => Err(format!("stopped after {} of the logged lines", comparison.line)),
+        None => Ok(()),
+    }
+}
+
+#[test]
+#[ignore = "needs the cpu_instrs ROMs in tests/roms/cpu_instrs/individual and gameboy-doctor's logs in tests/golden/gameboy-doctor/cpu_instrs"]
+fn cpu_instrs_match_gameboy_doctor_logs() {
+    for dir in [ROM_DIR, LOG_DIR] {
+        assert!(Path::new(dir).is_dir(), "{} is missing", dir);
+    }
+
+    let mut logs: Vec<PathBuf> = fs::read_dir(LOG_DIR).unwrap().filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| path.extension().is_some_and(|ext| ext == "log")).collect();
+    logs.sort();
+    assert!(!logs.is_empty(), "No logs in {}", LOG_DIR);
+
+    let mut failures = vec![];
+    for log in logs {
+        let Some(rom) = rom_for_log(&log) else {
+            failures.push(format!("{}: no matching ROM in {}", log.display(), ROM_DIR));
+            continue;
+        };
+
+        if let Err(e) = check_rom(&rom, &log) {
+            failures.push(format!("{}: {}", rom.display(), e));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
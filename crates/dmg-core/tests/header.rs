@@ -0,0 +1,21 @@
+//! Checks that `include/gameboy.h` matches the declarations cbindgen generates from `src/ffi.rs`.
+//! Run with `cargo test --features cbindgen --test header`, adding `UPDATE_SNAPSHOTS=1` to
+//! rewrite the header after changing the C API.
+#![cfg(feature = "cbindgen")]
+
+use std::env;
+use std::fs;
+
+const HEADER: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/include/gameboy.h");
+const GENERATED: &str = include_str!(concat!(env!("OUT_DIR"), "/gameboy.h"));
+
+#[test]
+fn header_matches_ffi() {
+    if env::var_os("UPDATE_SNAPSHOTS").is_some_and(|v| v == "1") {
+        fs::write(HEADER, GENERATED).unwrap();
+        return;
+    }
+
+    let header = fs::read_to_string(HEADER).unwrap();
+    assert!(header == GENERATED, "{} is out of date with src/ffi.rs, run with UPDATE_SNAPSHOTS=1 to regenerate it", HEADER);
+}
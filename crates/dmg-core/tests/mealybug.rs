@@ -0,0 +1,100 @@
+//! Runs the Mealybug Tearoom PPU tests (https://github.com/mattcurrie/mealybug-tearoom-tests)
+//! and compares the screen with their DMG reference images. Copy the ROMs to
+//! `tests/roms/mealybug/` and `expected/DMG-blob/*.png` to `tests/golden/mealybug/`, then run
+//! them with `cargo test --test mealybug -- --ignored`. Tests without a DMG image are skipped.
+//!
+//! Tests listed in `tests/mealybug_expected_failures.txt` are expected to fail, so the suite
+//! passes until a listed test starts passing (take it off the list) or another test fails.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use dmg::dmg::core::{Core, CLOCK_SPEED, CYCLES_PER_FRAME};
+use dmg::dmg::input::JoypadInput;
+use dmg::dmg::traits::{NullAudioSink, NullVideoSink};
+
+use common::compare_with_reference;
+
+mod common;
+
+const ROM_DIR: &str = "tests/roms/mealybug";
+const GOLDEN_DIR: &str = "tests/golden/mealybug";
+const EXPECTED_FAILURES: &str = "tests/mealybug_expected_failures.txt";
+
+// `ld b, b`, which the tests execute once the image is drawn
+const BREAKPOINT: u8 = 0x40;
+
+// Each test draws its image within a few frames
+const MAX_CYCLES: u64 = 10 * CLOCK_SPEED;
+
+// The two frames after the breakpoint, with some slack. No frame is drawn while the LCD is off
+const MAX_FRAME_CYCLES: u64 = 3 * CYCLES_PER_FRAME as u64;
+
+/// Runs `rom` to its breakpoint and returns the first whole frame drawn after it.
+fn run_to_breakpoint(rom: &Path) -> Result<Vec<u32>, String> {
+    let mut core = Core::load_without_boot_rom(Some(rom.to_string_lossy().into_owned())).map_err(|e| e.to_string())?;
+
+    while core.read_byte(core.registers().pc) != BREAKPOINT {
+        if core.cycles_elapsed() > MAX_CYCLES {
+            return Err("never reached the ld b, b breakpoint".to_string());
+        }
+        core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+    }
+    // The frame in progress may have started before the image was drawn, so take the next one
+    let deadline = core.cycles_elapsed() + MAX_FRAME_CYCLES;
+    for _ in 0..2 {
+        while !core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty()) {
+            if core.cycles_elapsed() > deadline {
+                return Err("drew no frame after the breakpoint, the LCD may be off".to_string());
+            }
+        }
+    }
+
+    Ok(core.frame().to_vec())
+}
+
+fn expected_failures() -> Vec<String> {
+    fs::read_to_string(EXPECTED_FAILURES)
+        .unwrap()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+#[ignore = "needs the Mealybug Tearoom ROMs in tests/roms/mealybug and their DMG images in tests/golden/mealybug"]
+fn mealybug_tearoom_results_match_expected_failures() {
+    for dir in [ROM_DIR, GOLDEN_DIR] {
+        assert!(Path::new(dir).is_dir(), "{} is missing", dir);
+    }
+
+    let mut roms: Vec<PathBuf> = fs::read_dir(ROM_DIR).unwrap().filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| path.extension().is_some_and(|ext| ext == "gb")).collect();
+    roms.sort();
+
+    let expected_failures = expected_failures();
+    let (mut compared, mut passed, mut unexpected) = (0, 0, vec![]);
+
+    for rom in roms {
+        let name = rom.file_stem().unwrap().to_string_lossy().into_owned();
+        let golden = Path::new(GOLDEN_DIR).join(format!("{}.png", name));
+        if !golden.exists() {
+            continue;
+        }
+        compared += 1;
+
+        let result = run_to_breakpoint(&rom).and_then(|pixels| compare_with_reference(&pixels, &golden));
+        let expected_to_fail = expected_failures.contains(&name);
+        match result {
+            Ok(()) if expected_to_fail => unexpected.push(format!("{}: passes, remove it from {}", name, EXPECTED_FAILURES)),
+            Ok(()) => passed += 1,
+            Err(e) if !expected_to_fail => unexpected.push(format!("{}: {}", name, e)),
+            Err(_) => {}
+        }
+    }
+
+    assert!(compared > 0, "No ROM in {} has an image in {}", ROM_DIR, GOLDEN_DIR);
+    eprintln!("Mealybug Tearoom: {} passed, {} expected to fail", passed, expected_failures.len());
+    assert!(unexpected.is_empty(), "{}", unexpected.join("\n"));
+}
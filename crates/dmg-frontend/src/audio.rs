@@ -0,0 +1,126 @@
+use std::sync::{Arc, Mutex};
+
+use cpal::{FromSample, Sample, SampleFormat, Stream, StreamConfig};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use log::error;
+
+use dmg::dmg::traits::AudioSink;
+use dmg::Error;
+
+// Samples queued for the device beyond which the oldest are dropped, about a third of a second.
+// Keeps latency bounded when samples come in faster than they're played, e.g. while fast-forwarding.
+const MAX_QUEUED_SAMPLES: usize = 16384;
+
+#[derive(Default)]
+pub struct AudioPlayer {
+    pub buffer: Arc<Mutex<Vec<(f32, f32)>>>,
+    pub sample_rate: u32,
+}
+
+impl AudioPlayer {
+    /// Drops samples that haven't been played yet.
+    pub fn clear(&self) {
+        self.buffer.lock().unwrap().clear();
+    }
+}
+
+impl AudioSink for AudioPlayer {
+    fn push_sample(&mut self, sample: (f32, f32)) {
+        let mut buffer = self.buffer.lock().unwrap();
+        if buffer.len() >= MAX_QUEUED_SAMPLES {
+            buffer.drain(..MAX_QUEUED_SAMPLES / 2);
+        }
+        buffer.push(sample);
+    }
+
+    fn queued_samples(&self) -> Option<usize> {
+        Some(self.buffer.lock().unwrap().len())
+    }
+}
+
+pub fn setup_audio_device() -> Result<(AudioPlayer, Stream), Error> {
+    let host = cpal::default_host();
+    let device = host.default_output_device()
+        .ok_or_else(|| Error::AudioInit("no output device available".to_string()))?;
+
+    let wanted_sample_rate = cpal::SampleRate(44100);
+
+    let mut supported_configs = device.supported_output_configs()
+        .map_err(|e| Error::AudioInit(format!("error while querying configs: {}", e)))?;
+
+    let supported_config = supported_configs.find_map(|f| {
+        if f.channels() == 2 && f.sample_format() == cpal::SampleFormat::F32 {
+            if f.min_sample_rate() <= wanted_sample_rate && wanted_sample_rate <= f.max_sample_rate() {
+                Some(f.with_sample_rate(wanted_sample_rate))
+            } else {
+                Some(f.with_max_sample_rate())
+            }
+        } else {
+            None
+        }
+    }).ok_or_else(|| Error::AudioInit("found no stereo f32 output config".to_string()))?;
+
+
+    let sample_format = supported_config.sample_format();
+    let config: StreamConfig = supported_config.into();
+
+    let err_fn = |err| error!(target: "audio", "An error occurred on the output audio stream: {}", err);
+
+
+    let shared_buffer = Arc::new(Mutex::new(Vec::new()));
+    let stream_buffer = shared_buffer.clone();
+
+
+    let player = AudioPlayer {
+        buffer: shared_buffer,
+        sample_rate: config.sample_rate.0,
+    };
+
+    let stream = match sample_format {
+        SampleFormat::F32 => device.build_output_stream(&config, move |data: &mut [f32], _| cpal_thread(data, &stream_buffer), err_fn, None),
+        SampleFormat::I16 => device.build_output_stream(&config, move |data: &mut [i16], _| cpal_thread(data, &stream_buffer), err_fn, None),
+        SampleFormat::U16 => device.build_output_stream(&config, move |data: &mut [u16], _| cpal_thread(data, &stream_buffer), err_fn, None),
+        sample_format => return Err(Error::AudioInit(format!("unhandled sample format {}", sample_format))),
+    }.map_err(|e| Error::AudioInit(e.to_string()))?;
+
+    stream.play().map_err(|e| Error::AudioInit(e.to_string()))?;
+
+    Ok((player, stream))
+}
+
+fn cpal_thread<T: Sample + FromSample<f32>>(outbuffer: &mut [T], audio_buffer: &Arc<Mutex<Vec<(f32, f32)>>>) {
+    let mut inbuffer = audio_buffer.lock().unwrap();
+    let outlen = ::std::cmp::min(outbuffer.len() / 2, inbuffer.len());
+    for (i, (in_l, in_r)) in inbuffer.drain(..outlen).enumerate() {
+        outbuffer[i * 2] = (&in_l).to_sample();
+        outbuffer[i * 2 + 1] = (&in_r).to_sample();
+    }
+    // Play silence rather than whatever the device left in the buffer when we run out of samples
+    for sample in &mut outbuffer[outlen * 2..] {
+        *sample = T::EQUILIBRIUM;
+    }
+    if inbuffer.len() > 2048 {
+        inbuffer.truncate(512)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dmg::dmg::traits::AudioSink;
+    use std::sync::{Arc, Mutex};
+
+    use crate::audio::{AudioPlayer, MAX_QUEUED_SAMPLES};
+
+    #[test]
+    fn player_drops_the_oldest_samples_when_backed_up() {
+        let mut player = AudioPlayer { buffer: Arc::new(Mutex::new(vec![])), sample_rate: 44100 };
+
+        for i in 0..MAX_QUEUED_SAMPLES + 1 {
+            player.push_sample((i as f32, 0.0));
+        }
+
+        let buffer = player.buffer.lock().unwrap();
+        assert_eq!(buffer.len(), MAX_QUEUED_SAMPLES / 2 + 1);
+        assert_eq!(buffer.last(), Some(&(MAX_QUEUED_SAMPLES as f32, 0.0)));
+    }
+}
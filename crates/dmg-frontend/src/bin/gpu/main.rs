@@ -0,0 +1,259 @@
+use std::env;
+use std::process;
+use std::time::Instant;
+
+use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
+use winit::dpi::LogicalSize;
+use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::{Fullscreen, Window, WindowBuilder};
+
+use dmg::dmg::boot_rom::find_boot_rom;
+use dmg::dmg::core::{Core, CoreBuilder};
+use dmg::dmg::input::JoypadInput;
+use dmg::dmg::traits::VideoSink;
+use dmg::{FRAME_DURATION, SCREEN_HEIGHT, SCREEN_WIDTH};
+use frontend::audio::setup_audio_device;
+use frontend::config::{Config, ConfigFile, CONFIG_FILE};
+
+#[cfg(feature = "debug-overlay")]
+mod overlay;
+mod shader;
+
+#[cfg(feature = "debug-overlay")]
+use overlay::{DebugAction, DebugOverlay};
+use shader::PostRenderer;
+
+// Initial window size as a multiple of the screen size
+const WINDOW_SCALE: u32 = 4;
+
+#[derive(Default)]
+struct Args {
+    game_rom: Option<String>,
+    boot_rom: Option<String>,
+    skip_boot_rom: bool,
+    no_vsync: bool,
+}
+
+fn parse_args() -> Args {
+    let mut args = Args::default();
+    let mut iter = env::args().skip(1);
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--boot-rom" => args.boot_rom = iter.next(),
+            "--skip-boot-rom" => args.skip_boot_rom = true,
+            "--no-vsync" => args.no_vsync = true,
+            _ => args.game_rom = Some(arg),
+        }
+    }
+
+    args
+}
+
+/// Writes lines straight into the RGBA texture that pixels uploads to the GPU
+struct PixelsFrame<'a> {
+    frame: &'a mut [u8],
+}
+
+impl VideoSink for PixelsFrame<'_> {
+    fn push_line(&mut self, y: usize, line: &[u32; SCREEN_WIDTH]) {
+        let row = &mut self.frame[y * SCREEN_WIDTH * 4..][..SCREEN_WIDTH * 4];
+        for (dst, pixel) in row.chunks_exact_mut(4).zip(line) {
+            let [_, r, g, b] = pixel.to_be_bytes();
+            dst.copy_from_slice(&[r, g, b, 0xff]);
+        }
+    }
+}
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let args = parse_args();
+
+    let core = load_core(&args.boot_rom, args.skip_boot_rom, args.game_rom).unwrap_or_else(|e| exit_with_error(e));
+
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title(core.cartridge_header().title())
+        .with_inner_size(LogicalSize::new(SCREEN_WIDTH as u32 * WINDOW_SCALE, SCREEN_HEIGHT as u32 * WINDOW_SCALE))
+        .with_min_inner_size(LogicalSize::new(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32))
+        .build(&event_loop)
+        .unwrap_or_else(|e| exit_with_error(e));
+
+    let pixels = create_pixels(&window, !args.no_vsync).unwrap_or_else(|e| exit_with_error(e));
+
+    run(event_loop, window, pixels, core);
+}
+
+fn create_pixels(window: &Window, vsync: bool) -> Result<Pixels, pixels::Error> {
+    let size = window.inner_size();
+    let surface_texture = SurfaceTexture::new(size.width, size.height, window);
+
+    PixelsBuilder::new(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32, surface_texture)
+        .enable_vsync(vsync)
+        .build()
+}
+
+fn run(event_loop: EventLoop<()>, window: Window, mut pixels: Pixels, mut core: Core) -> ! {
+    let (mut audio_player, audio_stream) = setup_audio_device().unwrap_or_else(|e| exit_with_error(e));
+
+    let config_file = ConfigFile::new(CONFIG_FILE);
+    let mut config: Config = config_file.load();
+
+    let mut keys_pressed = JoypadInput::empty();
+    let mut next_frame = Instant::now();
+
+    let size = window.inner_size();
+    let mut post = PostRenderer::new(&pixels, size.width, size.height);
+
+    #[cfg(feature = "debug-overlay")]
+    let mut overlay = DebugOverlay::new(&event_loop, &window, &pixels);
+
+    event_loop.run(move |event, _, control_flow| {
+        // The stream stops playing when dropped, so it lives as long as the event loop
+        let _ = &audio_stream;
+
+        #[cfg(feature = "debug-overlay")]
+        if let Event::WindowEvent { event, .. } = &event {
+            if overlay.handle_event(event) {
+                return;
+            }
+        }
+
+        match event {
+            Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => *control_flow = ControlFlow::Exit,
+            Event::WindowEvent { event: WindowEvent::Resized(size), .. } => {
+                if let Err(e) = pixels.resize_surface(size.width, size.height) {
+                    eprintln!("Failed resizing surface: {}", e);
+                    *control_flow = ControlFlow::Exit;
+                }
+                post.resize(pixels.queue(), size.width, size.height);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::KeyboardInput { input: KeyboardInput { virtual_keycode: Some(key), state, .. }, .. },
+                ..
+            } => {
+                let pressed = state == ElementState::Pressed;
+                match key {
+                    VirtualKeyCode::Escape => *control_flow = ControlFlow::Exit,
+                    #[cfg(feature = "debug-overlay")]
+                    VirtualKeyCode::F12 if pressed => overlay.visible = !overlay.visible,
+                    VirtualKeyCode::F11 if pressed => {
+                        let fullscreen = match window.fullscreen() {
+                            Some(_) => None,
+                            None => Some(Fullscreen::Borderless(None)),
+                        };
+                        window.set_fullscreen(fullscreen);
+                    }
+                    VirtualKeyCode::F10 if pressed => {
+                        config.shader = config.shader.next();
+                        eprintln!("Shader: {}", config.shader);
+                        if let Err(e) = config_file.save(&config) {
+                            eprintln!("Failed saving config: {}", e);
+                        }
+                        window.request_redraw();
+                    }
+                    _ => {
+                        if let Some(button) = joypad_button(key) {
+                            keys_pressed.set(button, pressed);
+                        }
+                    }
+                }
+            }
+            Event::MainEventsCleared => {
+                let now = Instant::now();
+                if now >= next_frame {
+                    let mut video = PixelsFrame { frame: pixels.frame_mut() };
+                    core.step_frame(&mut video, &mut audio_player, &mut keys_pressed);
+
+                    // Don't try to catch up after a stall, e.g. while the window was being dragged
+                    next_frame = (next_frame + FRAME_DURATION).max(now);
+                    window.request_redraw();
+                }
+
+                #[cfg(feature = "debug-overlay")]
+                match overlay.prepare(&window, &core) {
+                    Some(DebugAction::TogglePause) => {
+                        core.set_paused(!core.is_paused());
+                        if core.is_paused() {
+                            audio_player.clear();
+                        }
+                    }
+                    Some(DebugAction::Step) => {
+                        let mut video = PixelsFrame { frame: pixels.frame_mut() };
+                        core.step_instruction(&mut video, &mut audio_player, &mut keys_pressed);
+                    }
+                    Some(DebugAction::Reset) => core.reset(),
+                    Some(DebugAction::Poke(addr, value)) => {
+                        if core.poke(addr, value) {
+                            core.redraw(&mut PixelsFrame { frame: pixels.frame_mut() });
+                            window.request_redraw();
+                        }
+                    }
+                    None => {}
+                }
+                if *control_flow != ControlFlow::Exit {
+                    *control_flow = ControlFlow::WaitUntil(next_frame);
+                }
+            }
+            Event::RedrawRequested(_) => {
+                let result = pixels.render_with(|encoder, render_target, context| {
+                    post.render(config.shader, encoder, render_target, context);
+
+                    #[cfg(feature = "debug-overlay")]
+                    overlay.render(encoder, render_target, context);
+
+                    Ok(())
+                });
+                if let Err(e) = result {
+                    eprintln!("Failed rendering frame: {}", e);
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+            _ => {}
+        }
+    })
+}
+
+fn joypad_button(key: VirtualKeyCode) -> Option<JoypadInput> {
+    match key {
+        VirtualKeyCode::Up => Some(JoypadInput::UP),
+        VirtualKeyCode::Left => Some(JoypadInput::LEFT),
+        VirtualKeyCode::Down => Some(JoypadInput::DOWN),
+        VirtualKeyCode::Right => Some(JoypadInput::RIGHT),
+        VirtualKeyCode::Return => Some(JoypadInput::START),
+        VirtualKeyCode::RShift => Some(JoypadInput::SELECT),
+        VirtualKeyCode::Z => Some(JoypadInput::A),
+        VirtualKeyCode::X => Some(JoypadInput::B),
+        _ => None,
+    }
+}
+
+fn exit_with_error(e: impl std::fmt::Display) -> ! {
+    eprintln!("{}", e);
+    process::exit(1);
+}
+
+fn load_core(boot_rom: &Option<String>, skip_boot_rom: bool, game_rom: Option<String>) -> dmg::Result<Core> {
+    let mut builder = CoreBuilder::new();
+    if let Some(game_rom) = &game_rom {
+        builder = builder.game_rom(game_rom);
+    }
+
+    if skip_boot_rom {
+        return builder.build();
+    }
+
+    let boot_rom = boot_rom.clone().or_else(|| {
+        find_boot_rom(game_rom.as_deref()).map(|path| path.to_string_lossy().into_owned())
+    });
+
+    match boot_rom {
+        Some(path) => builder.boot_rom(path).build(),
+        #[cfg(feature = "bundled-boot-rom")]
+        None => builder.bundled_boot_rom().build(),
+        #[cfg(not(feature = "bundled-boot-rom"))]
+        None => builder.build(),
+    }
+}
@@ -0,0 +1,199 @@
+use egui::{ClippedPrimitive, Context, TexturesDelta};
+use egui_wgpu::renderer::{Renderer, ScreenDescriptor};
+use pixels::{wgpu, Pixels, PixelsContext};
+use winit::event::WindowEvent;
+use winit::event_loop::EventLoopWindowTarget;
+use winit::window::Window;
+
+use dmg::dmg::core::Core;
+use dmg::CYCLES_PER_FRAME;
+
+// Instructions listed from PC
+const DISASSEMBLY_LINES: usize = 16;
+
+/// What the overlay's buttons ask the frontend to do with the core.
+pub enum DebugAction {
+    TogglePause,
+    Step,
+    Reset,
+    /// Write a byte at a CPU address, from the memory editor.
+    Poke(u16, u8),
+}
+
+/// Text typed into the memory editor's fields.
+#[derive(Default)]
+struct MemoryEditor {
+    addr: String,
+    value: String,
+}
+
+/// egui window drawn on top of the screen, showing CPU, PPU and APU state.
+pub struct DebugOverlay {
+    context: Context,
+    state: egui_winit::State,
+    renderer: Renderer,
+    screen_descriptor: ScreenDescriptor,
+    paint_jobs: Vec<ClippedPrimitive>,
+    textures: TexturesDelta,
+    memory_editor: MemoryEditor,
+    pub visible: bool,
+}
+
+impl DebugOverlay {
+    pub fn new<T>(event_loop: &EventLoopWindowTarget<T>, window: &Window, pixels: &Pixels) -> Self {
+        let size = window.inner_size();
+        let scale_factor = window.scale_factor() as f32;
+
+        let mut state = egui_winit::State::new(event_loop);
+        state.set_max_texture_side(pixels.device().limits().max_texture_dimension_2d as usize);
+        state.set_pixels_per_point(scale_factor);
+
+        Self {
+            context: Context::default(),
+            state,
+            renderer: Renderer::new(pixels.device(), pixels.render_texture_format(), None, 1),
+            screen_descriptor: ScreenDescriptor { size_in_pixels: [size.width, size.height], pixels_per_point: scale_factor },
+            paint_jobs: vec![],
+            textures: TexturesDelta::default(),
+            memory_editor: MemoryEditor::default(),
+            visible: false,
+        }
+    }
+
+    /// Passes a window event to egui, returning whether egui used it.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::Resized(size) => self.screen_descriptor.size_in_pixels = [size.width, size.height],
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => self.screen_descriptor.pixels_per_point = *scale_factor as f32,
+            _ => {}
+        }
+
+        self.visible && self.state.on_event(&self.context, event).consumed
+    }
+
+    /// Lays out the overlay for the next render, returning the button pressed, if any.
+    pub fn prepare(&mut self, window: &Window, core: &Core) -> Option<DebugAction> {
+        if !self.visible {
+            return None;
+        }
+
+        let mut action = None;
+        let raw_input = self.state.take_egui_input(window);
+        let output = self.context.run(raw_input, |ctx| {
+            egui::Window::new("Debugger").default_width(220.0).show(ctx, |ui| {
+                action = debugger_ui(ui, core, &mut self.memory_editor);
+            });
+        });
+
+        self.textures.append(output.textures_delta);
+        self.state.handle_platform_output(window, &self.context, output.platform_output);
+        self.paint_jobs = self.context.tessellate(output.shapes);
+
+        action
+    }
+
+    pub fn render(&mut self, encoder: &mut wgpu::CommandEncoder, render_target: &wgpu::TextureView, context: &PixelsContext) {
+        if !self.visible {
+            return;
+        }
+
+        for (id, image_delta) in &self.textures.set {
+            self.renderer.update_texture(&context.device, &context.queue, *id, image_delta);
+        }
+        self.renderer.update_buffers(&context.device, &context.queue, encoder, &self.paint_jobs, &self.screen_descriptor);
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("egui"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: render_target,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: true },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.renderer.render(&mut render_pass, &self.paint_jobs, &self.screen_descriptor);
+        }
+
+        for id in &std::mem::take(&mut self.textures).free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
+
+fn debugger_ui(ui: &mut egui::Ui, core: &Core, memory_editor: &mut MemoryEditor) -> Option<DebugAction> {
+    let mut action = None;
+
+    ui.horizontal(|ui| {
+        if ui.button(if core.is_paused() { "Resume" } else { "Pause" }).clicked() {
+            action = Some(DebugAction::TogglePause);
+        }
+        if ui.add_enabled(core.is_paused(), egui::Button::new("Step")).clicked() {
+            action = Some(DebugAction::Step);
+        }
+        if ui.button("Reset").clicked() {
+            action = Some(DebugAction::Reset);
+        }
+    });
+
+    let r = core.registers();
+    ui.collapsing("CPU", |ui| {
+        ui.monospace(format!("AF {:02X}{:02X}  BC {:02X}{:02X}", r.a, r.f, r.b, r.c));
+        ui.monospace(format!("DE {:02X}{:02X}  HL {:02X}{:02X}", r.d, r.e, r.h, r.l));
+        ui.monospace(format!("SP {:04X}  PC {:04X}", r.sp, r.pc));
+        ui.monospace(format!("IME {}  HALT {}", r.ime as u8, r.halted as u8));
+    });
+
+    ui.collapsing("Disassembly", |ui| {
+        for instruction in core.disassemble(r.pc, DISASSEMBLY_LINES) {
+            let marker = if instruction.addr == r.pc { '>' } else { ' ' };
+            ui.monospace(format!("{} {:04X}  {}", marker, instruction.addr, instruction.text));
+        }
+    });
+
+    let ppu = core.ppu_state();
+    ui.collapsing("PPU", |ui| {
+        ui.monospace(format!("LY {:3}  mode {}", ppu.ly, ppu.mode()));
+        ui.monospace(format!("LCDC {:02X}  STAT {:02X}", ppu.lcdc, ppu.stat));
+        ui.monospace(format!("SCX {:3}  SCY {:3}", ppu.scx, ppu.scy));
+        ui.monospace(format!("WX {:3}  WY {:3}  WLY {:3}", ppu.wx, ppu.wy, ppu.window_line));
+        ui.monospace(format!("LYC {:3}", ppu.lyc));
+    });
+
+    ui.collapsing("APU", |ui| {
+        for (i, enabled) in core.apu_channels_enabled().iter().enumerate() {
+            ui.monospace(format!("CH{} {}", i + 1, if *enabled { "on" } else { "off" }));
+        }
+    });
+
+    let stats = core.frame_stats();
+    ui.collapsing("Last frame", |ui| {
+        let [vblank, stat, timer, serial, joypad] = stats.interrupts;
+        ui.monospace(format!("IRQ VBL {}  STAT {}  TIM {}", vblank, stat, timer));
+        ui.monospace(format!("IRQ SER {}  JOY {}", serial, joypad));
+        ui.monospace(format!("HALT {:.0}%", stats.halt_cycles as f32 * 100.0 / CYCLES_PER_FRAME as f32));
+        ui.monospace(format!("DMA {}  LCD toggles {}", stats.dma_transfers, stats.lcd_toggles));
+    });
+
+    ui.collapsing("Memory", |ui| {
+        let base = u16::from_str_radix(memory_editor.addr.trim_start_matches('$'), 16).unwrap_or(0xc000) & 0xfff0;
+        for line in core.hexdump(base, 64).lines() {
+            ui.monospace(line);
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut memory_editor.addr).hint_text("C000").desired_width(48.0));
+            ui.add(egui::TextEdit::singleline(&mut memory_editor.value).hint_text("00").desired_width(24.0));
+
+            let addr = u16::from_str_radix(memory_editor.addr.trim_start_matches('$'), 16);
+            let value = u8::from_str_radix(&memory_editor.value, 16);
+            if let (Ok(addr), Ok(value)) = (addr, value) {
+                if ui.add_enabled(core.is_paused(), egui::Button::new("Write")).clicked() {
+                    action = Some(DebugAction::Poke(addr, value));
+                }
+            }
+        });
+    });
+
+    action
+}
@@ -0,0 +1,75 @@
+// Post-processing passes drawing the screen texture to the window, one fragment entry point per shader
+
+struct Params {
+    source_size: vec2<f32>,
+    // Size of the viewport the screen is drawn to, in window pixels
+    output_size: vec2<f32>,
+}
+
+@group(0) @binding(0) var screen: texture_2d<f32>;
+@group(0) @binding(1) var screen_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: Params;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// One triangle covering the whole viewport
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let pos = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u)) * 2.0 - 1.0;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(pos, 0.0, 1.0);
+    out.uv = vec2<f32>(pos.x + 1.0, 1.0 - pos.y) * 0.5;
+    return out;
+}
+
+fn nearest(texel: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(screen, screen_sampler, (floor(texel) + 0.5) / params.source_size, 0.0).rgb;
+}
+
+@fragment
+fn fs_lcd_grid(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = in.uv * params.source_size;
+    let scale = params.output_size / params.source_size;
+
+    // Distance to the nearest pixel edge, in window pixels, darkening about one pixel's worth of gap
+    let edge = min(fract(texel), 1.0 - fract(texel)) * scale;
+    let gap = smoothstep(vec2<f32>(0.0), vec2<f32>(1.0), edge);
+    let shade = mix(0.7, 1.0, min(gap.x, gap.y));
+
+    return vec4<f32>(nearest(texel) * shade, 1.0);
+}
+
+@fragment
+fn fs_crt(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Bulge the picture out from the middle, leaving the corners black
+    let centered = in.uv * 2.0 - 1.0;
+    let curved = centered * (1.0 + 0.04 * centered.yx * centered.yx);
+    let uv = curved * 0.5 + 0.5;
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    let texel = uv * params.source_size;
+    let scanline = 0.85 + 0.15 * cos((fract(texel.y) - 0.5) * 6.2831853);
+    let vignette = 1.0 - 0.1 * dot(centered, centered);
+
+    return vec4<f32>(nearest(texel) * scanline * vignette, 1.0);
+}
+
+@fragment
+fn fs_sharp_bilinear(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = in.uv * params.source_size;
+    let scale = max(floor(params.output_size / params.source_size), vec2<f32>(1.0));
+
+    // Nearest inside each pixel, blending linearly only across the last window pixel at its edges
+    let from_center = fract(texel) - 0.5;
+    let region = 0.5 - 0.5 / scale;
+    let offset = (from_center - clamp(from_center, -region, region)) * scale + 0.5;
+    let uv = (floor(texel) + offset) / params.source_size;
+
+    return vec4<f32>(textureSampleLevel(screen, screen_sampler, uv, 0.0).rgb, 1.0);
+}
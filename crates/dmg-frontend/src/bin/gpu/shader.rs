@@ -0,0 +1,156 @@
+use pixels::{wgpu, Pixels, PixelsContext};
+
+use dmg::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use frontend::config::PostShader;
+
+/// Draws the screen texture to the window through one of the post shaders in `post.wgsl`,
+/// stretched to the largest size with the screen's aspect ratio that fits the window.
+pub struct PostRenderer {
+    bind_group: wgpu::BindGroup,
+    params: wgpu::Buffer,
+    lcd_grid: wgpu::RenderPipeline,
+    crt: wgpu::RenderPipeline,
+    sharp_bilinear: wgpu::RenderPipeline,
+    // x, y, width and height of the picture in the window
+    viewport: (f32, f32, f32, f32),
+}
+
+impl PostRenderer {
+    pub fn new(pixels: &Pixels, width: u32, height: u32) -> Self {
+        let device = pixels.device();
+        let module = device.create_shader_module(wgpu::include_wgsl!("post.wgsl"));
+
+        let view = pixels.texture().create_view(&wgpu::TextureViewDescriptor::default());
+        // Linear, for sharp-bilinear; the other shaders sample texel centers
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("post_sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("post_params"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("post_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("post_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&sampler) },
+                wgpu::BindGroupEntry { binding: 2, resource: params.as_entire_binding() },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("post_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState { module: &module, entry_point: "vs_main", buffers: &[] },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &module,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: pixels.render_texture_format(),
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            })
+        };
+
+        let mut renderer = Self {
+            bind_group,
+            params,
+            lcd_grid: pipeline("fs_lcd_grid"),
+            crt: pipeline("fs_crt"),
+            sharp_bilinear: pipeline("fs_sharp_bilinear"),
+            viewport: (0.0, 0.0, 0.0, 0.0),
+        };
+        renderer.resize(pixels.queue(), width, height);
+        renderer
+    }
+
+    /// Fits the picture to a window of `width` by `height` pixels.
+    pub fn resize(&mut self, queue: &wgpu::Queue, width: u32, height: u32) {
+        let (source_w, source_h) = (SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
+        let scale = (width as f32 / source_w).min(height as f32 / source_h);
+        let (w, h) = ((source_w * scale).floor(), (source_h * scale).floor());
+        self.viewport = (((width as f32 - w) / 2.0).floor(), ((height as f32 - h) / 2.0).floor(), w, h);
+
+        let params: Vec<u8> = [source_w, source_h, w, h].iter().flat_map(|v| v.to_le_bytes()).collect();
+        queue.write_buffer(&self.params, 0, &params);
+    }
+
+    pub fn render(&self, shader: PostShader, encoder: &mut wgpu::CommandEncoder, render_target: &wgpu::TextureView, context: &PixelsContext) {
+        let pipeline = match shader {
+            PostShader::None => return context.scaling_renderer.render(encoder, render_target),
+            PostShader::LcdGrid => &self.lcd_grid,
+            PostShader::Crt => &self.crt,
+            PostShader::SharpBilinear => &self.sharp_bilinear,
+        };
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("post_render_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: render_target,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: true },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        let (x, y, w, h) = self.viewport;
+        // Minimized windows have nowhere to draw to
+        if w < 1.0 || h < 1.0 {
+            return;
+        }
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_viewport(x, y, w, h, 0.0, 1.0);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,217 @@
+use std::env;
+use std::process;
+use std::thread;
+use std::time::Instant;
+
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
+use sdl2::event::Event;
+use sdl2::keyboard::{KeyboardState, Keycode, Scancode};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::Rect;
+use sdl2::video::FullscreenType;
+
+use dmg::dmg::boot_rom::find_boot_rom;
+use dmg::dmg::core::{Core, CoreBuilder};
+use dmg::dmg::input::JoypadInput;
+use dmg::dmg::traits::{AudioSink, InputSource, VideoSink};
+use dmg::{FRAME_DURATION, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// Initial window size as a multiple of the screen size
+const WINDOW_SCALE: u32 = 4;
+
+const SAMPLE_RATE: i32 = 44100;
+
+// Drop audio instead of queueing more than this many bytes, so latency can't build up
+const MAX_QUEUED_AUDIO: u32 = 8192 * 4;
+
+#[derive(Default)]
+struct Args {
+    game_rom: Option<String>,
+    boot_rom: Option<String>,
+    skip_boot_rom: bool,
+}
+
+fn parse_args() -> Args {
+    let mut args = Args::default();
+    let mut iter = env::args().skip(1);
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--boot-rom" => args.boot_rom = iter.next(),
+            "--skip-boot-rom" => args.skip_boot_rom = true,
+            _ => args.game_rom = Some(arg),
+        }
+    }
+
+    args
+}
+
+/// Frame in the byte layout of an RGB888 streaming texture
+struct TextureFrame {
+    pixels: Vec<u8>,
+}
+
+impl VideoSink for TextureFrame {
+    fn push_line(&mut self, y: usize, line: &[u32; SCREEN_WIDTH]) {
+        let row = &mut self.pixels[y * SCREEN_WIDTH * 4..][..SCREEN_WIDTH * 4];
+        for (dst, pixel) in row.chunks_exact_mut(4).zip(line) {
+            dst.copy_from_slice(&pixel.to_ne_bytes());
+        }
+    }
+}
+
+/// Interleaved samples waiting to be queued on the audio device
+#[derive(Default)]
+struct QueuedAudio {
+    samples: Vec<f32>,
+}
+
+impl AudioSink for QueuedAudio {
+    fn push_sample(&mut self, (left, right): (f32, f32)) {
+        self.samples.push(left);
+        self.samples.push(right);
+    }
+}
+
+struct Keyboard<'a> {
+    state: KeyboardState<'a>,
+}
+
+impl InputSource for Keyboard<'_> {
+    fn poll(&mut self) -> JoypadInput {
+        let mut keys_pressed = JoypadInput::empty();
+
+        for (scancode, key) in [
+            (Scancode::Up, JoypadInput::UP),
+            (Scancode::Left, JoypadInput::LEFT),
+            (Scancode::Down, JoypadInput::DOWN),
+            (Scancode::Right, JoypadInput::RIGHT),
+            (Scancode::Return, JoypadInput::START),
+            (Scancode::RShift, JoypadInput::SELECT),
+            (Scancode::Z, JoypadInput::A),
+            (Scancode::X, JoypadInput::B),
+        ] {
+            if self.state.is_scancode_pressed(scancode) {
+                keys_pressed |= key;
+            }
+        }
+
+        keys_pressed
+    }
+}
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let args = parse_args();
+
+    let core = load_core(&args.boot_rom, args.skip_boot_rom, args.game_rom).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    if let Err(e) = run(core) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn run(mut core: Core) -> Result<(), String> {
+    let sdl = sdl2::init()?;
+    let video = sdl.video()?;
+    let audio = sdl.audio()?;
+
+    let window = video
+        .window(&core.cartridge_header().title(), SCREEN_WIDTH as u32 * WINDOW_SCALE, SCREEN_HEIGHT as u32 * WINDOW_SCALE)
+        .position_centered()
+        .resizable()
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+
+    let texture_creator = canvas.texture_creator();
+    let mut texture = texture_creator
+        .create_texture_streaming(PixelFormatEnum::RGB888, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+        .map_err(|e| e.to_string())?;
+
+    let spec = AudioSpecDesired { freq: Some(SAMPLE_RATE), channels: Some(2), samples: Some(1024) };
+    let audio_queue: AudioQueue<f32> = audio.open_queue(None, &spec)?;
+    audio_queue.resume();
+
+    let mut frame = TextureFrame { pixels: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * 4] };
+    let mut samples = QueuedAudio::default();
+    let mut event_pump = sdl.event_pump()?;
+
+    'running: loop {
+        let frame_started = Instant::now();
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'running,
+                Event::KeyDown { keycode: Some(Keycode::F11), repeat: false, .. } => {
+                    let window = canvas.window_mut();
+                    let fullscreen = match window.fullscreen_state() {
+                        FullscreenType::Off => FullscreenType::Desktop,
+                        _ => FullscreenType::Off,
+                    };
+                    window.set_fullscreen(fullscreen)?;
+                }
+                _ => {}
+            }
+        }
+
+        let mut keyboard = Keyboard { state: event_pump.keyboard_state() };
+        core.step_frame(&mut frame, &mut samples, &mut keyboard);
+
+        if audio_queue.size() < MAX_QUEUED_AUDIO {
+            audio_queue.queue_audio(&samples.samples)?;
+        }
+        samples.samples.clear();
+
+        texture.update(None, &frame.pixels, SCREEN_WIDTH * 4).map_err(|e| e.to_string())?;
+        canvas.clear();
+        canvas.copy(&texture, None, screen_rect(canvas.output_size()?))?;
+        canvas.present();
+
+        if let Some(remaining) = FRAME_DURATION.checked_sub(frame_started.elapsed()) {
+            thread::sleep(remaining);
+        }
+    }
+
+    Ok(())
+}
+
+/// Largest whole-pixel scaling of the screen that fits the window, centered so the 10:9 aspect ratio is
+/// kept with black borders around it
+fn screen_rect((width, height): (u32, u32)) -> Rect {
+    let scale = (width / SCREEN_WIDTH as u32).min(height / SCREEN_HEIGHT as u32).max(1);
+    let (screen_width, screen_height) = (SCREEN_WIDTH as u32 * scale, SCREEN_HEIGHT as u32 * scale);
+    let left = width.saturating_sub(screen_width) / 2;
+    let top = height.saturating_sub(screen_height) / 2;
+
+    Rect::new(left as i32, top as i32, screen_width, screen_height)
+}
+
+fn load_core(boot_rom: &Option<String>, skip_boot_rom: bool, game_rom: Option<String>) -> dmg::Result<Core> {
+    let mut builder = CoreBuilder::new();
+    if let Some(game_rom) = &game_rom {
+        builder = builder.game_rom(game_rom);
+    }
+
+    if skip_boot_rom {
+        return builder.build();
+    }
+
+    let boot_rom = boot_rom.clone().or_else(|| {
+        find_boot_rom(game_rom.as_deref()).map(|path| path.to_string_lossy().into_owned())
+    });
+
+    match boot_rom {
+        Some(path) => builder.boot_rom(path).build(),
+        #[cfg(feature = "bundled-boot-rom")]
+        None => builder.bundled_boot_rom().build(),
+        #[cfg(not(feature = "bundled-boot-rom"))]
+        None => builder.build(),
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use dmg::dmg::core::Resampler;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// Where the frontends keep their config, in the working directory.
+pub const CONFIG_FILE: &str = "gameboy-rust.toml";
+
+/// Frontend settings that are kept between runs, stored as TOML.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    pub volume: f32,
+    pub muted: bool,
+    /// Silence the game while fast-forwarding rather than playing it sped up
+    pub mute_fast_forward: bool,
+    /// Post-processing in the GPU frontend
+    pub shader: PostShader,
+    /// Pace frames by the window's 60 FPS cap instead of the emulated clock, which runs slightly
+    /// fast against the Game Boy's 59.73 Hz
+    pub vsync: bool,
+    /// Pace frames by the audio device taking samples instead, which keeps the sound from
+    /// crackling on displays that aren't 60 Hz or where vsync is unreliable. Overrides `vsync`
+    pub audio_sync: bool,
+    /// Filter out the clicks of channels and DACs switching on and off instead of playing the raw
+    /// emulated output
+    pub pop_filter: bool,
+    /// Trade CPU for cleaner audio, see `Resampler`
+    pub resampler: Resampler,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { volume: 1.0, muted: false, mute_fast_forward: false, shader: PostShader::None, vsync: false, audio_sync: false, pop_filter: false, resampler: Resampler::Nearest }
+    }
+}
+
+/// How the GPU frontend draws the screen to the window.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PostShader {
+    /// Sharp pixels at the largest whole multiple of the screen size that fits
+    #[default]
+    None,
+    /// Fills the window, with dark lines between pixels like the DMG's LCD
+    LcdGrid,
+    /// Fills the window, slightly curved with scanlines
+    Crt,
+    /// Fills the window, keeping pixels sharp but blending their edges at odd scales
+    SharpBilinear,
+}
+
+impl PostShader {
+    /// The shader after this one, for switching through them with a key.
+    pub fn next(self) -> Self {
+        match self {
+            PostShader::None => PostShader::LcdGrid,
+            PostShader::LcdGrid => PostShader::Crt,
+            PostShader::Crt => PostShader::SharpBilinear,
+            PostShader::SharpBilinear => PostShader::None,
+        }
+    }
+}
+
+impl fmt::Display for PostShader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            PostShader::None => "none",
+            PostShader::LcdGrid => "lcd-grid",
+            PostShader::Crt => "crt",
+            PostShader::SharpBilinear => "sharp-bilinear",
+        })
+    }
+}
+
+/// Reads and writes the config file, falling back to defaults when it's missing or unreadable.
+pub struct ConfigFile {
+    path: PathBuf,
+}
+
+impl ConfigFile {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self { path: path.as_ref().to_path_buf() }
+    }
+
+    pub fn load(&self) -> Config {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Config::default(),
+            Err(e) => {
+                warn!("Failed reading {}: {}", self.path.display(), e);
+                return Config::default();
+            }
+        };
+
+        toml::from_str(&text).unwrap_or_else(|e| {
+            warn!("Ignoring invalid config {}: {}", self.path.display(), e);
+            Config::default()
+        })
+    }
+
+    pub fn save(&self, config: &Config) -> io::Result<()> {
+        let text = toml::to_string(config).map_err(io::Error::other)?;
+        fs::write(&self.path, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use dmg::dmg::core::Resampler;
+
+    use crate::config::{Config, ConfigFile, PostShader};
+
+    #[test]
+    fn config_round_trips_and_fills_in_defaults() {
+        let path = env::temp_dir().join("config_round_trips_and_fills_in_defaults.toml");
+        let file = ConfigFile::new(&path);
+        let config = Config { volume: 0.5, muted: true, mute_fast_forward: true, shader: PostShader::LcdGrid, vsync: true, audio_sync: true, pop_filter: true, resampler: Resampler::Sinc };
+
+        file.save(&config).unwrap();
+        assert_eq!(file.load(), config);
+
+        fs::write(&path, "muted = true\nshader = \"sharp-bilinear\"\nresampler = \"linear\"\n").unwrap();
+        assert_eq!(file.load(), Config { volume: 1.0, muted: true, mute_fast_forward: false, shader: PostShader::SharpBilinear, vsync: false, audio_sync: false, pop_filter: false, resampler: Resampler::Linear });
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::BTreeSet;
+use std::io;
+use std::time::Duration;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use dmg::dmg::annotations::describe_address;
+use dmg::dmg::core::Core;
+use dmg::dmg::expr::Expr;
+use dmg::dmg::input::JoypadInput;
+use dmg::dmg::traits::{NullAudioSink, NullVideoSink};
+
+// Instructions run between checks for Esc while continuing
+const CONTINUE_BATCH: usize = 10_000;
+
+// Rows of 16 bytes in the memory view
+const MEMORY_ROWS: u16 = 8;
+
+const HELP: &str = "s [n] step, c continue, f frame, b <addr> toggle breakpoint, m <addr> memory, w <addr> <byte> write, \
+    x <expr> watch, u <n> unwatch, r reset, q quit";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    Step(usize),
+    Continue,
+    Frame,
+    ToggleBreakpoint(u16),
+    Memory(u16),
+    Write(u16, u8),
+    Watch(Expr),
+    Unwatch(usize),
+    Reset,
+    Help,
+    Quit,
+}
+
+fn parse_command(line: &str) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let arg = words.next();
+
+    let address = || arg.ok_or("missing address".to_string()).and_then(parse_address);
+
+    match name {
+        "s" | "step" => match arg {
+            Some(count) => count.parse().map(Command::Step).map_err(|_| format!("invalid count: {}", count)),
+            None => Ok(Command::Step(1)),
+        },
+        "c" | "continue" => Ok(Command::Continue),
+        "f" | "frame" => Ok(Command::Frame),
+        "b" | "break" => address().map(Command::ToggleBreakpoint),
+        "m" | "mem" => address().map(Command::Memory),
+        "w" | "write" => {
+            let addr = address()?;
+            let value = words.next().ok_or("missing value".to_string())?;
+            u8::from_str_radix(value.trim_start_matches('$').trim_start_matches("0x"), 16)
+                .map(|value| Command::Write(addr, value))
+                .map_err(|_| format!("invalid value: {}", value))
+        }
+        "x" | "watch" => {
+            // Expressions may contain spaces, so they take the rest of the line
+            let text = line.trim_start()[name.len()..].trim();
+            Expr::parse(text).map(Command::Watch).map_err(|e| e.to_string())
+        }
+        "u" | "unwatch" => match arg {
+            Some(n) => n.parse().map(Command::Unwatch).map_err(|_| format!("invalid watch number: {}", n)),
+            None => Err("missing watch number".to_string()),
+        },
+        "r" | "reset" => Ok(Command::Reset),
+        "h" | "help" => Ok(Command::Help),
+        "q" | "quit" => Ok(Command::Quit),
+        _ => Err(format!("unknown command: {}", name)),
+    }
+}
+
+/// Parses a hex address, optionally prefixed with `$` or `0x`.
+fn parse_address(text: &str) -> Result<u16, String> {
+    let digits = text.trim_start_matches('$').trim_start_matches("0x");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("invalid address: {}", text))
+}
+
+/// Terminal debugger: disassembly at PC, registers and a memory view above a command prompt.
+/// The game runs headless, without video or audio output.
+struct Debugger {
+    core: Core,
+    breakpoints: BTreeSet<u16>,
+    // Evaluated again each time the screen is drawn, i.e. after every command
+    watches: Vec<Expr>,
+    memory_addr: u16,
+
+    input: String,
+    last_command: Option<Command>,
+    message: String,
+    quit: bool,
+}
+
+impl Debugger {
+    fn new(core: Core) -> Self {
+        Self {
+            core,
+            breakpoints: BTreeSet::new(),
+            watches: Vec::new(),
+            memory_addr: 0xc000,
+            input: String::new(),
+            last_command: None,
+            message: HELP.to_string(),
+            quit: false,
+        }
+    }
+
+    fn step(&mut self) -> bool {
+        self.core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty())
+    }
+
+    fn at_breakpoint(&self) -> bool {
+        self.breakpoints.contains(&self.core.registers().pc)
+    }
+
+    /// Runs `command`, calling `interrupted` now and then while continuing to let the user stop it.
+    fn execute(&mut self, command: Command, mut interrupted: impl FnMut() -> bool) {
+        self.message.clear();
+
+        match command {
+            Command::Step(count) => {
+                for _ in 0..count {
+                    self.step();
+                    if self.at_breakpoint() {
+                        break;
+                    }
+                }
+            }
+            Command::Continue => loop {
+                if (0..CONTINUE_BATCH).any(|_| {
+                    self.step();
+                    self.at_breakpoint()
+                }) {
+                    self.message = format!("Breakpoint at {:04X}", self.core.registers().pc);
+                    break;
+                }
+                if interrupted() {
+                    self.message = "Interrupted".to_string();
+                    break;
+                }
+            },
+            Command::Frame => {
+                while !self.step() {
+                    if self.at_breakpoint() {
+                        self.message = format!("Breakpoint at {:04X}", self.core.registers().pc);
+                        break;
+                    }
+                }
+            }
+            Command::ToggleBreakpoint(addr) => {
+                if self.breakpoints.remove(&addr) {
+                    self.message = format!("Removed breakpoint at {:04X}", addr);
+                } else {
+                    self.breakpoints.insert(addr);
+                    self.message = format!("Breakpoint at {:04X}", addr);
+                }
+            }
+            Command::Memory(addr) => self.memory_addr = addr & 0xfff0,
+            Command::Write(addr, value) => {
+                if !self.core.poke(addr, value) {
+                    self.message = format!("Can't write to {:04X}", addr);
+                }
+            }
+            Command::Watch(expr) => self.watches.push(expr),
+            Command::Unwatch(n) => {
+                if n >= 1 && n <= self.watches.len() {
+                    self.watches.remove(n - 1);
+                } else {
+                    self.message = format!("No watch {}", n);
+                }
+            }
+            Command::Reset => self.core.reset(),
+            Command::Help => self.message = HELP.to_string(),
+            Command::Quit => self.quit = true,
+        }
+    }
+
+    /// Runs the typed command, or repeats the last one for an empty line.
+    fn submit(&mut self, interrupted: impl FnMut() -> bool) {
+        let line = std::mem::take(&mut self.input);
+        let command = if line.trim().is_empty() {
+            match self.last_command.clone() {
+                Some(command) => Ok(command),
+                None => return,
+            }
+        } else {
+            parse_command(&line)
+        };
+
+        match command {
+            Ok(command) => {
+                self.last_command = Some(command.clone());
+                self.execute(command, interrupted);
+            }
+            Err(e) => self.message = e,
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let watch_rows = if self.watches.is_empty() { 0 } else { self.watches.len() as u16 + 2 };
+        let [top, watches, memory, prompt] = Layout::vertical([
+            Constraint::Min(8),
+            Constraint::Length(watch_rows),
+            Constraint::Length(MEMORY_ROWS + 2),
+            Constraint::Length(4),
+        ]).areas(frame.area());
+        let [disassembly, registers] = Layout::horizontal([Constraint::Min(30), Constraint::Length(24)]).areas(top);
+
+        frame.render_widget(self.disassembly(disassembly), disassembly);
+        frame.render_widget(self.registers(), registers);
+        if !self.watches.is_empty() {
+            frame.render_widget(self.watch_list(), watches);
+        }
+        frame.render_widget(self.memory(), memory);
+
+        let lines = vec![Line::from(self.message.as_str()), Line::from(format!("> {}", self.input))];
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title("Command")), prompt);
+    }
+
+    fn disassembly(&self, area: Rect) -> Paragraph<'_> {
+        let pc = self.core.registers().pc;
+        let lines = self.core.disassemble(pc, area.height.saturating_sub(2) as usize)
+            .into_iter()
+            .map(|instruction| {
+                let marker = if instruction.addr == pc { '>' } else { ' ' };
+                let breakpoint = if self.breakpoints.contains(&instruction.addr) { '*' } else { ' ' };
+                let line = Line::from(format!("{}{} {:04X}  {}", breakpoint, marker, instruction.addr, instruction.text));
+                if instruction.addr == pc { line.reversed() } else { line }
+            })
+            .collect::<Vec<_>>();
+
+        Paragraph::new(lines).block(Block::bordered().title("Disassembly"))
+    }
+
+    fn registers(&self) -> Paragraph<'_> {
+        let r = self.core.registers();
+        let ppu = self.core.ppu_state();
+        let flag = |bit: u8, name: char| if r.f & bit != 0 { name } else { '-' };
+
+        let lines = vec![
+            Line::from(format!("AF {:02X}{:02X}  BC {:02X}{:02X}", r.a, r.f, r.b, r.c)),
+            Line::from(format!("DE {:02X}{:02X}  HL {:02X}{:02X}", r.d, r.e, r.h, r.l)),
+            Line::from(format!("SP {:04X}  PC {:04X}", r.sp, r.pc)),
+            Line::from(format!("Flags {}{}{}{}", flag(0x80, 'Z'), flag(0x40, 'N'), flag(0x20, 'H'), flag(0x10, 'C'))),
+            Line::from(format!("IME {}  HALT {}", r.ime as u8, r.halted as u8)),
+            Line::from(""),
+            Line::from(format!("LY {:3}  mode {}", ppu.ly, ppu.mode())),
+            Line::from(format!("LCDC {:02X}  STAT {:02X}", ppu.lcdc, ppu.stat)),
+            Line::from(format!("Frame {}", self.core.frames_rendered())),
+        ];
+
+        Paragraph::new(lines).block(Block::bordered().title("Registers"))
+    }
+
+    fn watch_list(&self) -> Paragraph<'_> {
+        let lines = self.watches.iter()
+            .enumerate()
+            .map(|(i, expr)| {
+                let value = expr.eval(&self.core);
+                // `[FF40 LCDC]` rather than `[$FF40]`
+                let name = expr.address().map_or_else(|| expr.to_string(), |addr| format!("[{}]", describe_address(addr)));
+                Line::from(format!("{:2}  {} = ${:X} ({})", i + 1, name, value, value))
+            })
+            .collect::<Vec<_>>();
+
+        Paragraph::new(lines).block(Block::bordered().title("Watch"))
+    }
+
+    fn memory(&self) -> Paragraph<'_> {
+        let lines = self.core.hexdump(self.memory_addr, MEMORY_ROWS as usize * 16)
+            .lines()
+            .map(|line| Line::from(line.to_string()))
+            .collect::<Vec<_>>();
+
+        Paragraph::new(lines).block(Block::bordered().title("Memory"))
+    }
+}
+
+fn escape_pressed() -> bool {
+    matches!(
+        event::poll(Duration::ZERO).and_then(|ready| if ready { event::read().map(Some) } else { Ok(None) }),
+        Ok(Some(Event::Key(key))) if key.kind == KeyEventKind::Press && key.code == KeyCode::Esc
+    )
+}
+
+fn run_terminal(terminal: &mut DefaultTerminal, debugger: &mut Debugger) -> io::Result<()> {
+    while !debugger.quit {
+        terminal.draw(|frame| debugger.draw(frame))?;
+
+        let Event::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => debugger.quit = true,
+            KeyCode::Char(c) => debugger.input.push(c),
+            KeyCode::Backspace => {
+                debugger.input.pop();
+            }
+            KeyCode::Enter => {
+                debugger.message = "Running, Esc to stop".to_string();
+                terminal.draw(|frame| debugger.draw(frame))?;
+                debugger.submit(escape_pressed);
+            }
+            KeyCode::Esc => debugger.input.clear(),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Takes over the terminal with the debugger until the user quits, starting with `watches` in
+/// the watch list.
+pub fn run(core: Core, watches: Vec<Expr>) -> io::Result<()> {
+    let mut debugger = Debugger::new(core);
+    debugger.watches = watches;
+
+    let mut terminal = ratatui::init();
+    let result = run_terminal(&mut terminal, &mut debugger);
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use dmg::dmg::core::{Core, CoreBuilder};
+    use dmg::dmg::expr::Expr;
+    use dmg::test_support::looping_rom;
+    use crate::debugger::{parse_command, Command, Debugger};
+
+    // Counts up in A at the entry point: inc a; jr -3
+    fn counting_core() -> Core {
+        CoreBuilder::new().game_rom_data(looping_rom(&[0x3c, 0x18, 0xfd])).build().unwrap()
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse_command("s"), Ok(Command::Step(1)));
+        assert_eq!(parse_command("step 10"), Ok(Command::Step(10)));
+        assert_eq!(parse_command("b $c000"), Ok(Command::ToggleBreakpoint(0xc000)));
+        assert_eq!(parse_command("m 0xff40"), Ok(Command::Memory(0xff40)));
+        assert_eq!(parse_command("w c000 $3f"), Ok(Command::Write(0xc000, 0x3f)));
+        assert!(parse_command("w c000").is_err());
+        assert!(parse_command("b").is_err());
+        assert!(parse_command("b xyz").is_err());
+        assert!(parse_command("jump").is_err());
+        assert_eq!(parse_command("x [HL+1] * 256 + [HL]"), Ok(Command::Watch(Expr::parse("[HL+1] * 256 + [HL]").unwrap())));
+        assert_eq!(parse_command("u 2"), Ok(Command::Unwatch(2)));
+        assert!(parse_command("x [c000").is_err());
+    }
+
+    #[test]
+    fn continue_stops_at_breakpoint() {
+        let core = counting_core();
+        let mut debugger = Debugger::new(core);
+        let a = debugger.core.registers().a;
+
+        debugger.execute(Command::ToggleBreakpoint(0x101), || true);
+        debugger.execute(Command::Continue, || true);
+        assert_eq!(debugger.core.registers().pc, 0x101);
+        debugger.execute(Command::Continue, || true);
+        assert_eq!(debugger.core.registers().a, a.wrapping_add(2));
+        assert_eq!(debugger.message, "Breakpoint at 0101");
+    }
+
+    #[test]
+    fn empty_line_repeats_last_command() {
+        let core = counting_core();
+        let mut debugger = Debugger::new(core);
+        let a = debugger.core.registers().a;
+
+        debugger.input = "s 2".to_string();
+        debugger.submit(|| true);
+        debugger.submit(|| true);
+        assert_eq!(debugger.core.registers().a, a.wrapping_add(2));
+        assert_eq!(debugger.last_command, Some(Command::Step(2)));
+    }
+
+    #[test]
+    fn watches_follow_the_core() {
+        let core = counting_core();
+        let mut debugger = Debugger::new(core);
+
+        debugger.execute(Command::Watch(Expr::parse("a").unwrap()), || true);
+        let before = debugger.watches[0].eval(&debugger.core);
+        debugger.execute(Command::Step(2), || true);
+        assert_eq!(debugger.watches[0].eval(&debugger.core), (before + 1) & 0xff);
+
+        debugger.execute(Command::Unwatch(2), || true);
+        assert_eq!(debugger.message, "No watch 2");
+        debugger.execute(Command::Unwatch(1), || true);
+        assert!(debugger.watches.is_empty());
+    }
+}
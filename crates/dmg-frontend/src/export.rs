@@ -0,0 +1,58 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use dmg::dmg::core::Core;
+use dmg::dmg::crash::CrashReport;
+use dmg::dmg::export::Export;
+use crate::screenshot::unused_path;
+
+/// Writes every export of `core` to a new directory in `dir` named after the current time, one
+/// file per `Export` named by `Export::file_name`. Cartridge RAM is left out for cartridges
+/// without any.
+pub fn save_exports(dir: impl AsRef<Path>, core: &Core) -> io::Result<PathBuf> {
+    let dir = unused_path(dir.as_ref(), "export", "");
+    fs::create_dir_all(&dir)?;
+
+    for export in Export::ALL {
+        let bytes = core.export(export);
+        if !bytes.is_empty() {
+            fs::write(dir.join(export.file_name()), bytes)?;
+        }
+    }
+
+    Ok(dir)
+}
+
+/// Writes `report` as text to a new file in `dir` named after the current time, to attach to bug
+/// reports.
+pub fn save_crash_report(dir: impl AsRef<Path>, report: &CrashReport) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir.as_ref())?;
+    let path = unused_path(dir.as_ref(), "crash", "txt");
+
+    let mut writer = BufWriter::new(File::create(&path)?);
+    report.write_to(&mut writer)?;
+    writer.flush()?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use dmg::dmg::core::CoreBuilder;
+    use dmg::test_support::looping_rom;
+    use crate::export::save_exports;
+
+    #[test]
+    fn saves_a_file_per_export() {
+        let core = CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap();
+
+        let dir = save_exports(env::temp_dir().join("saves_a_file_per_export"), &core).unwrap();
+
+        assert_eq!(fs::metadata(dir.join("wram.bin")).unwrap().len(), 0x2000);
+        assert_eq!(fs::metadata(dir.join("vram.chr")).unwrap().len(), 0x1800);
+        assert!(fs::read_to_string(dir.join("oam.csv")).unwrap().starts_with("sprite,"));
+        assert!(!dir.join("cartridge_ram.sav").exists());
+    }
+}
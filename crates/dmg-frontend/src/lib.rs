@@ -0,0 +1,17 @@
+//! The parts of the frontends that are shared between the binaries: audio output, settings,
+//! screenshots, memory exports, recordings and the terminal debugger. The emulator itself is in `dmg-core`.
+
+#[cfg(feature = "frontend")]
+pub mod audio;
+#[cfg(feature = "frontend")]
+pub mod config;
+#[cfg(feature = "debugger")]
+pub mod debugger;
+#[cfg(feature = "frontend")]
+pub mod export;
+#[cfg(feature = "frontend")]
+pub mod recorder;
+#[cfg(feature = "frontend")]
+pub mod screenshot;
+#[cfg(feature = "frontend")]
+pub mod snapshot;
@@ -0,0 +1,1162 @@
+use std::env;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use minifb::{Key, KeyRepeat, Scale, Window, WindowOptions};
+
+use dmg::dmg::barcode::BarcodeBoy;
+use dmg::dmg::boot_rom::find_boot_rom;
+use dmg::dmg::core::{Core, CoreBuilder, MAX_SPEED, MAX_VOLUME, MIN_SPEED};
+use dmg::dmg::debug::{VRAM_VIEW_HEIGHT, VRAM_VIEW_WIDTH};
+use dmg::dmg::expr::Expr;
+use dmg::dmg::harness::{compare_cycle_log, write_cycle_log};
+use dmg::dmg::input::JoypadInput;
+use dmg::dmg::link::LinkCable;
+use dmg::dmg::model::{Model, PowerOnRam, Quirks};
+use dmg::dmg::serial_bridge::SerialBridge;
+use dmg::dmg::traits::{AudioSink, NullAudioSink, NullVideoSink};
+use dmg::emulator::battery::BatterySave;
+use dmg::emulator::bk2::import_bk2;
+use dmg::emulator::link_session::{LinkRecorder, LinkReplay};
+use dmg::emulator::movie::{load_movie, save_movie, Movie, MoviePlayer};
+use dmg::emulator::rewind::RewindBuffer;
+use dmg::emulator::save_location::SaveLocation;
+use dmg::emulator::scale::scale_to_window;
+use dmg::emulator::state::{SaveStateManager, StateError};
+use dmg::emulator::timing::{AudioPacer, FramePacer, TimingStats};
+use dmg::emulator::turbo::Turbo;
+
+use dmg::{CLOCK_SPEED, FRAME_DURATION, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use frontend::audio::setup_audio_device;
+use frontend::config::{Config, ConfigFile, CONFIG_FILE};
+use frontend::export::{save_crash_report, save_exports};
+use frontend::recorder::{FrameDump, GifRecorder, Recorder};
+use frontend::screenshot::{save_frame, save_screenshot};
+
+// Initial window size as a multiple of the screen size
+const WINDOW_SCALE: usize = 4;
+// Smaller with two screens side by side
+const LINKED_WINDOW_SCALE: usize = 3;
+
+const SCREENSHOT_DIR: &str = "screenshots";
+const RECORDING_DIR: &str = "recordings";
+const EXPORT_DIR: &str = "exports";
+const CRASH_DIR: &str = "crashes";
+
+// Frames dumped to PNGs per press of the burst key, about two seconds
+const DEFAULT_BURST_FRAMES: u32 = 120;
+
+// Instructions written by --cycle-log, a few seconds of emulated time
+const DEFAULT_CYCLE_LOG_INSTRUCTIONS: u64 = 1_000_000;
+
+// Frames run by --headless without a movie, about ten seconds
+const DEFAULT_HEADLESS_FRAMES: u32 = 600;
+const HEADLESS_OUTPUT_DIR: &str = "headless";
+
+// Take a rewind snapshot every few frames, keeping several minutes of history
+const REWIND_INTERVAL: u64 = 4;
+const REWIND_CAPACITY: usize = 16 * 1024 * 1024;
+
+// Volume change per key press, 1.0 being the default level
+const VOLUME_STEP: f32 = 0.1;
+
+// Speed while fast-forwarding, as a multiple of normal speed
+const DEFAULT_FAST_FORWARD_SPEED: f32 = 4.0;
+
+#[derive(Default)]
+struct Args {
+    game_rom: Option<String>,
+    boot_rom: Option<String>,
+    skip_boot_rom: bool,
+    record_movie: Option<String>,
+    play_movie: Option<String>,
+    resume: bool,
+    autosave_interval: Option<Duration>,
+    speed: Option<f32>,
+    fast_forward_speed: Option<f32>,
+    recording_format: Option<String>,
+    burst_frames: Option<u32>,
+    colorize: bool,
+    palette: Option<String>,
+    model: Model,
+    dmg0: bool,
+    stat_write_bug: Option<bool>,
+    power_on_ram: PowerOnRam,
+    run_ahead: u32,
+    save_location: Option<SaveLocation>,
+    debug: bool,
+    coverage: Option<String>,
+    diagnose: bool,
+    developer: bool,
+    barcodes: Vec<String>,
+    watches: Vec<String>,
+    cycle_log: Option<String>,
+    compare_cycle_log: Option<String>,
+    instructions: Option<u64>,
+    headless: bool,
+    frames: Option<u32>,
+    output: Option<String>,
+    bench: Option<Duration>,
+    link: bool,
+    link_rom: Option<String>,
+    serial_stdio: bool,
+    serial_pipes: Option<(String, String)>,
+    record_link: Option<String>,
+    replay_link: Option<String>,
+}
+
+fn parse_args() -> Args {
+    let mut args = Args::default();
+    let mut iter = env::args().skip(1);
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--boot-rom" => args.boot_rom = iter.next(),
+            "--skip-boot-rom" => args.skip_boot_rom = true,
+            "--record-movie" => args.record_movie = iter.next(),
+            "--play-movie" => args.play_movie = iter.next(),
+            "--resume" => args.resume = true,
+            "--autosave-interval" => {
+                args.autosave_interval = iter.next()
+                    .and_then(|s| s.parse().ok())
+                    .map(Duration::from_secs);
+            }
+            "--speed" => args.speed = iter.next().and_then(|s| s.parse().ok()),
+            "--fast-forward-speed" => args.fast_forward_speed = iter.next().and_then(|s| s.parse().ok()),
+            "--recording-format" => args.recording_format = iter.next(),
+            "--burst-frames" => args.burst_frames = iter.next().and_then(|s| s.parse().ok()),
+            "--colorize" => args.colorize = true,
+            "--palette" => args.palette = iter.next(),
+            "--debug" => args.debug = true,
+            "--coverage" => args.coverage = iter.next(),
+            "--diagnose" => args.diagnose = true,
+            "--developer" => args.developer = true,
+            "--barcode" => args.barcodes.extend(iter.next()),
+            "--watch" => args.watches.extend(iter.next()),
+            "--cycle-log" => args.cycle_log = iter.next(),
+            "--compare-cycle-log" => args.compare_cycle_log = iter.next(),
+            "--instructions" => args.instructions = iter.next().and_then(|s| s.parse().ok()),
+            "--headless" => args.headless = true,
+            "--frames" => args.frames = iter.next().and_then(|s| s.parse().ok()),
+            "--output" => args.output = iter.next(),
+            "--bench" => args.bench = iter.next().and_then(|s| s.parse().ok()).map(Duration::from_secs_f64),
+            "--link" => args.link = true,
+            "--link-rom" => {
+                args.link = true;
+                args.link_rom = iter.next();
+            }
+            "--serial-stdio" => args.serial_stdio = true,
+            "--serial-pipes" => args.serial_pipes = iter.next().zip(iter.next()),
+            "--record-link" => args.record_link = iter.next(),
+            "--replay-link" => args.replay_link = iter.next(),
+            "--model" => {
+                let name = iter.next();
+                args.dmg0 = name.as_deref() == Some("dmg0");
+                args.model = name.and_then(|s| parse_model(&s)).unwrap_or_default();
+            }
+            "--stat-write-bug" => args.stat_write_bug = iter.next().map(|s| s != "off"),
+            "--save-dir" => args.save_location = iter.next().map(|s| parse_save_location(&s)),
+            "--power-on-ram" => args.power_on_ram = iter.next().and_then(|s| parse_power_on_ram(&s)).unwrap_or_default(),
+            "--run-ahead" => args.run_ahead = iter.next().and_then(|s| s.parse().ok()).unwrap_or_default(),
+            _ => args.game_rom = Some(arg),
+        }
+    }
+
+    args
+}
+
+impl Args {
+    /// The model's usual quirks, unless `--model dmg0` or `--stat-write-bug` changed them.
+    fn quirks(&self) -> Quirks {
+        let mut quirks = self.model.quirks();
+        quirks.dmg0_registers = self.dmg0;
+        if let Some(stat_write_bug) = self.stat_write_bug {
+            quirks.stat_write_bug = stat_write_bug;
+        }
+        quirks
+    }
+}
+
+fn parse_model(name: &str) -> Option<Model> {
+    match name {
+        "dmg" | "dmg0" => Some(Model::Dmg),
+        "mgb" => Some(Model::Mgb),
+        "sgb" => Some(Model::Sgb),
+        "cgb" => Some(Model::Cgb),
+        _ => None,
+    }
+}
+
+// `rom` for next to the ROM, `data` for the user's data directory or a directory of per-ROM
+// subdirectories
+fn parse_save_location(dir: &str) -> SaveLocation {
+    match dir {
+        "rom" => SaveLocation::AlongsideRom,
+        "data" => SaveLocation::data_dir().unwrap_or_else(|| {
+            eprintln!("No data directory found, saving next to the ROM");
+            SaveLocation::AlongsideRom
+        }),
+        _ => SaveLocation::PerRom(PathBuf::from(dir)),
+    }
+}
+
+// `zeroed`, `fill:<hex byte>`, `random` or `random:<seed>`
+fn parse_power_on_ram(policy: &str) -> Option<PowerOnRam> {
+    match policy.split_once(':') {
+        None if policy == "zeroed" => Some(PowerOnRam::Zeroed),
+        None if policy == "random" => Some(PowerOnRam::random()),
+        Some(("fill", byte)) => u8::from_str_radix(byte.trim_start_matches("0x"), 16).ok().map(PowerOnRam::Fill),
+        Some(("random", seed)) => seed.parse().ok().map(PowerOnRam::Random),
+        _ => None,
+    }
+}
+
+fn main() {
+    // Subsystem diagnostics, e.g. RUST_LOG=apu=debug,serial=debug
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let args = parse_args();
+    let quirks = args.quirks();
+    let game_rom = args.game_rom;
+    let save_location = args.save_location.clone().unwrap_or_default();
+
+    if let Some(name) = &game_rom {
+        eprintln!("Loading {}", name);
+    }
+    // So a run with random RAM can be repeated with --power-on-ram random:<seed>
+    if let PowerOnRam::Random(seed) = args.power_on_ram {
+        eprintln!("Power-on RAM seed {}", seed);
+    }
+
+    let watches: Vec<Expr> = args.watches.iter()
+        .map(|text| Expr::parse(text))
+        .collect::<dmg::Result<_>>()
+        .unwrap_or_else(|e| exit_with_error(e));
+
+    if args.debug {
+        let core = load_core(&args.boot_rom, args.skip_boot_rom, game_rom, args.model, quirks, args.power_on_ram, args.run_ahead > 0).unwrap_or_else(|e| exit_with_error(e));
+        run_debugger(core, watches);
+        return;
+    }
+
+    if let Some(path) = &args.cycle_log {
+        let mut core = load_core(&args.boot_rom, args.skip_boot_rom, game_rom, args.model, quirks, args.power_on_ram, args.run_ahead > 0).unwrap_or_else(|e| exit_with_error(e));
+        let instructions = args.instructions.unwrap_or(DEFAULT_CYCLE_LOG_INSTRUCTIONS);
+        let result = File::create(path).and_then(|file| write_cycle_log(&mut core, instructions, BufWriter::new(file)));
+        match result {
+            Ok(()) => eprintln!("Wrote {} instructions to {}", instructions, path),
+            Err(e) => {
+                eprintln!("Failed writing cycle log {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(path) = &args.compare_cycle_log {
+        let mut core = load_core(&args.boot_rom, args.skip_boot_rom, game_rom, args.model, quirks, args.power_on_ram, args.run_ahead > 0).unwrap_or_else(|e| exit_with_error(e));
+        let reference = File::open(path).unwrap_or_else(|e| {
+            eprintln!("Failed reading cycle log {}: {}", path, e);
+            process::exit(1);
+        });
+        match compare_cycle_log(&mut core, BufReader::new(reference)).unwrap_or_else(|e| exit_with_error(e)) {
+            Some(divergence) => {
+                eprintln!("{}", divergence);
+                process::exit(1);
+            }
+            None => eprintln!("Matched all of {}", path),
+        }
+        return;
+    }
+
+    if let Some(duration) = args.bench {
+        let core = load_core(&args.boot_rom, args.skip_boot_rom, game_rom, args.model, quirks, args.power_on_ram, args.run_ahead > 0).unwrap_or_else(|e| exit_with_error(e));
+        run_bench(core, duration);
+        return;
+    }
+
+    if args.headless {
+        let mut core = load_core(&args.boot_rom, args.skip_boot_rom, game_rom.clone(), args.model, quirks, args.power_on_ram, args.run_ahead > 0).unwrap_or_else(|e| exit_with_error(e));
+        if args.colorize {
+            core.set_colorization(Some(core.boot_rom_colorization()));
+        }
+        let movie_player = args.play_movie.as_deref().map(|path| start_movie(path, &mut core));
+        let output = args.output.as_deref().unwrap_or(HEADLESS_OUTPUT_DIR);
+
+        if let Err(e) = run_headless(core, movie_player, args.frames, game_rom.as_deref(), Path::new(output)) {
+            eprintln!("Failed writing headless results to {}: {}", output, e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if args.link {
+        let link_rom = args.link_rom.clone().or_else(|| game_rom.clone());
+        let battery_saves = [
+            game_rom.as_ref().map(|rom| BatterySave::at(save_location.battery_path(rom))),
+            // A second copy of the same game keeps its own save next to the first one's
+            link_rom.as_ref().map(|rom| match &game_rom {
+                Some(first) if first == rom => BatterySave::at(save_location.battery_path(rom).with_extension("p2.sav")),
+                _ => BatterySave::at(save_location.battery_path(rom)),
+            }),
+        ];
+        let cores = [
+            load_core(&args.boot_rom, args.skip_boot_rom, game_rom, args.model, quirks, args.power_on_ram, args.run_ahead > 0).unwrap_or_else(|e| exit_with_error(e)),
+            load_core(&args.boot_rom, args.skip_boot_rom, link_rom, args.model, quirks, args.power_on_ram, args.run_ahead > 0).unwrap_or_else(|e| exit_with_error(e)),
+        ];
+        run_linked(cores, battery_saves, args.record_link.as_deref());
+        return;
+    }
+
+    let mut fullscreen = false;
+    let mut window = open_window("gameboy", fullscreen);
+    let mut window_buffer: Vec<u32> = vec![];
+
+    let (mut audio_player, _audio_stream) = setup_audio_device().unwrap_or_else(|e| exit_with_error(e));
+
+    let mut battery_save = game_rom.as_ref().map(|rom| BatterySave::at(save_location.battery_path(rom)));
+    // Save states stay in the working directory unless --save-dir says where they go
+    let state_directory = args.save_location.as_ref().zip(game_rom.as_ref())
+        .map(|(location, rom)| location.state_directory(rom))
+        .unwrap_or_else(|| PathBuf::from("states"));
+    let mut core = load_core(&args.boot_rom, args.skip_boot_rom, game_rom, args.model, quirks, args.power_on_ram, args.run_ahead > 0).unwrap_or_else(|e| exit_with_error(e));
+    if args.colorize {
+        core.set_colorization(Some(core.boot_rom_colorization()));
+    }
+    if let Some(palette) = &args.palette {
+        core.load_palette(palette).unwrap_or_else(|e| exit_with_error(e));
+    }
+    if args.coverage.is_some() {
+        core.enable_coverage();
+    }
+    if args.diagnose {
+        core.enable_execution_diagnostics();
+    }
+    if args.developer {
+        core.enable_developer_mode();
+    }
+
+    if let Some(battery_save) = &battery_save {
+        if let Err(e) = battery_save.load(&mut core) {
+            eprintln!("Failed loading {}: {}", battery_save.path().display(), e);
+        }
+    }
+
+    let save_states = SaveStateManager::new(state_directory, &core);
+
+    if args.resume && args.play_movie.is_none() {
+        match save_states.load_autosave() {
+            Ok(c) => core.restore_from(c),
+            Err(StateError::Io(_)) => eprintln!("No autosave to resume from"),
+            Err(e) => eprintln!("Failed resuming from autosave: {}", e),
+        }
+    }
+
+    let mut movie_player = args.play_movie.as_deref().map(|path| start_movie(path, &mut core));
+
+    let mut movie_recording = args.record_movie.as_ref().map(|_| Movie::starting_from(&core));
+
+    // core.initialize_gameboy_doctor();
+
+    let title = core.cartridge_header().title();
+
+    window.set_title(&title);
+
+    let mut turbo = Turbo::default();
+
+    let mut frame_advance = false;
+    let mut last_autosave = Instant::now();
+
+    let mut rewind_buffer = RewindBuffer::new(REWIND_CAPACITY);
+    let mut frames: u64 = 0;
+
+    let fast_forward_speed = args.fast_forward_speed.unwrap_or(DEFAULT_FAST_FORWARD_SPEED).clamp(1.0, MAX_SPEED);
+    // The speed when not fast-forwarding, slowed down with the slow motion key
+    let mut normal_speed = args.speed.unwrap_or(1.0).clamp(MIN_SPEED, MAX_SPEED);
+    // Whether frames are paced by `pacer` or sleeping rather than by the window's frame rate cap
+    let mut pacing_by_hand = false;
+    let mut pacer = FramePacer::new();
+    let audio_pacer = AudioPacer::new(audio_player.sample_rate);
+    let mut timing = TimingStats::new();
+    // Stands in for the audio device while fast-forwarding muted
+    let mut muted_audio = NullAudioSink;
+
+    let config_file = ConfigFile::new(CONFIG_FILE);
+    let mut config = config_file.load();
+
+    let mut recorder: Option<Box<dyn Recorder>> = None;
+    let mut recorded_audio = vec![];
+
+    let burst_frames = args.burst_frames.unwrap_or(DEFAULT_BURST_FRAMES).max(1);
+    let mut frame_dump: Option<FrameDump> = None;
+
+    // Swiped in turn, starting over after the last
+    let barcode_boy = (!args.barcodes.is_empty()).then(BarcodeBoy::new);
+    let mut next_barcode = 0;
+
+    let serial_bridge = open_serial_bridge(args.serial_stdio, args.serial_pipes.as_ref());
+
+    // Stands in for the other Game Boy of a session recorded with --link --record-link
+    let link_replay = args.replay_link.as_deref().map(|path| {
+        let session = File::open(path).unwrap_or_else(|e| {
+            eprintln!("Failed reading link session {}: {}", path, e);
+            process::exit(1);
+        });
+        let replay = LinkReplay::load(BufReader::new(session)).unwrap_or_else(|e| exit_with_error(e));
+        eprintln!("Replaying link session {} ({} transfers)", path, replay.remaining());
+        replay
+    });
+
+    // Logged whenever they change from one frame to the next
+    let mut watched_values: Vec<Option<u32>> = vec![None; watches.len()];
+
+    let mut vram_window: Option<Window> = None;
+    let mut vram_buffer = vec![0; VRAM_VIEW_WIDTH * VRAM_VIEW_HEIGHT];
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        let update_started = Instant::now();
+
+        if window.is_key_pressed(Key::F11, KeyRepeat::No) {
+            fullscreen = !fullscreen;
+            window = open_window(&title, fullscreen);
+            window.set_target_fps(if pacing_by_hand { 0 } else { 60 });
+        }
+
+        if window.is_key_pressed(Key::L, KeyRepeat::No) {
+            normal_speed = if normal_speed > 0.5 {
+                0.5
+            } else if normal_speed > 0.25 {
+                0.25
+            } else {
+                1.0
+            };
+            eprintln!("Speed {}%", normal_speed * 100.0);
+        }
+
+        let fast_forward = window.is_key_down(Key::Tab);
+        // Recordings take the audio as it's emitted, which has to stay at the emulated rate, so
+        // they always run at normal speed. Speed isn't part of save states, so this also covers
+        // cores loaded from one.
+        let speed = if recorder.is_some() {
+            1.0
+        } else if fast_forward && !frame_advance {
+            fast_forward_speed
+        } else {
+            normal_speed
+        };
+        core.set_speed(speed);
+
+        // Samples are still generated and recorded, just not queued for the device
+        let muted_fast_forward = fast_forward && config.mute_fast_forward;
+        // Pacing by audio needs the device to be taking the samples
+        let audio_synced = config.audio_sync && !muted_fast_forward;
+
+        // With vsync the window's 60 FPS cap paces normal speed, otherwise frames are always paced
+        // by hand
+        if (speed != 1.0 || !config.vsync || audio_synced) != pacing_by_hand {
+            pacing_by_hand = !pacing_by_hand;
+            window.set_target_fps(if pacing_by_hand { 0 } else { 60 });
+        }
+        if muted_fast_forward && window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+            audio_player.clear();
+        }
+
+        let advance_frame = window.is_key_pressed(Key::F, KeyRepeat::Yes);
+        if advance_frame {
+            frame_advance = true;
+        } else if window.is_key_pressed(Key::G, KeyRepeat::No) {
+            frame_advance = false;
+        }
+
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            core.set_paused(!core.is_paused());
+            if core.is_paused() {
+                audio_player.clear();
+            }
+        }
+
+        if handle_volume_keys(&window, &mut config) {
+            if let Err(e) = config_file.save(&config) {
+                eprintln!("Failed saving config: {}", e);
+            }
+        }
+        // Volume isn't part of save states, so this also covers cores loaded from one
+        core.set_volume(if config.muted { 0.0 } else { config.volume });
+        core.set_pop_filter(config.pop_filter);
+        core.set_resampler(config.resampler);
+
+        // Neither is the link port's device
+        if let Some(serial_bridge) = &serial_bridge {
+            core.connect_serial(serial_bridge.clone());
+        }
+        if let Some(link_replay) = &link_replay {
+            core.connect_serial(link_replay.clone());
+        }
+        if let Some(barcode_boy) = &barcode_boy {
+            core.connect_serial(barcode_boy.clone());
+
+            if window.is_key_pressed(Key::B, KeyRepeat::No) {
+                let barcode = &args.barcodes[next_barcode % args.barcodes.len()];
+                next_barcode += 1;
+                match barcode_boy.scan(barcode) {
+                    Ok(()) => eprintln!("Swiped barcode {}", barcode),
+                    Err(e) => eprintln!("Failed swiping barcode: {}", e),
+                }
+            }
+        }
+
+        if window.is_key_pressed(Key::F9, KeyRepeat::No) {
+            match recorder.take() {
+                Some(r) => finish_recording(r),
+                None => recorder = start_recording(args.recording_format.as_deref()),
+            }
+        }
+
+        if window.is_key_pressed(Key::F7, KeyRepeat::No) {
+            match frame_dump.take() {
+                Some(dump) => finish_recording(Box::new(dump)),
+                None => frame_dump = start_frame_dump(burst_frames),
+            }
+        }
+
+        if window.is_key_pressed(Key::F8, KeyRepeat::No) {
+            vram_window = match vram_window {
+                Some(_) => None,
+                None => Some(open_vram_window()),
+            };
+        }
+
+        let rewinding = window.is_key_down(Key::Backspace) && movie_recording.is_none() && movie_player.is_none();
+        let mut ran_frames = false;
+        let mut predicted_frame = None;
+
+        if core.is_paused() {
+            // Keep presenting the last frame so the window stays responsive
+        } else if rewinding {
+            if let Some(c) = rewind_buffer.rewind() {
+                core.restore_from(c);
+                core.step_frame(&mut NullVideoSink, &mut audio_player, &mut JoypadInput::empty());
+            }
+        } else if !frame_advance || advance_frame {
+            ran_frames = true;
+            // Only the last of the frames run while fast-forwarding is drawn
+            let frames_this_update = if frame_advance { 1 } else { core.frames_for_update() };
+            let mut last_keys = JoypadInput::empty();
+
+            for i in 0..frames_this_update {
+                // Every frame of a burst is drawn, even while fast-forwarding
+                core.set_frame_skip(i + 1 < frames_this_update && frame_dump.is_none());
+
+                // Input is latched once per frame so that recorded movies replay deterministically
+                let mut keys_pressed = next_frame_input(&window, &turbo, &movie_player);
+                last_keys = keys_pressed;
+
+                // The core resamples for the speed, so the device takes the samples as they are
+                let mut audio: &mut dyn AudioSink = if muted_fast_forward { &mut muted_audio } else { &mut audio_player };
+
+                if let Some(r) = &mut recorder {
+                    core.step_frame(&mut NullVideoSink, &mut (&mut audio, &mut recorded_audio), &mut keys_pressed);
+                    if let Err(e) = r.push_frame(core.emulated_time(), core.frame(), &recorded_audio) {
+                        eprintln!("Failed recording frame: {}", e);
+                        recorder = None;
+                    }
+                    recorded_audio.clear();
+                } else {
+                    core.step_frame(&mut NullVideoSink, &mut audio, &mut keys_pressed);
+                }
+
+                if let Some(dump) = &mut frame_dump {
+                    let result = dump.push_frame(core.emulated_time(), core.frame(), &[]);
+                    if let Err(e) = result {
+                        eprintln!("Failed dumping frame: {}", e);
+                        frame_dump = None;
+                    } else if dump.frames() >= burst_frames {
+                        finish_recording(Box::new(frame_dump.take().unwrap()));
+                    }
+                }
+
+                if let Some(movie) = &mut movie_recording {
+                    movie.record_frame(keys_pressed);
+                }
+                if let Some(player) = &mut movie_player {
+                    player.next_frame();
+                    if player.is_finished() {
+                        eprintln!("Movie playback finished");
+                        movie_player = None;
+                    }
+                }
+                turbo.next_frame();
+                timing.frame_finished(core.emulated_time());
+
+                for (expr, last) in watches.iter().zip(&mut watched_values) {
+                    let value = expr.eval(&core);
+                    if last.replace(value) != Some(value) {
+                        eprintln!("Frame {}: {} = ${:X} ({})", core.frames_rendered(), expr, value, value);
+                    }
+                }
+                for diagnostic in core.take_diagnostics() {
+                    eprintln!("Warning: {}", diagnostic);
+                }
+                if let Some(report) = core.take_crash() {
+                    eprintln!("Emulation crashed: {}", report);
+                    match save_crash_report(CRASH_DIR, &report) {
+                        Ok(path) => eprintln!("Saved crash report to {}", path.display()),
+                        Err(e) => eprintln!("Failed saving crash report: {}", e),
+                    }
+                }
+
+                frames += 1;
+                if frames.is_multiple_of(REWIND_INTERVAL) {
+                    rewind_buffer.push(&core);
+                }
+            }
+
+            // Shows where the keys held now lead, while the game carries on from the real frame
+            if args.run_ahead > 0 {
+                predicted_frame = Some(core.predict_frame(last_keys, args.run_ahead));
+            }
+        }
+
+        if core.is_paused() {
+            present(&mut window, &dimmed(core.frame()), &mut window_buffer);
+        } else {
+            present(&mut window, predicted_frame.as_deref().unwrap_or(core.frame()), &mut window_buffer);
+        }
+
+        if let Some(w) = &mut vram_window {
+            if w.is_open() {
+                core.render_vram(&mut vram_buffer);
+                w.update_with_buffer(&vram_buffer, VRAM_VIEW_WIDTH, VRAM_VIEW_HEIGHT).unwrap();
+            } else {
+                vram_window = None;
+            }
+        }
+
+        if timing.frame_presented() {
+            if core.is_paused() {
+                window.set_title(&format!("{} - Paused", title));
+            } else if core.is_running_boot_rom() {
+                window.set_title(&format!("{} - Booting", title));
+            } else {
+                window.set_title(&format!("{} - {:.0} FPS - {:.0}%", title, timing.host_fps(), timing.speed_percent()));
+            }
+        }
+
+        if pacing_by_hand && ran_frames && audio_synced {
+            audio_pacer.wait(&audio_player);
+        } else if pacing_by_hand && ran_frames && !config.vsync {
+            pacer.wait(core.emulated_time(), core.speed() as f64);
+        } else if pacing_by_hand {
+            // Nothing was emulated to pace by, e.g. while paused or rewinding, or vsync is on and
+            // fast-forward and slow motion take a fixed time per update
+            let frame_time = FRAME_DURATION.div_f32(speed.min(1.0));
+            if let Some(remaining) = frame_time.checked_sub(update_started.elapsed()) {
+                thread::sleep(remaining);
+            }
+        }
+
+        if window.is_key_down(Key::LeftSuper) && window.is_key_pressed(Key::S, KeyRepeat::No) {
+            let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+            take_screenshot(&window, core.frame(), shift);
+        }
+
+        if window.is_key_pressed(Key::F5, KeyRepeat::No) {
+            reload_boot_rom_and_palette(&mut core, args.boot_rom.as_deref(), args.palette.as_deref());
+        }
+
+        if window.is_key_down(Key::LeftSuper) && window.is_key_pressed(Key::E, KeyRepeat::No) {
+            match save_exports(EXPORT_DIR, &core) {
+                Ok(path) => eprintln!("Saved exports to {}", path.display()),
+                Err(e) => eprintln!("Failed saving exports: {}", e),
+            }
+        }
+
+        handle_save_state_keys(&window, &mut core, &save_states);
+
+        if window.is_key_down(Key::LeftSuper) && window.is_key_pressed(Key::R, KeyRepeat::No) {
+            core.reset();
+            eprintln!("Reset");
+        }
+
+        if let Some(battery_save) = &mut battery_save {
+            if let Err(e) = battery_save.update(&mut core) {
+                eprintln!("Failed writing {}: {}", battery_save.path().display(), e);
+            }
+        }
+
+        if args.autosave_interval.is_some_and(|interval| last_autosave.elapsed() >= interval) {
+            autosave(&core, &save_states);
+            last_autosave = Instant::now();
+        }
+    }
+
+    autosave(&core, &save_states);
+
+    if let Some(battery_save) = &mut battery_save {
+        if let Err(e) = battery_save.flush(&mut core) {
+            eprintln!("Failed writing {}: {}", battery_save.path().display(), e);
+        }
+    }
+
+    if let Some(r) = recorder {
+        finish_recording(r);
+    }
+    if let Some(dump) = frame_dump {
+        finish_recording(Box::new(dump));
+    }
+
+    if let (Some(path), Some(movie)) = (&args.record_movie, &movie_recording) {
+        match save_movie(path, movie) {
+            Ok(_) => eprintln!("Saved movie to {} ({} frames)", path, movie.len()),
+            Err(e) => eprintln!("Failed saving movie: {}", e),
+        }
+    }
+
+    if let (Some(path), Some(coverage)) = (&args.coverage, core.coverage()) {
+        match coverage.save(path) {
+            Ok(_) => eprintln!("Saved coverage to {}", path),
+            Err(e) => eprintln!("Failed saving coverage: {}", e),
+        }
+    }
+}
+
+/// Runs two cores joined by a link cable side by side in one window, for local two-player games.
+/// Player 1 is on the left with the usual keys and the only one heard, player 2 on the right with
+/// `detect_player_two_keys`. The cores take turns a frame at a time. With `record_session`, player
+/// 1's side of the link is written there on exit, for `--replay-link`.
+fn run_linked(mut cores: [Core; 2], mut battery_saves: [Option<BatterySave>; 2], record_session: Option<&str>) {
+    let (a, b) = LinkCable::pair();
+    let recorder = LinkRecorder::new(a);
+    cores[0].connect_serial(recorder.clone());
+    cores[1].connect_serial(b);
+
+    for (core, battery_save) in cores.iter_mut().zip(&battery_saves) {
+        if let Some(battery_save) = battery_save {
+            if let Err(e) = battery_save.load(core) {
+                eprintln!("Failed loading {}: {}", battery_save.path().display(), e);
+            }
+        }
+    }
+
+    let options = WindowOptions { resize: true, ..WindowOptions::default() };
+    let title = format!("{} / {} - Link", cores[0].cartridge_header().title(), cores[1].cartridge_header().title());
+    let mut window = Window::new(&title, 2 * SCREEN_WIDTH * LINKED_WINDOW_SCALE, SCREEN_HEIGHT * LINKED_WINDOW_SCALE, options).unwrap_or_else(|e| {
+        panic!("{}", e);
+    });
+    window.set_target_fps(60);
+    let mut window_buffer = vec![];
+    let mut screen_buffers = [vec![], vec![]];
+
+    let (mut audio_player, _audio_stream) = setup_audio_device().unwrap_or_else(|e| exit_with_error(e));
+    let config = ConfigFile::new(CONFIG_FILE).load();
+    cores[0].set_volume(if config.muted { 0.0 } else { config.volume });
+    cores[0].set_pop_filter(config.pop_filter);
+    cores[0].set_resampler(config.resampler);
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        cores[0].step_frame(&mut NullVideoSink, &mut audio_player, &mut detect_keys(&window));
+        cores[1].step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut detect_player_two_keys(&window));
+
+        present_side_by_side(&mut window, [cores[0].frame(), cores[1].frame()], &mut screen_buffers, &mut window_buffer);
+
+        for (core, battery_save) in cores.iter_mut().zip(&mut battery_saves) {
+            if let Some(battery_save) = battery_save {
+                if let Err(e) = battery_save.update(core) {
+                    eprintln!("Failed writing {}: {}", battery_save.path().display(), e);
+                }
+            }
+        }
+    }
+
+    for (core, battery_save) in cores.iter_mut().zip(&mut battery_saves) {
+        if let Some(battery_save) = battery_save {
+            if let Err(e) = battery_save.flush(core) {
+                eprintln!("Failed writing {}: {}", battery_save.path().display(), e);
+            }
+        }
+    }
+
+    if let Some(path) = record_session {
+        match File::create(path).and_then(|file| recorder.save(BufWriter::new(file))) {
+            Ok(()) => eprintln!("Saved link session to {} ({} transfers)", path, recorder.transfers().len()),
+            Err(e) => eprintln!("Failed saving link session {}: {}", path, e),
+        }
+    }
+}
+
+/// Loads the movie at `path`, putting `core` in the state the movie starts from.
+fn start_movie(path: &str, core: &mut Core) -> MoviePlayer {
+    let movie = if path.ends_with(".bk2") {
+        import_bk2(path, core.rom_hash()).unwrap_or_else(|e| panic!("Failed to import BizHawk movie {}: {}", path, e))
+    } else {
+        load_movie(path).unwrap_or_else(|e| panic!("Failed to read movie {}: {}", path, e))
+    };
+    if movie.rom_hash != core.rom_hash() {
+        eprintln!("Movie was recorded with a different ROM ({:08X}), playback will likely desync", movie.rom_hash);
+    }
+    if let Some(c) = movie.restore_initial_state() {
+        core.restore_from(c);
+    }
+    eprintln!("Playing movie {} ({} frames)", path, movie.len());
+    MoviePlayer::new(movie)
+}
+
+/// Runs `frames` frames without a window or audio, by default the movie's length or
+/// `DEFAULT_HEADLESS_FRAMES`, then writes the last frame to `<rom>.png` and the state hash to
+/// `<rom>.hash` in `output`. The hash is also printed, for scripts.
+fn run_headless(mut core: Core, mut movie_player: Option<MoviePlayer>, frames: Option<u32>, game_rom: Option<&str>, output: &Path) -> std::io::Result<()> {
+    let frames = frames
+        .or_else(|| movie_player.as_ref().map(|player| player.remaining_frames() as u32))
+        .unwrap_or(DEFAULT_HEADLESS_FRAMES);
+
+    for _ in 0..frames {
+        let mut keys_pressed = movie_player.as_ref().and_then(|p| p.current_input()).unwrap_or_else(JoypadInput::empty);
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut keys_pressed);
+        if let Some(player) = &mut movie_player {
+            player.next_frame();
+        }
+    }
+
+    let name = game_rom.and_then(|rom| Path::new(rom).file_stem()).map_or("boot".into(), |stem| stem.to_string_lossy());
+    let hash = core.state_hash();
+
+    std::fs::create_dir_all(output)?;
+    save_frame(output.join(format!("{}.png", name)), core.frame(), 1).map_err(std::io::Error::other)?;
+    std::fs::write(output.join(format!("{}.hash", name)), format!("{:08X}\n", hash))?;
+
+    println!("{:08X}", hash);
+    eprintln!("Ran {} frames, wrote {}.png and {}.hash to {}", frames, name, name, output.display());
+    Ok(())
+}
+
+/// Emulates frames as fast as possible for `duration` of wall-clock time, without a window or
+/// audio, and prints how fast that went.
+fn run_bench(mut core: Core, duration: Duration) {
+    let start = Instant::now();
+    let mut frames: u64 = 0;
+
+    while start.elapsed() < duration {
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+        frames += 1;
+    }
+
+    let seconds = start.elapsed().as_secs_f64();
+    let emulated = core.emulated_time().as_secs_f64();
+    println!("{} frames in {:.2} s", frames, seconds);
+    println!("{:.1} frames per second", frames as f64 / seconds);
+    println!("{:.2} MHz ({:.0}% of a DMG's {:.2} MHz)", core.cycles_elapsed() as f64 / seconds / 1e6, emulated / seconds * 100.0, CLOCK_SPEED as f64 / 1e6);
+}
+
+// Deterministic for running ahead, which needs input latched per frame
+fn load_core(boot_rom: &Option<String>, skip_boot_rom: bool, game_rom: Option<String>, model: Model, quirks: Quirks, power_on_ram: PowerOnRam, deterministic: bool) -> dmg::Result<Core> {
+    let mut builder = CoreBuilder::new().model(model).quirks(quirks).power_on_ram(power_on_ram).deterministic(deterministic);
+    if let Some(game_rom) = &game_rom {
+        builder = builder.game_rom(game_rom);
+    }
+
+    let boot_rom = if skip_boot_rom {
+        None
+    } else {
+        boot_rom.clone().or_else(|| find_boot_rom(game_rom.as_deref()).map(|path| path.to_string_lossy().into_owned()))
+    };
+
+    let mut core = match boot_rom {
+        Some(path) => builder.boot_rom(path).build()?,
+        #[cfg(feature = "bundled-boot-rom")]
+        None if !skip_boot_rom => builder.bundled_boot_rom().build()?,
+        None => builder.build()?,
+    };
+
+    // Labels from an RGBDS symbol file next to the ROM, e.g. game.sym for game.gb
+    if let Some(path) = game_rom.map(|rom| Path::new(&rom).with_extension("sym")).filter(|path| path.is_file()) {
+        if let Err(e) = core.load_symbols(&path.to_string_lossy()) {
+            eprintln!("{}", e);
+        }
+    }
+
+    Ok(core)
+}
+
+#[cfg(feature = "debugger")]
+fn run_debugger(core: Core, watches: Vec<Expr>) {
+    if let Err(e) = frontend::debugger::run(core, watches) {
+        eprintln!("Debugger failed: {}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(not(feature = "debugger"))]
+fn run_debugger(_core: Core, _watches: Vec<Expr>) {
+    eprintln!("--debug needs a build with the debugger feature: cargo run --features debugger");
+    process::exit(1);
+}
+
+/// Connects the link port to stdin/stdout with `--serial-stdio`, or to a pair of files or named
+/// pipes with `--serial-pipes <in> <out>`. Named pipes block until the other end opens them, the
+/// output first.
+fn open_serial_bridge(stdio: bool, pipes: Option<&(String, String)>) -> Option<SerialBridge> {
+    if stdio {
+        return Some(SerialBridge::stdio());
+    }
+
+    let (input, output) = pipes?;
+    let result = File::create(output).and_then(|output| Ok(SerialBridge::new(File::open(input)?, output)));
+    match result {
+        Ok(bridge) => Some(bridge),
+        Err(e) => {
+            eprintln!("Failed opening serial pipes {} and {}: {}", input, output, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn exit_with_error(e: dmg::Error) -> ! {
+    eprintln!("{}", e);
+    process::exit(1);
+}
+
+fn next_frame_input(window: &Window, turbo: &Turbo, movie_player: &Option<MoviePlayer>) -> JoypadInput {
+    match movie_player.as_ref().and_then(|p| p.current_input()) {
+        Some(keys) => keys,
+        None => turbo.apply(detect_keys(window), detect_turbo_keys(window)),
+    }
+}
+
+fn open_window(title: &str, fullscreen: bool) -> Window {
+    // minifb can't switch to fullscreen, so fullscreen is a borderless window as large as the screen
+    // allows. The frame is scaled to the window by hand either way.
+    let (options, width, height) = if fullscreen {
+        let options = WindowOptions { borderless: true, topmost: true, scale: Scale::FitScreen, ..WindowOptions::default() };
+        (options, SCREEN_WIDTH, SCREEN_HEIGHT)
+    } else {
+        let options = WindowOptions { resize: true, ..WindowOptions::default() };
+        (options, SCREEN_WIDTH * WINDOW_SCALE, SCREEN_HEIGHT * WINDOW_SCALE)
+    };
+
+    let mut window = Window::new(title, width, height, options).unwrap_or_else(|e| {
+        panic!("{}", e);
+    });
+    window.set_target_fps(60);
+    window
+}
+
+fn open_vram_window() -> Window {
+    let options = WindowOptions { scale: Scale::X2, ..WindowOptions::default() };
+    let mut window = Window::new("gameboy - VRAM", VRAM_VIEW_WIDTH, VRAM_VIEW_HEIGHT, options).unwrap_or_else(|e| {
+        panic!("{}", e);
+    });
+    // Updated alongside the main window, which does the frame pacing
+    window.set_target_fps(0);
+    window
+}
+
+fn present(window: &mut Window, buffer: &[u32], window_buffer: &mut Vec<u32>) {
+    let (width, height) = window.get_size();
+    if width == 0 || height == 0 {
+        window.update();
+        return;
+    }
+
+    scale_to_window(buffer, width, height, window_buffer);
+    // We unwrap here as we want this code to exit if it fails. Real applications may want to handle this in a different way
+    window.update_with_buffer(window_buffer, width, height).unwrap();
+}
+
+/// Presents two frames, each scaled into its half of the window.
+fn present_side_by_side(window: &mut Window, frames: [&[u32]; 2], screen_buffers: &mut [Vec<u32>; 2], window_buffer: &mut Vec<u32>) {
+    let (width, height) = window.get_size();
+    let half = width / 2;
+    if half == 0 || height == 0 {
+        window.update();
+        return;
+    }
+
+    window_buffer.clear();
+    window_buffer.resize(width * height, 0);
+    for (side, (frame, screen)) in frames.into_iter().zip(screen_buffers.iter_mut()).enumerate() {
+        scale_to_window(frame, half, height, screen);
+        for (y, row) in screen.chunks_exact(half).enumerate() {
+            window_buffer[y * width + side * half..][..half].copy_from_slice(row);
+        }
+    }
+    window.update_with_buffer(window_buffer, width, height).unwrap();
+}
+
+fn dimmed(buffer: &[u32]) -> Vec<u32> {
+    buffer.iter().map(|pixel| (pixel >> 1) & 0x7f7f7f).collect()
+}
+
+fn autosave(core: &Core, save_states: &SaveStateManager) {
+    if let Err(e) = save_states.save_autosave(core) {
+        eprintln!("Failed writing autosave: {}", e);
+    }
+}
+
+fn handle_save_state_keys(window: &Window, core: &mut Core, save_states: &SaveStateManager) {
+    let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+
+    for (slot, key) in [(1, Key::F1), (2, Key::F2), (3, Key::F3), (4, Key::F4)] {
+        if !window.is_key_pressed(key, KeyRepeat::No) {
+            continue;
+        }
+
+        if shift {
+            match save_states.save_slot(slot, core) {
+                Ok(_) => eprintln!("Saved state to slot {}", slot),
+                Err(e) => eprintln!("Failed saving state to slot {}: {}", slot, e),
+            }
+        } else {
+            match save_states.load_slot(slot) {
+                Ok(c) => {
+                    core.restore_from(c);
+                    eprintln!("Loaded state from slot {}", slot);
+                }
+                Err(StateError::Io(_)) => eprintln!("No state in slot {}", slot),
+                Err(e) => eprintln!("Failed loading state from slot {}: {}", slot, e),
+            }
+        }
+    }
+}
+
+fn detect_keys(window: &Window) -> JoypadInput {
+    let mut keys_pressed = JoypadInput::empty();
+
+    if window.is_key_down(Key::Up) { keys_pressed |= JoypadInput::UP; }
+    if window.is_key_down(Key::Left) { keys_pressed |= JoypadInput::LEFT; }
+    if window.is_key_down(Key::Down) { keys_pressed |= JoypadInput::DOWN; }
+    if window.is_key_down(Key::Right) { keys_pressed |= JoypadInput::RIGHT; }
+    if window.is_key_down(Key::Enter) { keys_pressed |= JoypadInput::START; }
+    if window.is_key_down(Key::RightShift) { keys_pressed |= JoypadInput::SELECT; }
+    if window.is_key_down(Key::Z) { keys_pressed |= JoypadInput::A; }
+    if window.is_key_down(Key::X) { keys_pressed |= JoypadInput::B; }
+
+    keys_pressed
+}
+
+/// The second player's buttons on the left of the keyboard, while linked.
+fn detect_player_two_keys(window: &Window) -> JoypadInput {
+    let mut keys_pressed = JoypadInput::empty();
+
+    if window.is_key_down(Key::W) { keys_pressed |= JoypadInput::UP; }
+    if window.is_key_down(Key::A) { keys_pressed |= JoypadInput::LEFT; }
+    if window.is_key_down(Key::S) { keys_pressed |= JoypadInput::DOWN; }
+    if window.is_key_down(Key::D) { keys_pressed |= JoypadInput::RIGHT; }
+    if window.is_key_down(Key::E) { keys_pressed |= JoypadInput::START; }
+    if window.is_key_down(Key::Q) { keys_pressed |= JoypadInput::SELECT; }
+    if window.is_key_down(Key::K) { keys_pressed |= JoypadInput::A; }
+    if window.is_key_down(Key::J) { keys_pressed |= JoypadInput::B; }
+
+    keys_pressed
+}
+
+fn detect_turbo_keys(window: &Window) -> JoypadInput {
+    let mut keys_pressed = JoypadInput::empty();
+
+    if window.is_key_down(Key::C) { keys_pressed |= JoypadInput::A; }
+    if window.is_key_down(Key::V) { keys_pressed |= JoypadInput::B; }
+
+    keys_pressed
+}
+
+/// Returns whether the volume settings changed.
+fn handle_volume_keys(window: &Window, config: &mut Config) -> bool {
+    let volume = config.volume;
+
+    if window.is_key_pressed(Key::Equal, KeyRepeat::Yes) || window.is_key_pressed(Key::NumPadPlus, KeyRepeat::Yes) {
+        config.volume = (config.volume + VOLUME_STEP).min(MAX_VOLUME);
+    }
+    if window.is_key_pressed(Key::Minus, KeyRepeat::Yes) || window.is_key_pressed(Key::NumPadMinus, KeyRepeat::Yes) {
+        config.volume = (config.volume - VOLUME_STEP).max(0.0);
+    }
+    if config.volume != volume {
+        config.muted = false;
+        eprintln!("Volume {:.0}%", config.volume * 100.0);
+        return true;
+    }
+
+    if window.is_key_pressed(Key::M, KeyRepeat::No) {
+        config.muted = !config.muted;
+        eprintln!("{}", if config.muted { "Muted" } else { "Unmuted" });
+        return true;
+    }
+
+    false
+}
+
+fn start_recording(format: Option<&str>) -> Option<Box<dyn Recorder>> {
+    let recorder: std::io::Result<Box<dyn Recorder>> = match format.unwrap_or("gif") {
+        "gif" => GifRecorder::create(RECORDING_DIR).map(|r| Box::new(r) as _),
+        #[cfg(feature = "ffmpeg")]
+        "mp4" => frontend::recorder::FfmpegRecorder::create(RECORDING_DIR).map(|r| Box::new(r) as _),
+        other => {
+            eprintln!("Unsupported recording format {}", other);
+            return None;
+        }
+    };
+
+    match recorder {
+        Ok(r) => {
+            eprintln!("Recording started");
+            Some(r)
+        }
+        Err(e) => {
+            eprintln!("Failed starting recording: {}", e);
+            None
+        }
+    }
+}
+
+fn start_frame_dump(frames: u32) -> Option<FrameDump> {
+    match FrameDump::create(SCREENSHOT_DIR) {
+        Ok(dump) => {
+            eprintln!("Dumping the next {} frames", frames);
+            Some(dump)
+        }
+        Err(e) => {
+            eprintln!("Failed starting frame dump: {}", e);
+            None
+        }
+    }
+}
+
+fn finish_recording(recorder: Box<dyn Recorder>) {
+    match recorder.finish() {
+        Ok(path) => eprintln!("Saved recording to {}", path.display()),
+        Err(e) => eprintln!("Failed saving recording: {}", e),
+    }
+}
+
+// Reads the boot ROM and palette files given on the command line again, keeping the game running
+fn reload_boot_rom_and_palette(core: &mut Core, boot_rom: Option<&str>, palette: Option<&str>) {
+    if let Some(path) = boot_rom {
+        match core.load_boot_rom(path) {
+            Ok(()) => eprintln!("Reloaded boot ROM {}", path),
+            Err(e) => eprintln!("Failed reloading boot ROM: {}", e),
+        }
+    }
+    if let Some(path) = palette {
+        match core.load_palette(path) {
+            Ok(()) => eprintln!("Reloaded palette {}", path),
+            Err(e) => eprintln!("Failed reloading palette: {}", e),
+        }
+    }
+}
+
+fn take_screenshot(window: &Window, buffer: &[u32], at_window_scale: bool) {
+    let scale = if at_window_scale {
+        let (width, height) = window.get_size();
+        (width / SCREEN_WIDTH).min(height / SCREEN_HEIGHT)
+    } else {
+        1
+    };
+
+    match save_screenshot(SCREENSHOT_DIR, buffer, scale) {
+        Ok(path) => eprintln!("Saved screenshot to {}", path.display()),
+        Err(e) => eprintln!("Failed saving screenshot: {}", e),
+    }
+}
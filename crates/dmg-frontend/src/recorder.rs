@@ -0,0 +1,269 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, RgbaImage};
+
+use dmg::{FRAME_DURATION, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::screenshot::{frame_image, unused_path};
+
+/// Captures gameplay, one emulated frame at a time, for bug reports and clips.
+pub trait Recorder {
+    /// Adds a frame of 0RGB pixels and the audio generated while it was emulated, finished at
+    /// `time` by `Core::emulated_time`.
+    fn push_frame(&mut self, time: Duration, pixels: &[u32], audio: &[(f32, f32)]) -> io::Result<()>;
+
+    /// Finishes the file and returns where it was written.
+    fn finish(self: Box<Self>) -> io::Result<PathBuf>;
+}
+
+/// Writes every other frame to an animated GIF, without audio.
+///
+/// Browsers show GIF frames with delays under 2/100 s far slower than asked, so the GIF runs at
+/// half the frame rate with delays alternating between 3/100 and 4/100 s to keep real time.
+pub struct GifRecorder {
+    encoder: GifEncoder<BufWriter<File>>,
+    path: PathBuf,
+    frames: u64,
+    last_time: Option<Duration>,
+    // Emulated time of the frames pushed so far
+    recorded: Duration,
+    elapsed_cs: u64,
+}
+
+impl GifRecorder {
+    pub fn create(dir: impl AsRef<Path>) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let path = unused_path(dir.as_ref(), "recording", "gif");
+
+        let mut encoder = GifEncoder::new(BufWriter::new(File::create(&path)?));
+        encoder.set_repeat(Repeat::Infinite).map_err(image_error)?;
+
+        Ok(Self { encoder, path, frames: 0, last_time: None, recorded: Duration::ZERO, elapsed_cs: 0 })
+    }
+}
+
+impl Recorder for GifRecorder {
+    fn push_frame(&mut self, time: Duration, pixels: &[u32], _audio: &[(f32, f32)]) -> io::Result<()> {
+        // The first frame has nothing to go by, and rewinding moves time backwards
+        let frame_time = self.last_time.replace(time).map_or(FRAME_DURATION, |last| time.saturating_sub(last));
+        self.recorded += frame_time;
+
+        self.frames += 1;
+        if self.frames % 2 == 1 {
+            return Ok(());
+        }
+
+        // Delays are whole hundredths, so round the running total rather than each frame
+        let total_cs = (self.recorded.as_nanos() / 10_000_000) as u64;
+        let delay = total_cs - self.elapsed_cs;
+        self.elapsed_cs = total_cs;
+
+        let image = RgbaImage::from_fn(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32, |x, y| {
+            let [_, r, g, b] = pixels[y as usize * SCREEN_WIDTH + x as usize].to_be_bytes();
+            image::Rgba([r, g, b, 0xff])
+        });
+        let frame = image::Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(delay as u32 * 10, 1));
+
+        self.encoder.encode_frame(frame).map_err(image_error)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<PathBuf> {
+        // The trailer is written when the encoder is dropped
+        drop(self.encoder);
+        Ok(self.path)
+    }
+}
+
+/// Writes every frame to its own numbered PNG in a new directory, for making animations and
+/// stepping through rendering glitches frame by frame.
+pub struct FrameDump {
+    dir: PathBuf,
+    frames: u32,
+}
+
+impl FrameDump {
+    pub fn create(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = unused_path(dir.as_ref(), "frames", "");
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self { dir, frames: 0 })
+    }
+
+    /// How many frames have been written so far.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+}
+
+impl Recorder for FrameDump {
+    fn push_frame(&mut self, _time: Duration, pixels: &[u32], _audio: &[(f32, f32)]) -> io::Result<()> {
+        self.frames += 1;
+        // Zero-padded so the files sort in order
+        let path = self.dir.join(format!("frame-{:05}.png", self.frames));
+        frame_image(pixels, 1).save(path).map_err(image_error)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<PathBuf> {
+        Ok(self.dir)
+    }
+}
+
+fn image_error(e: image::ImageError) -> io::Error {
+    io::Error::other(e)
+}
+
+#[cfg(feature = "ffmpeg")]
+pub use self::ffmpeg::FfmpegRecorder;
+
+#[cfg(feature = "ffmpeg")]
+mod ffmpeg {
+    use std::fs;
+    use std::fs::File;
+    use std::io;
+    use std::io::{BufWriter, Write};
+    use std::path::{Path, PathBuf};
+    use std::process::{Child, ChildStdin, Command, Stdio};
+    use std::time::Duration;
+
+    use dmg::{CLOCK_SPEED, CYCLES_PER_FRAME};
+    use crate::recorder::Recorder;
+    use crate::screenshot::unused_path;
+
+    // One stereo sample every 95 clock cycles
+    const SAMPLE_RATE: u64 = CLOCK_SPEED / 95;
+
+    /// Pipes every frame to `ffmpeg` and muxes in the audio when finished, giving an MP4 at the
+    /// full 59.7 fps. Needs `ffmpeg` on the PATH.
+    pub struct FfmpegRecorder {
+        ffmpeg: Child,
+        video_in: BufWriter<ChildStdin>,
+        audio: BufWriter<File>,
+        video_path: PathBuf,
+        audio_path: PathBuf,
+        path: PathBuf,
+    }
+
+    impl FfmpegRecorder {
+        pub fn create(dir: impl AsRef<Path>) -> io::Result<Self> {
+            fs::create_dir_all(&dir)?;
+            let path = unused_path(dir.as_ref(), "recording", "mp4");
+            let video_path = path.with_extension("video.mkv");
+            let audio_path = path.with_extension("audio.raw");
+
+            let framerate = format!("{}/{}", CLOCK_SPEED, CYCLES_PER_FRAME);
+            let mut ffmpeg = Command::new("ffmpeg")
+                .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pixel_format", "bgra", "-video_size", "160x144"])
+                .args(["-framerate", &framerate, "-i", "-"])
+                .args(["-c:v", "libx264", "-pix_fmt", "yuv420p", "-crf", "0"])
+                .arg(&video_path)
+                .stdin(Stdio::piped())
+                .spawn()?;
+            let video_in = BufWriter::new(ffmpeg.stdin.take().expect("ffmpeg stdin is piped"));
+
+            Ok(Self {
+                ffmpeg,
+                video_in,
+                audio: BufWriter::new(File::create(&audio_path)?),
+                video_path,
+                audio_path,
+                path,
+            })
+        }
+    }
+
+    impl Recorder for FfmpegRecorder {
+        // Frames go in at a constant rate, the same as the audio
+        fn push_frame(&mut self, _time: Duration, pixels: &[u32], audio: &[(f32, f32)]) -> io::Result<()> {
+            for pixel in pixels {
+                self.video_in.write_all(&(pixel | 0xff000000).to_le_bytes())?;
+            }
+            for (left, right) in audio {
+                self.audio.write_all(&left.to_le_bytes())?;
+                self.audio.write_all(&right.to_le_bytes())?;
+            }
+            Ok(())
+        }
+
+        fn finish(mut self: Box<Self>) -> io::Result<PathBuf> {
+            self.audio.flush()?;
+            // Closing stdin lets ffmpeg finish the video
+            drop(self.video_in);
+            self.ffmpeg.wait()?;
+
+            let status = Command::new("ffmpeg")
+                .args(["-y", "-loglevel", "error", "-i"])
+                .arg(&self.video_path)
+                .args(["-f", "f32le", "-ar", &SAMPLE_RATE.to_string(), "-ac", "2", "-i"])
+                .arg(&self.audio_path)
+                .args(["-c:v", "copy", "-c:a", "aac", "-shortest"])
+                .arg(&self.path)
+                .status()?;
+
+            let _ = fs::remove_file(&self.video_path);
+            let _ = fs::remove_file(&self.audio_path);
+
+            if !status.success() {
+                return Err(io::Error::other(format!("ffmpeg exited with {}", status)));
+            }
+            Ok(self.path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+
+    use dmg::dmg::core::FRAME_DURATION;
+    use dmg::{SCREEN_HEIGHT, SCREEN_WIDTH};
+    use crate::recorder::{FrameDump, GifRecorder, Recorder};
+
+    #[test]
+    fn gif_keeps_real_time_at_half_frame_rate() {
+        let dir = env::temp_dir().join("gif_keeps_real_time_at_half_frame_rate");
+        let mut recorder = Box::new(GifRecorder::create(&dir).unwrap());
+        let pixels = vec![0xff88c070; SCREEN_WIDTH * SCREEN_HEIGHT];
+
+        for frame in 1..=60 {
+            recorder.push_frame(FRAME_DURATION * frame, &pixels, &[]).unwrap();
+        }
+        let path = recorder.finish().unwrap();
+
+        let decoder = GifDecoder::new(BufReader::new(File::open(path).unwrap())).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        let total_ms: u32 = frames.iter().map(|f| f.delay().numer_denom_ms().0 / f.delay().numer_denom_ms().1).sum();
+
+        assert_eq!(frames.len(), 30);
+        assert_eq!(frames[0].buffer().get_pixel(0, 0).0, [0x88, 0xc0, 0x70, 0xff]);
+        assert_eq!(total_ms, 1000);
+    }
+
+    #[test]
+    fn frame_dump_numbers_every_frame() {
+        let dir = env::temp_dir().join("frame_dump_numbers_every_frame");
+        let mut dump = Box::new(FrameDump::create(&dir).unwrap());
+        let mut pixels = vec![0xff000000; SCREEN_WIDTH * SCREEN_HEIGHT];
+
+        for frame in 1..=3 {
+            pixels[0] = 0xff000000 | frame;
+            dump.push_frame(FRAME_DURATION * frame, &pixels, &[]).unwrap();
+        }
+        assert_eq!(dump.frames(), 3);
+        let path = dump.finish().unwrap();
+
+        let last = image::open(path.join("frame-00003.png")).unwrap().to_rgb8();
+        assert!(path.join("frame-00001.png").exists());
+        assert_eq!(last.dimensions(), (SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32));
+        assert_eq!(last.get_pixel(0, 0).0, [0, 0, 3]);
+    }
+}
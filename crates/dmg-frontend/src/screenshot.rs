@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::{ImageResult, RgbImage};
+
+use dmg::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Saves a frame of 0RGB pixels as a PNG in `dir`, each pixel enlarged to `scale` x `scale`.
+/// Files are named after the current time so earlier screenshots are never overwritten.
+pub fn save_screenshot(dir: impl AsRef<Path>, pixels: &[u32], scale: usize) -> ImageResult<PathBuf> {
+    fs::create_dir_all(&dir)?;
+    let path = unused_path(dir.as_ref(), "screenshot", "png");
+    save_frame(&path, pixels, scale)?;
+
+    Ok(path)
+}
+
+/// Saves a frame of 0RGB pixels to `path`, in the format its extension names, each pixel
+/// enlarged to `scale` x `scale`.
+pub fn save_frame(path: impl AsRef<Path>, pixels: &[u32], scale: usize) -> ImageResult<()> {
+    frame_image(pixels, scale).save(path)
+}
+
+/// A frame of 0RGB pixels as an image, each pixel enlarged to `scale` x `scale`.
+pub(crate) fn frame_image(pixels: &[u32], scale: usize) -> RgbImage {
+    let scale = scale.max(1);
+    RgbImage::from_fn((SCREEN_WIDTH * scale) as u32, (SCREEN_HEIGHT * scale) as u32, |x, y| {
+        let pixel = pixels[y as usize / scale * SCREEN_WIDTH + x as usize / scale];
+        let [_, r, g, b] = pixel.to_be_bytes();
+        image::Rgb([r, g, b])
+    })
+}
+
+/// A file in `dir` named after the current time, that doesn't exist yet. An empty `extension`
+/// gives a name without one, for directories.
+pub(crate) fn unused_path(dir: &Path, prefix: &str, extension: &str) -> PathBuf {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+
+    let mut path = dir.join(format!("{}-{}", prefix, millis)).with_extension(extension);
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{}-{}-{}", prefix, millis, n)).with_extension(extension);
+        n += 1;
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use dmg::{SCREEN_HEIGHT, SCREEN_WIDTH};
+    use crate::screenshot::save_screenshot;
+
+    #[test]
+    fn saves_rgb_colors_without_overwriting() {
+        let dir = env::temp_dir().join("saves_rgb_colors_without_overwriting");
+        let mut pixels = vec![0xff000000; SCREEN_WIDTH * SCREEN_HEIGHT];
+        pixels[0] = 0xff88c070;
+
+        let first = save_screenshot(&dir, &pixels, 2).unwrap();
+        let second = save_screenshot(&dir, &pixels, 2).unwrap();
+        let image = image::open(&first).unwrap().to_rgb8();
+
+        assert_ne!(first, second);
+        assert_eq!(image.dimensions(), (SCREEN_WIDTH as u32 * 2, SCREEN_HEIGHT as u32 * 2));
+        assert_eq!(image.get_pixel(1, 1).0, [0x88, 0xc0, 0x70]);
+        assert_eq!(image.get_pixel(2, 0).0, [0, 0, 0]);
+    }
+}
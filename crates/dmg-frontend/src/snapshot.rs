@@ -0,0 +1,109 @@
+use std::fs;
+use std::path::Path;
+
+use dmg::dmg::core::Core;
+use dmg::dmg::input::JoypadInput;
+use dmg::dmg::traits::{NullAudioSink, NullVideoSink};
+use crate::screenshot::frame_image;
+
+/// Set to 1 to have `assert_frame_snapshot!` write the frames it renders as the new snapshots.
+pub const UPDATE_SNAPSHOTS_VAR: &str = "UPDATE_SNAPSHOTS";
+
+/// Frames rendered by `assert_frame_snapshot!` when no count is given, a second of emulated time.
+pub const SNAPSHOT_FRAMES: usize = 60;
+
+/// Renders `frames` frames on `core` without input and compares the last one with the PNG at
+/// `path`, or writes it there when `update` is set. Returns what differs.
+pub fn check_frame_snapshot(core: &mut Core, frames: usize, path: &Path, update: bool) -> Result<(), String> {
+    for _ in 0..frames {
+        core.step_frame(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+    }
+    let actual = frame_image(core.frame(), 1);
+
+    if update {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed creating {}: {}", dir.display(), e))?;
+        }
+        return actual.save(path).map_err(|e| format!("Failed writing {}: {}", path.display(), e));
+    }
+
+    let expected = image::open(path)
+        .map_err(|e| format!("Failed reading snapshot {}: {}, run with {}=1 to create it", path.display(), e, UPDATE_SNAPSHOTS_VAR))?
+        .to_rgb8();
+
+    if expected.dimensions() != actual.dimensions() {
+        return Err(format!("Snapshot {} is {:?}, expected {:?}", path.display(), expected.dimensions(), actual.dimensions()));
+    }
+
+    let mut differing = expected.enumerate_pixels().filter(|&(x, y, pixel)| actual.get_pixel(x, y) != pixel);
+    match differing.next() {
+        Some((x, y, _)) => Err(format!(
+            "{} pixels differ from snapshot {}, first at ({}, {}), run with {}=1 to update it",
+            differing.count() + 1,
+            path.display(),
+            x,
+            y,
+            UPDATE_SNAPSHOTS_VAR
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Renders frames on a core and compares the last one with `tests/snapshots/<name>.png` in the
+/// calling crate. Run with `UPDATE_SNAPSHOTS=1` to write new snapshots instead.
+///
+/// ```no_run
+/// # use frontend::assert_frame_snapshot;
+/// # use dmg::dmg::core::Core;
+/// let mut core = Core::load_without_boot_rom(Some("tetris.gb".to_string())).unwrap();
+/// assert_frame_snapshot!(core, "tetris_title");
+/// assert_frame_snapshot!(core, "tetris_menu", 120);
+/// ```
+#[macro_export]
+macro_rules! assert_frame_snapshot {
+    ($core:expr, $name:expr) => {
+        $crate::assert_frame_snapshot!($core, $name, $crate::snapshot::SNAPSHOT_FRAMES)
+    };
+    ($core:expr, $name:expr, $frames:expr) => {{
+        let path = ::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("snapshots").join(format!("{}.png", $name));
+        let update = ::std::env::var_os($crate::snapshot::UPDATE_SNAPSHOTS_VAR).is_some_and(|v| v == "1");
+        if let Err(e) = $crate::snapshot::check_frame_snapshot(&mut $core, $frames, &path, update) {
+            panic!("{}", e);
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use dmg::dmg::core::{Core, CoreBuilder};
+    use dmg::test_support::looping_rom;
+    use crate::snapshot::check_frame_snapshot;
+
+    fn looping_core() -> Core {
+        CoreBuilder::new().game_rom_data(looping_rom(&[])).build().unwrap()
+    }
+
+    #[test]
+    fn snapshots_are_written_then_compared() {
+        let path = env::temp_dir().join("snapshots_are_written_then_compared").join("frame.png");
+        let _ = fs::remove_file(&path);
+
+        let mut core = looping_core();
+        assert!(check_frame_snapshot(&mut core, 2, &path, false).unwrap_err().contains("UPDATE_SNAPSHOTS=1"));
+
+        let mut core = looping_core();
+        check_frame_snapshot(&mut core, 2, &path, true).unwrap();
+
+        let mut core = looping_core();
+        check_frame_snapshot(&mut core, 2, &path, false).unwrap();
+
+        let mut image = image::open(&path).unwrap().to_rgb8();
+        image.put_pixel(3, 4, image::Rgb([0, 0, 0]));
+        image.save(&path).unwrap();
+        let mut core = looping_core();
+        assert!(check_frame_snapshot(&mut core, 2, &path, false).unwrap_err().starts_with("1 pixels differ"));
+    }
+}
@@ -0,0 +1,63 @@
+//! Replays input movies headlessly and checks the state hash after the last frame, catching
+//! timing, PPU and APU changes that alter emulation without failing a unit test. Each replay in
+//! `tests/replays/` is a movie `<name>.movie` recorded with `--skip-boot-rom --record-movie`, the
+//! game `<name>.gb`, and the expected hash in `<name>.hash`. Run with `UPDATE_SNAPSHOTS=1` to
+//! write the hashes after an intended change.
+//!
+//! `input_sum.gb` is a 32 KiB homebrew ROM written for this test: from 0x150 it reads both joypad
+//! rows, adds the pressed keys to 0xC000 and XORs LY into 0xC001 in a loop, so both the input and
+//! the instruction timing end up in the state.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use dmg::dmg::core::CoreBuilder;
+use dmg::emulator::movie::load_movie;
+use frontend::snapshot::UPDATE_SNAPSHOTS_VAR;
+
+const REPLAY_DIR: &str = "tests/replays";
+
+/// Replays the movie at `movie_path`, returning the state hash after its last frame.
+fn replay(movie_path: &Path) -> u32 {
+    let movie = load_movie(&movie_path.to_string_lossy()).unwrap();
+    let rom = movie_path.with_extension("gb");
+
+    let mut core = match movie.restore_initial_state() {
+        Some(core) => core,
+        None => CoreBuilder::new().game_rom(rom.to_string_lossy()).deterministic(true).build().unwrap(),
+    };
+    assert_eq!(core.rom_hash(), movie.rom_hash, "{} wasn't recorded with {}", movie_path.display(), rom.display());
+
+    movie.replay(&mut core);
+    core.state_hash()
+}
+
+#[test]
+fn replays_end_in_recorded_state() {
+    let entries = fs::read_dir(REPLAY_DIR).unwrap();
+    let update = env::var_os(UPDATE_SNAPSHOTS_VAR).is_some_and(|v| v == "1");
+
+    let mut movies: Vec<PathBuf> = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| path.extension().is_some_and(|ext| ext == "movie")).collect();
+    movies.sort();
+    assert!(!movies.is_empty(), "No movies in {}", REPLAY_DIR);
+
+    let mut failures = vec![];
+    for movie in movies {
+        let hash = replay(&movie);
+        let hash_path = movie.with_extension("hash");
+
+        if update {
+            fs::write(&hash_path, format!("{:08X}\n", hash)).unwrap();
+            continue;
+        }
+
+        match fs::read_to_string(&hash_path) {
+            Ok(expected) if expected.trim() == format!("{:08X}", hash) => {}
+            Ok(expected) => failures.push(format!("{}: state hash {:08X}, expected {}", movie.display(), hash, expected.trim())),
+            Err(e) => failures.push(format!("{}: failed reading {}: {}", movie.display(), hash_path.display(), e)),
+        }
+    }
+
+    assert!(failures.is_empty(), "{}\nRun with {}=1 if the change is intended", failures.join("\n"), UPDATE_SNAPSHOTS_VAR);
+}
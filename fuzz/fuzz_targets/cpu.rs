@@ -0,0 +1,31 @@
+//! Executes random instruction streams. The input is placed at the entry point of an otherwise
+//! empty 32K ROM without an MBC, so the CPU also runs into whatever the stream jumps to.
+#![no_main]
+
+use dmg::dmg::core::CoreBuilder;
+use dmg::dmg::input::JoypadInput;
+use dmg::dmg::traits::{NullAudioSink, NullVideoSink};
+use libfuzzer_sys::fuzz_target;
+
+const ROM_SIZE: usize = 0x8000;
+const ENTRY_POINT: usize = 0x100;
+const CARTRIDGE_TYPE: usize = 0x147;
+
+// Enough to reach interrupts, DMA and a few frames of PPU and APU state
+const INSTRUCTIONS: usize = 100_000;
+
+fuzz_target!(|data: &[u8]| {
+    let mut rom = vec![0; ROM_SIZE];
+    let len = data.len().min(ROM_SIZE - ENTRY_POINT);
+    rom[ENTRY_POINT..ENTRY_POINT + len].copy_from_slice(&data[..len]);
+    rom[CARTRIDGE_TYPE] = 0x00;
+
+    let mut core = CoreBuilder::new().game_rom_data(rom).build().unwrap();
+    for _ in 0..INSTRUCTIONS {
+        core.step_instruction(&mut NullVideoSink, &mut NullAudioSink, &mut JoypadInput::empty());
+        // An illegal opcode locks the CPU up, leaving nothing more to run
+        if core.take_crash().is_some() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,40 @@
+//! Makes random MBC register and cartridge RAM writes, reading back all of ROM and RAM after each
+//! one. The first byte picks the cartridge type and RAM size from the header, the rest are
+//! (address high, address low, value) triples.
+#![no_main]
+
+use dmg::dmg::core::CoreBuilder;
+use libfuzzer_sys::fuzz_target;
+
+const ROM_SIZE: usize = 0x20000;
+const CARTRIDGE_TYPE: usize = 0x147;
+const ROM_SIZE_CODE: usize = 0x148;
+const RAM_SIZE_CODE: usize = 0x149;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&header, writes)) = data.split_first() else {
+        return;
+    };
+
+    let mut rom = vec![0; ROM_SIZE];
+    rom[CARTRIDGE_TYPE] = header & 0x1f;
+    rom[ROM_SIZE_CODE] = 0x02;
+    rom[RAM_SIZE_CODE] = header >> 5;
+
+    // Unsupported cartridge types are rejected with an error, which isn't a bug
+    let Ok(mut core) = CoreBuilder::new().game_rom_data(rom).build() else {
+        return;
+    };
+
+    for write in writes.chunks_exact(3) {
+        let addr = u16::from_be_bytes([write[0], write[1]]);
+        // Only the cartridge's address ranges
+        if addr < 0x8000 || (0xa000..0xc000).contains(&addr) {
+            core.write_byte(addr, write[2]);
+        }
+
+        for addr in (0x0000..0x8000).chain(0xa000..0xc000).step_by(0x100) {
+            core.read_byte(addr);
+        }
+    }
+});
@@ -1,10 +0,0 @@
-pub mod core;
-mod cpu;
-mod gpu;
-mod intf;
-mod mem;
-mod mbc;
-pub mod input;
-mod serial;
-mod sound;
-pub mod traits;
@@ -1,2 +0,0 @@
-pub mod audio;
-pub mod state;
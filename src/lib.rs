@@ -1,2 +0,0 @@
-pub mod dmg;
-pub mod emulator;